@@ -1,443 +0,0 @@
-import psycopg2
-import csv
-from typing import List, Tuple, Callable
-import re
-from dataclasses import dataclass
-import json
-
-with open("people_replacement.json", "r") as f:
-    people_replacement = json.load(f)
-
-
-def replace_people(people: List[str]) -> List[str]:
-    rtn = []
-    for p in people:
-        if p in people_replacement:
-            rep = people_replacement[p]
-            if isinstance(rep, list):
-                rtn.extend(rep)
-            else:
-                rtn.append(rep)
-        else:
-            rtn.append(p)
-    return rtn
-
-
-@dataclass
-class Meal:
-    date: str
-    time: str
-    name: str
-    people: List[str]
-    meal_type: str
-    comment: str
-    location: str
-
-
-@dataclass
-class Day:
-    date: str
-    breakfast: List[Meal]
-    lunch: List[Meal]
-    dinner: List[Meal]
-    special: str
-    exercise: str
-    activity: str
-    entertainment: str
-    housekeeping: str
-
-
-def re_extract(pattern: str, text: str) -> Tuple[List[str], str]:
-    matches = re.findall(pattern, text)
-    for match in matches:
-        text = text.replace(match, "").strip()
-    return matches, text
-
-
-def process_meal(s: str, date: str, time: str) -> List[Meal]:
-    # re.split(r'[,，+]', i)
-    meal_type = None
-    m, s = re_extract(r"(bento|leftover)", s)
-    if m:
-        meal_type = "leftover" if "leftover" in m else "takeout"
-    location = None
-    m, s = re_extract(r"@.*", s)
-    if m:
-        location = m[0][1:].strip()
-    comment = None
-    m, s = re_extract(r"\(.*\)", s)
-    if m:
-        comment = m[0][1:-1].strip()
-    m, s = re_extract(r"（.*）", s)
-    if m:
-        comment = m[0][1:-1].strip()
-    people = []
-    m, s = re_extract(r"w\/.*", s)
-    if m:
-        people = [p.strip() for p in re.split(r"[,，+]", m[0][2:].strip())]
-    people = replace_people(people)
-    return [
-        Meal(
-            date=date,
-            time=time,
-            name=n.strip(),
-            people=people,
-            meal_type=meal_type,
-            comment=comment,
-            location=location,
-        )
-        for n in re.split(r"[,，+]", s)
-    ]
-
-
-def process_meal_time(meal_s: List[str], date: str, time: str) -> List[List[Meal]]:
-    return [process_meal(s, date, time) for s in meal_s if s]
-
-
-def parse(fname: str) -> List[Day]:
-    days = []
-    with open(fname, "r") as f:
-        csv_reader = csv.reader(f.readlines())
-    for row in csv_reader:
-        if len(row) < 10:
-            continue
-        day = Day(
-            date=row[1],
-            breakfast=process_meal_time(row[2:4], row[1], "breakfast"),
-            lunch=process_meal_time(row[4:6], row[1], "lunch"),
-            dinner=process_meal_time(row[6:8], row[1], "dinner"),
-            special=row[8],
-            exercise=row[9],
-            activity=row[10],
-            entertainment=row[11],
-            housekeeping=row[12],
-        )
-        days.append(day)
-    return days
-
-
-def get_who(food_source: str, idx: int, total: int) -> List[str]:
-    if food_source == "recipe" or total == 1:
-        return ["ww", "xx"]
-    return ["ww"] if idx == 1 else ["xx"]
-
-
-def extract_meal(
-    keyword: str,
-    days: List[Day],
-    food_source: str = None,
-    exact: bool = True,
-    dry_run: bool = False,
-    matcher: Callable = None,
-    multi_matcher: Callable = None,
-) -> List[Meal]:
-    def match(meal: Meal) -> bool:
-        if exact:
-            return keyword.lower() == meal.name.lower()
-        return keyword.lower() in meal.name.lower()
-
-    if not matcher:
-        matcher = match
-    rtn = []
-    for day in days:
-        for time in ["breakfast", "lunch", "dinner"]:
-            meals = getattr(day, time)
-            extracted_idx = []
-            for i, cell in enumerate(meals):
-                who = get_who(food_source, i, len(meals))
-                if multi_matcher:
-                    indexes = multi_matcher(cell)
-                    if indexes:
-                        cell[indexes[0]].people += who
-                        rtn.append(cell[indexes[0]])
-                        extracted_idx.extend([(i, j) for j in indexes])
-                    continue
-                for j, meal in enumerate(cell):
-                    if "TBD" not in meal.people and matcher(meal):
-                        meal.people += who
-                        rtn.append(meal)
-                        extracted_idx.append((i, j))
-            if not dry_run and extracted_idx:
-                setattr(
-                    day,
-                    time,
-                    [
-                        [
-                            meal
-                            for j, meal in enumerate(cell)
-                            if (i, j) not in extracted_idx
-                        ]
-                        for i, cell in enumerate(meals)
-                    ],
-                )
-    print(f"{'Found' if dry_run else 'Extracted'} {len(rtn)} records for '{keyword}'")
-    return rtn
-
-
-def merge_meal(meals: List[Meal]) -> List[Meal]:
-    if not meals:
-        return []
-    rtn = [meals[0]]
-    for m in meals[1:]:
-        if (
-            m.date == rtn[-1].date
-            and m.name == rtn[-1].name
-            and m.time == rtn[-1].time
-            and m.meal_type == rtn[-1].meal_type
-            and set(m.people + rtn[-1].people) == set(["xx", "ww"])
-        ):
-            rtn[-1].people = ["xx", "ww"]
-        else:
-            rtn.append(m)
-    if len(rtn) < len(meals):
-        print(f"Merged {len(meals) - len(rtn)} records")
-    return rtn
-
-
-def get_food_id(
-    conn: psycopg2.extensions.connection, food_name: str, food_source: str
-) -> int:
-    cur = conn.cursor()
-    cur.execute(f"SELECT id FROM {food_source} WHERE name = %s", (food_name,))
-    row = cur.fetchone()
-    conn.commit()
-    cur.close()
-    return row[0] if row else -1
-
-
-def get_people_id(conn: psycopg2.extensions.connection, people_names: List[str]) -> int:
-    cur = conn.cursor()
-    cur.execute("SELECT id FROM people WHERE name = ANY(%s)", (people_names,))
-    rows = cur.fetchall()
-    conn.commit()
-    cur.close()
-    return [r[0] for r in rows]
-
-
-def direct_insert_meal(
-    conn: psycopg2.extensions.connection,
-    meals: List[Meal],
-    food_name: str,
-    food_source: str,
-    meal_type_override: str = None,
-    comment_override: str = None,
-) -> bool:
-    fid = get_food_id(conn, food_name, food_source)
-    if fid == -1:
-        print(f"Food item '{food_name}' not found in '{food_source}' table.")
-        return False
-    for meal in meals:
-        people_ids = get_people_id(conn, meal.people)
-        if not people_ids:
-            print(f"People '{meal.people}' not found in 'people' table.")
-            return False
-        # insert into meal table and get auto-generated id
-        cur = conn.cursor()
-        comment = meal.comment if meal.comment else comment_override
-        cur.execute(
-            "INSERT INTO meal (date, time, notes) VALUES (%s, %s, %s) RETURNING id",
-            (meal.date, meal.time, comment),
-        )
-        meal_id = cur.fetchone()[0]
-        conn.commit()
-        cur.close()
-        # insert into meal_<food_source> table
-        cur = conn.cursor()
-        mtm = {"restaurant": "dine-in", "product": "manufactured", "recipe": "cooked"}
-        meal_type = (
-            meal.meal_type
-            if meal.meal_type
-            else (meal_type_override if meal_type_override else mtm[food_source])
-        )
-        cur.execute(
-            f"INSERT INTO meal_{food_source} (meal, {food_source}, type) VALUES (%s, %s, %s)",
-            (meal_id, fid, meal_type),
-        )
-        conn.commit()
-        cur.close()
-        # insert into meal_people table
-        for pid in people_ids:
-            cur = conn.cursor()
-            cur.execute(
-                "INSERT INTO meal_people (meal, people) VALUES (%s, %s)", (meal_id, pid)
-            )
-            conn.commit()
-            cur.close()
-    print(f"Inserted {len(meals)} records for '{food_name}' of '{food_source}'")
-    return True
-
-
-def clear_names(names: List[str], days: List[Day], exact: bool = True):
-    for name in names:
-        extract_meal(name, days, exact=exact)
-
-
-def insert_one_food(
-    conn: psycopg2.extensions.connection,
-    days: List[Day],
-    food_name: str,
-    food_source: str,
-    replace_name: str = None,
-    exact: bool = True,
-    meal_type_override: str = None,
-    comment_override: str = None,
-    matcher: Callable = None,
-    multi_matcher: Callable = None,
-):
-    if not replace_name:
-        replace_name = food_name
-    meals = merge_meal(
-        extract_meal(
-            food_name,
-            days,
-            food_source=food_source,
-            exact=exact,
-            dry_run=True,
-            matcher=matcher,
-            multi_matcher=multi_matcher,
-        )
-    )
-    if not meals:
-        return
-    if direct_insert_meal(
-        conn,
-        meals,
-        replace_name,
-        food_source,
-        meal_type_override=meal_type_override,
-        comment_override=comment_override,
-    ):
-        extract_meal(
-            food_name,
-            days,
-            food_source=food_source,
-            exact=exact,
-            matcher=matcher,
-            multi_matcher=multi_matcher,
-        )
-
-
-conn = psycopg2.connect(dbname="xnote", user="postgres", host="localhost", port="5432")
-data = parse("old_data.csv")
-
-
-def multi_matcher_factory(keywords: List[str]) -> Callable:
-    def matcher(cell):
-        meal_names = [m.name for m in cell]
-        indexes = [meal_names.index(k) for k in keywords if k in meal_names]
-        return indexes if len(indexes) == len(keywords) else []
-
-    return matcher
-
-
-multi_matchers = [
-    multi_matcher_factory(["bread", "jam"]),
-    multi_matcher_factory(["bread", "fried egg", "bacon"]),
-    multi_matcher_factory(["bread", "fried egg"]),
-    multi_matcher_factory(["bread", "cream cheese"]),
-    multi_matcher_factory(["葱油饼", "egg"]),
-    multi_matcher_factory(["葱油饼", "煎蛋"]),
-]
-for i, multi_matcher in enumerate(multi_matchers):
-    extract_meal(f"multi matcher {i}", data, multi_matcher=multi_matcher)
-
-clear_names(
-    [
-        "milk",
-        "十九金",
-        "巧克力派",
-        "巧克力面包",
-        "Google 食堂",
-        "土豆烧排骨",
-        "醋溜白菜",
-        "蛋炒饭",
-        "小锅米线",
-        "酱肘子",
-        "成都滋味",
-        "红烧金鲳鱼",
-        "炝炒油麦菜",
-        "红烧牛肉面",
-        "西红柿鸡蛋面",
-        "煮饺子",
-        "蛋黄派",
-        "ritz",
-        "重庆小面",
-        "煮速冻饺子",
-        "番茄土豆炖牛腩",
-        "红烧平鱼",
-        "炒油菜",
-        "小葱拌豆腐",
-        "牛丼",
-        "猪脚饭",
-        "鸡肉炖粉条",
-        "餐车卤肉饭",
-        "chipotle",
-        "速食螺蛳粉",
-        "banana",
-        "yogurt",
-        "banana bread",
-        "mango bread",
-        "coconut bread",
-        "bread n jam",
-        "bread",
-        "威化",
-        "biscuit",
-        "丹麦点心",
-        "藕粉",
-        "山楂锅盔",
-        "鸡蛋葱油饼",
-        "葱油饼",
-        "彩迪卷",
-        "cookie",
-        "蓝莓面包",
-        "scone",
-        "山头火",
-        "Ishian",
-        "Cinque Terre Ristorante",
-        "shinya shokudo",
-        "shakeshack",
-        "mamnoon",
-        "清汤牛腩面",
-        "豆角土豆炖排骨",
-        "土豆豆角炖排骨",
-        "酸菜粉条炖排骨",
-        "西红柿鸡蛋疙瘩汤",
-    ],
-    data,
-)
-clear_names(
-    [
-        "盐焗鸡",
-        "YGF",
-        "McDonald",
-        "西红柿鸡蛋面",
-        "croissant",
-        "方便面",
-        "阳春面",
-        "白汤意面",
-        "猪蹄",
-        "impecc",
-        "泡面",
-        "可乐鸡翅",
-        "茄丁",
-        "牛油果",
-        "汤圆",
-        "饼干",
-        "月饼",
-        "baton",
-        "天府",
-        "Issian",
-        "Hosoonyi",
-        "Cactus",
-        "domino",
-        "KFC",
-    ],
-    data,
-    exact=False,
-)
-matchers = [
-    lambda m: "意面" in m.name and "番茄" in m.name,
-    lambda m: "意面" in m.name and "奶油" in m.name,
-]
-for i, matcher in enumerate(matchers):
-    extract_meal(f"matcher {i}", data, matcher=matcher)
@@ -138,75 +138,32 @@ CREATE TABLE IF NOT EXISTS drink_people (
     FOREIGN KEY (people) REFERENCES people(id)
 );
 
--- enum table initialization
-INSERT INTO location (name) VALUES ('SLU');
-INSERT INTO location (name) VALUES ('Seattle Downtown');
-INSERT INTO location (name) VALUES ('Northgate');
-INSERT INTO location (name) VALUES ('Capitol Hill');
-INSERT INTO location (name) VALUES ('Wallingford');
-INSERT INTO location (name) VALUES ('Bellevue');
-INSERT INTO location (name) VALUES ('Ballard');
-INSERT INTO location (name) VALUES ('U District');
-INSERT INTO location (name) VALUES ('Lynwood');
-INSERT INTO location (name) VALUES ('Fremont');
-INSERT INTO location (name) VALUES ('Chinatown');
-INSERT INTO location (name) VALUES ('Kirkland');
-INSERT INTO location (name) VALUES ('NYC');
-INSERT INTO location (name) VALUES ('Portland');
-INSERT INTO location (name) VALUES ('Olympia');
-INSERT INTO location (name) VALUES ('Bay Area');
-INSERT INTO location (name) VALUES ('Columbus');
-INSERT INTO location (name) VALUES ('Hawaii');
-INSERT INTO location (name) VALUES ('');
-
-INSERT INTO food_type (name) VALUES ('mexican');
-INSERT INTO food_type (name) VALUES ('japanese');
-INSERT INTO food_type (name) VALUES ('chinese');
-INSERT INTO food_type (name) VALUES ('pizza');
-INSERT INTO food_type (name) VALUES ('fast food');
-INSERT INTO food_type (name) VALUES ('cafeteria');
-INSERT INTO food_type (name) VALUES ('salad');
-INSERT INTO food_type (name) VALUES ('korean');
-INSERT INTO food_type (name) VALUES ('Turkish');
-INSERT INTO food_type (name) VALUES ('seafood');
-INSERT INTO food_type (name) VALUES ('vietnamese');
-INSERT INTO food_type (name) VALUES ('Italian');
-INSERT INTO food_type (name) VALUES ('Indian');
-INSERT INTO food_type (name) VALUES ('Thai');
-INSERT INTO food_type (name) VALUES ('brunch');
-INSERT INTO food_type (name) VALUES ('');
-
-INSERT INTO meal_time (name) VALUES ('breakfast');
-INSERT INTO meal_time (name) VALUES ('lunch');
-INSERT INTO meal_time (name) VALUES ('dinner');
-
-INSERT INTO meal_type (name) VALUES ('cooked');
-INSERT INTO meal_type (name) VALUES ('dine-in');
-INSERT INTO meal_type (name) VALUES ('takeout');
-INSERT INTO meal_type (name) VALUES ('manufactured');
-INSERT INTO meal_type (name) VALUES ('leftover');
-
-INSERT INTO activity_type (name) VALUES ('chore');
-INSERT INTO activity_type (name) VALUES ('vedio game');
-INSERT INTO activity_type (name) VALUES ('housekeeping');
-INSERT INTO activity_type (name) VALUES ('work');
-INSERT INTO activity_type (name) VALUES ('sport');
-INSERT INTO activity_type (name) VALUES ('side project');
-INSERT INTO activity_type (name) VALUES ('study');
-INSERT INTO activity_type (name) VALUES ('entertainment');
-INSERT INTO activity_type (name) VALUES ('transport');
-
-INSERT INTO drink_option (name) VALUES ('Sip House - Ube Latte');
-INSERT INTO drink_option (name) VALUES ('自己做的latte');
-INSERT INTO drink_option (name) VALUES ('吃茶三千');
-INSERT INTO drink_option (name) VALUES ('鲜榨水果汁');
-INSERT INTO drink_option (name) VALUES ('喜茶');
-INSERT INTO drink_option (name) VALUES ('CAN U C');
-INSERT INTO drink_option (name) VALUES ('茶宴');
-INSERT INTO drink_option (name) VALUES ('Our Place');
-INSERT INTO drink_option (name) VALUES ('Sunright');
-INSERT INTO drink_option (name) VALUES ('Phe');
-INSERT INTO drink_option (name) VALUES ('茉莉奶白');
-INSERT INTO drink_option (name) VALUES ('less and more');
-INSERT INTO drink_option (name) VALUES ('aroom');
-INSERT INTO drink_option (name) VALUES ('coffeeholic');
\ No newline at end of file
+-- full-text search
+-- CJK characters are split into single-character lexemes so that phrase
+-- queries can match substrings of names like '吃茶三千'.
+CREATE OR REPLACE FUNCTION search_document(body TEXT) RETURNS tsvector
+LANGUAGE sql IMMUTABLE PARALLEL SAFE AS $$
+    SELECT to_tsvector('simple', regexp_replace(COALESCE(body, ''), '([぀-ヿ㐀-鿿가-힯])', ' \1 ', 'g'))
+$$;
+
+CREATE OR REPLACE FUNCTION search_query(input TEXT) RETURNS tsquery
+LANGUAGE sql IMMUTABLE PARALLEL SAFE AS $$
+    SELECT COALESCE(string_agg('(' || q::text || ')', ' & '), '')::tsquery
+    FROM (
+        SELECT phraseto_tsquery('simple', regexp_replace(term, '([぀-ヿ㐀-鿿가-힯])', ' \1 ', 'g')) AS q
+        FROM regexp_split_to_table(COALESCE(input, ''), '\s+') AS term
+    ) terms
+    WHERE numnode(q) > 0
+$$;
+
+CREATE INDEX IF NOT EXISTS meal_search_idx ON meal USING GIN (search_document(notes));
+CREATE INDEX IF NOT EXISTS event_search_idx ON event USING GIN (
+    search_document(COALESCE(notes, '') || ' ' || COALESCE(measure, '') || ' ' || COALESCE(location, ''))
+);
+CREATE INDEX IF NOT EXISTS drink_search_idx ON drink USING GIN (search_document(name));
+CREATE INDEX IF NOT EXISTS recipe_search_idx ON recipe USING GIN (
+    search_document(name || ' ' || ingredients || ' ' || procedure)
+);
+CREATE INDEX IF NOT EXISTS restaurant_search_idx ON restaurant USING GIN (search_document(name));
+CREATE INDEX IF NOT EXISTS product_search_idx ON product USING GIN (search_document(name));
+CREATE INDEX IF NOT EXISTS people_search_idx ON people USING GIN (search_document(name));
@@ -0,0 +1,86 @@
+-- enum table initialization
+-- ON CONFLICT keeps this safe to apply on databases created from the old init.sql
+
+INSERT INTO location (name) VALUES
+    ('SLU'),
+    ('Seattle Downtown'),
+    ('Northgate'),
+    ('Capitol Hill'),
+    ('Wallingford'),
+    ('Bellevue'),
+    ('Ballard'),
+    ('U District'),
+    ('Lynwood'),
+    ('Fremont'),
+    ('Chinatown'),
+    ('Kirkland'),
+    ('NYC'),
+    ('Portland'),
+    ('Olympia'),
+    ('Bay Area'),
+    ('Columbus'),
+    ('Hawaii'),
+    ('')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO food_type (name) VALUES
+    ('mexican'),
+    ('japanese'),
+    ('chinese'),
+    ('pizza'),
+    ('fast food'),
+    ('cafeteria'),
+    ('salad'),
+    ('korean'),
+    ('Turkish'),
+    ('seafood'),
+    ('vietnamese'),
+    ('Italian'),
+    ('Indian'),
+    ('Thai'),
+    ('brunch'),
+    ('')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO meal_time (name) VALUES
+    ('breakfast'),
+    ('lunch'),
+    ('dinner')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO meal_type (name) VALUES
+    ('cooked'),
+    ('dine-in'),
+    ('takeout'),
+    ('manufactured'),
+    ('leftover')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO activity_type (name) VALUES
+    ('chore'),
+    ('vedio game'),
+    ('housekeeping'),
+    ('work'),
+    ('sport'),
+    ('side project'),
+    ('study'),
+    ('entertainment'),
+    ('transport')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO drink_option (name) VALUES
+    ('Sip House - Ube Latte'),
+    ('自己做的latte'),
+    ('吃茶三千'),
+    ('鲜榨水果汁'),
+    ('喜茶'),
+    ('CAN U C'),
+    ('茶宴'),
+    ('Our Place'),
+    ('Sunright'),
+    ('Phe'),
+    ('茉莉奶白'),
+    ('less and more'),
+    ('aroom'),
+    ('coffeeholic')
+ON CONFLICT (name) DO NOTHING;
@@ -0,0 +1,8 @@
+-- People who live together. Summaries list household members first, in this
+-- order, and leave out the people list when it is exactly the household.
+-- NULL means the person is not a household member.
+ALTER TABLE people ADD COLUMN IF NOT EXISTS household_order INTEGER;
+
+-- Existing databases used the hardcoded 'xx' and 'ww' as the household
+UPDATE people SET household_order = 1 WHERE LOWER(name) = 'xx';
+UPDATE people SET household_order = 2 WHERE LOWER(name) = 'ww';
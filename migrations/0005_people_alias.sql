@@ -0,0 +1,8 @@
+-- Other names a person goes by in imported data. An alias may stand for
+-- several people, e.g. a couple written as one name.
+CREATE TABLE IF NOT EXISTS people_alias (
+    alias TEXT NOT NULL,
+    people INTEGER NOT NULL,
+    PRIMARY KEY (alias, people),
+    FOREIGN KEY (people) REFERENCES people(id) ON DELETE CASCADE
+);
@@ -0,0 +1,8 @@
+-- Structured times and amounts for events, next to the free-text measure.
+-- Existing measures are parsed into quantity/unit by `xnote backfill-measures`.
+
+ALTER TABLE event ADD COLUMN IF NOT EXISTS start_time TIME;
+ALTER TABLE event ADD COLUMN IF NOT EXISTS end_time TIME;
+-- Hours for durations and kilometres for distances; other units are kept as written
+ALTER TABLE event ADD COLUMN IF NOT EXISTS quantity DOUBLE PRECISION;
+ALTER TABLE event ADD COLUMN IF NOT EXISTS unit TEXT;
@@ -0,0 +1,24 @@
+-- Reusable partial entries. `body` is a CreateMeal, CreateEvent or CreateDrink
+-- without its date; `recurrence` says on which days a pending entry is created.
+CREATE TABLE IF NOT EXISTS template (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('meal', 'event', 'drink')),
+    body JSONB NOT NULL,
+    recurrence JSONB
+);
+
+-- Entries materialized from recurring templates, waiting to be confirmed
+-- (which creates the meal, event or drink) or dismissed. Kept after either, so
+-- the same day is not materialized again.
+CREATE TABLE IF NOT EXISTS pending_entry (
+    id SERIAL PRIMARY KEY,
+    template INTEGER NOT NULL,
+    date DATE NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'confirmed', 'dismissed')),
+    entry_id INTEGER,
+    UNIQUE (template, date),
+    FOREIGN KEY (template) REFERENCES template(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS pending_entry_date_idx ON pending_entry (date);
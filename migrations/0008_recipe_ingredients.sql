@@ -0,0 +1,15 @@
+-- Structured ingredient lines of recipes, parsed from the free-text
+-- `ingredients`, which stays the recipe's source of truth. Recipes written
+-- before this are indexed by `xnote backfill-ingredients`.
+ALTER TABLE recipe ADD COLUMN IF NOT EXISTS servings INTEGER CHECK (servings > 0);
+
+CREATE TABLE IF NOT EXISTS recipe_ingredient (
+    recipe INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    quantity DOUBLE PRECISION,
+    unit TEXT,
+    item TEXT NOT NULL,
+    note TEXT,
+    PRIMARY KEY (recipe, position),
+    FOREIGN KEY (recipe) REFERENCES recipe(id) ON DELETE CASCADE
+);
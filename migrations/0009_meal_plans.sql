@@ -0,0 +1,39 @@
+-- Meals planned for a day. Marking a plan eaten logs it as a meal, linked
+-- from `meal`; plans still without one the day after their date are skipped.
+CREATE TABLE IF NOT EXISTS meal_plan (
+    id SERIAL PRIMARY KEY,
+    date DATE NOT NULL,
+    "time" TEXT NOT NULL,
+    notes TEXT,
+    meal INTEGER,
+    FOREIGN KEY ("time") REFERENCES meal_time(name),
+    FOREIGN KEY (meal) REFERENCES meal(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS meal_plan_date_idx ON meal_plan (date);
+
+-- Each planned dish is a recipe, product or restaurant, like the meal_recipe,
+-- meal_product and meal_restaurant links of a meal.
+CREATE TABLE IF NOT EXISTS meal_plan_source (
+    plan INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    recipe INTEGER,
+    product INTEGER,
+    restaurant INTEGER,
+    type TEXT NOT NULL,
+    PRIMARY KEY (plan, position),
+    CHECK (num_nonnulls(recipe, product, restaurant) = 1),
+    FOREIGN KEY (plan) REFERENCES meal_plan(id) ON DELETE CASCADE,
+    FOREIGN KEY (recipe) REFERENCES recipe(id),
+    FOREIGN KEY (product) REFERENCES product(id),
+    FOREIGN KEY (restaurant) REFERENCES restaurant(id),
+    FOREIGN KEY (type) REFERENCES meal_type(name)
+);
+
+CREATE TABLE IF NOT EXISTS meal_plan_people (
+    plan INTEGER NOT NULL,
+    people INTEGER NOT NULL,
+    PRIMARY KEY (plan, people),
+    FOREIGN KEY (plan) REFERENCES meal_plan(id) ON DELETE CASCADE,
+    FOREIGN KEY (people) REFERENCES people(id)
+);
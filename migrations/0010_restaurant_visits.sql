@@ -0,0 +1,12 @@
+-- Prices are money, so keep them exact.
+ALTER TABLE restaurant ALTER COLUMN price TYPE NUMERIC(10, 2) USING round(price::numeric, 2);
+
+-- What happened on each visit: the amount paid, in an ISO 4217 currency, a
+-- 1-5 rating and the dishes ordered. All are optional, so archives from before
+-- still restore.
+ALTER TABLE meal_restaurant
+    ADD COLUMN IF NOT EXISTS amount NUMERIC(10, 2) CHECK (amount >= 0),
+    ADD COLUMN IF NOT EXISTS currency TEXT
+        CHECK (currency IS NULL OR (currency ~ '^[A-Z]{3}$' AND amount IS NOT NULL)),
+    ADD COLUMN IF NOT EXISTS rating SMALLINT CHECK (rating BETWEEN 1 AND 5),
+    ADD COLUMN IF NOT EXISTS dishes TEXT[];
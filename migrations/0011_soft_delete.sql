@@ -0,0 +1,10 @@
+-- Deleted rows are only marked, and stay in the trash until restored or
+-- purged. Their links to other rows are kept so that restoring brings them
+-- back whole.
+ALTER TABLE meal ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE event ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE drink ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE people ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE recipe ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE product ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE restaurant ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
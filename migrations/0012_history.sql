@@ -0,0 +1,28 @@
+-- Every change made through the API, with images of the record and its links
+-- before and after. `before` is NULL for creations and `after` for purges.
+-- The actor is kept by name so entries outlive accounts and archives.
+CREATE TABLE IF NOT EXISTS history (
+    id SERIAL PRIMARY KEY,
+    entity TEXT NOT NULL,
+    entity_id INTEGER NOT NULL,
+    action TEXT NOT NULL CHECK (action IN ('create', 'update', 'delete', 'restore', 'purge', 'revert')),
+    actor TEXT,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    before JSONB,
+    after JSONB
+);
+
+CREATE INDEX IF NOT EXISTS history_entity_idx ON history (entity, entity_id, id);
+CREATE INDEX IF NOT EXISTS history_changed_at_idx ON history (changed_at);
+
+-- The log is append-only
+CREATE OR REPLACE FUNCTION history_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'history is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS history_append_only ON history;
+CREATE TRIGGER history_append_only
+    BEFORE UPDATE OR DELETE ON history
+    FOR EACH ROW EXECUTE FUNCTION history_append_only();
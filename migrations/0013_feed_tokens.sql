@@ -0,0 +1,4 @@
+-- Feed tokens only read the calendar feed. They are passed in the feed URL,
+-- which calendar apps store and servers log, so they must not open the API.
+ALTER TABLE api_token ADD COLUMN IF NOT EXISTS scope TEXT NOT NULL DEFAULT 'api'
+    CHECK (scope IN ('api', 'feed'));
@@ -0,0 +1,6 @@
+-- The last day the scheduler materialized recurring templates for, so that it
+-- can catch up on days it missed without reaching back past its first run.
+CREATE TABLE IF NOT EXISTS template_schedule (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    materialized_through DATE NOT NULL
+);
@@ -0,0 +1,3 @@
+-- Meal search also matches the names of a meal's dishes and people, which no
+-- index on the meal table can cover.
+DROP INDEX IF EXISTS meal_search_idx;
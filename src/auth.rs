@@ -0,0 +1,281 @@
+use crate::error::{AppError, Result, ResultExt};
+use crate::models::auth::TokenScope;
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::{header, Method};
+use actix_web::middleware::Next;
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::future::{ready, Ready};
+
+/// Cookie holding the session token of a logged in browser.
+pub const SESSION_COOKIE: &str = "xnote_session";
+
+pub const SESSION_DAYS: i64 = 30;
+
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Checked against when the username does not exist, so that unknown usernames
+/// take as long to reject as wrong passwords. Made by `hash_password` with the
+/// default parameters; no password is known to match it.
+const DUMMY_PASSWORD_HASH: &str =
+    "$argon2id$v=19$m=19456,t=2,p=1$p9esdgsnNcTZ3Xi3HSD77A$XFcHGtX7brUapdzOxnOCD0ZKgCwDD90IbaKZvSJx63M";
+
+/// The account a request was authenticated as. Inserted into the request
+/// extensions by `require_auth`, and available to handlers as an extractor.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct AuthUser {
+    pub id: i32,
+    pub username: String,
+}
+
+impl FromRequest for AuthUser {
+    type Error = AppError;
+    type Future = Ready<Result<Self>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<AuthUser>()
+                .cloned()
+                .ok_or_else(|| AppError::Unauthorized("Authentication required".to_string())),
+        )
+    }
+}
+
+pub fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| {
+            log::error!("Failed to hash password: {}", e);
+            AppError::Internal("Failed to hash password".to_string())
+        })
+}
+
+fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .map(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+/// A new random session or API token, hex encoded.
+fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    hex(&bytes)
+}
+
+/// Tokens are stored hashed, so a leaked database does not leak live credentials.
+fn hash_token(token: &str) -> String {
+    hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub async fn create_user(pool: &PgPool, username: &str, password: &str) -> Result<AuthUser> {
+    let username = username.trim();
+    if username.is_empty() {
+        return Err(AppError::bad_request("Username must not be empty"));
+    }
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(AppError::bad_request(format!(
+            "Password must be at least {} characters",
+            MIN_PASSWORD_LENGTH
+        )));
+    }
+
+    let password_hash = hash_password(password)?;
+    sqlx::query_as::<_, AuthUser>(
+        "INSERT INTO app_user (username, password_hash) VALUES ($1, $2) RETURNING id, username",
+    )
+    .bind(username)
+    .bind(password_hash)
+    .fetch_one(pool)
+    .await
+    .context("Failed to create user")
+}
+
+/// Check a username and password, with the same error for either being wrong.
+pub async fn authenticate(pool: &PgPool, username: &str, password: &str) -> Result<AuthUser> {
+    let row: Option<(i32, String, String)> =
+        sqlx::query_as("SELECT id, username, password_hash FROM app_user WHERE username = $1")
+            .bind(username.trim())
+            .fetch_optional(pool)
+            .await
+            .context("Failed to log in")?;
+
+    match row {
+        Some((id, username, password_hash)) if verify_password(password, &password_hash) => {
+            Ok(AuthUser { id, username })
+        }
+        row => {
+            if row.is_none() {
+                verify_password(password, DUMMY_PASSWORD_HASH);
+            }
+            Err(AppError::Unauthorized(
+                "Invalid username or password".to_string(),
+            ))
+        }
+    }
+}
+
+/// Start a browser session and return its token, to be set as `SESSION_COOKIE`.
+pub async fn create_session(pool: &PgPool, user_id: i32) -> Result<String> {
+    let token = generate_token();
+    sqlx::query(
+        "INSERT INTO user_session (token_hash, user_id, expires_at)
+         VALUES ($1, $2, now() + make_interval(days => $3))",
+    )
+    .bind(hash_token(&token))
+    .bind(user_id)
+    .bind(SESSION_DAYS as i32)
+    .execute(pool)
+    .await
+    .context("Failed to create session")?;
+
+    Ok(token)
+}
+
+pub async fn delete_session(pool: &PgPool, token: &str) -> Result<()> {
+    sqlx::query("DELETE FROM user_session WHERE token_hash = $1 OR expires_at < now()")
+        .bind(hash_token(token))
+        .execute(pool)
+        .await
+        .context("Failed to log out")?;
+
+    Ok(())
+}
+
+/// Create a named API token and return its id and plaintext, which is not stored.
+pub async fn create_api_token(pool: &PgPool, user_id: i32, name: &str) -> Result<(i32, String)> {
+    create_token(pool, user_id, name, TokenScope::Api).await
+}
+
+/// Create a named token that can only read the calendar feed.
+pub async fn create_feed_token(pool: &PgPool, user_id: i32, name: &str) -> Result<(i32, String)> {
+    create_token(pool, user_id, name, TokenScope::Feed).await
+}
+
+pub async fn create_token(
+    pool: &PgPool,
+    user_id: i32,
+    name: &str,
+    scope: TokenScope,
+) -> Result<(i32, String)> {
+    let token = generate_token();
+    let id = sqlx::query_scalar(
+        "INSERT INTO api_token (user_id, name, token_hash, scope) VALUES ($1, $2, $3, $4) RETURNING id",
+    )
+    .bind(user_id)
+    .bind(name.trim())
+    .bind(hash_token(&token))
+    .bind(scope)
+    .fetch_one(pool)
+    .await
+    .context("Failed to create API token")?;
+
+    Ok((id, token))
+}
+
+async fn user_for_api_token(
+    pool: &PgPool,
+    token: &str,
+    scope: TokenScope,
+) -> Result<Option<AuthUser>> {
+    sqlx::query_as::<_, AuthUser>(
+        r#"
+        WITH used AS (
+            UPDATE api_token SET last_used_at = now()
+            WHERE token_hash = $1 AND scope = $2
+            RETURNING user_id
+        )
+        SELECT u.id, u.username FROM app_user u JOIN used ON used.user_id = u.id
+        "#,
+    )
+    .bind(hash_token(token))
+    .bind(scope)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to authenticate")
+}
+
+async fn user_for_session(pool: &PgPool, token: &str) -> Result<Option<AuthUser>> {
+    sqlx::query_as::<_, AuthUser>(
+        r#"
+        SELECT u.id, u.username
+        FROM user_session s
+        JOIN app_user u ON u.id = s.user_id
+        WHERE s.token_hash = $1 AND s.expires_at > now()
+        "#,
+    )
+    .bind(hash_token(token))
+    .fetch_optional(pool)
+    .await
+    .context("Failed to authenticate")
+}
+
+async fn authenticate_request(req: &ServiceRequest) -> Result<AuthUser> {
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .ok_or_else(|| AppError::Internal("Database pool is not configured".to_string()))?;
+
+    let bearer = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string());
+
+    let user = match (bearer, feed_token(req), req.cookie(SESSION_COOKIE)) {
+        (Some(token), _, _) => user_for_api_token(pool, &token, TokenScope::Api).await?,
+        (None, Some(token), _) => user_for_api_token(pool, &token, TokenScope::Feed).await?,
+        (None, None, Some(cookie)) => user_for_session(pool, cookie.value()).await?,
+        (None, None, None) => None,
+    };
+
+    user.ok_or_else(|| AppError::Unauthorized("Authentication required".to_string()))
+}
+
+/// Calendar apps subscribe to feeds by URL and cannot send headers, so reading
+/// the calendar feed also accepts a feed token as the `token` query parameter.
+/// Full API tokens are never taken from the URL.
+fn feed_token(req: &ServiceRequest) -> Option<String> {
+    if req.method() != Method::GET || !req.path().ends_with("/calendar.ics") {
+        return None;
+    }
+    web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .ok()?
+        .into_inner()
+        .remove("token")
+}
+
+/// Middleware rejecting requests that carry neither a valid `Authorization: Bearer`
+/// API token nor a session cookie.
+pub async fn require_auth(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> std::result::Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    match authenticate_request(&req).await {
+        Ok(user) => {
+            req.extensions_mut().insert(user);
+            next.call(req)
+                .await
+                .map(ServiceResponse::map_into_left_body)
+        }
+        Err(e) => Ok(req.error_response(e).map_into_right_body()),
+    }
+}
@@ -0,0 +1,243 @@
+use crate::error::{AppError, Result};
+use crate::import::{parse_shorthand, Catalog};
+use crate::models::event::CreateEvent;
+use crate::models::import::{ImportPreview, ImportRecord};
+use crate::util::non_empty;
+use chrono::{DateTime, NaiveDate, Utc};
+
+const PRODID: &str = "-//xnote//calendar//EN";
+
+/// Content lines longer than this many octets are folded (RFC 5545 3.1).
+const MAX_LINE_OCTETS: usize = 75;
+
+/// One all-day calendar entry, rendered as a VEVENT.
+#[derive(Debug)]
+pub struct CalendarEntry {
+    pub uid: String,
+    pub date: NaiveDate,
+    pub summary: String,
+    pub location: Option<String>,
+    pub category: Option<String>,
+    /// People ids and names.
+    pub attendees: Vec<(i32, String)>,
+}
+
+/// Render entries as a VCALENDAR with CRLF line endings.
+pub fn render(entries: &[CalendarEntry], stamp: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODID),
+        "CALSCALE:GREGORIAN".to_string(),
+        "X-WR-CALNAME:xnote".to_string(),
+    ];
+
+    for entry in entries {
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", entry.uid));
+        lines.push(format!("DTSTAMP:{}", stamp.format("%Y%m%dT%H%M%SZ")));
+        lines.push(format!(
+            "DTSTART;VALUE=DATE:{}",
+            entry.date.format("%Y%m%d")
+        ));
+        if let Some(end) = entry.date.succ_opt() {
+            lines.push(format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")));
+        }
+        lines.push(format!("SUMMARY:{}", escape(&entry.summary)));
+        if let Some(location) = &entry.location {
+            lines.push(format!("LOCATION:{}", escape(location)));
+        }
+        if let Some(category) = &entry.category {
+            lines.push(format!("CATEGORIES:{}", escape(category)));
+        }
+        for (id, name) in &entry.attendees {
+            lines.push(format!(
+                "ATTENDEE;CN=\"{}\":urn:xnote:people:{}",
+                name.replace('"', "'"),
+                id
+            ));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// Split a content line into CRLF-terminated lines of at most
+/// `MAX_LINE_OCTETS`, continuation lines starting with a space.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+/// The properties of a VEVENT that the importer uses.
+#[derive(Debug, Default)]
+pub struct CalendarEvent {
+    pub date: Option<NaiveDate>,
+    pub summary: String,
+    pub location: Option<String>,
+    pub description: Option<String>,
+    /// Attendee common names.
+    pub attendees: Vec<String>,
+}
+
+/// Read every VEVENT of a calendar, ignoring properties that are not used.
+pub fn parse(text: &str) -> Vec<CalendarEvent> {
+    // Unfold continuation lines before splitting properties
+    let unfolded = text
+        .replace("\r\n ", "")
+        .replace("\r\n\t", "")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+
+    let mut events = Vec::new();
+    let mut current: Option<CalendarEvent> = None;
+    for line in unfolded.lines() {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, params) = name.split_once(';').unwrap_or((name, ""));
+
+        match (name.to_ascii_uppercase().as_str(), current.as_mut()) {
+            ("BEGIN", _) if value.eq_ignore_ascii_case("VEVENT") => {
+                current = Some(CalendarEvent::default());
+            }
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VEVENT") => {
+                events.extend(current.take());
+            }
+            // Times of day are dropped; events are all-day
+            ("DTSTART", Some(event)) => {
+                event.date = value
+                    .get(..8)
+                    .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok());
+            }
+            ("SUMMARY", Some(event)) => event.summary = unescape(value),
+            ("LOCATION", Some(event)) => event.location = non_empty(&unescape(value)),
+            ("DESCRIPTION", Some(event)) => event.description = non_empty(&unescape(value)),
+            ("ATTENDEE", Some(event)) => {
+                let common_name = params.split(';').find_map(|param| {
+                    let (key, value) = param.split_once('=')?;
+                    key.eq_ignore_ascii_case("CN")
+                        .then(|| value.trim_matches('"').to_string())
+                });
+                event.attendees.extend(common_name);
+            }
+            _ => {}
+        }
+    }
+
+    events
+}
+
+/// Resolve the VEVENTs of a calendar into events. The summary is read as
+/// shorthand, so `Hike w/ bob @Fremont` works as in the spreadsheet; LOCATION,
+/// DESCRIPTION and attendees fill in what the summary does not say. `row` is
+/// the position of the VEVENT in the calendar.
+pub fn plan(catalog: &Catalog, data: &[u8]) -> Result<ImportPreview> {
+    let text = std::str::from_utf8(data)
+        .map_err(|_| AppError::bad_request("Calendar is not valid UTF-8"))?;
+
+    let mut preview = ImportPreview {
+        committed: false,
+        skipped_rows: Vec::new(),
+        meals: Vec::new(),
+        events: Vec::new(),
+        unresolved: 0,
+    };
+
+    for (index, event) in parse(text).into_iter().enumerate() {
+        let row = index + 1;
+        let Some(date) = event.date else {
+            preview.skipped_rows.push(row);
+            continue;
+        };
+
+        let shorthand = parse_shorthand(&event.summary);
+        let mut people = shorthand.people.clone();
+        people.extend(event.attendees.iter().cloned());
+        let companions = catalog.resolve_people(&people);
+        let location = shorthand.location.clone().or(event.location.clone());
+        let notes = shorthand.comment.clone().or(event.description.clone());
+
+        for name in &shorthand.names {
+            let mut issues = Vec::new();
+            let matched = catalog
+                .match_activity(name)
+                .map_err(|e| issues.push(e))
+                .ok();
+            let companions = companions.clone().map_err(|e| issues.push(e)).ok();
+
+            let record = match (&matched, companions) {
+                (Some(matched), Some(companions)) => {
+                    let mut people_ids = catalog.household.clone();
+                    for id in companions {
+                        if !people_ids.contains(&id) {
+                            people_ids.push(id);
+                        }
+                    }
+                    Some(CreateEvent {
+                        date,
+                        activity_id: matched.id,
+                        measure: None,
+                        location: location.clone(),
+                        notes: notes.clone(),
+                        people_ids,
+                        start_time: None,
+                        end_time: None,
+                        quantity: None,
+                        unit: None,
+                    })
+                }
+                _ => None,
+            };
+
+            preview.events.push(ImportRecord {
+                row,
+                column: "VEVENT".to_string(),
+                text: event.summary.clone(),
+                record,
+                matched,
+                issues,
+            });
+        }
+    }
+
+    preview.unresolved = preview.events.iter().filter(|e| e.record.is_none()).count();
+
+    Ok(preview)
+}
@@ -1,10 +1,19 @@
+use sqlx::migrate::{MigrateError, Migrator};
 use sqlx::{PgPool, Pool, Postgres};
 use std::env;
 
 pub type DbPool = Pool<Postgres>;
 
+// Numbered migrations from ./migrations, embedded at compile time. Applied
+// versions are recorded in the _sqlx_migrations table.
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
 pub async fn create_pool() -> Result<PgPool, sqlx::Error> {
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
 
     PgPool::connect(&database_url).await
 }
+
+pub async fn run_migrations(pool: &PgPool) -> Result<(), MigrateError> {
+    MIGRATOR.run(pool).await
+}
@@ -1 +1,26 @@
+pub mod database;
 
+use chrono_tz::Tz;
+use std::sync::OnceLock;
+
+/// Days records stay in the trash before they are purged, from
+/// `TRASH_RETENTION_DAYS`. Defaults to 30; 0 keeps them until purged by hand.
+pub fn trash_retention_days() -> u32 {
+    std::env::var("TRASH_RETENTION_DAYS")
+        .ok()
+        .and_then(|days| days.trim().parse().ok())
+        .unwrap_or(30)
+}
+
+/// The time zone whose calendar day is "today", from `TIMEZONE` (an IANA name
+/// such as `America/Los_Angeles`). Defaults to UTC.
+pub fn timezone() -> Tz {
+    static TIMEZONE: OnceLock<Tz> = OnceLock::new();
+    *TIMEZONE.get_or_init(|| match std::env::var("TIMEZONE") {
+        Ok(name) => name.trim().parse().unwrap_or_else(|_| {
+            log::warn!("Unknown TIMEZONE '{}', using UTC", name);
+            Tz::UTC
+        }),
+        Err(_) => Tz::UTC,
+    })
+}
@@ -0,0 +1,352 @@
+use crate::models::daily_summary::{
+    DailySummary, EventItem, MealItem, PlannedItem, SummaryDrink, SummaryEvent, SummaryMeal,
+    SummaryPerson, SummaryPlan,
+};
+use crate::util::non_empty;
+use chrono::NaiveDate;
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::collections::{BTreeMap, HashMap};
+
+/// Everything recorded in the range, as the queries return it.
+pub struct Records {
+    /// Live people only; anyone else is left out of the lists.
+    pub people: Vec<SummaryPerson>,
+    /// One row per dish, ordered by meal and then dish name.
+    pub meals: Vec<SummaryMeal>,
+    pub events: Vec<SummaryEvent>,
+    pub drinks: Vec<SummaryDrink>,
+    pub plans: Vec<SummaryPlan>,
+}
+
+/// Dishes eaten together: the same source at the same date, time and type.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct MealKey {
+    date: NaiveDate,
+    time: String,
+    source: String,
+    source_id: i32,
+    meal_type: String,
+}
+
+struct MealGroup {
+    key: MealKey,
+    name: String,
+    ids: Vec<i32>,
+    people_ids: Vec<i32>,
+    notes: Vec<String>,
+}
+
+/// Lay the records out day by day from `start` to `end`.
+///
+/// Dishes shared by several meals, such as one household member logging a
+/// dinner another one also logged, become a single item carrying every meal
+/// id and everyone who had it. Meal times other than breakfast, lunch and
+/// dinner are kept under their own name.
+pub fn assemble(start: NaiveDate, end: NaiveDate, records: Records) -> Vec<DailySummary> {
+    let people = People::new(records.people);
+
+    let mut days: BTreeMap<NaiveDate, DailySummary> = BTreeMap::new();
+    let mut date = start;
+    while date <= end {
+        days.insert(date, empty_day(date));
+        let Some(next) = date.succ_opt() else {
+            break;
+        };
+        date = next;
+    }
+
+    for group in group_meals(records.meals) {
+        let Some(day) = days.get_mut(&group.key.date) else {
+            continue;
+        };
+        let notes = if group.notes.is_empty() {
+            None
+        } else {
+            Some(group.notes.join("; "))
+        };
+        let item = MealItem {
+            ids: group.ids,
+            source: group.key.source,
+            source_id: group.key.source_id,
+            name: group.name,
+            people: people.names(&group.people_ids).join(", "),
+            notes,
+            meal_type: group.key.meal_type,
+        };
+        match group.key.time.as_str() {
+            "breakfast" => day.breakfast.push(item),
+            "lunch" => day.lunch.push(item),
+            "dinner" => day.dinner.push(item),
+            time => day
+                .other_meals
+                .entry(time.to_string())
+                .or_default()
+                .push(item),
+        }
+    }
+
+    for event in records.events {
+        let Some(day) = days.get_mut(&event.date) else {
+            continue;
+        };
+        day.events.push(EventItem {
+            id: event.id,
+            text: event_text(&event, &people),
+            activity_type: event.activity_type,
+        });
+    }
+
+    for drink in records.drinks {
+        let Some(day) = days.get_mut(&drink.date) else {
+            continue;
+        };
+        let mut parts = people.unless_household(&drink.people_ids);
+        parts.push(drink.name);
+        day.drinks.push(parts.join(" "));
+    }
+
+    for plan in records.plans {
+        let Some(day) = days.get_mut(&plan.date) else {
+            continue;
+        };
+        day.planned.push(PlannedItem {
+            id: plan.id,
+            time: plan.time,
+            name: plan.name,
+            people: people.names(&plan.people_ids).join(", "),
+            notes: plan.notes,
+            meal_type: plan.meal_type,
+            skipped: plan.skipped,
+        });
+    }
+
+    days.into_values().collect()
+}
+
+fn empty_day(date: NaiveDate) -> DailySummary {
+    DailySummary {
+        date,
+        day_of_week: date.format("%a").to_string(),
+        breakfast: Vec::new(),
+        lunch: Vec::new(),
+        dinner: Vec::new(),
+        other_meals: BTreeMap::new(),
+        planned: Vec::new(),
+        drinks: Vec::new(),
+        events: Vec::new(),
+    }
+}
+
+/// Merge dishes eaten together, in the order their first meal came in.
+fn group_meals(meals: Vec<SummaryMeal>) -> Vec<MealGroup> {
+    let mut groups: Vec<MealGroup> = Vec::new();
+    let mut index: HashMap<MealKey, usize> = HashMap::new();
+
+    for meal in meals {
+        let key = MealKey {
+            date: meal.date,
+            time: meal.time,
+            source: meal.source,
+            source_id: meal.source_id,
+            meal_type: meal.meal_type,
+        };
+        let group = match index.get(&key) {
+            Some(&position) => &mut groups[position],
+            None => {
+                index.insert(key.clone(), groups.len());
+                groups.push(MealGroup {
+                    key,
+                    name: meal.name,
+                    ids: Vec::new(),
+                    people_ids: Vec::new(),
+                    notes: Vec::new(),
+                });
+                groups.last_mut().expect("group was just pushed")
+            }
+        };
+
+        if !group.ids.contains(&meal.id) {
+            group.ids.push(meal.id);
+        }
+        for person in meal.people_ids {
+            if !group.people_ids.contains(&person) {
+                group.people_ids.push(person);
+            }
+        }
+        if let Some(notes) = meal.notes.filter(|notes| !notes.is_empty()) {
+            if !group.notes.contains(&notes) {
+                group.notes.push(notes);
+            }
+        }
+    }
+    groups
+}
+
+/// An event as one line: people unless they are the whole household, the
+/// activity, its times, `@location`, `for` the amount and notes in parentheses.
+fn event_text(event: &SummaryEvent, people: &People) -> String {
+    let mut parts = people.unless_household(&event.people_ids);
+    parts.push(event.activity.clone());
+    if let Some(start) = event.start_time {
+        let mut times = start.format("%H:%M").to_string();
+        if let Some(end) = event.end_time {
+            times.push_str(&end.format("-%H:%M").to_string());
+        }
+        parts.push(times);
+    }
+    if let Some(location) = event.location.as_deref().and_then(non_empty) {
+        parts.push(format!("@{}", location));
+    }
+    if let Some(measure) = event.measure.as_deref().and_then(non_empty) {
+        parts.push(format!("for {}", measure));
+    } else if let (Some(quantity), Some(unit)) = (event.quantity, &event.unit) {
+        parts.push(format!("for {} {}", format_quantity(quantity), unit));
+    }
+    if let Some(notes) = event.notes.as_deref().and_then(non_empty) {
+        parts.push(format!("({})", notes));
+    }
+    parts.join(" ")
+}
+
+/// At most two decimals, without trailing zeros.
+fn format_quantity(quantity: f64) -> String {
+    match quantity.to_string().parse::<Decimal>() {
+        Ok(quantity) => quantity
+            .round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero)
+            .normalize()
+            .to_string(),
+        Err(_) => quantity.to_string(),
+    }
+}
+
+/// Live people by id, with the household in its own order.
+struct People {
+    by_id: HashMap<i32, SummaryPerson>,
+    household: Vec<String>,
+}
+
+impl People {
+    fn new(people: Vec<SummaryPerson>) -> Self {
+        let mut household: Vec<&SummaryPerson> = people
+            .iter()
+            .filter(|person| person.household_order.is_some())
+            .collect();
+        household.sort_by(|a, b| {
+            a.household_order
+                .cmp(&b.household_order)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        let household = household
+            .into_iter()
+            .map(|person| person.name.clone())
+            .collect();
+
+        People {
+            by_id: people
+                .into_iter()
+                .map(|person| (person.id, person))
+                .collect(),
+            household,
+        }
+    }
+
+    /// Names of the live people among `ids`, household first in its order and
+    /// then everyone else by name.
+    fn names(&self, ids: &[i32]) -> Vec<String> {
+        let mut people: Vec<&SummaryPerson> =
+            ids.iter().filter_map(|id| self.by_id.get(id)).collect();
+        people.sort_by(|a, b| {
+            (a.household_order.is_none(), a.household_order, &a.name).cmp(&(
+                b.household_order.is_none(),
+                b.household_order,
+                &b.name,
+            ))
+        });
+        people.dedup_by_key(|person| person.id);
+        people
+            .into_iter()
+            .map(|person| person.name.clone())
+            .collect()
+    }
+
+    /// The names joined into one part, left out when nobody or exactly the
+    /// household took part.
+    fn unless_household(&self, ids: &[i32]) -> Vec<String> {
+        let names = self.names(ids);
+        if names.is_empty() || names == self.household {
+            Vec::new()
+        } else {
+            vec![names.join(", ")]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assemble_groups_by_source_and_type() {
+        let date = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let person = |id: i32, name: &str, household_order: Option<i32>| SummaryPerson {
+            id,
+            name: name.to_string(),
+            household_order,
+        };
+        let meal = |id: i32, time: &str, source_id: i32, meal_type: &str, people_ids: Vec<i32>| {
+            SummaryMeal {
+                id,
+                date,
+                time: time.to_string(),
+                notes: None,
+                source: "restaurant".to_string(),
+                source_id,
+                name: format!("Restaurant {}", source_id),
+                meal_type: meal_type.to_string(),
+                people_ids,
+            }
+        };
+
+        let summaries = assemble(
+            date,
+            date.succ_opt().unwrap(),
+            Records {
+                people: vec![person(1, "Amy", Some(1)), person(2, "Bob", None)],
+                meals: vec![
+                    meal(1, "lunch", 1, "dine-in", vec![1]),
+                    meal(2, "lunch", 1, "dine-in", vec![2, 1]),
+                    // Taken out from the same place is a different item
+                    meal(3, "lunch", 1, "takeout", vec![2]),
+                    meal(4, "lunch", 2, "dine-in", vec![2]),
+                    meal(5, "brunch", 1, "dine-in", vec![1]),
+                    // Trashed people are not listed
+                    meal(6, "dinner", 1, "dine-in", vec![9]),
+                ],
+                events: Vec::new(),
+                drinks: Vec::new(),
+                plans: Vec::new(),
+            },
+        );
+
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].day_of_week, "Wed");
+        assert_eq!(summaries[1].day_of_week, "Thu");
+        assert!(summaries[1].lunch.is_empty());
+
+        let lunch: Vec<(Vec<i32>, &str, &str)> = summaries[0]
+            .lunch
+            .iter()
+            .map(|m| (m.ids.clone(), m.people.as_str(), m.meal_type.as_str()))
+            .collect();
+        assert_eq!(
+            lunch,
+            vec![
+                (vec![1, 2], "Amy, Bob", "dine-in"),
+                (vec![3], "Bob", "takeout"),
+                (vec![4], "Bob", "dine-in"),
+            ]
+        );
+        assert_eq!(summaries[0].other_meals["brunch"][0].ids, vec![5]);
+        assert_eq!(summaries[0].dinner[0].people, "");
+    }
+}
@@ -0,0 +1,231 @@
+use actix_web::{http::StatusCode, web, HttpResponse, ResponseError};
+use sqlx::error::ErrorKind;
+use sqlx::postgres::PgDatabaseError;
+use std::fmt;
+
+pub type Result<T, E = AppError> = std::result::Result<T, E>;
+
+/// Error type shared by all handlers. Every variant renders as a JSON body of
+/// the form `{"error": <message>, "code": <code>}`, where `code` is stable and
+/// meant for scripts to branch on.
+#[derive(Debug)]
+pub enum AppError {
+    BadRequest(String),
+    Unauthorized(String),
+    NotFound(String),
+    AlreadyExists(String),
+    StillReferenced(String),
+    /// Like `StillReferenced`, but also lists the referencing records under `references`.
+    ReferencedBy {
+        message: String,
+        references: serde_json::Value,
+    },
+    InvalidReference {
+        field: String,
+        message: String,
+    },
+    Internal(String),
+}
+
+impl AppError {
+    pub fn not_found(message: impl Into<String>) -> Self {
+        AppError::NotFound(message.into())
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        AppError::BadRequest(message.into())
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::BadRequest(_) => "bad_request",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::NotFound(_) => "not_found",
+            AppError::AlreadyExists(_) => "already_exists",
+            AppError::StillReferenced(_) | AppError::ReferencedBy { .. } => "still_referenced",
+            AppError::InvalidReference { .. } => "invalid_reference",
+            AppError::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::BadRequest(message)
+            | AppError::Unauthorized(message)
+            | AppError::NotFound(message)
+            | AppError::AlreadyExists(message)
+            | AppError::StillReferenced(message)
+            | AppError::ReferencedBy { message, .. }
+            | AppError::InvalidReference { message, .. }
+            | AppError::Internal(message) => message,
+        }
+    }
+
+    /// Classify a database error. Constraint violations caused by the request
+    /// become client errors; anything else is logged and reported as `context`.
+    pub fn from_sqlx(error: sqlx::Error, context: &str) -> Self {
+        if let sqlx::Error::RowNotFound = error {
+            return AppError::NotFound("Record not found".to_string());
+        }
+
+        let Some(db_error) = error.as_database_error() else {
+            log::error!("{}: {}", context, error);
+            return AppError::Internal(context.to_string());
+        };
+
+        let detail = db_error
+            .try_downcast_ref::<PgDatabaseError>()
+            .and_then(|pg_error| pg_error.detail())
+            .and_then(parse_key_detail);
+
+        match db_error.kind() {
+            ErrorKind::ForeignKeyViolation
+                if db_error.message().starts_with("update or delete") =>
+            {
+                log::warn!("{}: {}", context, error);
+                AppError::StillReferenced(format!(
+                    "{}: it is still referenced by other records",
+                    context
+                ))
+            }
+            ErrorKind::ForeignKeyViolation => {
+                log::warn!("{}: {}", context, error);
+                let field = db_error
+                    .constraint()
+                    .and_then(foreign_key_field)
+                    .map(str::to_string)
+                    .or_else(|| detail.as_ref().map(|(column, _)| column.clone()))
+                    .unwrap_or_else(|| "unknown".to_string());
+                let message = match detail {
+                    Some((_, value)) => format!("Unknown {} '{}'", field, value),
+                    None => format!("Unknown {}", field),
+                };
+                AppError::InvalidReference { field, message }
+            }
+            ErrorKind::UniqueViolation => {
+                log::warn!("{}: {}", context, error);
+                let message = match (db_error.table(), detail) {
+                    (Some(table), Some((column, value))) => {
+                        format!("{} with {} '{}' already exists", table, column, value)
+                    }
+                    _ => "Record already exists".to_string(),
+                };
+                AppError::AlreadyExists(message)
+            }
+            _ => {
+                log::error!("{}: {}", context, error);
+                AppError::Internal(context.to_string())
+            }
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::AlreadyExists(_)
+            | AppError::StillReferenced(_)
+            | AppError::ReferencedBy { .. } => StatusCode::CONFLICT,
+            AppError::InvalidReference { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut body = serde_json::json!({
+            "error": self.message(),
+            "code": self.code(),
+        });
+        match self {
+            AppError::InvalidReference { field, .. } => body["field"] = serde_json::json!(field),
+            AppError::ReferencedBy { references, .. } => body["references"] = references.clone(),
+            _ => {}
+        }
+        HttpResponse::build(self.status_code()).json(body)
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(error: sqlx::Error) -> Self {
+        AppError::from_sqlx(error, "Database error")
+    }
+}
+
+/// Attach the message reported to clients when a database call fails unexpectedly.
+pub trait ResultExt<T> {
+    fn context(self, context: &str) -> Result<T>;
+}
+
+impl<T> ResultExt<T> for std::result::Result<T, sqlx::Error> {
+    fn context(self, context: &str) -> Result<T> {
+        self.map_err(|e| AppError::from_sqlx(e, context))
+    }
+}
+
+/// Request field names for foreign keys, keyed by Postgres' default constraint names.
+const FOREIGN_KEY_FIELDS: &[(&str, &str)] = &[
+    ("meal_time_fkey", "time"),
+    ("meal_recipe_recipe_fkey", "food_sources.recipe_id"),
+    ("meal_product_product_fkey", "food_sources.product_id"),
+    (
+        "meal_restaurant_restaurant_fkey",
+        "food_sources.restaurant_id",
+    ),
+    ("meal_recipe_type_fkey", "food_sources.meal_type"),
+    ("meal_product_type_fkey", "food_sources.meal_type"),
+    ("meal_restaurant_type_fkey", "food_sources.meal_type"),
+    ("meal_people_people_fkey", "people_ids"),
+    ("meal_plan_time_fkey", "time"),
+    ("meal_plan_source_recipe_fkey", "food_sources.recipe_id"),
+    ("meal_plan_source_product_fkey", "food_sources.product_id"),
+    (
+        "meal_plan_source_restaurant_fkey",
+        "food_sources.restaurant_id",
+    ),
+    ("meal_plan_source_type_fkey", "food_sources.meal_type"),
+    ("meal_plan_people_people_fkey", "people_ids"),
+    ("event_people_people_fkey", "people_ids"),
+    ("drink_people_people_fkey", "people_ids"),
+    ("people_alias_people_fkey", "people_ids"),
+    ("event_activity_fkey", "activity_id"),
+    ("drink_name_fkey", "name"),
+    ("restaurant_location_fkey", "location"),
+    ("restaurant_type_fkey", "type"),
+    ("activity_type_fkey", "type"),
+];
+
+fn foreign_key_field(constraint: &str) -> Option<&'static str> {
+    FOREIGN_KEY_FIELDS
+        .iter()
+        .find(|(name, _)| *name == constraint)
+        .map(|(_, field)| *field)
+}
+
+/// Extract column and value from details like `Key (name)=(SLU) already exists.`
+fn parse_key_detail(detail: &str) -> Option<(String, String)> {
+    let rest = detail.strip_prefix("Key (")?;
+    let (column, rest) = rest.split_once(")=(")?;
+    let end = rest.rfind(')')?;
+    Some((column.to_string(), rest[..end].to_string()))
+}
+
+/// JSON extractor config that reports malformed bodies with the shared error format.
+pub fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default()
+        .error_handler(|err, _req| AppError::BadRequest(err.to_string()).into())
+}
+
+/// Query extractor config that reports malformed query strings with the shared error format.
+pub fn query_config() -> web::QueryConfig {
+    web::QueryConfig::default()
+        .error_handler(|err, _req| AppError::BadRequest(err.to_string()).into())
+}
@@ -0,0 +1,158 @@
+use crate::error::{AppError, Result, ResultExt};
+use crate::models::export::{Archive, ArchiveTable, RestoredTable};
+use sqlx::PgPool;
+
+/// Bumped whenever a migration changes the archived tables in a way older
+/// archives cannot be restored into.
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// Enum-like tables. Migrations seed them, so on restore their rows are merged
+/// rather than required to be new.
+const VOCABULARIES: [&str; 6] = [
+    "location",
+    "food_type",
+    "meal_time",
+    "meal_type",
+    "activity_type",
+    "drink_option",
+];
+
+/// The remaining tables in dependency order, with whether they have a serial
+/// `id` whose sequence must be moved past the restored ids. Accounts, sessions
+/// and API tokens are deliberately left out of archives.
+const TABLES: [(&str, bool); 22] = [
+    ("people", true),
+    ("people_alias", false),
+    ("recipe", true),
+    ("recipe_ingredient", false),
+    ("restaurant", true),
+    ("product", true),
+    ("activity", true),
+    ("meal", true),
+    ("meal_recipe", false),
+    ("meal_product", false),
+    ("meal_restaurant", false),
+    ("meal_people", false),
+    ("meal_plan", true),
+    ("meal_plan_source", false),
+    ("meal_plan_people", false),
+    ("event", true),
+    ("event_people", false),
+    ("drink", true),
+    ("drink_people", false),
+    ("template", true),
+    ("pending_entry", true),
+    ("history", true),
+];
+
+fn table_names() -> impl Iterator<Item = &'static str> {
+    VOCABULARIES
+        .into_iter()
+        .chain(TABLES.into_iter().map(|(name, _)| name))
+}
+
+/// Dump every table. Rows are sorted so that unchanged data exports identically.
+pub async fn export(pool: &PgPool) -> Result<Archive> {
+    let mut tx = pool.begin().await.context("Failed to export")?;
+    // One snapshot for all tables, so the archive is consistent under writes
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ READ ONLY")
+        .execute(&mut *tx)
+        .await
+        .context("Failed to export")?;
+
+    let mut tables = Vec::new();
+    for name in table_names() {
+        let rows = sqlx::query_scalar(&format!("SELECT to_jsonb(t) FROM {name} t ORDER BY t"))
+            .fetch_all(&mut *tx)
+            .await
+            .context("Failed to export")?;
+        tables.push(ArchiveTable {
+            name: name.to_string(),
+            rows,
+        });
+    }
+    tx.commit().await.context("Failed to export")?;
+
+    Ok(Archive {
+        version: ARCHIVE_VERSION,
+        exported_at: chrono::Utc::now(),
+        tables,
+    })
+}
+
+/// Replay an archive into a database with no data yet, keeping every id. All
+/// tables are restored in one transaction.
+pub async fn restore(pool: &PgPool, archive: &Archive) -> Result<Vec<RestoredTable>> {
+    if archive.version != ARCHIVE_VERSION {
+        return Err(AppError::bad_request(format!(
+            "Unsupported archive version {} (expected {})",
+            archive.version, ARCHIVE_VERSION
+        )));
+    }
+    if let Some(table) = archive
+        .tables
+        .iter()
+        .find(|table| !table_names().any(|name| name == table.name))
+    {
+        return Err(AppError::bad_request(format!(
+            "Unknown table '{}' in archive",
+            table.name
+        )));
+    }
+
+    let mut tx = pool.begin().await.context("Failed to restore")?;
+
+    for (name, _) in TABLES {
+        let has_rows: bool = sqlx::query_scalar(&format!("SELECT EXISTS (SELECT 1 FROM {name})"))
+            .fetch_one(&mut *tx)
+            .await
+            .context("Failed to restore")?;
+        if has_rows {
+            return Err(AppError::AlreadyExists(format!(
+                "Cannot restore into a database that already has data ({} is not empty)",
+                name
+            )));
+        }
+    }
+
+    let mut restored = Vec::new();
+    for name in table_names() {
+        let rows = archive
+            .tables
+            .iter()
+            .filter(|table| table.name == name)
+            .flat_map(|table| table.rows.iter().cloned())
+            .collect::<Vec<_>>();
+        let on_conflict = if VOCABULARIES.contains(&name) {
+            "ON CONFLICT DO NOTHING"
+        } else {
+            ""
+        };
+
+        let result = sqlx::query(&format!(
+            "INSERT INTO {name} SELECT * FROM jsonb_populate_recordset(NULL::{name}, $1) {on_conflict}"
+        ))
+        .bind(serde_json::Value::Array(rows))
+        .execute(&mut *tx)
+        .await
+        .context("Failed to restore")?;
+
+        restored.push(RestoredTable {
+            name: name.to_string(),
+            rows: result.rows_affected(),
+        });
+    }
+
+    for (name, _) in TABLES.into_iter().filter(|(_, has_id)| *has_id) {
+        sqlx::query(&format!(
+            "SELECT setval(pg_get_serial_sequence('{name}', 'id'), COALESCE(MAX(id), 0) + 1, false) FROM {name}"
+        ))
+        .execute(&mut *tx)
+        .await
+        .context("Failed to restore")?;
+    }
+
+    tx.commit().await.context("Failed to restore")?;
+
+    Ok(restored)
+}
@@ -1,5 +1,11 @@
+use crate::auth::AuthUser;
+use crate::error::{AppError, Result, ResultExt};
+use crate::handlers::listing::{check_filters, ListBuilder};
+use crate::history;
 use crate::models::activity::{Activity, CreateActivity, UpdateActivity};
-use actix_web::{web, HttpResponse, Result};
+use crate::models::history::{HistoryAction, HistoryEntity};
+use crate::models::list::ListQuery;
+use actix_web::{web, HttpResponse};
 use sqlx::PgPool;
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
@@ -16,26 +22,32 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
     );
 }
 
-async fn get_activities(pool: web::Data<PgPool>) -> Result<HttpResponse> {
-    match sqlx::query_as::<_, Activity>("SELECT id, name, type FROM activity ORDER BY id")
-        .fetch_all(pool.get_ref())
-        .await
-    {
-        Ok(activities) => Ok(HttpResponse::Ok().json(activities)),
-        Err(e) => {
-            log::error!("Failed to fetch activities: {}", e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch activities"
-            })))
-        }
+async fn get_activities(
+    pool: web::Data<PgPool>,
+    query: web::Query<ListQuery>,
+) -> Result<HttpResponse> {
+    check_filters(&query, "activities", &["activity_type"])?;
+
+    let mut list = ListBuilder::new("SELECT id, name, type FROM activity", &query, None, "id");
+    if let Some(activity_type) = &query.activity_type {
+        list.filter("type = {}", activity_type.as_str());
     }
+
+    let page = list
+        .fetch::<Activity>(&pool, "Failed to fetch activities")
+        .await?;
+
+    Ok(page.into_response())
 }
 
 async fn create_activity(
     pool: web::Data<PgPool>,
     activity_data: web::Json<CreateActivity>,
+    user: Option<AuthUser>,
 ) -> Result<HttpResponse> {
-    match sqlx::query_as::<_, Activity>(
+    let mut tx = pool.begin().await.context("Failed to create activity")?;
+
+    let activity = sqlx::query_as::<_, Activity>(
         r#"
         INSERT INTO activity (name, type)
         VALUES ($1, $2)
@@ -44,44 +56,43 @@ async fn create_activity(
     )
     .bind(&activity_data.name)
     .bind(&activity_data.activity_type)
-    .fetch_one(pool.get_ref())
+    .fetch_one(&mut *tx)
     .await
-    {
-        Ok(activity) => Ok(HttpResponse::Created().json(activity)),
-        Err(e) => {
-            log::error!("Failed to create activity: {}", e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to create activity"
-            })))
-        }
-    }
+    .context("Failed to create activity")?;
+    history::record(
+        &mut tx,
+        HistoryEntity::Activity,
+        activity.id,
+        HistoryAction::Create,
+        None,
+        user.as_ref(),
+    )
+    .await?;
+
+    tx.commit().await.context("Failed to create activity")?;
+
+    Ok(HttpResponse::Created().json(activity))
 }
 
 async fn get_activity(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse> {
     let activity_id = path.into_inner();
 
-    match sqlx::query_as::<_, Activity>("SELECT id, name, type FROM activity WHERE id = $1")
-        .bind(activity_id)
-        .fetch_optional(pool.get_ref())
-        .await
-    {
-        Ok(Some(activity)) => Ok(HttpResponse::Ok().json(activity)),
-        Ok(None) => Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Activity not found"
-        }))),
-        Err(e) => {
-            log::error!("Failed to fetch activity {}: {}", activity_id, e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch activity"
-            })))
-        }
-    }
+    let activity =
+        sqlx::query_as::<_, Activity>("SELECT id, name, type FROM activity WHERE id = $1")
+            .bind(activity_id)
+            .fetch_optional(pool.get_ref())
+            .await
+            .context("Failed to fetch activity")?
+            .ok_or_else(|| AppError::not_found("Activity not found"))?;
+
+    Ok(HttpResponse::Ok().json(activity))
 }
 
 async fn update_activity(
     pool: web::Data<PgPool>,
     path: web::Path<i32>,
     activity_data: web::Json<UpdateActivity>,
+    user: Option<AuthUser>,
 ) -> Result<HttpResponse> {
     let activity_id = path.into_inner();
 
@@ -98,9 +109,7 @@ async fn update_activity(
     }
 
     if query_parts.is_empty() {
-        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "No fields to update"
-        })));
+        return Err(AppError::bad_request("No fields to update"));
     }
 
     let query = format!(
@@ -108,6 +117,9 @@ async fn update_activity(
         query_parts.join(", ")
     );
 
+    let mut tx = pool.begin().await.context("Failed to update activity")?;
+    let before = history::snapshot(&mut tx, HistoryEntity::Activity, activity_id).await?;
+
     let mut query_builder = sqlx::query_as::<_, Activity>(&query).bind(activity_id);
 
     // Bind parameters in the same order
@@ -118,42 +130,39 @@ async fn update_activity(
         query_builder = query_builder.bind(activity_type);
     }
 
-    match query_builder.fetch_optional(pool.get_ref()).await {
-        Ok(Some(activity)) => Ok(HttpResponse::Ok().json(activity)),
-        Ok(None) => Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Activity not found"
-        }))),
-        Err(e) => {
-            log::error!("Failed to update activity {}: {}", activity_id, e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to update activity"
-            })))
-        }
-    }
+    let activity = query_builder
+        .fetch_optional(&mut *tx)
+        .await
+        .context("Failed to update activity")?
+        .ok_or_else(|| AppError::not_found("Activity not found"))?;
+    history::record(
+        &mut tx,
+        HistoryEntity::Activity,
+        activity_id,
+        HistoryAction::Update,
+        before,
+        user.as_ref(),
+    )
+    .await?;
+
+    tx.commit().await.context("Failed to update activity")?;
+
+    Ok(HttpResponse::Ok().json(activity))
 }
 
-async fn delete_activity(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse> {
+async fn delete_activity(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
     let activity_id = path.into_inner();
 
     // First check if activity exists
-    let activity_exists = sqlx::query!("SELECT id FROM activity WHERE id = $1", activity_id)
+    sqlx::query!("SELECT id FROM activity WHERE id = $1", activity_id)
         .fetch_optional(pool.get_ref())
-        .await;
-
-    match activity_exists {
-        Ok(None) => {
-            return Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "Activity not found"
-            })));
-        }
-        Err(e) => {
-            log::error!("Failed to check activity existence {}: {}", activity_id, e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to delete activity"
-            })));
-        }
-        Ok(Some(_)) => {} // Activity exists, continue
-    }
+        .await
+        .context("Failed to delete activity")?
+        .ok_or_else(|| AppError::not_found("Activity not found"))?;
 
     // Check if activity is referenced in events
     let event_count = sqlx::query!(
@@ -161,58 +170,44 @@ async fn delete_activity(pool: web::Data<PgPool>, path: web::Path<i32>) -> Resul
         activity_id
     )
     .fetch_one(pool.get_ref())
-    .await;
-
-    match event_count {
-        Ok(result) => {
-            if result.count.unwrap_or(0) > 0 {
-                return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                    "error": format!("Cannot delete activity: it is referenced in {} event(s). Please delete those events first.", result.count.unwrap_or(0))
-                })));
-            }
-        }
-        Err(e) => {
-            log::error!(
-                "Failed to check event references for activity {}: {}",
-                activity_id,
-                e
-            );
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to delete activity"
-            })));
-        }
+    .await
+    .context("Failed to delete activity")?
+    .count
+    .unwrap_or(0);
+
+    if event_count > 0 {
+        return Err(AppError::StillReferenced(format!(
+            "Cannot delete activity: it is referenced in {} event(s). Please delete those events first.",
+            event_count
+        )));
     }
 
     // Now safe to delete the activity
-    match sqlx::query!("DELETE FROM activity WHERE id = $1", activity_id)
-        .execute(pool.get_ref())
+    let mut tx = pool.begin().await.context("Failed to delete activity")?;
+    let before = history::snapshot(&mut tx, HistoryEntity::Activity, activity_id).await?;
+
+    let result = sqlx::query!("DELETE FROM activity WHERE id = $1", activity_id)
+        .execute(&mut *tx)
         .await
-    {
-        Ok(result) => {
-            if result.rows_affected() > 0 {
-                Ok(HttpResponse::Ok().json(serde_json::json!({
-                    "message": "Activity deleted successfully"
-                })))
-            } else {
-                // This shouldn't happen since we checked existence above
-                Ok(HttpResponse::NotFound().json(serde_json::json!({
-                    "error": "Activity not found"
-                })))
-            }
-        }
-        Err(e) => {
-            log::error!("Failed to delete activity {}: {}", activity_id, e);
-
-            // Check if it's a foreign key constraint error
-            let error_message = if e.to_string().contains("foreign key") {
-                "Cannot delete activity: it is still referenced by other records"
-            } else {
-                "Failed to delete activity"
-            };
-
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": error_message
-            })))
-        }
+        .context("Cannot delete activity")?;
+
+    if result.rows_affected() == 0 {
+        // This shouldn't happen since we checked existence above
+        return Err(AppError::not_found("Activity not found"));
     }
+    history::record(
+        &mut tx,
+        HistoryEntity::Activity,
+        activity_id,
+        HistoryAction::Delete,
+        before,
+        user.as_ref(),
+    )
+    .await?;
+
+    tx.commit().await.context("Failed to delete activity")?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Activity deleted successfully"
+    })))
 }
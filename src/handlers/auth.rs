@@ -0,0 +1,117 @@
+use crate::auth::{self, AuthUser, SESSION_COOKIE, SESSION_DAYS};
+use crate::error::{AppError, Result, ResultExt};
+use crate::models::auth::{ApiToken, CreateApiToken, CreateApiTokenResponse, Credentials};
+use actix_web::cookie::{time::Duration, Cookie, SameSite};
+use actix_web::{web, HttpRequest, HttpResponse};
+use sqlx::PgPool;
+
+/// Login and logout, which must stay reachable without credentials.
+pub fn configure_public(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/auth/login").route(web::post().to(login)))
+        .service(web::resource("/auth/logout").route(web::post().to(logout)));
+}
+
+/// Token management for authenticated users, mounted under `/api/v1`. Accounts
+/// are only created with the `create-user` command, and all of them see the
+/// same household data.
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/auth/me").route(web::get().to(get_current_user)))
+        .service(
+            web::resource("/auth/tokens")
+                .route(web::get().to(get_api_tokens))
+                .route(web::post().to(create_api_token)),
+        )
+        .service(web::resource("/auth/tokens/{id}").route(web::delete().to(delete_api_token)));
+}
+
+/// The session cookie is only marked `Secure` when the login came over HTTPS,
+/// directly or through a proxy setting `X-Forwarded-Proto`, so that logins
+/// over plain HTTP on the local network keep working.
+async fn login(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    credentials: web::Json<Credentials>,
+) -> Result<HttpResponse> {
+    let user = auth::authenticate(&pool, &credentials.username, &credentials.password).await?;
+    let token = auth::create_session(&pool, user.id).await?;
+
+    let cookie = Cookie::build(SESSION_COOKIE, token)
+        .path("/")
+        .http_only(true)
+        .secure(req.connection_info().scheme() == "https")
+        .same_site(SameSite::Lax)
+        .max_age(Duration::days(SESSION_DAYS))
+        .finish();
+
+    Ok(HttpResponse::Ok().cookie(cookie).json(user))
+}
+
+async fn logout(pool: web::Data<PgPool>, req: HttpRequest) -> Result<HttpResponse> {
+    if let Some(cookie) = req.cookie(SESSION_COOKIE) {
+        auth::delete_session(&pool, cookie.value()).await?;
+    }
+
+    let mut removal = Cookie::build(SESSION_COOKIE, "").path("/").finish();
+    removal.make_removal();
+
+    Ok(HttpResponse::Ok().cookie(removal).json(serde_json::json!({
+        "message": "Logged out"
+    })))
+}
+
+async fn get_current_user(user: AuthUser) -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(user))
+}
+
+async fn get_api_tokens(pool: web::Data<PgPool>, user: AuthUser) -> Result<HttpResponse> {
+    let tokens = sqlx::query_as::<_, ApiToken>(
+        "SELECT id, name, scope, created_at, last_used_at FROM api_token WHERE user_id = $1 ORDER BY id",
+    )
+    .bind(user.id)
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to fetch API tokens")?;
+
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+async fn create_api_token(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    token_data: web::Json<CreateApiToken>,
+) -> Result<HttpResponse> {
+    if token_data.name.trim().is_empty() {
+        return Err(AppError::bad_request("Token name must not be empty"));
+    }
+
+    let (id, token) =
+        auth::create_token(&pool, user.id, &token_data.name, token_data.scope).await?;
+
+    Ok(HttpResponse::Created().json(CreateApiTokenResponse {
+        id,
+        name: token_data.name.trim().to_string(),
+        scope: token_data.scope,
+        token,
+    }))
+}
+
+async fn delete_api_token(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    path: web::Path<i32>,
+) -> Result<HttpResponse> {
+    let result = sqlx::query("DELETE FROM api_token WHERE id = $1 AND user_id = $2")
+        .bind(path.into_inner())
+        .bind(user.id)
+        .execute(pool.get_ref())
+        .await
+        .context("Failed to delete API token")?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("API token not found"));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "API token deleted successfully"
+    })))
+}
@@ -0,0 +1,237 @@
+use crate::calendar::{self, CalendarEntry};
+use crate::error::{AppError, Result, ResultExt};
+use crate::handlers::daily_summary::build_daily_summaries;
+use crate::models::calendar::CalendarQuery;
+use crate::models::daily_summary::MealItem;
+use crate::util::{self, capitalize};
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+/// How many days back the feed goes without a `start_date`.
+const DEFAULT_DAYS: i64 = 90;
+
+/// Longest range the feed covers, about ten years.
+const MAX_DAYS: i64 = 3660;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/calendar.ics").route(web::get().to(get_calendar)));
+}
+
+/// Location and attendees of an event or meal, keyed by its id. Meals have no
+/// location of their own; each restaurant dish takes its restaurant's.
+#[derive(sqlx::FromRow)]
+struct EntryDetails {
+    id: i32,
+    location: Option<String>,
+    people_ids: Vec<i32>,
+    people_names: Vec<String>,
+}
+
+impl EntryDetails {
+    fn attendees(&self) -> Vec<(i32, String)> {
+        self.people_ids
+            .iter()
+            .copied()
+            .zip(self.people_names.iter().cloned())
+            .collect()
+    }
+}
+
+const EVENT_DETAILS: &str = r#"
+    SELECT
+        e.id,
+        NULLIF(e.location, '') AS location,
+        COALESCE(array_agg(pe.id ORDER BY pe.household_order NULLS LAST, pe.name) FILTER (WHERE pe.id IS NOT NULL), '{}') AS people_ids,
+        COALESCE(array_agg(pe.name ORDER BY pe.household_order NULLS LAST, pe.name) FILTER (WHERE pe.id IS NOT NULL), '{}') AS people_names
+    FROM event e
+    JOIN activity a ON a.id = e.activity
+    LEFT JOIN event_people ep ON ep.event = e.id
+    LEFT JOIN people pe ON pe.id = ep.people AND pe.deleted_at IS NULL
+    WHERE e.date BETWEEN $1 AND $2 AND e.deleted_at IS NULL
+      AND ($3::text IS NULL OR a.type = $3)
+      AND ($4::int IS NULL OR EXISTS (SELECT 1 FROM event_people f WHERE f.event = e.id AND f.people = $4))
+    GROUP BY e.id
+"#;
+
+const MEAL_DETAILS: &str = r#"
+    SELECT
+        m.id,
+        NULL::text AS location,
+        COALESCE(array_agg(pe.id ORDER BY pe.household_order NULLS LAST, pe.name) FILTER (WHERE pe.id IS NOT NULL), '{}') AS people_ids,
+        COALESCE(array_agg(pe.name ORDER BY pe.household_order NULLS LAST, pe.name) FILTER (WHERE pe.id IS NOT NULL), '{}') AS people_names
+    FROM meal m
+    LEFT JOIN meal_people mp ON mp.meal = m.id
+    LEFT JOIN people pe ON pe.id = mp.people AND pe.deleted_at IS NULL
+    WHERE m.date BETWEEN $1 AND $2 AND m.deleted_at IS NULL
+      AND ($3::int IS NULL OR EXISTS (SELECT 1 FROM meal_people f WHERE f.meal = m.id AND f.people = $3))
+    GROUP BY m.id
+"#;
+
+/// All-day entries for events, and optionally meals, with the same text as the
+/// daily summary. Calendar apps subscribe to it with a feed token passed as
+/// `?token=`.
+async fn get_calendar(
+    pool: web::Data<PgPool>,
+    query: web::Query<CalendarQuery>,
+) -> Result<HttpResponse> {
+    let context = "Failed to build calendar";
+
+    let end_date = query.end_date.unwrap_or_else(util::today);
+    let start_date = match query.start_date {
+        Some(start_date) => start_date,
+        None => end_date
+            .checked_sub_signed(chrono::Duration::days(DEFAULT_DAYS))
+            .ok_or_else(|| AppError::bad_request("end_date is out of range"))?,
+    };
+    if start_date > end_date {
+        return Err(AppError::bad_request(
+            "start_date must not be after end_date",
+        ));
+    }
+    if (end_date - start_date).num_days() >= MAX_DAYS {
+        return Err(AppError::bad_request(format!(
+            "The calendar covers at most {} days; narrow start_date and end_date",
+            MAX_DAYS
+        )));
+    }
+
+    let events: HashMap<i32, EntryDetails> = sqlx::query_as::<_, EntryDetails>(EVENT_DETAILS)
+        .bind(start_date)
+        .bind(end_date)
+        .bind(&query.activity_type)
+        .bind(query.person_id)
+        .fetch_all(pool.get_ref())
+        .await
+        .context(context)?
+        .into_iter()
+        .map(|details| (details.id, details))
+        .collect();
+
+    let meals: HashMap<i32, EntryDetails> = if query.meals {
+        sqlx::query_as::<_, EntryDetails>(MEAL_DETAILS)
+            .bind(start_date)
+            .bind(end_date)
+            .bind(query.person_id)
+            .fetch_all(pool.get_ref())
+            .await
+            .context(context)?
+            .into_iter()
+            .map(|details| (details.id, details))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+
+    let household: String = sqlx::query_scalar(
+        r#"
+        SELECT COALESCE(string_agg(name, ', ' ORDER BY household_order, name), '') FROM people
+        WHERE household_order IS NOT NULL AND deleted_at IS NULL
+        "#,
+    )
+    .fetch_one(pool.get_ref())
+    .await
+    .context(context)?;
+
+    let summaries = build_daily_summaries(&pool, start_date, end_date, query.meals)
+        .await
+        .context(context)?;
+
+    let restaurant_ids: Vec<i32> = summaries
+        .iter()
+        .flat_map(|summary| {
+            summary
+                .breakfast
+                .iter()
+                .chain(&summary.lunch)
+                .chain(&summary.dinner)
+                .chain(summary.other_meals.values().flatten())
+        })
+        .filter(|item| item.source == "restaurant")
+        .map(|item| item.source_id)
+        .collect();
+    let restaurant_locations: HashMap<i32, String> = sqlx::query_as::<_, (i32, String)>(
+        "SELECT id, location FROM restaurant WHERE id = ANY($1) AND location <> ''",
+    )
+    .bind(&restaurant_ids)
+    .fetch_all(pool.get_ref())
+    .await
+    .context(context)?
+    .into_iter()
+    .collect();
+
+    let mut entries = Vec::new();
+    for summary in summaries {
+        let mut meal_times = vec![
+            ("Breakfast".to_string(), &summary.breakfast),
+            ("Lunch".to_string(), &summary.lunch),
+            ("Dinner".to_string(), &summary.dinner),
+        ];
+        meal_times.extend(
+            summary
+                .other_meals
+                .iter()
+                .map(|(time, items)| (capitalize(time), items)),
+        );
+        for (time, items) in meal_times {
+            for item in items {
+                let details: Vec<&EntryDetails> =
+                    item.ids.iter().filter_map(|id| meals.get(id)).collect();
+                let Some(first) = details.first() else {
+                    continue;
+                };
+
+                let mut attendees = Vec::new();
+                for (id, name) in details.iter().flat_map(|d| d.attendees()) {
+                    if !attendees.iter().any(|(seen, _)| *seen == id) {
+                        attendees.push((id, name));
+                    }
+                }
+                entries.push(CalendarEntry {
+                    // One entry per dish, so the dish is part of the UID
+                    uid: format!("meal-{}-{}-{}@xnote", first.id, item.source, item.source_id),
+                    date: summary.date,
+                    summary: format!("{}: {}", time, meal_text(item, &household)),
+                    location: match item.source.as_str() {
+                        "restaurant" => restaurant_locations.get(&item.source_id).cloned(),
+                        _ => None,
+                    },
+                    category: Some(item.meal_type.clone()),
+                    attendees,
+                });
+            }
+        }
+
+        for item in &summary.events {
+            let Some(details) = events.get(&item.id) else {
+                continue;
+            };
+            entries.push(CalendarEntry {
+                uid: format!("event-{}@xnote", item.id),
+                date: summary.date,
+                summary: item.text.clone(),
+                location: details.location.clone(),
+                category: Some(item.activity_type.clone()),
+                attendees: details.attendees(),
+            });
+        }
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/calendar; charset=utf-8")
+        .body(calendar::render(&entries, chrono::Utc::now())))
+}
+
+/// A meal as the daily summary shows it: people unless they are the whole
+/// household, the dish, and notes in parentheses.
+fn meal_text(item: &MealItem, household: &str) -> String {
+    let mut parts = Vec::new();
+    if !item.people.is_empty() && item.people != household {
+        parts.push(item.people.clone());
+    }
+    parts.push(item.name.clone());
+    if let Some(notes) = item.notes.as_ref().filter(|notes| !notes.is_empty()) {
+        parts.push(format!("({})", notes));
+    }
+    parts.join(" ")
+}
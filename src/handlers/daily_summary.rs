@@ -1,5 +1,11 @@
-use crate::models::daily_summary::{DailySummary, DailySummaryQuery, EventItem, MealItem};
-use actix_web::{web, HttpResponse, Result};
+use crate::daily_summary::{self, Records};
+use crate::error::{Result, ResultExt};
+use crate::models::daily_summary::{
+    DailySummary, DailySummaryQuery, SummaryDrink, SummaryEvent, SummaryMeal, SummaryPerson,
+    SummaryPlan,
+};
+use crate::util;
+use actix_web::{web, HttpResponse};
 use chrono::NaiveDate;
 use sqlx::PgPool;
 
@@ -13,245 +19,148 @@ async fn get_daily_summary(
 ) -> Result<HttpResponse> {
     let start_date = query
         .start_date
-        .unwrap_or_else(|| chrono::Utc::now().naive_utc().date() - chrono::Duration::days(30));
+        .unwrap_or_else(|| util::today() - chrono::Duration::days(30));
 
-    let end_date = query
-        .end_date
-        .unwrap_or_else(|| chrono::Utc::now().naive_utc().date());
+    let end_date = query.end_date.unwrap_or_else(util::today);
 
-    match build_daily_summaries(&pool, start_date, end_date).await {
-        Ok(summaries) => Ok(HttpResponse::Ok().json(summaries)),
-        Err(e) => {
-            log::error!("Failed to fetch daily summaries: {}", e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch daily summaries"
-            })))
-        }
-    }
+    let summaries = build_daily_summaries(&pool, start_date, end_date, true)
+        .await
+        .context("Failed to fetch daily summaries")?;
+
+    Ok(HttpResponse::Ok().json(summaries))
 }
 
-async fn build_daily_summaries(
+// Every dish of a meal is a row of its own; meals without one are left out
+const MEALS: &str = r#"
+    WITH meal_sources AS (
+        SELECT meal, 'recipe' AS source, recipe AS source_id, type FROM meal_recipe
+        UNION ALL
+        SELECT meal, 'product', product, type FROM meal_product
+        UNION ALL
+        SELECT meal, 'restaurant', restaurant, type FROM meal_restaurant
+    )
+    SELECT
+        m.id,
+        m.date,
+        m."time" AS time,
+        m.notes,
+        s.source,
+        s.source_id,
+        COALESCE(r.name, p.name, rt.name) AS name,
+        s.type AS meal_type,
+        ARRAY(SELECT people FROM meal_people WHERE meal = m.id ORDER BY people) AS people_ids
+    FROM meal m
+    JOIN meal_sources s ON s.meal = m.id
+    LEFT JOIN recipe r ON s.source = 'recipe' AND r.id = s.source_id
+    LEFT JOIN product p ON s.source = 'product' AND p.id = s.source_id
+    LEFT JOIN restaurant rt ON s.source = 'restaurant' AND rt.id = s.source_id
+    WHERE m.date BETWEEN $1 AND $2 AND m.deleted_at IS NULL
+    ORDER BY m.id, name
+"#;
+
+/// Summaries of every day from `start_date` to `end_date`, leaving meals out
+/// unless `with_meals`.
+pub(crate) async fn build_daily_summaries(
     pool: &PgPool,
     start_date: NaiveDate,
     end_date: NaiveDate,
-) -> Result<Vec<DailySummary>, sqlx::Error> {
-    let summaries = sqlx::query!(
+    with_meals: bool,
+) -> std::result::Result<Vec<DailySummary>, sqlx::Error> {
+    let people = sqlx::query_as::<_, SummaryPerson>(
+        "SELECT id, name, household_order FROM people WHERE deleted_at IS NULL",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let meals = if with_meals {
+        sqlx::query_as::<_, SummaryMeal>(MEALS)
+            .bind(start_date)
+            .bind(end_date)
+            .fetch_all(pool)
+            .await?
+    } else {
+        Vec::new()
+    };
+
+    let events = sqlx::query_as::<_, SummaryEvent>(
         r#"
-        WITH date_range AS (
-            SELECT generate_series($1::date, $2::date, '1 day'::interval)::date AS date
-        ),
-        meal_aggregated AS (
-            SELECT 
-                m.id as meal_id,
-                m.date,
-                m."time" as meal_time,
-                CASE 
-                    WHEN mr.meal IS NOT NULL THEN r.name
-                    WHEN mp.meal IS NOT NULL THEN p.name  
-                    WHEN mrt.meal IS NOT NULL THEN rt.name
-                END as food_source_name,
-                COALESCE(mr.type, mp.type, mrt.type) as meal_type,
-                m.notes,
-                array_agg(pe.name ORDER BY 
-                    CASE 
-                        WHEN LOWER(pe.name) = 'xx' THEN 1
-                        WHEN LOWER(pe.name) = 'ww' THEN 2
-                        ELSE 3
-                    END,
-                    pe.name
-                ) FILTER (WHERE pe.name IS NOT NULL) as people_names
-            FROM meal m
-            LEFT JOIN meal_recipe mr ON m.id = mr.meal
-            LEFT JOIN recipe r ON mr.recipe = r.id
-            LEFT JOIN meal_product mp ON m.id = mp.meal
-            LEFT JOIN product p ON mp.product = p.id
-            LEFT JOIN meal_restaurant mrt ON m.id = mrt.meal
-            LEFT JOIN restaurant rt ON mrt.restaurant = rt.id
-            LEFT JOIN meal_people mpe ON m.id = mpe.meal
-            LEFT JOIN people pe ON mpe.people = pe.id
-            WHERE m.date BETWEEN $1 AND $2
-            GROUP BY m.date, m."time", m.id, food_source_name, meal_type, m.notes
-        ),
-        event_aggregated AS (
-            SELECT 
-                e.id as event_id,
-                e.date,
-                a.name as activity_name,
-                a.type as activity_type,
-                e.measure,
-                e.location,
-                e.notes,
-                array_agg(pe.name ORDER BY 
-                    CASE 
-                        WHEN LOWER(pe.name) = 'xx' THEN 1
-                        WHEN LOWER(pe.name) = 'ww' THEN 2
-                        ELSE 3
-                    END,
-                    pe.name
-                ) FILTER (WHERE pe.name IS NOT NULL) as people_names
-            FROM event e
-            JOIN activity a ON e.activity = a.id
-            LEFT JOIN event_people ep ON e.id = ep.event
-            LEFT JOIN people pe ON ep.people = pe.id
-            WHERE e.date BETWEEN $1 AND $2
-            GROUP BY e.date, e.id, a.name, a.type, e.measure, e.location, e.notes
-        ),
-        event_formatted AS (
-            SELECT 
-                event_id,
-                date,
-                TRIM(CONCAT_WS(' ',
-                    CASE 
-                        WHEN array_length(people_names, 1) > 0 AND NOT (array_length(people_names, 1) = 2 AND people_names = ARRAY['xx', 'ww']) 
-                        THEN array_to_string(people_names, ', ') 
-                    END,
-                    activity_name,
-                    CASE WHEN location IS NOT NULL AND location != '' THEN '@' || location END,
-                    CASE WHEN measure IS NOT NULL AND measure != '' THEN 'for ' || measure END,
-                    CASE WHEN notes IS NOT NULL AND notes != '' THEN '(' || notes || ')' END
-                )) as formatted_event,
-                activity_type
-            FROM event_aggregated
-        ),
-        drink_aggregated AS (
-            SELECT 
-                d.date,
-                d.name as drink_name,
-                array_agg(pe.name ORDER BY 
-                    CASE 
-                        WHEN LOWER(pe.name) = 'xx' THEN 1
-                        WHEN LOWER(pe.name) = 'ww' THEN 2
-                        ELSE 3
-                    END,
-                    pe.name
-                ) FILTER (WHERE pe.name IS NOT NULL) as people_names
-            FROM drink d
-            LEFT JOIN drink_people dp ON d.id = dp.drink
-            LEFT JOIN people pe ON dp.people = pe.id
-            WHERE d.date BETWEEN $1 AND $2
-            GROUP BY d.date, d.id, d.name
-        ),
-        drink_formatted AS (
-            SELECT 
-                date,
-                TRIM(CONCAT_WS(' ',
-                    CASE 
-                        WHEN array_length(people_names, 1) > 0 AND NOT (array_length(people_names, 1) = 2 AND people_names = ARRAY['xx', 'ww']) 
-                        THEN array_to_string(people_names, ', ') 
-                    END,
-                    drink_name
-                )) as formatted_drink
-            FROM drink_aggregated
-        )
-        SELECT 
-            dr.date,
-            CASE 
-                WHEN EXTRACT(dow FROM dr.date) = 0 THEN 'Sun'
-                WHEN EXTRACT(dow FROM dr.date) = 1 THEN 'Mon'
-                WHEN EXTRACT(dow FROM dr.date) = 2 THEN 'Tue'
-                WHEN EXTRACT(dow FROM dr.date) = 3 THEN 'Wed'
-                WHEN EXTRACT(dow FROM dr.date) = 4 THEN 'Thu'
-                WHEN EXTRACT(dow FROM dr.date) = 5 THEN 'Fri'
-                WHEN EXTRACT(dow FROM dr.date) = 6 THEN 'Sat'
-            END as day_of_week,
-            COALESCE(breakfast.meals, ARRAY[]::json[]) as "breakfast!",
-            COALESCE(lunch.meals, ARRAY[]::json[]) as "lunch!",
-            COALESCE(dinner.meals, ARRAY[]::json[]) as "dinner!",
-            COALESCE(drinks.drink_list, ARRAY[]::text[]) as "drinks!",
-            COALESCE(events.event_list, ARRAY[]::json[]) as "events!"
-        FROM date_range dr
-        LEFT JOIN (
-            SELECT 
-                date,
-                array_agg(json_build_object(
-                    'ids', array[meal_id],
-                    'name', food_source_name,
-                    'people', COALESCE(array_to_string(people_names, ', '), ''),
-                    'notes', notes,
-                    'type', meal_type
-                )) as meals
-            FROM meal_aggregated 
-            WHERE meal_time = 'breakfast' AND food_source_name IS NOT NULL
-            GROUP BY date
-        ) breakfast ON dr.date = breakfast.date
-        LEFT JOIN (
-            SELECT 
-                date,
-                array_agg(json_build_object(
-                    'ids', array[meal_id],
-                    'name', food_source_name,
-                    'people', COALESCE(array_to_string(people_names, ', '), ''),
-                    'notes', notes,
-                    'type', meal_type
-                )) as meals
-            FROM meal_aggregated 
-            WHERE meal_time = 'lunch' AND food_source_name IS NOT NULL
-            GROUP BY date
-        ) lunch ON dr.date = lunch.date
-        LEFT JOIN (
-            SELECT 
-                date,
-                array_agg(json_build_object(
-                    'ids', array[meal_id],
-                    'name', food_source_name,
-                    'people', COALESCE(array_to_string(people_names, ', '), ''),
-                    'notes', notes,
-                    'type', meal_type
-                )) as meals
-            FROM meal_aggregated 
-            WHERE meal_time = 'dinner' AND food_source_name IS NOT NULL
-            GROUP BY date
-        ) dinner ON dr.date = dinner.date
-        LEFT JOIN (
-            SELECT 
-                date,
-                array_agg(formatted_drink) as drink_list
-            FROM drink_formatted
-            GROUP BY date
-        ) drinks ON dr.date = drinks.date
-        LEFT JOIN (
-            SELECT 
-                date,
-                array_agg(json_build_object('id', event_id, 'text', formatted_event, 'type', activity_type)) as event_list
-            FROM event_formatted
-            GROUP BY date
-        ) events ON dr.date = events.date
-        ORDER BY dr.date
+        SELECT
+            e.id,
+            e.date,
+            a.name AS activity,
+            a.type AS activity_type,
+            e.measure,
+            e.quantity,
+            e.unit,
+            e.start_time,
+            e.end_time,
+            e.location,
+            e.notes,
+            ARRAY(SELECT people FROM event_people WHERE event = e.id ORDER BY people) AS people_ids
+        FROM event e
+        JOIN activity a ON a.id = e.activity
+        WHERE e.date BETWEEN $1 AND $2 AND e.deleted_at IS NULL
+        ORDER BY e.date, e.id
         "#,
-        start_date,
-        end_date
     )
+    .bind(start_date)
+    .bind(end_date)
     .fetch_all(pool)
     .await?;
 
-    let result = summaries
-        .into_iter()
-        .map(|row| {
-            let parse_meals = |meals: Vec<serde_json::Value>| -> Vec<MealItem> {
-                meals
-                    .into_iter()
-                    .filter_map(|meal| serde_json::from_value(meal).ok())
-                    .collect()
-            };
-
-            let parse_events = |events: Vec<serde_json::Value>| -> Vec<EventItem> {
-                events
-                    .into_iter()
-                    .filter_map(|event| serde_json::from_value(event).ok())
-                    .collect()
-            };
+    let drinks = sqlx::query_as::<_, SummaryDrink>(
+        r#"
+        SELECT
+            d.id,
+            d.date,
+            d.name,
+            ARRAY(SELECT people FROM drink_people WHERE drink = d.id ORDER BY people) AS people_ids
+        FROM drink d
+        WHERE d.date BETWEEN $1 AND $2 AND d.deleted_at IS NULL
+        ORDER BY d.date, d.id
+        "#,
+    )
+    .bind(start_date)
+    .bind(end_date)
+    .fetch_all(pool)
+    .await?;
 
-            DailySummary {
-                date: row.date.expect("Date should always be present"),
-                day_of_week: row.day_of_week.unwrap_or_else(|| "Unknown".to_string()),
-                breakfast: parse_meals(row.breakfast),
-                lunch: parse_meals(row.lunch),
-                dinner: parse_meals(row.dinner),
-                drinks: row.drinks,
-                events: parse_events(row.events),
-            }
-        })
-        .collect();
+    // Plans still to be eaten, one item per planned dish
+    let plans = sqlx::query_as::<_, SummaryPlan>(
+        r#"
+        SELECT
+            p.id,
+            p.date,
+            p."time" AS time,
+            p.notes,
+            COALESCE(r.name, pr.name, rt.name) AS name,
+            s.type AS meal_type,
+            p.date < $3 - 1 AS skipped,
+            ARRAY(SELECT people FROM meal_plan_people WHERE plan = p.id ORDER BY people) AS people_ids
+        FROM meal_plan p
+        JOIN meal_plan_source s ON s.plan = p.id
+        LEFT JOIN recipe r ON r.id = s.recipe
+        LEFT JOIN product pr ON pr.id = s.product
+        LEFT JOIN restaurant rt ON rt.id = s.restaurant
+        WHERE p.date BETWEEN $1 AND $2 AND p.meal IS NULL
+        ORDER BY p.date, p.id, s.position
+        "#,
+    )
+    .bind(start_date)
+    .bind(end_date)
+    .bind(util::today())
+    .fetch_all(pool)
+    .await?;
 
-    Ok(result)
+    Ok(daily_summary::assemble(
+        start_date,
+        end_date,
+        Records {
+            people,
+            meals,
+            events,
+            drinks,
+            plans,
+        },
+    ))
 }
@@ -1,123 +1,94 @@
+use crate::auth::AuthUser;
+use crate::error::{Result, ResultExt};
+use crate::handlers::vocabulary::{Reference, Vocabulary};
 use crate::models::drink::DrinkOption;
-use actix_web::{web, HttpResponse, Result};
+use crate::models::history::HistoryEntity;
+use crate::models::template::EntryKind;
+use crate::models::vocabulary::{DeleteVocabularyQuery, RenameVocabulary};
+use actix_web::{web, HttpResponse};
 use sqlx::PgPool;
 
+const DRINK_OPTIONS: Vocabulary = Vocabulary {
+    table: "drink_option",
+    name: "drink option",
+    references: &[Reference {
+        table: "drink",
+        column: "name",
+        kind: "drink",
+        key: "drinks",
+        label: "date::text",
+        entity: HistoryEntity::Drink,
+    }],
+    template_fields: &[(EntryKind::Drink, "name")],
+};
+
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::resource("/drink-options")
             .route(web::get().to(get_drink_options))
             .route(web::post().to(create_drink_option)),
     )
-    .service(web::resource("/drink-options/{name}").route(web::delete().to(delete_drink_option)));
+    .service(
+        web::resource("/drink-options/{name}")
+            .route(web::put().to(rename_drink_option))
+            .route(web::delete().to(delete_drink_option)),
+    );
 }
 
 async fn get_drink_options(pool: web::Data<PgPool>) -> Result<HttpResponse> {
-    match sqlx::query_as::<_, DrinkOption>("SELECT name FROM drink_option ORDER BY name")
-        .fetch_all(pool.get_ref())
-        .await
-    {
-        Ok(drink_options) => Ok(HttpResponse::Ok().json(drink_options)),
-        Err(e) => {
-            log::error!("Failed to fetch drink options: {}", e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch drink options"
-            })))
-        }
-    }
+    let drink_options =
+        sqlx::query_as::<_, DrinkOption>("SELECT name FROM drink_option ORDER BY name")
+            .fetch_all(pool.get_ref())
+            .await
+            .context("Failed to fetch drink options")?;
+
+    Ok(HttpResponse::Ok().json(drink_options))
 }
 
 async fn create_drink_option(
     pool: web::Data<PgPool>,
     drink_option: web::Json<DrinkOption>,
 ) -> Result<HttpResponse> {
-    match sqlx::query!(
-        "INSERT INTO drink_option (name) VALUES ($1)",
-        drink_option.name
-    )
-    .execute(pool.get_ref())
-    .await
-    {
-        Ok(_) => Ok(HttpResponse::Created().json(&*drink_option)),
-        Err(e) => {
-            log::error!("Failed to create drink option: {}", e);
-            if e.to_string().contains("duplicate key") {
-                Ok(HttpResponse::Conflict().json(serde_json::json!({
-                    "error": "Drink option already exists"
-                })))
-            } else {
-                Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                    "error": "Failed to create drink option"
-                })))
-            }
-        }
-    }
+    let name = DRINK_OPTIONS.create(&pool, &drink_option.name).await?;
+
+    Ok(HttpResponse::Created().json(DrinkOption { name }))
 }
 
-async fn delete_drink_option(
+/// Rename a drink option and every drink that uses it.
+async fn rename_drink_option(
     pool: web::Data<PgPool>,
     path: web::Path<String>,
+    rename: web::Json<RenameVocabulary>,
+    user: Option<AuthUser>,
 ) -> Result<HttpResponse> {
-    let drink_option_name = path.into_inner();
+    let name = DRINK_OPTIONS
+        .rename(&pool, &path.into_inner(), &rename.name, user.as_ref())
+        .await?;
 
-    // Check if drink option is referenced in drinks
-    let drink_count = sqlx::query!(
-        "SELECT COUNT(*) as count FROM drink WHERE name = $1",
-        drink_option_name
-    )
-    .fetch_one(pool.get_ref())
-    .await;
+    Ok(HttpResponse::Ok().json(DrinkOption { name }))
+}
 
-    match drink_count {
-        Ok(result) => {
-            if result.count.unwrap_or(0) > 0 {
-                return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                    "error": format!("Cannot delete drink option: it is referenced in {} drink(s). Please delete those drinks first.", result.count.unwrap_or(0))
-                })));
-            }
-        }
-        Err(e) => {
-            log::error!(
-                "Failed to check drink references for drink option {}: {}",
-                drink_option_name,
-                e
-            );
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to delete drink option"
-            })));
-        }
-    }
+async fn delete_drink_option(
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    query: web::Query<DeleteVocabularyQuery>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let drink_option_name = path.into_inner();
 
-    match sqlx::query!(
-        "DELETE FROM drink_option WHERE name = $1",
-        drink_option_name
-    )
-    .execute(pool.get_ref())
-    .await
-    {
-        Ok(result) => {
-            if result.rows_affected() > 0 {
-                Ok(HttpResponse::Ok().json(serde_json::json!({
-                    "message": "Drink option deleted successfully"
-                })))
-            } else {
-                Ok(HttpResponse::NotFound().json(serde_json::json!({
-                    "error": "Drink option not found"
-                })))
-            }
-        }
-        Err(e) => {
-            log::error!("Failed to delete drink option {}: {}", drink_option_name, e);
+    DRINK_OPTIONS
+        .delete(
+            &pool,
+            &drink_option_name,
+            query.merge_into.as_deref(),
+            user.as_ref(),
+        )
+        .await?;
 
-            // Check if it's a foreign key constraint error
-            let error_message = if e.to_string().contains("foreign key") {
-                "Cannot delete drink option: it is still referenced by other records"
-            } else {
-                "Failed to delete drink option"
-            };
+    let message = match &query.merge_into {
+        Some(target) => format!("Drink option merged into '{}' and deleted", target),
+        None => "Drink option deleted successfully".to_string(),
+    };
 
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": error_message
-            })))
-        }
-    }
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": message })))
 }
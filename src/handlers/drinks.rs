@@ -1,8 +1,17 @@
+use crate::auth::AuthUser;
+use crate::error::{AppError, Result, ResultExt};
+use crate::handlers::listing::{check_filters, ListBuilder};
+use crate::history;
 use crate::models::detail::DrinkDetail;
 use crate::models::drink::{CreateDrink, CreateDrinkResponse, Drink};
+use crate::models::history::{HistoryAction, HistoryEntity};
+use crate::models::list::ListQuery;
 use crate::models::people::People;
-use actix_web::{web, HttpResponse, Result};
-use sqlx::PgPool;
+use crate::models::trash::TrashKind;
+use crate::trash;
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+use sqlx::{PgPool, Postgres, Transaction};
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -16,40 +25,66 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
             .route(web::put().to(update_drink))
             .route(web::delete().to(delete_drink)),
     )
-    .service(web::resource("/drinks/{id}/details").route(web::get().to(get_drink_details)));
+    .service(web::resource("/drinks/{id}/details").route(web::get().to(get_drink_details)))
+    .service(web::resource("/drinks/batch/delete").route(web::post().to(delete_drinks_batch)));
 }
 
-async fn get_drinks(pool: web::Data<PgPool>) -> Result<HttpResponse> {
-    match sqlx::query_as::<_, Drink>("SELECT id, name, date FROM drink ORDER BY date DESC")
-        .fetch_all(pool.get_ref())
-        .await
-    {
-        Ok(drinks) => Ok(HttpResponse::Ok().json(drinks)),
-        Err(e) => {
-            log::error!("Failed to fetch drinks: {}", e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch drinks"
-            })))
-        }
+async fn get_drinks(pool: web::Data<PgPool>, query: web::Query<ListQuery>) -> Result<HttpResponse> {
+    check_filters(&query, "drinks", &["start_date", "end_date", "person_id"])?;
+
+    let mut list = ListBuilder::new(
+        "SELECT d.id, d.name, d.date FROM drink d",
+        &query,
+        Some("d.date"),
+        "d.id",
+    );
+    list.condition("d.deleted_at IS NULL");
+    if let Some(person_id) = query.person_id {
+        list.filter(
+            "EXISTS (SELECT 1 FROM drink_people dp WHERE dp.drink = d.id AND dp.people = {})",
+            person_id,
+        );
     }
+
+    let page = list.fetch::<Drink>(&pool, "Failed to fetch drinks").await?;
+
+    Ok(page.into_response())
 }
 
 async fn create_drink(
     pool: web::Data<PgPool>,
     drink_data: web::Json<CreateDrink>,
+    user: Option<AuthUser>,
 ) -> Result<HttpResponse> {
-    let mut tx = match pool.begin().await {
-        Ok(tx) => tx,
-        Err(e) => {
-            log::error!("Failed to start transaction: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to create drink"
-            })));
-        }
-    };
+    let mut tx = pool.begin().await.context("Failed to create drink")?;
+
+    let drink_id = insert_drink(&mut tx, &drink_data).await?;
+    history::record(
+        &mut tx,
+        HistoryEntity::Drink,
+        drink_id,
+        HistoryAction::Create,
+        None,
+        user.as_ref(),
+    )
+    .await?;
 
+    // Commit the transaction
+    tx.commit().await.context("Failed to create drink")?;
+
+    Ok(HttpResponse::Created().json(CreateDrinkResponse {
+        id: drink_id,
+        message: "Drink created successfully".to_string(),
+    }))
+}
+
+/// Insert a drink with its people, as `POST /drinks` does.
+pub(crate) async fn insert_drink(
+    tx: &mut Transaction<'_, Postgres>,
+    drink_data: &CreateDrink,
+) -> Result<i32> {
     // Insert the drink record
-    let drink_result = sqlx::query!(
+    let drink_id = sqlx::query!(
         r#"
         INSERT INTO drink (date, name)
         VALUES ($1, $2)
@@ -58,86 +93,139 @@ async fn create_drink(
         drink_data.date,
         drink_data.name
     )
-    .fetch_one(&mut *tx)
-    .await;
-
-    let drink_id = match drink_result {
-        Ok(row) => row.id,
-        Err(e) => {
-            log::error!("Failed to insert drink: {}", e);
-            if let Err(rollback_err) = tx.rollback().await {
-                log::error!("Failed to rollback transaction: {}", rollback_err);
-            }
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to create drink"
-            })));
-        }
-    };
+    .fetch_one(&mut **tx)
+    .await
+    .context("Failed to create drink")?
+    .id;
 
     // Insert drink-people relationships
+    trash::check_live(tx, TrashKind::Person, &drink_data.people_ids, "people_ids").await?;
     for person_id in &drink_data.people_ids {
-        if let Err(e) = sqlx::query!(
+        sqlx::query!(
             "INSERT INTO drink_people (drink, people) VALUES ($1, $2)",
             drink_id,
             person_id
         )
-        .execute(&mut *tx)
+        .execute(&mut **tx)
         .await
-        {
-            log::error!("Failed to insert drink_people relationship: {}", e);
-            if let Err(rollback_err) = tx.rollback().await {
-                log::error!("Failed to rollback transaction: {}", rollback_err);
-            }
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to create drink"
-            })));
-        }
-    }
-
-    // Commit the transaction
-    if let Err(e) = tx.commit().await {
-        log::error!("Failed to commit transaction: {}", e);
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to create drink"
-        })));
+        .context("Failed to create drink")?;
     }
 
-    Ok(HttpResponse::Created().json(CreateDrinkResponse {
-        id: drink_id,
-        message: "Drink created successfully".to_string(),
-    }))
+    Ok(drink_id)
 }
 
 async fn get_drink(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse> {
     let drink_id = path.into_inner();
 
-    match sqlx::query_as::<_, Drink>("SELECT id, name, date FROM drink WHERE id = $1")
-        .bind(drink_id)
-        .fetch_optional(pool.get_ref())
+    let drink = sqlx::query_as::<_, Drink>(
+        "SELECT id, name, date FROM drink WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(drink_id)
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to fetch drink")?
+    .ok_or_else(|| AppError::not_found("Drink not found"))?;
+
+    Ok(HttpResponse::Ok().json(drink))
+}
+
+async fn update_drink(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    drink_data: web::Json<CreateDrink>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let drink_id = path.into_inner();
+
+    // Start transaction
+    let mut tx = pool.begin().await.context("Failed to update drink")?;
+
+    // Step 1: Check if drink exists
+    sqlx::query!(
+        "SELECT id FROM drink WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+        drink_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .context("Failed to update drink")?
+    .ok_or_else(|| AppError::not_found("Drink not found"))?;
+    let before = history::snapshot(&mut tx, HistoryEntity::Drink, drink_id).await?;
+
+    // Step 2: Update the main drink record
+    sqlx::query!(
+        "UPDATE drink SET date = $1, name = $2 WHERE id = $3",
+        drink_data.date,
+        drink_data.name,
+        drink_id
+    )
+    .execute(&mut *tx)
+    .await
+    .context("Failed to update drink")?;
+
+    // Step 3: Remove existing people relationships
+    sqlx::query!("DELETE FROM drink_people WHERE drink = $1", drink_id)
+        .execute(&mut *tx)
         .await
-    {
-        Ok(Some(drink)) => Ok(HttpResponse::Ok().json(drink)),
-        Ok(None) => Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Drink not found"
-        }))),
-        Err(e) => {
-            log::error!("Failed to fetch drink {}: {}", drink_id, e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch drink"
-            })))
-        }
+        .context("Failed to update drink")?;
+
+    // Step 4: Insert new people relationships
+    trash::check_live(
+        &mut tx,
+        TrashKind::Person,
+        &drink_data.people_ids,
+        "people_ids",
+    )
+    .await?;
+    for person_id in &drink_data.people_ids {
+        sqlx::query!(
+            "INSERT INTO drink_people (drink, people) VALUES ($1, $2)",
+            drink_id,
+            person_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to update drink people relationships")?;
     }
-}
 
-async fn update_drink(_pool: web::Data<PgPool>, _path: web::Path<i32>) -> Result<HttpResponse> {
+    history::record(
+        &mut tx,
+        HistoryEntity::Drink,
+        drink_id,
+        HistoryAction::Update,
+        before,
+        user.as_ref(),
+    )
+    .await?;
+
+    // Step 5: Commit transaction
+    tx.commit().await.context("Failed to update drink")?;
+
+    // Return success response
     Ok(HttpResponse::Ok().json(serde_json::json!({
-        "message": "Update drink - TODO: implement"
+        "message": "Drink updated successfully",
+        "id": drink_id
     })))
 }
 
-async fn delete_drink(_pool: web::Data<PgPool>, _path: web::Path<i32>) -> Result<HttpResponse> {
+/// Move a drink to the trash, where it keeps its people.
+async fn delete_drink(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let drink_id = path.into_inner();
+
+    let mut tx = pool.begin().await.context("Failed to delete drink")?;
+
+    if !trash::move_to_trash(&mut tx, TrashKind::Drink, drink_id, user.as_ref()).await? {
+        return Err(AppError::not_found("Drink not found"));
+    }
+
+    // Commit the transaction
+    tx.commit().await.context("Failed to delete drink")?;
+
     Ok(HttpResponse::Ok().json(serde_json::json!({
-        "message": "Delete drink - TODO: implement"
+        "message": "Drink deleted successfully"
     })))
 }
 
@@ -145,48 +233,29 @@ async fn get_drink_details(pool: web::Data<PgPool>, path: web::Path<i32>) -> Res
     let drink_id = path.into_inner();
 
     // Get drink basic info
-    let drink_query = sqlx::query!("SELECT id, name, date FROM drink WHERE id = $1", drink_id)
-        .fetch_optional(pool.get_ref())
-        .await;
-
-    let drink = match drink_query {
-        Ok(Some(drink)) => drink,
-        Ok(None) => {
-            return Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "Drink not found"
-            })));
-        }
-        Err(e) => {
-            log::error!("Failed to fetch drink {}: {}", drink_id, e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch drink"
-            })));
-        }
-    };
+    let drink = sqlx::query!(
+        "SELECT id, name, date FROM drink WHERE id = $1 AND deleted_at IS NULL",
+        drink_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to fetch drink")?
+    .ok_or_else(|| AppError::not_found("Drink not found"))?;
 
     // Get people associated with this drink
-    let people_result = sqlx::query_as::<_, People>(
+    let people = sqlx::query_as::<_, People>(
         r#"
-        SELECT p.id, p.name, p.notes
+        SELECT p.id, p.name, p.notes, p.household_order
         FROM people p
         JOIN drink_people dp ON p.id = dp.people
-        WHERE dp.drink = $1
+        WHERE dp.drink = $1 AND p.deleted_at IS NULL
         ORDER BY p.name
         "#,
     )
     .bind(drink_id)
     .fetch_all(pool.get_ref())
-    .await;
-
-    let people = match people_result {
-        Ok(people) => people,
-        Err(e) => {
-            log::error!("Failed to fetch drink people {}: {}", drink_id, e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch drink details"
-            })));
-        }
-    };
+    .await
+    .context("Failed to fetch drink details")?;
 
     let drink_detail = DrinkDetail {
         id: drink.id,
@@ -197,3 +266,38 @@ async fn get_drink_details(pool: web::Data<PgPool>, path: web::Path<i32>) -> Res
 
     Ok(HttpResponse::Ok().json(drink_detail))
 }
+
+#[derive(Debug, Deserialize)]
+pub struct BatchDeleteDrinksRequest {
+    pub drink_ids: Vec<i32>,
+}
+
+async fn delete_drinks_batch(
+    pool: web::Data<PgPool>,
+    request: web::Json<BatchDeleteDrinksRequest>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    if request.drink_ids.is_empty() {
+        return Err(AppError::bad_request("No drink IDs provided"));
+    }
+
+    let mut tx = pool.begin().await.context("Failed to delete drinks")?;
+
+    let mut deleted_count = 0;
+
+    for drink_id in &request.drink_ids {
+        if trash::move_to_trash(&mut tx, TrashKind::Drink, *drink_id, user.as_ref()).await? {
+            deleted_count += 1;
+        } else {
+            log::warn!("Drink ID {} not found, skipping", drink_id);
+        }
+    }
+
+    // Commit the transaction
+    tx.commit().await.context("Failed to delete drinks")?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": format!("{} drinks deleted successfully", deleted_count),
+        "deleted_count": deleted_count
+    })))
+}
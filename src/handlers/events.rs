@@ -1,8 +1,17 @@
+use crate::auth::AuthUser;
+use crate::error::{AppError, Result, ResultExt};
+use crate::handlers::listing::{check_filters, ListBuilder};
+use crate::history;
+use crate::measure::{normalize, parse_measure, Measure};
 use crate::models::detail::{ActivityDetail, EventDetail};
 use crate::models::event::{CreateEvent, CreateEventResponse, Event};
+use crate::models::history::{HistoryAction, HistoryEntity};
+use crate::models::list::ListQuery;
 use crate::models::people::People;
-use actix_web::{web, HttpResponse, Result};
-use sqlx::PgPool;
+use crate::models::trash::TrashKind;
+use crate::trash;
+use actix_web::{web, HttpResponse};
+use sqlx::{PgPool, Postgres, Transaction};
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -19,217 +28,237 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
     .service(web::resource("/events/{id}/details").route(web::get().to(get_event_details)));
 }
 
-async fn get_events(pool: web::Data<PgPool>) -> Result<HttpResponse> {
-    match sqlx::query_as::<_, Event>(
-        "SELECT id, date, activity, measure, location, notes FROM event ORDER BY date DESC",
-    )
-    .fetch_all(pool.get_ref())
-    .await
-    {
-        Ok(events) => Ok(HttpResponse::Ok().json(events)),
-        Err(e) => {
-            log::error!("Failed to fetch events: {}", e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch events"
-            })))
-        }
+async fn get_events(pool: web::Data<PgPool>, query: web::Query<ListQuery>) -> Result<HttpResponse> {
+    check_filters(
+        &query,
+        "events",
+        &[
+            "start_date",
+            "end_date",
+            "person_id",
+            "activity_type",
+            "location",
+        ],
+    )?;
+
+    let mut list = ListBuilder::new(
+        "SELECT e.id, e.date, e.activity, e.measure, e.location, e.notes, e.start_time, e.end_time, e.quantity, e.unit FROM event e",
+        &query,
+        Some("e.date"),
+        "e.id",
+    );
+    list.condition("e.deleted_at IS NULL");
+    if let Some(person_id) = query.person_id {
+        list.filter(
+            "EXISTS (SELECT 1 FROM event_people ep WHERE ep.event = e.id AND ep.people = {})",
+            person_id,
+        );
+    }
+    if let Some(activity_type) = &query.activity_type {
+        list.filter(
+            "EXISTS (SELECT 1 FROM activity a WHERE a.id = e.activity AND a.type = {})",
+            activity_type.as_str(),
+        );
     }
+    if let Some(location) = &query.location {
+        list.filter("e.location = {}", location.as_str());
+    }
+
+    let page = list.fetch::<Event>(&pool, "Failed to fetch events").await?;
+
+    Ok(page.into_response())
 }
 
 async fn create_event(
     pool: web::Data<PgPool>,
     event_data: web::Json<CreateEvent>,
+    user: Option<AuthUser>,
 ) -> Result<HttpResponse> {
-    let mut tx = match pool.begin().await {
-        Ok(tx) => tx,
-        Err(e) => {
-            log::error!("Failed to start transaction: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to create event"
-            })));
-        }
-    };
+    let mut tx = pool.begin().await.context("Failed to create event")?;
+
+    let event_id = insert_event(&mut tx, &event_data).await?;
+    history::record(
+        &mut tx,
+        HistoryEntity::Event,
+        event_id,
+        HistoryAction::Create,
+        None,
+        user.as_ref(),
+    )
+    .await?;
+
+    // Commit the transaction
+    tx.commit().await.context("Failed to create event")?;
+
+    Ok(HttpResponse::Created().json(CreateEventResponse {
+        id: event_id,
+        message: "Event created successfully".to_string(),
+    }))
+}
 
-    // Insert the event record
-    let event_result = sqlx::query!(
+/// Insert an event with its people, as `POST /events` does.
+pub(crate) async fn insert_event(
+    tx: &mut Transaction<'_, Postgres>,
+    event_data: &CreateEvent,
+) -> Result<i32> {
+    let measure = structured_measure(event_data)?;
+    let event_id = sqlx::query!(
         r#"
-        INSERT INTO event (date, activity, measure, location, notes)
-        VALUES ($1, $2, $3, $4, $5)
+        INSERT INTO event (date, activity, measure, location, notes, start_time, end_time, quantity, unit)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
         RETURNING id
         "#,
         event_data.date,
         event_data.activity_id,
         event_data.measure,
         event_data.location,
-        event_data.notes
+        event_data.notes,
+        event_data.start_time,
+        event_data.end_time,
+        measure.as_ref().map(|m| m.quantity),
+        measure.as_ref().map(|m| m.unit.as_str())
     )
-    .fetch_one(&mut *tx)
-    .await;
-
-    let event_id = match event_result {
-        Ok(row) => row.id,
-        Err(e) => {
-            log::error!("Failed to insert event: {}", e);
-            if let Err(rollback_err) = tx.rollback().await {
-                log::error!("Failed to rollback transaction: {}", rollback_err);
-            }
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to create event"
-            })));
-        }
-    };
+    .fetch_one(&mut **tx)
+    .await
+    .context("Failed to create event")?
+    .id;
 
     // Insert event-people relationships
+    trash::check_live(tx, TrashKind::Person, &event_data.people_ids, "people_ids").await?;
     for person_id in &event_data.people_ids {
-        if let Err(e) = sqlx::query!(
+        sqlx::query!(
             "INSERT INTO event_people (event, people) VALUES ($1, $2)",
             event_id,
             person_id
         )
-        .execute(&mut *tx)
+        .execute(&mut **tx)
         .await
-        {
-            log::error!("Failed to insert event_people relationship: {}", e);
-            if let Err(rollback_err) = tx.rollback().await {
-                log::error!("Failed to rollback transaction: {}", rollback_err);
-            }
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to create event"
-            })));
-        }
+        .context("Failed to create event")?;
     }
 
-    // Commit the transaction
-    if let Err(e) = tx.commit().await {
-        log::error!("Failed to commit transaction: {}", e);
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to create event"
-        })));
-    }
+    Ok(event_id)
+}
 
-    Ok(HttpResponse::Created().json(CreateEventResponse {
-        id: event_id,
-        message: "Event created successfully".to_string(),
-    }))
+/// The quantity and unit to store: the given ones in hours or kilometres, or
+/// else those parsed from the free-text measure.
+fn structured_measure(event_data: &CreateEvent) -> Result<Option<Measure>> {
+    match (event_data.quantity, &event_data.unit) {
+        (Some(quantity), _) if !quantity.is_finite() || quantity < 0.0 => Err(
+            AppError::bad_request("Quantity must be a non-negative number"),
+        ),
+        (Some(quantity), Some(unit)) if !unit.trim().is_empty() => {
+            normalize(quantity, unit).map(Some).ok_or_else(|| {
+                AppError::bad_request(format!(
+                    "Unknown unit '{}'; use a duration like h or min, or a distance like km or mi",
+                    unit.trim()
+                ))
+            })
+        }
+        (None, None) => Ok(event_data.measure.as_deref().and_then(parse_measure)),
+        _ => Err(AppError::bad_request(
+            "Quantity and unit must be given together",
+        )),
+    }
 }
 
 async fn get_event(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse> {
     let event_id = path.into_inner();
 
-    match sqlx::query_as::<_, Event>(
-        "SELECT id, date, activity, measure, location, notes FROM event WHERE id = $1",
+    let event = sqlx::query_as::<_, Event>(
+        "SELECT id, date, activity, measure, location, notes, start_time, end_time, quantity, unit FROM event WHERE id = $1 AND deleted_at IS NULL",
     )
     .bind(event_id)
     .fetch_optional(pool.get_ref())
     .await
-    {
-        Ok(Some(event)) => Ok(HttpResponse::Ok().json(event)),
-        Ok(None) => Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Event not found"
-        }))),
-        Err(e) => {
-            log::error!("Failed to fetch event {}: {}", event_id, e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch event"
-            })))
-        }
-    }
+    .context("Failed to fetch event")?
+    .ok_or_else(|| AppError::not_found("Event not found"))?;
+
+    Ok(HttpResponse::Ok().json(event))
 }
 
 async fn update_event(
     pool: web::Data<PgPool>,
     path: web::Path<i32>,
     event_data: web::Json<CreateEvent>,
+    user: Option<AuthUser>,
 ) -> Result<HttpResponse> {
     let event_id = path.into_inner();
 
     // Start transaction
-    let mut tx = match pool.begin().await {
-        Ok(tx) => tx,
-        Err(e) => {
-            log::error!("Failed to start transaction: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to update event"
-            })));
-        }
-    };
+    let mut tx = pool.begin().await.context("Failed to update event")?;
 
     // Step 1: Check if event exists
-    let _existing_event = match sqlx::query_as::<_, Event>(
-        "SELECT id, date, activity, measure, location, notes FROM event WHERE id = $1",
+    sqlx::query!(
+        "SELECT id FROM event WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+        event_id
     )
-    .bind(event_id)
     .fetch_optional(&mut *tx)
     .await
-    {
-        Ok(Some(event)) => event,
-        Ok(None) => {
-            return Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "Event not found"
-            })));
-        }
-        Err(e) => {
-            log::error!("Failed to fetch event: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to update event"
-            })));
-        }
-    };
+    .context("Failed to update event")?
+    .ok_or_else(|| AppError::not_found("Event not found"))?;
+    let before = history::snapshot(&mut tx, HistoryEntity::Event, event_id).await?;
 
     // Step 2: Update the main event record
-    if let Err(e) = sqlx::query!(
+    let measure = structured_measure(&event_data)?;
+    sqlx::query!(
         r#"
         UPDATE event 
-        SET date = $1, activity = $2, measure = $3, location = $4, notes = $5 
-        WHERE id = $6
+        SET date = $1, activity = $2, measure = $3, location = $4, notes = $5,
+            start_time = $6, end_time = $7, quantity = $8, unit = $9
+        WHERE id = $10
         "#,
         event_data.date,
         event_data.activity_id,
         event_data.measure,
         event_data.location,
         event_data.notes,
+        event_data.start_time,
+        event_data.end_time,
+        measure.as_ref().map(|m| m.quantity),
+        measure.as_ref().map(|m| m.unit.as_str()),
         event_id
     )
     .execute(&mut *tx)
     .await
-    {
-        log::error!("Failed to update event: {}", e);
-        let _ = tx.rollback().await;
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to update event"
-        })));
-    }
+    .context("Failed to update event")?;
 
     // Step 3: Remove existing people relationships
-    let _ = sqlx::query!("DELETE FROM event_people WHERE event = $1", event_id)
+    sqlx::query!("DELETE FROM event_people WHERE event = $1", event_id)
         .execute(&mut *tx)
-        .await;
+        .await
+        .context("Failed to update event")?;
 
     // Step 4: Insert new people relationships
+    trash::check_live(
+        &mut tx,
+        TrashKind::Person,
+        &event_data.people_ids,
+        "people_ids",
+    )
+    .await?;
     for person_id in &event_data.people_ids {
-        if let Err(e) = sqlx::query!(
+        sqlx::query!(
             "INSERT INTO event_people (event, people) VALUES ($1, $2)",
             event_id,
             person_id
         )
         .execute(&mut *tx)
         .await
-        {
-            log::error!("Failed to update event people relationship: {}", e);
-            let _ = tx.rollback().await;
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to update event people relationships"
-            })));
-        }
+        .context("Failed to update event people relationships")?;
     }
 
+    history::record(
+        &mut tx,
+        HistoryEntity::Event,
+        event_id,
+        HistoryAction::Update,
+        before,
+        user.as_ref(),
+    )
+    .await?;
+
     // Step 5: Commit transaction
-    if let Err(e) = tx.commit().await {
-        log::error!("Failed to commit transaction: {}", e);
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to update event"
-        })));
-    }
+    tx.commit().await.context("Failed to update event")?;
 
     // Return success response
     Ok(HttpResponse::Ok().json(serde_json::json!({
@@ -238,78 +267,22 @@ async fn update_event(
     })))
 }
 
-async fn delete_event(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse> {
+/// Move an event to the trash, where it keeps its people.
+async fn delete_event(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
     let event_id = path.into_inner();
 
-    let mut tx = match pool.begin().await {
-        Ok(tx) => tx,
-        Err(e) => {
-            log::error!("Failed to start transaction: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to delete event"
-            })));
-        }
-    };
+    let mut tx = pool.begin().await.context("Failed to delete event")?;
 
-    // First check if the event exists
-    match sqlx::query!("SELECT id FROM event WHERE id = $1", event_id)
-        .fetch_optional(&mut *tx)
-        .await
-    {
-        Ok(Some(_)) => {
-            // Event exists, continue with deletion
-        }
-        Ok(None) => {
-            return Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "Event not found"
-            })));
-        }
-        Err(e) => {
-            log::error!("Failed to check if event exists: {}", e);
-            if let Err(rollback_err) = tx.rollback().await {
-                log::error!("Failed to rollback transaction: {}", rollback_err);
-            }
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to delete event"
-            })));
-        }
-    }
-
-    // Delete event_people relationships (will cascade automatically, but explicit is better)
-    if let Err(e) = sqlx::query!("DELETE FROM event_people WHERE event = $1", event_id)
-        .execute(&mut *tx)
-        .await
-    {
-        log::error!("Failed to delete event_people relationships: {}", e);
-        if let Err(rollback_err) = tx.rollback().await {
-            log::error!("Failed to rollback transaction: {}", rollback_err);
-        }
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to delete event"
-        })));
-    }
-
-    // Delete the event itself
-    if let Err(e) = sqlx::query!("DELETE FROM event WHERE id = $1", event_id)
-        .execute(&mut *tx)
-        .await
-    {
-        log::error!("Failed to delete event: {}", e);
-        if let Err(rollback_err) = tx.rollback().await {
-            log::error!("Failed to rollback transaction: {}", rollback_err);
-        }
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to delete event"
-        })));
+    if !trash::move_to_trash(&mut tx, TrashKind::Event, event_id, user.as_ref()).await? {
+        return Err(AppError::not_found("Event not found"));
     }
 
     // Commit the transaction
-    if let Err(e) = tx.commit().await {
-        log::error!("Failed to commit transaction: {}", e);
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to delete event"
-        })));
-    }
+    tx.commit().await.context("Failed to delete event")?;
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "message": "Event deleted successfully"
@@ -320,58 +293,37 @@ async fn get_event_details(pool: web::Data<PgPool>, path: web::Path<i32>) -> Res
     let event_id = path.into_inner();
 
     // Get event with activity details in a single query
-    let event_query = sqlx::query!(
+    let event_row = sqlx::query!(
         r#"
         SELECT 
             e.id, e.date, e.measure, e.location, e.notes,
+            e.start_time, e.end_time, e.quantity, e.unit,
             a.id as activity_id, a.name as activity_name, a.type as activity_type
         FROM event e
         JOIN activity a ON e.activity = a.id
-        WHERE e.id = $1
+        WHERE e.id = $1 AND e.deleted_at IS NULL
         "#,
         event_id
     )
     .fetch_optional(pool.get_ref())
-    .await;
-
-    let event_row = match event_query {
-        Ok(Some(event)) => event,
-        Ok(None) => {
-            return Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "Event not found"
-            })));
-        }
-        Err(e) => {
-            log::error!("Failed to fetch event {}: {}", event_id, e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch event"
-            })));
-        }
-    };
+    .await
+    .context("Failed to fetch event")?
+    .ok_or_else(|| AppError::not_found("Event not found"))?;
 
     // Get people associated with this event
-    let people_result = sqlx::query_as::<_, People>(
+    let people = sqlx::query_as::<_, People>(
         r#"
-        SELECT p.id, p.name, p.notes
+        SELECT p.id, p.name, p.notes, p.household_order
         FROM people p
         JOIN event_people ep ON p.id = ep.people
-        WHERE ep.event = $1
+        WHERE ep.event = $1 AND p.deleted_at IS NULL
         ORDER BY p.name
         "#,
     )
     .bind(event_id)
     .fetch_all(pool.get_ref())
-    .await;
-
-    let people = match people_result {
-        Ok(people) => people,
-        Err(e) => {
-            log::error!("Failed to fetch event people {}: {}", event_id, e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch event details"
-            })));
-        }
-    };
+    .await
+    .context("Failed to fetch event details")?;
 
     let event_detail = EventDetail {
         id: event_row.id,
@@ -385,6 +337,10 @@ async fn get_event_details(pool: web::Data<PgPool>, path: web::Path<i32>) -> Res
         location: event_row.location,
         notes: event_row.notes,
         people,
+        start_time: event_row.start_time,
+        end_time: event_row.end_time,
+        quantity: event_row.quantity,
+        unit: event_row.unit,
     };
 
     Ok(HttpResponse::Ok().json(event_detail))
@@ -0,0 +1,38 @@
+use crate::error::{self, Result};
+use crate::export;
+use crate::models::export::Archive;
+use actix_web::{http::header, web, HttpResponse};
+use sqlx::PgPool;
+
+// Archives of the whole database are larger than the default JSON limit
+const MAX_ARCHIVE_BYTES: usize = 256 * 1024 * 1024;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/export").route(web::get().to(export_archive)))
+        .service(
+            web::resource("/restore")
+                .app_data(error::json_config().limit(MAX_ARCHIVE_BYTES))
+                .route(web::post().to(restore_archive)),
+        );
+}
+
+async fn export_archive(pool: web::Data<PgPool>) -> Result<HttpResponse> {
+    let archive = export::export(&pool).await?;
+    let filename = format!("xnote-{}.json", archive.exported_at.format("%Y-%m-%d"));
+
+    Ok(HttpResponse::Ok()
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", filename),
+        ))
+        .json(archive))
+}
+
+async fn restore_archive(
+    pool: web::Data<PgPool>,
+    archive: web::Json<Archive>,
+) -> Result<HttpResponse> {
+    let restored = export::restore(&pool, &archive).await?;
+
+    Ok(HttpResponse::Created().json(restored))
+}
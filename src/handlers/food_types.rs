@@ -1,42 +1,92 @@
+use crate::auth::AuthUser;
+use crate::error::{Result, ResultExt};
+use crate::handlers::vocabulary::{Reference, Vocabulary};
+use crate::models::history::HistoryEntity;
 use crate::models::restaurant::FoodType;
-use actix_web::{web, HttpResponse, Result};
+use crate::models::vocabulary::{DeleteVocabularyQuery, RenameVocabulary};
+use actix_web::{web, HttpResponse};
 use sqlx::PgPool;
 
+const FOOD_TYPES: Vocabulary = Vocabulary {
+    table: "food_type",
+    name: "food type",
+    references: &[Reference {
+        table: "restaurant",
+        column: "type",
+        kind: "restaurant",
+        key: "restaurants",
+        label: "name",
+        entity: HistoryEntity::Restaurant,
+    }],
+    template_fields: &[],
+};
+
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::resource("/food-types")
             .route(web::get().to(get_food_types))
             .route(web::post().to(create_food_type)),
     )
-    .service(web::resource("/food-types/{name}").route(web::delete().to(delete_food_type)));
+    .service(
+        web::resource("/food-types/{name}")
+            .route(web::put().to(rename_food_type))
+            .route(web::delete().to(delete_food_type)),
+    );
 }
 
 async fn get_food_types(pool: web::Data<PgPool>) -> Result<HttpResponse> {
-    match sqlx::query_as::<_, FoodType>("SELECT name FROM food_type ORDER BY name")
+    let food_types = sqlx::query_as::<_, FoodType>("SELECT name FROM food_type ORDER BY name")
         .fetch_all(pool.get_ref())
         .await
-    {
-        Ok(food_types) => Ok(HttpResponse::Ok().json(food_types)),
-        Err(e) => {
-            log::error!("Failed to fetch food types: {}", e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch food types"
-            })))
-        }
-    }
+        .context("Failed to fetch food types")?;
+
+    Ok(HttpResponse::Ok().json(food_types))
 }
 
-async fn create_food_type(_pool: web::Data<PgPool>) -> Result<HttpResponse> {
-    Ok(HttpResponse::Created().json(serde_json::json!({
-        "message": "Create food type - TODO: implement"
-    })))
+async fn create_food_type(
+    pool: web::Data<PgPool>,
+    food_type: web::Json<FoodType>,
+) -> Result<HttpResponse> {
+    let name = FOOD_TYPES.create(&pool, &food_type.name).await?;
+
+    Ok(HttpResponse::Created().json(FoodType { name }))
+}
+
+/// Rename a food type and every restaurant that uses it.
+async fn rename_food_type(
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    rename: web::Json<RenameVocabulary>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let name = FOOD_TYPES
+        .rename(&pool, &path.into_inner(), &rename.name, user.as_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(FoodType { name }))
 }
 
 async fn delete_food_type(
-    _pool: web::Data<PgPool>,
-    _path: web::Path<String>,
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    query: web::Query<DeleteVocabularyQuery>,
+    user: Option<AuthUser>,
 ) -> Result<HttpResponse> {
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "message": "Delete food type - TODO: implement"
-    })))
+    let food_type_name = path.into_inner();
+
+    FOOD_TYPES
+        .delete(
+            &pool,
+            &food_type_name,
+            query.merge_into.as_deref(),
+            user.as_ref(),
+        )
+        .await?;
+
+    let message = match &query.merge_into {
+        Some(target) => format!("Food type merged into '{}' and deleted", target),
+        None => "Food type deleted successfully".to_string(),
+    };
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": message })))
 }
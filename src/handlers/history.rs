@@ -0,0 +1,70 @@
+use crate::auth::AuthUser;
+use crate::error::{AppError, Result, ResultExt};
+use crate::handlers::listing::ListBuilder;
+use crate::history::{self, ENTRY_COLUMNS};
+use crate::models::history::{HistoryEntry, HistoryQuery};
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/history").route(web::get().to(get_history)))
+        .service(web::resource("/history/{id}").route(web::get().to(get_history_entry)))
+        .service(web::resource("/history/{id}/revert").route(web::post().to(revert_entry)));
+}
+
+/// Changes newest first, for one record when `entity` and `id` are given and
+/// across everything otherwise.
+async fn get_history(
+    pool: web::Data<PgPool>,
+    query: web::Query<HistoryQuery>,
+) -> Result<HttpResponse> {
+    if query.id.is_some() && query.entity.is_none() {
+        return Err(AppError::bad_request("id requires entity"));
+    }
+
+    let select = format!("SELECT {ENTRY_COLUMNS} FROM history h");
+    let list_query = query.list_query();
+    let mut list = ListBuilder::new(
+        &select,
+        &list_query,
+        Some("(h.changed_at AT TIME ZONE 'UTC')::date"),
+        "h.id",
+    );
+    if let Some(entity) = query.entity {
+        list.filter("h.entity = {}", entity);
+    }
+    if let Some(id) = query.id {
+        list.filter("h.entity_id = {}", id);
+    }
+
+    let page = list
+        .fetch::<HistoryEntry>(&pool, "Failed to fetch history")
+        .await?;
+
+    Ok(page.into_response())
+}
+
+async fn get_history_entry(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse> {
+    let entry = sqlx::query_as::<_, HistoryEntry>(&format!(
+        "SELECT {ENTRY_COLUMNS} FROM history WHERE id = $1"
+    ))
+    .bind(path.into_inner())
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to fetch history entry")?
+    .ok_or_else(|| AppError::not_found("History entry not found"))?;
+
+    Ok(HttpResponse::Ok().json(entry))
+}
+
+/// Put the record back the way this change left it. Responds with the new
+/// entry logging the revert.
+async fn revert_entry(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let entry = history::revert(&pool, path.into_inner(), user.as_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(entry))
+}
@@ -0,0 +1,69 @@
+use crate::auth::AuthUser;
+use crate::error::Result;
+use crate::import;
+use crate::models::import::{ImportPreview, ImportQuery};
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+// Spreadsheets covering years of days are larger than the default payload limit
+const MAX_UPLOAD_BYTES: usize = 16 * 1024 * 1024;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/import")
+            .app_data(web::PayloadConfig::new(MAX_UPLOAD_BYTES))
+            .route(web::post().to(import_csv)),
+    )
+    .service(
+        web::resource("/import/ics")
+            .app_data(web::PayloadConfig::new(MAX_UPLOAD_BYTES))
+            .route(web::post().to(import_ics)),
+    );
+}
+
+/// Import the old daily spreadsheet, sent as the raw CSV request body. Returns a
+/// preview of every parsed record; `commit=true` also writes them.
+async fn import_csv(
+    pool: web::Data<PgPool>,
+    query: web::Query<ImportQuery>,
+    body: web::Bytes,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let preview = import::import_csv(
+        &pool,
+        &body,
+        query.commit,
+        query.skip_unmatched,
+        user.as_ref(),
+    )
+    .await?;
+
+    Ok(preview_response(preview))
+}
+
+/// Import the VEVENTs of an iCalendar file as events, like `import_csv`.
+async fn import_ics(
+    pool: web::Data<PgPool>,
+    query: web::Query<ImportQuery>,
+    body: web::Bytes,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let preview = import::import_ics(
+        &pool,
+        &body,
+        query.commit,
+        query.skip_unmatched,
+        user.as_ref(),
+    )
+    .await?;
+
+    Ok(preview_response(preview))
+}
+
+fn preview_response(preview: ImportPreview) -> HttpResponse {
+    if preview.committed {
+        HttpResponse::Created().json(preview)
+    } else {
+        HttpResponse::Ok().json(preview)
+    }
+}
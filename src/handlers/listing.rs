@@ -0,0 +1,212 @@
+use crate::error::{AppError, Result, ResultExt};
+use crate::models::list::{Cursor, Keyset, ListQuery, SortOrder};
+use actix_web::HttpResponse;
+use serde::Serialize;
+use sqlx::postgres::PgRow;
+use sqlx::{Encode, FromRow, PgPool, Postgres, QueryBuilder, Type};
+
+const MAX_LIMIT: i64 = 1000;
+
+/// Header carrying the cursor of the next page, absent on the last page.
+pub const NEXT_CURSOR_HEADER: &str = "X-Next-Cursor";
+
+/// Builds a filtered, keyset-paginated list query from a `ListQuery`.
+pub struct ListBuilder<'a> {
+    builder: QueryBuilder<'a, Postgres>,
+    query: &'a ListQuery,
+    date_column: Option<&'static str>,
+    id_column: &'static str,
+}
+
+impl<'a> ListBuilder<'a> {
+    /// `select` is the query up to (but excluding) its WHERE clause. Lists with a
+    /// `date_column` default to newest first; the others to ascending ids.
+    pub fn new(
+        select: &str,
+        query: &'a ListQuery,
+        date_column: Option<&'static str>,
+        id_column: &'static str,
+    ) -> Self {
+        Self::from_builder(QueryBuilder::new(select), query, date_column, id_column)
+    }
+
+    /// Like `new`, for a `select` whose `{}` is bound to `value`.
+    pub fn with_bind<T>(
+        select: &str,
+        value: T,
+        query: &'a ListQuery,
+        date_column: Option<&'static str>,
+        id_column: &'static str,
+    ) -> Self
+    where
+        T: 'a + Encode<'a, Postgres> + Type<Postgres> + Send,
+    {
+        let (before, after) = select
+            .split_once("{}")
+            .expect("select must contain a {} placeholder");
+        let mut builder = QueryBuilder::new(before);
+        builder.push_bind(value);
+        builder.push(after);
+        Self::from_builder(builder, query, date_column, id_column)
+    }
+
+    fn from_builder(
+        mut builder: QueryBuilder<'a, Postgres>,
+        query: &'a ListQuery,
+        date_column: Option<&'static str>,
+        id_column: &'static str,
+    ) -> Self {
+        builder.push(" WHERE TRUE");
+
+        let mut list = ListBuilder {
+            builder,
+            query,
+            date_column,
+            id_column,
+        };
+        if let Some(date_column) = date_column {
+            if let Some(start_date) = query.start_date {
+                list.filter(&format!("{} >= {{}}", date_column), start_date);
+            }
+            if let Some(end_date) = query.end_date {
+                list.filter(&format!("{} <= {{}}", date_column), end_date);
+            }
+        }
+        list
+    }
+
+    /// Add `AND <condition>` for a condition without parameters.
+    pub fn condition(&mut self, condition: &str) -> &mut Self {
+        self.builder.push(" AND ").push(condition);
+        self
+    }
+
+    /// Add `AND <condition>`, where the `{}` in `condition` is bound to `value`.
+    pub fn filter<T>(&mut self, condition: &str, value: T) -> &mut Self
+    where
+        T: 'a + Encode<'a, Postgres> + Type<Postgres> + Send,
+    {
+        let (before, after) = condition
+            .split_once("{}")
+            .expect("filter condition must contain a {} placeholder");
+        self.builder.push(" AND ").push(before);
+        self.builder.push_bind(value);
+        self.builder.push(after);
+        self
+    }
+
+    fn order(&self) -> SortOrder {
+        self.query.sort.unwrap_or(if self.date_column.is_some() {
+            SortOrder::Desc
+        } else {
+            SortOrder::Asc
+        })
+    }
+
+    pub async fn fetch<T>(mut self, pool: &PgPool, context: &str) -> Result<Page<T>>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Keyset + Send + Unpin,
+    {
+        let order = self.order();
+        let comparison = match order {
+            SortOrder::Asc => ">",
+            SortOrder::Desc => "<",
+        };
+
+        if let Some(cursor) = &self.query.cursor {
+            let cursor = Cursor::parse(cursor)
+                .filter(|cursor| cursor.date.is_some() == self.date_column.is_some())
+                .ok_or_else(|| AppError::bad_request(format!("Invalid cursor '{}'", cursor)))?;
+
+            self.builder.push(" AND ");
+            match (self.date_column, cursor.date) {
+                (Some(date_column), Some(date)) => {
+                    self.builder
+                        .push(format!(
+                            "({}, {}) {} (",
+                            date_column, self.id_column, comparison
+                        ))
+                        .push_bind(date)
+                        .push(", ")
+                        .push_bind(cursor.id)
+                        .push(")");
+                }
+                _ => {
+                    self.builder
+                        .push(format!("{} {} ", self.id_column, comparison))
+                        .push_bind(cursor.id);
+                }
+            }
+        }
+
+        self.builder.push(" ORDER BY ");
+        if let Some(date_column) = self.date_column {
+            self.builder
+                .push(format!("{} {}, ", date_column, order.sql()));
+        }
+        self.builder
+            .push(format!("{} {}", self.id_column, order.sql()));
+
+        let limit = self.query.limit.map(|limit| limit.clamp(1, MAX_LIMIT));
+        if let Some(limit) = limit {
+            // Fetch one extra row to learn whether there is a next page
+            self.builder.push(" LIMIT ").push_bind(limit + 1);
+        }
+
+        let mut items = self
+            .builder
+            .build_query_as::<T>()
+            .fetch_all(pool)
+            .await
+            .context(context)?;
+
+        let next_cursor = match limit {
+            Some(limit) if items.len() as i64 > limit => {
+                items.truncate(limit as usize);
+                items.last().map(|item| item.cursor().to_string())
+            }
+            _ => None,
+        };
+
+        Ok(Page { items, next_cursor })
+    }
+}
+
+/// Reject filters the endpoint does not support, instead of silently ignoring them.
+pub fn check_filters(query: &ListQuery, list: &str, supported: &[&str]) -> Result<()> {
+    match query.filters().into_iter().find(|f| !supported.contains(f)) {
+        Some(filter) => Err(AppError::bad_request(format!(
+            "Filter '{}' is not supported for {}",
+            filter, list
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Parse a comma-separated list of ids given as the query parameter `field`.
+pub fn parse_ids(text: &str, field: &str) -> Result<Vec<i32>> {
+    text.split(',')
+        .map(|id| {
+            id.trim().parse().map_err(|_| {
+                AppError::bad_request(format!("Invalid id '{}' in {}", id.trim(), field))
+            })
+        })
+        .collect()
+}
+
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+impl<T: Serialize> Page<T> {
+    /// Respond with the items as a plain JSON array, so existing clients keep
+    /// working, and the next cursor in a header.
+    pub fn into_response(self) -> HttpResponse {
+        let mut response = HttpResponse::Ok();
+        if let Some(next_cursor) = &self.next_cursor {
+            response.insert_header((NEXT_CURSOR_HEADER, next_cursor.as_str()));
+        }
+        response.json(self.items)
+    }
+}
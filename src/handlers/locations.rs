@@ -1,42 +1,104 @@
+use crate::auth::AuthUser;
+use crate::error::{Result, ResultExt};
+use crate::handlers::vocabulary::{Reference, Vocabulary};
+use crate::models::history::HistoryEntity;
 use crate::models::location::Location;
-use actix_web::{web, HttpResponse, Result};
+use crate::models::template::EntryKind;
+use crate::models::vocabulary::{DeleteVocabularyQuery, RenameVocabulary};
+use actix_web::{web, HttpResponse};
 use sqlx::PgPool;
 
+const LOCATIONS: Vocabulary = Vocabulary {
+    table: "location",
+    name: "location",
+    references: &[
+        Reference {
+            table: "restaurant",
+            column: "location",
+            kind: "restaurant",
+            key: "restaurants",
+            label: "name",
+            entity: HistoryEntity::Restaurant,
+        },
+        Reference {
+            table: "event",
+            column: "location",
+            kind: "event",
+            key: "events",
+            label:
+                "date::text || ' ' || (SELECT a.name FROM activity a WHERE a.id = event.activity)",
+            entity: HistoryEntity::Event,
+        },
+    ],
+    template_fields: &[(EntryKind::Event, "location")],
+};
+
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::resource("/locations")
             .route(web::get().to(get_locations))
             .route(web::post().to(create_location)),
     )
-    .service(web::resource("/locations/{name}").route(web::delete().to(delete_location)));
+    .service(
+        web::resource("/locations/{name}")
+            .route(web::put().to(rename_location))
+            .route(web::delete().to(delete_location)),
+    );
 }
 
 async fn get_locations(pool: web::Data<PgPool>) -> Result<HttpResponse> {
-    match sqlx::query_as::<_, Location>("SELECT name FROM location ORDER BY name")
+    let locations = sqlx::query_as::<_, Location>("SELECT name FROM location ORDER BY name")
         .fetch_all(pool.get_ref())
         .await
-    {
-        Ok(locations) => Ok(HttpResponse::Ok().json(locations)),
-        Err(e) => {
-            log::error!("Failed to fetch locations: {}", e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch locations"
-            })))
-        }
-    }
+        .context("Failed to fetch locations")?;
+
+    Ok(HttpResponse::Ok().json(locations))
 }
 
-async fn create_location(_pool: web::Data<PgPool>) -> Result<HttpResponse> {
-    Ok(HttpResponse::Created().json(serde_json::json!({
-        "message": "Create location - TODO: implement"
-    })))
+async fn create_location(
+    pool: web::Data<PgPool>,
+    location: web::Json<Location>,
+) -> Result<HttpResponse> {
+    let name = LOCATIONS.create(&pool, &location.name).await?;
+
+    Ok(HttpResponse::Created().json(Location { name }))
+}
+
+/// Rename a location and every restaurant and event that uses it.
+async fn rename_location(
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    rename: web::Json<RenameVocabulary>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let name = LOCATIONS
+        .rename(&pool, &path.into_inner(), &rename.name, user.as_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(Location { name }))
 }
 
 async fn delete_location(
-    _pool: web::Data<PgPool>,
-    _path: web::Path<String>,
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    query: web::Query<DeleteVocabularyQuery>,
+    user: Option<AuthUser>,
 ) -> Result<HttpResponse> {
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "message": "Delete location - TODO: implement"
-    })))
+    let location_name = path.into_inner();
+
+    LOCATIONS
+        .delete(
+            &pool,
+            &location_name,
+            query.merge_into.as_deref(),
+            user.as_ref(),
+        )
+        .await?;
+
+    let message = match &query.merge_into {
+        Some(target) => format!("Location merged into '{}' and deleted", target),
+        None => "Location deleted successfully".to_string(),
+    };
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": message })))
 }
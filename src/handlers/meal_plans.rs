@@ -0,0 +1,388 @@
+use crate::auth::AuthUser;
+use crate::error::{AppError, Result, ResultExt};
+use crate::handlers::listing::{check_filters, ListBuilder};
+use crate::handlers::meals;
+use crate::history;
+use crate::models::history::{HistoryAction, HistoryEntity};
+use crate::models::list::ListQuery;
+use crate::models::meal::{CreateMeal, CreateMealFoodSource, CreateMealResponse};
+use crate::models::meal_plan::{EatenQuery, MealPlan};
+use crate::models::trash::TrashKind;
+use crate::trash;
+use crate::util;
+use actix_web::{web, HttpResponse};
+use sqlx::{PgPool, Postgres, Transaction};
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/meal-plans")
+            .route(web::get().to(get_meal_plans))
+            .route(web::post().to(create_meal_plan)),
+    )
+    .service(web::resource("/meal-plans/skipped").route(web::get().to(get_skipped_meal_plans)))
+    .service(
+        web::resource("/meal-plans/{id}")
+            .route(web::get().to(get_meal_plan))
+            .route(web::put().to(update_meal_plan))
+            .route(web::delete().to(delete_meal_plan)),
+    )
+    .service(web::resource("/meal-plans/{id}/eaten").route(web::post().to(mark_eaten)));
+}
+
+// Food sources come back in the shape they were planned with. A plan is
+// skipped once the day after its date has passed without it being eaten; the
+// `{}` is today's date.
+const MEAL_PLANS: &str = r#"
+    SELECT
+        p.id,
+        p.date,
+        p."time",
+        p.notes,
+        COALESCE((
+            SELECT json_agg(json_strip_nulls(json_build_object(
+                'type', CASE
+                    WHEN s.recipe IS NOT NULL THEN 'recipe'
+                    WHEN s.product IS NOT NULL THEN 'product'
+                    ELSE 'restaurant'
+                END,
+                'recipe_id', s.recipe,
+                'product_id', s.product,
+                'restaurant_id', s.restaurant,
+                'meal_type', s.type
+            )) ORDER BY s.position)
+            FROM meal_plan_source s WHERE s.plan = p.id
+        ), '[]') AS food_sources,
+        COALESCE((
+            SELECT array_agg(pp.people ORDER BY pp.people)
+            FROM meal_plan_people pp WHERE pp.plan = p.id
+        ), ARRAY[]::int[]) AS people_ids,
+        p.meal AS meal_id,
+        CASE
+            WHEN p.meal IS NOT NULL THEN 'eaten'
+            WHEN p.date < {} - 1 THEN 'skipped'
+            ELSE 'planned'
+        END AS status
+    FROM meal_plan p
+"#;
+
+async fn get_meal_plans(
+    pool: web::Data<PgPool>,
+    query: web::Query<ListQuery>,
+) -> Result<HttpResponse> {
+    check_filters(
+        &query,
+        "meal plans",
+        &["start_date", "end_date", "person_id"],
+    )?;
+
+    let mut list =
+        ListBuilder::with_bind(MEAL_PLANS, util::today(), &query, Some("p.date"), "p.id");
+    if let Some(person_id) = query.person_id {
+        list.filter(
+            "EXISTS (SELECT 1 FROM meal_plan_people pp WHERE pp.plan = p.id AND pp.people = {})",
+            person_id,
+        );
+    }
+
+    let page = list
+        .fetch::<MealPlan>(&pool, "Failed to fetch meal plans")
+        .await?;
+
+    Ok(page.into_response())
+}
+
+/// Plans that were not eaten by the day after their date.
+async fn get_skipped_meal_plans(
+    pool: web::Data<PgPool>,
+    query: web::Query<ListQuery>,
+) -> Result<HttpResponse> {
+    check_filters(&query, "meal plans", &["start_date", "end_date"])?;
+
+    let today = util::today();
+    let mut list = ListBuilder::with_bind(MEAL_PLANS, today, &query, Some("p.date"), "p.id");
+    list.filter("p.meal IS NULL AND p.date < {} - 1", today);
+
+    let page = list
+        .fetch::<MealPlan>(&pool, "Failed to fetch meal plans")
+        .await?;
+
+    Ok(page.into_response())
+}
+
+async fn fetch_meal_plan(
+    executor: impl sqlx::PgExecutor<'_>,
+    plan_id: i32,
+    for_update: bool,
+) -> Result<MealPlan> {
+    let lock = if for_update { " FOR UPDATE OF p" } else { "" };
+    let select = MEAL_PLANS.replacen("{}", "$1", 1);
+    sqlx::query_as::<_, MealPlan>(&format!("{select} WHERE p.id = $2{lock}"))
+        .bind(util::today())
+        .bind(plan_id)
+        .fetch_optional(executor)
+        .await
+        .context("Failed to fetch meal plan")?
+        .ok_or_else(|| AppError::not_found("Meal plan not found"))
+}
+
+async fn get_meal_plan(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse> {
+    let plan = fetch_meal_plan(pool.get_ref(), path.into_inner(), false).await?;
+
+    Ok(HttpResponse::Ok().json(plan))
+}
+
+async fn insert_meal_plan_links(
+    tx: &mut Transaction<'_, Postgres>,
+    plan_id: i32,
+    plan_data: &CreateMeal,
+) -> Result<()> {
+    if plan_data.food_sources.is_empty() {
+        return Err(AppError::bad_request(
+            "A meal plan needs at least one food source",
+        ));
+    }
+
+    for (position, food_source) in (0..).zip(&plan_data.food_sources) {
+        let (recipe, product, restaurant, meal_type) = match food_source {
+            CreateMealFoodSource::Recipe {
+                recipe_id,
+                meal_type,
+            } => (Some(recipe_id), None, None, meal_type),
+            CreateMealFoodSource::Product {
+                product_id,
+                meal_type,
+            } => (None, Some(product_id), None, meal_type),
+            // What a visit costs and how it was is only known once eaten
+            CreateMealFoodSource::Restaurant {
+                restaurant_id,
+                meal_type,
+                ..
+            } => (None, None, Some(restaurant_id), meal_type),
+        };
+        for (kind, id, field) in [
+            (TrashKind::Recipe, recipe, "food_sources.recipe_id"),
+            (TrashKind::Product, product, "food_sources.product_id"),
+            (
+                TrashKind::Restaurant,
+                restaurant,
+                "food_sources.restaurant_id",
+            ),
+        ] {
+            if let Some(id) = id {
+                trash::check_live(tx, kind, &[*id], field).await?;
+            }
+        }
+        sqlx::query!(
+            r#"
+            INSERT INTO meal_plan_source (plan, position, recipe, product, restaurant, type)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            plan_id,
+            position,
+            recipe,
+            product,
+            restaurant,
+            meal_type
+        )
+        .execute(&mut **tx)
+        .await
+        .context("Failed to save meal plan food source")?;
+    }
+
+    trash::check_live(tx, TrashKind::Person, &plan_data.people_ids, "people_ids").await?;
+    for person_id in &plan_data.people_ids {
+        sqlx::query!(
+            "INSERT INTO meal_plan_people (plan, people) VALUES ($1, $2)",
+            plan_id,
+            person_id
+        )
+        .execute(&mut **tx)
+        .await
+        .context("Failed to save meal plan people relationships")?;
+    }
+
+    Ok(())
+}
+
+async fn create_meal_plan(
+    pool: web::Data<PgPool>,
+    plan_data: web::Json<CreateMeal>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let mut tx = pool.begin().await.context("Failed to create meal plan")?;
+
+    let plan_id = sqlx::query_scalar!(
+        r#"INSERT INTO meal_plan (date, "time", notes) VALUES ($1, $2, $3) RETURNING id"#,
+        plan_data.date,
+        plan_data.time,
+        plan_data.notes
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .context("Failed to create meal plan")?;
+
+    insert_meal_plan_links(&mut tx, plan_id, &plan_data).await?;
+    let plan = fetch_meal_plan(&mut *tx, plan_id, false).await?;
+    history::record(
+        &mut tx,
+        HistoryEntity::MealPlan,
+        plan_id,
+        HistoryAction::Create,
+        None,
+        user.as_ref(),
+    )
+    .await?;
+
+    tx.commit().await.context("Failed to create meal plan")?;
+
+    Ok(HttpResponse::Created().json(plan))
+}
+
+async fn update_meal_plan(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    plan_data: web::Json<CreateMeal>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let plan_id = path.into_inner();
+    let mut tx = pool.begin().await.context("Failed to update meal plan")?;
+    let before = history::snapshot(&mut tx, HistoryEntity::MealPlan, plan_id).await?;
+
+    let result = sqlx::query!(
+        r#"UPDATE meal_plan SET date = $1, "time" = $2, notes = $3 WHERE id = $4"#,
+        plan_data.date,
+        plan_data.time,
+        plan_data.notes,
+        plan_id
+    )
+    .execute(&mut *tx)
+    .await
+    .context("Failed to update meal plan")?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Meal plan not found"));
+    }
+
+    for table in ["meal_plan_source", "meal_plan_people"] {
+        sqlx::query(&format!("DELETE FROM {} WHERE plan = $1", table))
+            .bind(plan_id)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to update meal plan")?;
+    }
+
+    insert_meal_plan_links(&mut tx, plan_id, &plan_data).await?;
+    let plan = fetch_meal_plan(&mut *tx, plan_id, false).await?;
+    history::record(
+        &mut tx,
+        HistoryEntity::MealPlan,
+        plan_id,
+        HistoryAction::Update,
+        before,
+        user.as_ref(),
+    )
+    .await?;
+
+    tx.commit().await.context("Failed to update meal plan")?;
+
+    Ok(HttpResponse::Ok().json(plan))
+}
+
+/// Deleting a plan keeps the meal logged from it, if any.
+async fn delete_meal_plan(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let plan_id = path.into_inner();
+    let mut tx = pool.begin().await.context("Failed to delete meal plan")?;
+    let before = history::snapshot(&mut tx, HistoryEntity::MealPlan, plan_id).await?;
+
+    let result = sqlx::query!("DELETE FROM meal_plan WHERE id = $1", plan_id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete meal plan")?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Meal plan not found"));
+    }
+    history::record(
+        &mut tx,
+        HistoryEntity::MealPlan,
+        plan_id,
+        HistoryAction::Delete,
+        before,
+        user.as_ref(),
+    )
+    .await?;
+
+    tx.commit().await.context("Failed to delete meal plan")?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Meal plan deleted successfully"
+    })))
+}
+
+/// Log a plan as a meal, on its planned date unless `date` says otherwise.
+async fn mark_eaten(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    query: web::Query<EatenQuery>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let plan_id = path.into_inner();
+    let mut tx = pool.begin().await.context("Failed to log meal plan")?;
+
+    let plan = fetch_meal_plan(&mut *tx, plan_id, true).await?;
+    if plan.meal_id.is_some() {
+        return Err(AppError::AlreadyExists(
+            "Meal plan was already eaten".to_string(),
+        ));
+    }
+
+    let meal_data = CreateMeal {
+        date: query.date.unwrap_or(plan.date),
+        time: plan.time,
+        notes: plan.notes,
+        food_sources: plan.food_sources.0,
+        people_ids: plan.people_ids,
+    };
+    let before = history::snapshot(&mut tx, HistoryEntity::MealPlan, plan_id).await?;
+    let meal_id = meals::insert_meal(&mut tx, &meal_data).await?;
+    history::record(
+        &mut tx,
+        HistoryEntity::Meal,
+        meal_id,
+        HistoryAction::Create,
+        None,
+        user.as_ref(),
+    )
+    .await?;
+
+    sqlx::query!(
+        "UPDATE meal_plan SET meal = $1 WHERE id = $2",
+        meal_id,
+        plan_id
+    )
+    .execute(&mut *tx)
+    .await
+    .context("Failed to log meal plan")?;
+    history::record(
+        &mut tx,
+        HistoryEntity::MealPlan,
+        plan_id,
+        HistoryAction::Update,
+        before,
+        user.as_ref(),
+    )
+    .await?;
+
+    tx.commit().await.context("Failed to log meal plan")?;
+
+    Ok(HttpResponse::Created().json(CreateMealResponse {
+        id: meal_id,
+        date: meal_data.date,
+        time: meal_data.time,
+        notes: meal_data.notes,
+    }))
+}
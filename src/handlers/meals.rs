@@ -1,9 +1,18 @@
+use crate::auth::AuthUser;
+use crate::error::{AppError, Result, ResultExt};
+use crate::handlers::listing::{check_filters, ListBuilder};
+use crate::history;
 use crate::models::detail::{MealDetail, MealFoodSource};
+use crate::models::history::{HistoryAction, HistoryEntity};
+use crate::models::list::ListQuery;
 use crate::models::meal::{CreateMeal, CreateMealFoodSource, CreateMealResponse, Meal};
-use crate::models::{people::People, product::Product, recipe::Recipe, restaurant::Restaurant};
-use actix_web::{web, HttpResponse, Result};
+use crate::models::restaurant::{Restaurant, Visit, DEFAULT_CURRENCY};
+use crate::models::trash::TrashKind;
+use crate::models::{people::People, product::Product, recipe::Recipe};
+use crate::trash;
+use actix_web::{web, HttpResponse};
 use serde::Deserialize;
-use sqlx::PgPool;
+use sqlx::{PgPool, Postgres, Transaction};
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -21,208 +30,294 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
     .service(web::resource("/meals/batch/delete").route(web::post().to(delete_meals_batch)));
 }
 
-async fn get_meals(pool: web::Data<PgPool>) -> Result<HttpResponse> {
-    match sqlx::query_as::<_, Meal>(
-        "SELECT id, date, \"time\", notes FROM meal ORDER BY date DESC, \"time\"",
-    )
-    .fetch_all(pool.get_ref())
-    .await
-    {
-        Ok(meals) => Ok(HttpResponse::Ok().json(meals)),
-        Err(e) => {
-            log::error!("Failed to fetch meals: {}", e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch meals"
-            })))
-        }
+async fn get_meals(pool: web::Data<PgPool>, query: web::Query<ListQuery>) -> Result<HttpResponse> {
+    check_filters(
+        &query,
+        "meals",
+        &[
+            "start_date",
+            "end_date",
+            "person_id",
+            "meal_time",
+            "meal_type",
+            "location",
+        ],
+    )?;
+
+    let mut list = ListBuilder::new(
+        r#"SELECT m.id, m.date, m."time", m.notes FROM meal m"#,
+        &query,
+        Some("m.date"),
+        "m.id",
+    );
+    list.condition("m.deleted_at IS NULL");
+    if let Some(person_id) = query.person_id {
+        list.filter(
+            "EXISTS (SELECT 1 FROM meal_people mp WHERE mp.meal = m.id AND mp.people = {})",
+            person_id,
+        );
+    }
+    if let Some(meal_time) = &query.meal_time {
+        list.filter(r#"m."time" = {}"#, meal_time.as_str());
     }
+    if let Some(meal_type) = &query.meal_type {
+        list.filter(
+            r#"EXISTS (
+                SELECT 1 FROM (
+                    SELECT meal, type FROM meal_recipe
+                    UNION ALL SELECT meal, type FROM meal_product
+                    UNION ALL SELECT meal, type FROM meal_restaurant
+                ) fs
+                WHERE fs.meal = m.id AND fs.type = {})"#,
+            meal_type.as_str(),
+        );
+    }
+    if let Some(location) = &query.location {
+        list.filter(
+            r#"EXISTS (
+                SELECT 1 FROM meal_restaurant mr JOIN restaurant r ON mr.restaurant = r.id
+                WHERE mr.meal = m.id AND r.location = {})"#,
+            location.as_str(),
+        );
+    }
+
+    let page = list.fetch::<Meal>(&pool, "Failed to fetch meals").await?;
+
+    Ok(page.into_response())
 }
 
 async fn create_meal(
     pool: web::Data<PgPool>,
     meal_data: web::Json<CreateMeal>,
+    user: Option<AuthUser>,
 ) -> Result<HttpResponse> {
-    let mut tx = match pool.begin().await {
-        Ok(tx) => tx,
-        Err(e) => {
-            log::error!("Failed to start transaction: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to create meal"
-            })));
-        }
+    let mut tx = pool.begin().await.context("Failed to create meal")?;
+
+    let meal_id = insert_meal(&mut tx, &meal_data).await?;
+    history::record(
+        &mut tx,
+        HistoryEntity::Meal,
+        meal_id,
+        HistoryAction::Create,
+        None,
+        user.as_ref(),
+    )
+    .await?;
+
+    // Commit transaction
+    tx.commit().await.context("Failed to create meal")?;
+
+    // Return the created meal response
+    let response = CreateMealResponse {
+        id: meal_id,
+        date: meal_data.date,
+        time: meal_data.time.clone(),
+        notes: meal_data.notes.clone(),
     };
 
-    // Step 1: Insert the main meal record
-    let meal_result = sqlx::query!(
+    Ok(HttpResponse::Created().json(response))
+}
+
+/// Insert a meal with its food sources and people, as `POST /meals` does.
+pub(crate) async fn insert_meal(
+    tx: &mut Transaction<'_, Postgres>,
+    meal_data: &CreateMeal,
+) -> Result<i32> {
+    let meal_id = sqlx::query!(
         r#"INSERT INTO meal (date, "time", notes) VALUES ($1, $2, $3) RETURNING id"#,
         meal_data.date,
         meal_data.time,
         meal_data.notes
     )
-    .fetch_one(&mut *tx)
-    .await;
-
-    let meal_id = match meal_result {
-        Ok(row) => row.id,
-        Err(e) => {
-            log::error!("Failed to insert meal: {}", e);
-            let _ = tx.rollback().await;
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to create meal"
-            })));
-        }
-    };
+    .fetch_one(&mut **tx)
+    .await
+    .context("Failed to create meal")?
+    .id;
+
+    insert_meal_links(tx, meal_id, meal_data).await?;
+
+    Ok(meal_id)
+}
+
+async fn insert_meal_links(
+    tx: &mut Transaction<'_, Postgres>,
+    meal_id: i32,
+    meal_data: &CreateMeal,
+) -> Result<()> {
+    if meal_data.food_sources.is_empty() {
+        return Err(AppError::bad_request(
+            "A meal needs at least one food source",
+        ));
+    }
 
-    // Step 2: Insert food source relationship based on type
-    let food_source_result = match &meal_data.food_source {
-        CreateMealFoodSource::Recipe {
-            recipe_id,
-            meal_type,
-        } => {
-            sqlx::query!(
-                "INSERT INTO meal_recipe (meal, recipe, type) VALUES ($1, $2, $3)",
-                meal_id,
+    // Insert food source relationships based on type
+    for food_source in &meal_data.food_sources {
+        match food_source {
+            CreateMealFoodSource::Recipe {
                 recipe_id,
-                meal_type
-            )
-            .execute(&mut *tx)
-            .await
-        }
-        CreateMealFoodSource::Product {
-            product_id,
-            meal_type,
-        } => {
-            sqlx::query!(
-                "INSERT INTO meal_product (meal, product, type) VALUES ($1, $2, $3)",
-                meal_id,
+                meal_type,
+            } => {
+                trash::check_live(tx, TrashKind::Recipe, &[*recipe_id], "food_sources.recipe_id")
+                    .await?;
+                sqlx::query!(
+                    "INSERT INTO meal_recipe (meal, recipe, type) VALUES ($1, $2, $3)",
+                    meal_id,
+                    recipe_id,
+                    meal_type
+                )
+                .execute(&mut **tx)
+                .await
+            }
+            CreateMealFoodSource::Product {
                 product_id,
-                meal_type
-            )
-            .execute(&mut *tx)
-            .await
-        }
-        CreateMealFoodSource::Restaurant {
-            restaurant_id,
-            meal_type,
-        } => {
-            sqlx::query!(
-                "INSERT INTO meal_restaurant (meal, restaurant, type) VALUES ($1, $2, $3)",
-                meal_id,
+                meal_type,
+            } => {
+                trash::check_live(
+                    tx,
+                    TrashKind::Product,
+                    &[*product_id],
+                    "food_sources.product_id",
+                )
+                .await?;
+                sqlx::query!(
+                    "INSERT INTO meal_product (meal, product, type) VALUES ($1, $2, $3)",
+                    meal_id,
+                    product_id,
+                    meal_type
+                )
+                .execute(&mut **tx)
+                .await
+            }
+            CreateMealFoodSource::Restaurant {
                 restaurant_id,
-                meal_type
-            )
-            .execute(&mut *tx)
-            .await
+                meal_type,
+                visit,
+            } => {
+                trash::check_live(
+                    tx,
+                    TrashKind::Restaurant,
+                    &[*restaurant_id],
+                    "food_sources.restaurant_id",
+                )
+                .await?;
+                let visit = check_visit(visit)?;
+                sqlx::query!(
+                    r#"
+                    INSERT INTO meal_restaurant (meal, restaurant, type, amount, currency, rating, dishes)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)
+                    "#,
+                    meal_id,
+                    restaurant_id,
+                    meal_type,
+                    visit.amount,
+                    visit.currency,
+                    visit.rating,
+                    (!visit.dishes.is_empty()).then_some(&visit.dishes[..])
+                )
+                .execute(&mut **tx)
+                .await
+            }
         }
-    };
-
-    if let Err(e) = food_source_result {
-        log::error!("Failed to insert meal food source: {}", e);
-        let _ = tx.rollback().await;
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to create meal food source"
-        })));
+        .context("Failed to save meal food source")?;
     }
 
-    // Step 3: Insert people relationships
+    // Insert people relationships
+    trash::check_live(tx, TrashKind::Person, &meal_data.people_ids, "people_ids").await?;
     for person_id in &meal_data.people_ids {
-        if let Err(e) = sqlx::query!(
+        sqlx::query!(
             "INSERT INTO meal_people (meal, people) VALUES ($1, $2)",
             meal_id,
             person_id
         )
-        .execute(&mut *tx)
+        .execute(&mut **tx)
         .await
-        {
-            log::error!("Failed to insert meal people relationship: {}", e);
-            let _ = tx.rollback().await;
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to create meal people relationships"
-            })));
-        }
+        .context("Failed to save meal people relationships")?;
     }
 
-    // Step 4: Commit transaction
-    if let Err(e) = tx.commit().await {
-        log::error!("Failed to commit transaction: {}", e);
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to create meal"
-        })));
+    Ok(())
+}
+
+/// Validate a restaurant visit, giving amounts the default currency.
+fn check_visit(visit: &Visit) -> Result<Visit> {
+    if visit.amount.is_some_and(|amount| amount.is_sign_negative()) {
+        return Err(AppError::bad_request("amount must not be negative"));
+    }
+    if visit
+        .rating
+        .is_some_and(|rating| !(1..=5).contains(&rating))
+    {
+        return Err(AppError::bad_request("rating must be between 1 and 5"));
     }
 
-    // Return the created meal response
-    let response = CreateMealResponse {
-        id: meal_id,
-        date: meal_data.date,
-        time: meal_data.time.clone(),
-        notes: meal_data.notes.clone(),
+    let currency = match (&visit.amount, &visit.currency) {
+        (None, Some(_)) => {
+            return Err(AppError::bad_request("currency needs an amount"));
+        }
+        (None, None) => None,
+        (Some(_), None) => Some(DEFAULT_CURRENCY.to_string()),
+        (Some(_), Some(currency)) => {
+            let currency = currency.trim().to_uppercase();
+            if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+                return Err(AppError::bad_request(
+                    "currency must be a three-letter code such as USD",
+                ));
+            }
+            Some(currency)
+        }
     };
 
-    Ok(HttpResponse::Created().json(response))
+    Ok(Visit {
+        amount: visit.amount.map(|amount| amount.round_dp(2)),
+        currency,
+        rating: visit.rating,
+        dishes: visit
+            .dishes
+            .iter()
+            .map(|dish| dish.trim().to_string())
+            .filter(|dish| !dish.is_empty())
+            .collect(),
+    })
 }
 
 async fn get_meal(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse> {
     let meal_id = path.into_inner();
 
-    match sqlx::query_as::<_, Meal>("SELECT id, date, \"time\", notes FROM meal WHERE id = $1")
-        .bind(meal_id)
-        .fetch_optional(pool.get_ref())
-        .await
-    {
-        Ok(Some(meal)) => Ok(HttpResponse::Ok().json(meal)),
-        Ok(None) => Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Meal not found"
-        }))),
-        Err(e) => {
-            log::error!("Failed to fetch meal {}: {}", meal_id, e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch meal"
-            })))
-        }
-    }
+    let meal = sqlx::query_as::<_, Meal>(
+        "SELECT id, date, \"time\", notes FROM meal WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(meal_id)
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to fetch meal")?
+    .ok_or_else(|| AppError::not_found("Meal not found"))?;
+
+    Ok(HttpResponse::Ok().json(meal))
 }
 
 async fn update_meal(
     pool: web::Data<PgPool>,
     path: web::Path<i32>,
     meal_data: web::Json<CreateMeal>,
+    user: Option<AuthUser>,
 ) -> Result<HttpResponse> {
     let meal_id = path.into_inner();
 
     // Start transaction
-    let mut tx = match pool.begin().await {
-        Ok(tx) => tx,
-        Err(e) => {
-            log::error!("Failed to start transaction: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to update meal"
-            })));
-        }
-    };
+    let mut tx = pool.begin().await.context("Failed to update meal")?;
 
     // Step 1: Check if meal exists
-    let _existing_meal =
-        match sqlx::query_as::<_, Meal>("SELECT id, date, \"time\", notes FROM meal WHERE id = $1")
-            .bind(meal_id)
-            .fetch_optional(&mut *tx)
-            .await
-        {
-            Ok(Some(meal)) => meal,
-            Ok(None) => {
-                return Ok(HttpResponse::NotFound().json(serde_json::json!({
-                    "error": "Meal not found"
-                })));
-            }
-            Err(e) => {
-                log::error!("Failed to fetch meal: {}", e);
-                return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                    "error": "Failed to update meal"
-                })));
-            }
-        };
+    sqlx::query!(
+        "SELECT id FROM meal WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+        meal_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .context("Failed to update meal")?
+    .ok_or_else(|| AppError::not_found("Meal not found"))?;
+    let before = history::snapshot(&mut tx, HistoryEntity::Meal, meal_id).await?;
 
     // Step 2: Update the main meal record
-    if let Err(e) = sqlx::query!(
+    sqlx::query!(
         r#"UPDATE meal SET date = $1, "time" = $2, notes = $3 WHERE id = $4"#,
         meal_data.date,
         meal_data.time,
@@ -231,107 +326,36 @@ async fn update_meal(
     )
     .execute(&mut *tx)
     .await
-    {
-        log::error!("Failed to update meal: {}", e);
-        let _ = tx.rollback().await;
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to update meal"
-        })));
-    }
+    .context("Failed to update meal")?;
 
-    // Step 3: Remove existing food source relationships
-    // Delete from all food source tables (only one will have data)
-    let _ = sqlx::query!("DELETE FROM meal_recipe WHERE meal = $1", meal_id)
-        .execute(&mut *tx)
-        .await;
-    let _ = sqlx::query!("DELETE FROM meal_product WHERE meal = $1", meal_id)
-        .execute(&mut *tx)
-        .await;
-    let _ = sqlx::query!("DELETE FROM meal_restaurant WHERE meal = $1", meal_id)
-        .execute(&mut *tx)
-        .await;
-
-    // Step 4: Insert new food source relationship
-    let food_source_result = match &meal_data.food_source {
-        CreateMealFoodSource::Recipe {
-            recipe_id,
-            meal_type,
-        } => {
-            sqlx::query!(
-                "INSERT INTO meal_recipe (meal, recipe, type) VALUES ($1, $2, $3)",
-                meal_id,
-                recipe_id,
-                meal_type
-            )
-            .execute(&mut *tx)
-            .await
-        }
-        CreateMealFoodSource::Product {
-            product_id,
-            meal_type,
-        } => {
-            sqlx::query!(
-                "INSERT INTO meal_product (meal, product, type) VALUES ($1, $2, $3)",
-                meal_id,
-                product_id,
-                meal_type
-            )
-            .execute(&mut *tx)
-            .await
-        }
-        CreateMealFoodSource::Restaurant {
-            restaurant_id,
-            meal_type,
-        } => {
-            sqlx::query!(
-                "INSERT INTO meal_restaurant (meal, restaurant, type) VALUES ($1, $2, $3)",
-                meal_id,
-                restaurant_id,
-                meal_type
-            )
+    // Step 3: Remove existing food source and people relationships
+    for table in [
+        "meal_recipe",
+        "meal_product",
+        "meal_restaurant",
+        "meal_people",
+    ] {
+        sqlx::query(&format!("DELETE FROM {} WHERE meal = $1", table))
+            .bind(meal_id)
             .execute(&mut *tx)
             .await
-        }
-    };
-
-    if let Err(e) = food_source_result {
-        log::error!("Failed to update meal food source: {}", e);
-        let _ = tx.rollback().await;
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to update meal food source"
-        })));
+            .context("Failed to update meal")?;
     }
 
-    // Step 5: Remove existing people relationships
-    let _ = sqlx::query!("DELETE FROM meal_people WHERE meal = $1", meal_id)
-        .execute(&mut *tx)
-        .await;
-
-    // Step 6: Insert new people relationships
-    for person_id in &meal_data.people_ids {
-        if let Err(e) = sqlx::query!(
-            "INSERT INTO meal_people (meal, people) VALUES ($1, $2)",
-            meal_id,
-            person_id
-        )
-        .execute(&mut *tx)
-        .await
-        {
-            log::error!("Failed to update meal people relationship: {}", e);
-            let _ = tx.rollback().await;
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to update meal people relationships"
-            })));
-        }
-    }
+    // Step 4: Insert new food source and people relationships
+    insert_meal_links(&mut tx, meal_id, &meal_data).await?;
+    history::record(
+        &mut tx,
+        HistoryEntity::Meal,
+        meal_id,
+        HistoryAction::Update,
+        before,
+        user.as_ref(),
+    )
+    .await?;
 
-    // Step 7: Commit transaction
-    if let Err(e) = tx.commit().await {
-        log::error!("Failed to commit transaction: {}", e);
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to update meal"
-        })));
-    }
+    // Step 5: Commit transaction
+    tx.commit().await.context("Failed to update meal")?;
 
     // Return success response
     Ok(HttpResponse::Ok().json(serde_json::json!({
@@ -340,88 +364,22 @@ async fn update_meal(
     })))
 }
 
-async fn delete_meal(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse> {
+/// Move a meal to the trash, where it keeps its food sources and people.
+async fn delete_meal(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
     let meal_id = path.into_inner();
 
-    let mut tx = match pool.begin().await {
-        Ok(tx) => tx,
-        Err(e) => {
-            log::error!("Failed to start transaction: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to delete meal"
-            })));
-        }
-    };
-
-    // First check if the meal exists
-    match sqlx::query!("SELECT id FROM meal WHERE id = $1", meal_id)
-        .fetch_optional(&mut *tx)
-        .await
-    {
-        Ok(Some(_)) => {
-            // Meal exists, continue with deletion
-        }
-        Ok(None) => {
-            return Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "Meal not found"
-            })));
-        }
-        Err(e) => {
-            log::error!("Failed to check if meal exists: {}", e);
-            if let Err(rollback_err) = tx.rollback().await {
-                log::error!("Failed to rollback transaction: {}", rollback_err);
-            }
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to delete meal"
-            })));
-        }
-    }
-
-    // Delete meal relationships (CASCADE should handle this, but being explicit)
-    let relationship_tables = [
-        "meal_people",
-        "meal_recipe",
-        "meal_product",
-        "meal_restaurant",
-    ];
+    let mut tx = pool.begin().await.context("Failed to delete meal")?;
 
-    for table in relationship_tables {
-        if let Err(e) = sqlx::query(&format!("DELETE FROM {} WHERE meal = $1", table))
-            .bind(meal_id)
-            .execute(&mut *tx)
-            .await
-        {
-            log::error!("Failed to delete {} relationships: {}", table, e);
-            if let Err(rollback_err) = tx.rollback().await {
-                log::error!("Failed to rollback transaction: {}", rollback_err);
-            }
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to delete meal"
-            })));
-        }
-    }
-
-    // Delete the meal itself
-    if let Err(e) = sqlx::query!("DELETE FROM meal WHERE id = $1", meal_id)
-        .execute(&mut *tx)
-        .await
-    {
-        log::error!("Failed to delete meal: {}", e);
-        if let Err(rollback_err) = tx.rollback().await {
-            log::error!("Failed to rollback transaction: {}", rollback_err);
-        }
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to delete meal"
-        })));
+    if !trash::move_to_trash(&mut tx, TrashKind::Meal, meal_id, user.as_ref()).await? {
+        return Err(AppError::not_found("Meal not found"));
     }
 
     // Commit the transaction
-    if let Err(e) = tx.commit().await {
-        log::error!("Failed to commit transaction: {}", e);
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to delete meal"
-        })));
-    }
+    tx.commit().await.context("Failed to delete meal")?;
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "message": "Meal deleted successfully"
@@ -432,125 +390,123 @@ async fn get_meal_details(pool: web::Data<PgPool>, path: web::Path<i32>) -> Resu
     let meal_id = path.into_inner();
 
     // First get the meal basic info
-    let meal_query = sqlx::query!(
-        r#"SELECT id, date, "time", notes FROM meal WHERE id = $1"#,
+    let meal = sqlx::query!(
+        r#"SELECT id, date, "time", notes FROM meal WHERE id = $1 AND deleted_at IS NULL"#,
         meal_id
     )
     .fetch_optional(pool.get_ref())
-    .await;
-
-    let meal = match meal_query {
-        Ok(Some(meal)) => meal,
-        Ok(None) => {
-            return Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "Meal not found"
-            })));
-        }
-        Err(e) => {
-            log::error!("Failed to fetch meal {}: {}", meal_id, e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch meal"
-            })));
-        }
-    };
+    .await
+    .context("Failed to fetch meal")?
+    .ok_or_else(|| AppError::not_found("Meal not found"))?;
 
-    // Get food source (recipe, product, or restaurant)
-    let food_source_result = sqlx::query!(
+    // Get every food source: recipes, then products, then restaurants
+    let context = "Failed to fetch meal details";
+    let recipes = sqlx::query!(
         r#"
-        SELECT 
-            CASE 
-                WHEN mr.meal IS NOT NULL THEN 'recipe'
-                WHEN mp.meal IS NOT NULL THEN 'product' 
-                WHEN mrt.meal IS NOT NULL THEN 'restaurant'
-            END as food_source_type,
-            COALESCE(mr.type, mp.type, mrt.type) as meal_type,
-            r.id as "recipe_id?", r.name as "recipe_name?", r.ingredients as "ingredients?", r.procedure as "procedure?", r.cautions as "cautions?",
-            p.id as "product_id?", p.name as "product_name?",
-            rt.id as "restaurant_id?", rt.name as "restaurant_name?", rt.location as "location?", rt.type as "restaurant_type?", rt.price as "price?"
-        FROM meal m
-        LEFT JOIN meal_recipe mr ON m.id = mr.meal
-        LEFT JOIN recipe r ON mr.recipe = r.id
-        LEFT JOIN meal_product mp ON m.id = mp.meal
-        LEFT JOIN product p ON mp.product = p.id  
-        LEFT JOIN meal_restaurant mrt ON m.id = mrt.meal
-        LEFT JOIN restaurant rt ON mrt.restaurant = rt.id
-        WHERE m.id = $1
+        SELECT r.id, r.name, r.ingredients, r.procedure, r.cautions, r.servings, mr.type AS meal_type
+        FROM meal_recipe mr
+        JOIN recipe r ON mr.recipe = r.id
+        WHERE mr.meal = $1
+        ORDER BY r.name
         "#,
         meal_id
     )
-    .fetch_optional(pool.get_ref())
-    .await;
-
-    let food_source = match food_source_result {
-        Ok(Some(row)) => match row.food_source_type.as_deref() {
-            Some("recipe") if row.recipe_id.is_some() => Some(MealFoodSource::Recipe {
-                recipe: Recipe {
-                    id: row.recipe_id.unwrap(),
-                    name: row.recipe_name.unwrap(),
-                    ingredients: row.ingredients.unwrap(),
-                    procedure: row.procedure.unwrap(),
-                    cautions: row.cautions,
-                },
-                meal_type: row.meal_type.unwrap_or_else(|| "unknown".to_string()),
-            }),
-            Some("product") if row.product_id.is_some() => Some(MealFoodSource::Product {
-                product: Product {
-                    id: row.product_id.unwrap(),
-                    name: row.product_name.unwrap(),
-                },
-                meal_type: row.meal_type.unwrap_or_else(|| "unknown".to_string()),
-            }),
-            Some("restaurant") if row.restaurant_id.is_some() => Some(MealFoodSource::Restaurant {
-                restaurant: Restaurant {
-                    id: row.restaurant_id.unwrap(),
-                    name: row.restaurant_name.unwrap(),
-                    location: row.location.unwrap(),
-                    food_type: row.restaurant_type.unwrap(),
-                    price: row.price,
-                },
-                meal_type: row.meal_type.unwrap_or_else(|| "unknown".to_string()),
-            }),
-            _ => None,
-        },
-        Ok(None) => None,
-        Err(e) => {
-            log::error!("Failed to fetch meal food source {}: {}", meal_id, e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch meal details"
-            })));
-        }
-    };
+    .fetch_all(pool.get_ref())
+    .await
+    .context(context)?;
+
+    let products = sqlx::query!(
+        r#"
+        SELECT p.id, p.name, mp.type AS meal_type
+        FROM meal_product mp
+        JOIN product p ON mp.product = p.id
+        WHERE mp.meal = $1
+        ORDER BY p.name
+        "#,
+        meal_id
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context(context)?;
+
+    let restaurants = sqlx::query!(
+        r#"
+        SELECT rt.id, rt.name, rt.location, rt.type AS food_type, rt.price, mrt.type AS meal_type,
+               mrt.amount, mrt.currency, mrt.rating, COALESCE(mrt.dishes, '{}') AS "dishes!"
+        FROM meal_restaurant mrt
+        JOIN restaurant rt ON mrt.restaurant = rt.id
+        WHERE mrt.meal = $1
+        ORDER BY rt.name
+        "#,
+        meal_id
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context(context)?;
+
+    let food_sources = recipes
+        .into_iter()
+        .map(|row| MealFoodSource::Recipe {
+            recipe: Recipe {
+                id: row.id,
+                name: row.name,
+                ingredients: row.ingredients,
+                procedure: row.procedure,
+                cautions: row.cautions,
+                servings: row.servings,
+            },
+            meal_type: row.meal_type,
+        })
+        .chain(products.into_iter().map(|row| MealFoodSource::Product {
+            product: Product {
+                id: row.id,
+                name: row.name,
+            },
+            meal_type: row.meal_type,
+        }))
+        .chain(
+            restaurants
+                .into_iter()
+                .map(|row| MealFoodSource::Restaurant {
+                    restaurant: Restaurant {
+                        id: row.id,
+                        name: row.name,
+                        location: row.location,
+                        food_type: row.food_type,
+                        price: row.price,
+                    },
+                    meal_type: row.meal_type,
+                    visit: Visit {
+                        amount: row.amount,
+                        currency: row.currency,
+                        rating: row.rating,
+                        dishes: row.dishes,
+                    },
+                }),
+        )
+        .collect();
 
     // Get people associated with this meal
-    let people_result = sqlx::query_as::<_, People>(
+    let people = sqlx::query_as::<_, People>(
         r#"
-        SELECT p.id, p.name, p.notes
+        SELECT p.id, p.name, p.notes, p.household_order
         FROM people p
         JOIN meal_people mp ON p.id = mp.people
-        WHERE mp.meal = $1
+        WHERE mp.meal = $1 AND p.deleted_at IS NULL
         ORDER BY p.name
         "#,
     )
     .bind(meal_id)
     .fetch_all(pool.get_ref())
-    .await;
-
-    let people = match people_result {
-        Ok(people) => people,
-        Err(e) => {
-            log::error!("Failed to fetch meal people {}: {}", meal_id, e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch meal details"
-            })));
-        }
-    };
+    .await
+    .context(context)?;
 
     let meal_detail = MealDetail {
         id: meal.id,
         date: meal.date,
         time: meal.time,
         notes: meal.notes,
-        food_source,
+        food_sources,
         people,
     };
 
@@ -565,104 +521,26 @@ pub struct BatchDeleteMealsRequest {
 async fn delete_meals_batch(
     pool: web::Data<PgPool>,
     request: web::Json<BatchDeleteMealsRequest>,
+    user: Option<AuthUser>,
 ) -> Result<HttpResponse> {
     if request.meal_ids.is_empty() {
-        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "No meal IDs provided"
-        })));
+        return Err(AppError::bad_request("No meal IDs provided"));
     }
 
-    let mut tx = match pool.begin().await {
-        Ok(tx) => tx,
-        Err(e) => {
-            log::error!("Failed to start transaction: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to delete meals"
-            })));
-        }
-    };
+    let mut tx = pool.begin().await.context("Failed to delete meals")?;
 
     let mut deleted_count = 0;
 
     for meal_id in &request.meal_ids {
-        // Check if meal exists
-        let meal_exists = match sqlx::query!("SELECT id FROM meal WHERE id = $1", meal_id)
-            .fetch_optional(&mut *tx)
-            .await
-        {
-            Ok(Some(_)) => true,
-            Ok(None) => {
-                log::warn!("Meal ID {} not found, skipping", meal_id);
-                continue;
-            }
-            Err(e) => {
-                log::error!("Failed to check if meal {} exists: {}", meal_id, e);
-                if let Err(rollback_err) = tx.rollback().await {
-                    log::error!("Failed to rollback transaction: {}", rollback_err);
-                }
-                return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                    "error": "Failed to delete meals"
-                })));
-            }
-        };
-
-        if !meal_exists {
-            continue;
+        if trash::move_to_trash(&mut tx, TrashKind::Meal, *meal_id, user.as_ref()).await? {
+            deleted_count += 1;
+        } else {
+            log::warn!("Meal ID {} not found, skipping", meal_id);
         }
-
-        // Delete meal relationships
-        let relationship_tables = [
-            "meal_people",
-            "meal_recipe",
-            "meal_product",
-            "meal_restaurant",
-        ];
-
-        for table in relationship_tables {
-            if let Err(e) = sqlx::query(&format!("DELETE FROM {} WHERE meal = $1", table))
-                .bind(meal_id)
-                .execute(&mut *tx)
-                .await
-            {
-                log::error!(
-                    "Failed to delete {} relationships for meal {}: {}",
-                    table,
-                    meal_id,
-                    e
-                );
-                if let Err(rollback_err) = tx.rollback().await {
-                    log::error!("Failed to rollback transaction: {}", rollback_err);
-                }
-                return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                    "error": "Failed to delete meals"
-                })));
-            }
-        }
-
-        // Delete the meal itself
-        if let Err(e) = sqlx::query!("DELETE FROM meal WHERE id = $1", meal_id)
-            .execute(&mut *tx)
-            .await
-        {
-            log::error!("Failed to delete meal {}: {}", meal_id, e);
-            if let Err(rollback_err) = tx.rollback().await {
-                log::error!("Failed to rollback transaction: {}", rollback_err);
-            }
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to delete meals"
-            })));
-        }
-
-        deleted_count += 1;
     }
 
     // Commit the transaction
-    if let Err(e) = tx.commit().await {
-        log::error!("Failed to commit transaction: {}", e);
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to delete meals"
-        })));
-    }
+    tx.commit().await.context("Failed to delete meals")?;
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "message": format!("{} meals deleted successfully", deleted_count),
@@ -0,0 +1,383 @@
+use crate::auth::AuthUser;
+use crate::error::{AppError, Result, ResultExt};
+use crate::history;
+use crate::import::{normalize, MATCH_THRESHOLD};
+use crate::models::history::{HistoryAction, HistoryEntity};
+use crate::models::merge::{DuplicatePair, DuplicateRecord, MergeReport, MergeRequest};
+use crate::models::trash::TrashKind;
+use serde_json::Value;
+use sqlx::{PgPool, Postgres, Transaction};
+
+/// How much each shared attribute adds to the name similarity of a pair.
+const SHARED_BONUS: f64 = 0.1;
+
+/// What happens to a duplicate's link when the survivor has one like it.
+pub enum Collision {
+    /// `owner` and the column form a key: the duplicate's link is dropped.
+    Drop,
+    /// Links are keyed otherwise and always move.
+    Move,
+    /// The rows describe the record itself: they move only if the survivor
+    /// has none.
+    KeepSurvivor,
+}
+
+/// A column in another table that points at the merged records.
+pub struct Link {
+    pub table: &'static str,
+    pub column: &'static str,
+    /// The column naming the row the link belongs to.
+    pub owner: &'static str,
+    /// The kind of record `owner` is, when its history images include the link.
+    pub entity: Option<HistoryEntity>,
+    pub collision: Collision,
+}
+
+/// A record kind whose duplicates can be found and merged, together with every
+/// link pointing at it. Like vocabularies, merges rewrite all of them in one
+/// transaction.
+pub struct Mergeable {
+    pub kind: TrashKind,
+    /// Columns compared when proposing duplicates, besides the name.
+    pub attributes: &'static [&'static str],
+    /// Nullable columns the survivor takes from a duplicate when it has none.
+    pub fill: &'static [&'static str],
+    pub links: &'static [Link],
+    /// The key under which template bodies refer to the record.
+    pub template_key: &'static str,
+}
+
+impl Mergeable {
+    /// Pairs of live records whose names are alike, best first.
+    pub async fn duplicates(&self, pool: &PgPool) -> Result<Vec<DuplicatePair>> {
+        let attributes = self
+            .attributes
+            .iter()
+            .map(|column| format!("{column}::text"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let rows: Vec<(i32, String, Vec<Option<String>>)> = sqlx::query_as(&format!(
+            "SELECT id, name, ARRAY[{attributes}]::text[] FROM {} WHERE deleted_at IS NULL ORDER BY id",
+            self.kind.table()
+        ))
+        .fetch_all(pool)
+        .await
+        .context("Failed to find duplicates")?;
+
+        Ok(find_duplicates(&rows, self.attributes))
+    }
+
+    /// Fold `duplicate_ids` into `survivor_id`: every link moves to the
+    /// survivor and the duplicates are deleted for good.
+    pub async fn merge(
+        &self,
+        pool: &PgPool,
+        request: &MergeRequest,
+        actor: Option<&AuthUser>,
+    ) -> Result<MergeReport> {
+        let survivor = request.survivor_id;
+        let mut duplicates = request.duplicate_ids.clone();
+        duplicates.sort_unstable();
+        duplicates.dedup();
+        if duplicates.is_empty() {
+            return Err(AppError::bad_request("Nothing to merge"));
+        }
+        if duplicates.contains(&survivor) {
+            return Err(AppError::bad_request(format!(
+                "Cannot merge {} {} into itself",
+                self.kind.label().to_lowercase(),
+                survivor
+            )));
+        }
+
+        let context = format!("Failed to merge {}", self.kind.label().to_lowercase());
+        let table = self.kind.table();
+        let entity = HistoryEntity::from(self.kind);
+        let mut tx = pool.begin().await.context(&context)?;
+
+        let mut ids = duplicates.clone();
+        ids.push(survivor);
+        let live: Vec<i32> = sqlx::query_scalar(&format!(
+            "SELECT id FROM {table} WHERE id = ANY($1) AND deleted_at IS NULL ORDER BY id FOR UPDATE"
+        ))
+        .bind(&ids)
+        .fetch_all(&mut *tx)
+        .await
+        .context(&context)?;
+        if let Some(missing) = ids.iter().find(|id| !live.contains(id)) {
+            return Err(AppError::not_found(format!(
+                "{} {} not found",
+                self.kind.label(),
+                missing
+            )));
+        }
+
+        // Everything whose image changes, as it was before
+        let mut changed = vec![(entity, survivor)];
+        changed.extend(duplicates.iter().map(|id| (entity, *id)));
+        for link in self.links {
+            let Some(owner_entity) = link.entity else {
+                continue;
+            };
+            let owners: Vec<i32> = sqlx::query_scalar(&format!(
+                "SELECT DISTINCT {owner} FROM {table} WHERE {column} = ANY($1) ORDER BY 1",
+                owner = link.owner,
+                table = link.table,
+                column = link.column
+            ))
+            .bind(&duplicates)
+            .fetch_all(&mut *tx)
+            .await
+            .context(&context)?;
+            for owner in owners {
+                if !changed.contains(&(owner_entity, owner)) {
+                    changed.push((owner_entity, owner));
+                }
+            }
+        }
+        let templates = self
+            .rewire_templates(&mut tx, &duplicates, survivor, &context)
+            .await?;
+        changed.extend(
+            templates
+                .iter()
+                .map(|(id, _)| (HistoryEntity::Template, *id)),
+        );
+
+        let mut before = Vec::new();
+        for (entity, id) in &changed {
+            before.push(history::snapshot(&mut tx, *entity, *id).await?);
+        }
+
+        for (id, body) in &templates {
+            sqlx::query("UPDATE template SET body = $1 WHERE id = $2")
+                .bind(body)
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .context(&context)?;
+        }
+
+        for column in self.fill {
+            sqlx::query(&format!(
+                r#"
+                UPDATE {table} SET {column} = (
+                    SELECT d.{column} FROM {table} d
+                    WHERE d.id = ANY($2) AND d.{column} IS NOT NULL
+                    ORDER BY d.id LIMIT 1
+                )
+                WHERE id = $1 AND {column} IS NULL
+                "#
+            ))
+            .bind(survivor)
+            .bind(&duplicates)
+            .execute(&mut *tx)
+            .await
+            .context(&context)?;
+        }
+
+        let mut moved = 0;
+        let mut dropped = 0;
+        for link in self.links {
+            // One duplicate at a time, so two of them linked from the same
+            // owner cannot collide with each other
+            for duplicate in &duplicates {
+                let (link_moved, link_dropped) =
+                    move_link(&mut tx, link, *duplicate, survivor, &context).await?;
+                moved += link_moved;
+                dropped += link_dropped;
+            }
+        }
+
+        sqlx::query(&format!("DELETE FROM {table} WHERE id = ANY($1)"))
+            .bind(&duplicates)
+            .execute(&mut *tx)
+            .await
+            .context(&context)?;
+
+        for ((entity, id), before) in changed.into_iter().zip(before) {
+            let action = if duplicates.contains(&id) && entity == HistoryEntity::from(self.kind) {
+                HistoryAction::Purge
+            } else {
+                HistoryAction::Update
+            };
+            history::record(&mut tx, entity, id, action, before, actor).await?;
+        }
+
+        tx.commit().await.context(&context)?;
+
+        Ok(MergeReport {
+            survivor_id: survivor,
+            merged_ids: duplicates,
+            moved,
+            dropped,
+            templates: templates.len() as u64,
+        })
+    }
+
+    /// Template bodies that refer to a duplicate, rewritten to the survivor.
+    async fn rewire_templates(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        duplicates: &[i32],
+        survivor: i32,
+        context: &str,
+    ) -> Result<Vec<(i32, Value)>> {
+        let rows: Vec<(i32, Value)> =
+            sqlx::query_as("SELECT id, body FROM template ORDER BY id FOR UPDATE")
+                .fetch_all(&mut **tx)
+                .await
+                .context(context)?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(id, mut body)| {
+                rewire_body(&mut body, self.template_key, duplicates, survivor)
+                    .then_some((id, body))
+            })
+            .collect())
+    }
+}
+
+/// Move the links of `duplicate` to `survivor`, returning how many moved and
+/// how many were dropped.
+async fn move_link(
+    tx: &mut Transaction<'_, Postgres>,
+    link: &Link,
+    duplicate: i32,
+    survivor: i32,
+    context: &str,
+) -> Result<(u64, u64)> {
+    let table = link.table;
+    let column = link.column;
+    let owner = link.owner;
+
+    let condition = match link.collision {
+        Collision::Move => String::new(),
+        Collision::Drop => format!(
+            "AND NOT EXISTS (SELECT 1 FROM {table} s WHERE s.{owner} = l.{owner} AND s.{column} = $2)"
+        ),
+        Collision::KeepSurvivor => {
+            format!("AND NOT EXISTS (SELECT 1 FROM {table} s WHERE s.{column} = $2)")
+        }
+    };
+    let moved = sqlx::query(&format!(
+        "UPDATE {table} l SET {column} = $2 WHERE {column} = $1 {condition}"
+    ))
+    .bind(duplicate)
+    .bind(survivor)
+    .execute(&mut **tx)
+    .await
+    .context(context)?
+    .rows_affected();
+
+    let dropped = sqlx::query(&format!("DELETE FROM {table} WHERE {column} = $1"))
+        .bind(duplicate)
+        .execute(&mut **tx)
+        .await
+        .context(context)?
+        .rows_affected();
+
+    Ok((moved, dropped))
+}
+
+/// Point every `key` in a template body that names a duplicate at the
+/// survivor, whether it holds one id or a list of them. Returns whether
+/// anything changed.
+pub fn rewire_body(body: &mut Value, key: &str, duplicates: &[i32], survivor: i32) -> bool {
+    let is_duplicate = |value: &Value| {
+        value
+            .as_i64()
+            .is_some_and(|id| duplicates.iter().any(|duplicate| *duplicate as i64 == id))
+    };
+
+    let mut changed = false;
+    match body {
+        Value::Object(fields) => {
+            for (field, value) in fields.iter_mut() {
+                if field != key {
+                    changed |= rewire_body(value, key, duplicates, survivor);
+                } else if let Value::Array(ids) = value {
+                    if ids.iter().any(is_duplicate) {
+                        let mut rewired: Vec<Value> = Vec::new();
+                        for id in ids.iter() {
+                            let id = if is_duplicate(id) {
+                                Value::from(survivor)
+                            } else {
+                                id.clone()
+                            };
+                            if !rewired.contains(&id) {
+                                rewired.push(id);
+                            }
+                        }
+                        *ids = rewired;
+                        changed = true;
+                    }
+                } else if is_duplicate(value) {
+                    *value = Value::from(survivor);
+                    changed = true;
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                changed |= rewire_body(item, key, duplicates, survivor);
+            }
+        }
+        _ => {}
+    }
+    changed
+}
+
+/// Propose pairs among `(id, name, attribute values)` rows. A pair is proposed
+/// when its name similarity, raised by `SHARED_BONUS` for each attribute both
+/// records share, reaches `MATCH_THRESHOLD`.
+pub fn find_duplicates(
+    records: &[(i32, String, Vec<Option<String>>)],
+    attributes: &[&str],
+) -> Vec<DuplicatePair> {
+    let keys: Vec<String> = records.iter().map(|(_, name, _)| normalize(name)).collect();
+
+    let mut pairs = Vec::new();
+    for (i, (first_id, first_name, first_values)) in records.iter().enumerate() {
+        for (j, (second_id, second_name, second_values)) in records.iter().enumerate().skip(i + 1) {
+            let similarity = if keys[i] == keys[j] {
+                1.0
+            } else {
+                strsim::normalized_levenshtein(&keys[i], &keys[j])
+            };
+            let shared: Vec<String> = attributes
+                .iter()
+                .zip(first_values.iter().zip(second_values))
+                .filter(|(_, (a, b))| a.is_some() && a == b)
+                .map(|(attribute, _)| attribute.to_string())
+                .collect();
+            let score = (similarity + SHARED_BONUS * shared.len() as f64).min(1.0);
+
+            if score >= MATCH_THRESHOLD {
+                pairs.push(DuplicatePair {
+                    first: DuplicateRecord {
+                        id: *first_id,
+                        name: first_name.clone(),
+                    },
+                    second: DuplicateRecord {
+                        id: *second_id,
+                        name: second_name.clone(),
+                    },
+                    similarity,
+                    shared,
+                    score,
+                });
+            }
+        }
+    }
+
+    pairs.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(b.similarity.total_cmp(&a.similarity))
+            .then(a.first.id.cmp(&b.first.id))
+            .then(a.second.id.cmp(&b.second.id))
+    });
+    pairs
+}
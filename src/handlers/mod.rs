@@ -1,13 +1,29 @@
 pub mod activities;
 pub mod activity_types;
+pub mod auth;
+pub mod calendar;
 pub mod daily_summary;
 pub mod drink_options;
 pub mod drinks;
 pub mod events;
+pub mod export;
 pub mod food_types;
+pub mod history;
+pub mod import;
+mod listing;
 pub mod locations;
+pub mod meal_plans;
 pub mod meals;
+pub mod merge;
 pub mod people;
 pub mod products;
+pub mod quick_log;
 pub mod recipes;
 pub mod restaurants;
+pub mod search;
+pub mod shopping_list;
+pub mod stats;
+pub mod suggestions;
+pub mod templates;
+pub mod trash;
+mod vocabulary;
@@ -1,7 +1,132 @@
-use crate::models::people::People;
-use actix_web::{web, HttpResponse, Result};
+use crate::auth::AuthUser;
+use crate::error::{AppError, Result, ResultExt};
+use crate::handlers::listing::{check_filters, ListBuilder};
+use crate::handlers::merge::{Collision, Link, Mergeable};
+use crate::history;
+use crate::models::history::{HistoryAction, HistoryEntity};
+use crate::models::import::PeopleAlias;
+use crate::models::list::ListQuery;
+use crate::models::merge::MergeRequest;
+use crate::models::people::{
+    ActivityTogether, MeetupGap, MonthlyCount, People, PersonSummary, TimelineItem, UpdateHousehold,
+};
+use crate::models::stats::RestaurantVisits;
+use crate::models::trash::TrashKind;
+use crate::trash;
+use actix_web::{web, HttpResponse};
+use chrono::NaiveDate;
 use serde::Deserialize;
-use sqlx::PgPool;
+use sqlx::{PgPool, Postgres, Transaction};
+
+const FAVORITES_LIMIT: i64 = 5;
+
+// Meals, events and drinks shared with `t.person`. `seq` gives every row a unique
+// key across the three kinds for keyset pagination.
+const TIMELINE: &str = r#"
+    SELECT t.kind, t.id, t.date, t.title, t.detail, t.link, t.seq FROM (
+        SELECT
+            'meal' AS kind,
+            m.id,
+            m.date,
+            m."time" || COALESCE(': ' || (
+                SELECT string_agg(fs.name, ', ') FROM (
+                    SELECT r.name FROM meal_recipe mr JOIN recipe r ON mr.recipe = r.id WHERE mr.meal = m.id
+                    UNION ALL SELECT p.name FROM meal_product mpr JOIN product p ON mpr.product = p.id WHERE mpr.meal = m.id
+                    UNION ALL SELECT rt.name FROM meal_restaurant mrt JOIN restaurant rt ON mrt.restaurant = rt.id WHERE mrt.meal = m.id
+                ) fs
+            ), '') AS title,
+            m.notes AS detail,
+            '/api/v1/meals/' || m.id || '/details' AS link,
+            mp.people AS person,
+            m.id::bigint * 3 AS seq
+        FROM meal m
+        JOIN meal_people mp ON mp.meal = m.id
+        WHERE m.deleted_at IS NULL
+        UNION ALL
+        SELECT
+            'event',
+            e.id,
+            e.date,
+            a.name,
+            NULLIF(CONCAT_WS(' ', e.measure, e.location, e.notes), ''),
+            '/api/v1/events/' || e.id || '/details',
+            ep.people,
+            e.id::bigint * 3 + 1
+        FROM event e
+        JOIN activity a ON e.activity = a.id
+        JOIN event_people ep ON ep.event = e.id
+        WHERE e.deleted_at IS NULL
+        UNION ALL
+        SELECT
+            'drink',
+            d.id,
+            d.date,
+            d.name,
+            NULL::text,
+            '/api/v1/drinks/' || d.id || '/details',
+            dp.people,
+            d.id::bigint * 3 + 2
+        FROM drink d
+        JOIN drink_people dp ON dp.drink = d.id
+        WHERE d.deleted_at IS NULL
+    ) t
+"#;
+
+// Distinct dates with anything shared with the person given as $1.
+const MEETUP_DATES: &str = r#"
+    SELECT m.date FROM meal m JOIN meal_people mp ON mp.meal = m.id
+    WHERE mp.people = $1 AND m.deleted_at IS NULL
+    UNION
+    SELECT e.date FROM event e JOIN event_people ep ON ep.event = e.id
+    WHERE ep.people = $1 AND e.deleted_at IS NULL
+    UNION
+    SELECT d.date FROM drink d JOIN drink_people dp ON dp.drink = d.id
+    WHERE dp.people = $1 AND d.deleted_at IS NULL
+"#;
+
+const PEOPLE: Mergeable = Mergeable {
+    kind: TrashKind::Person,
+    attributes: &[],
+    fill: &["notes", "household_order"],
+    links: &[
+        Link {
+            table: "meal_people",
+            column: "people",
+            owner: "meal",
+            entity: Some(HistoryEntity::Meal),
+            collision: Collision::Drop,
+        },
+        Link {
+            table: "event_people",
+            column: "people",
+            owner: "event",
+            entity: Some(HistoryEntity::Event),
+            collision: Collision::Drop,
+        },
+        Link {
+            table: "drink_people",
+            column: "people",
+            owner: "drink",
+            entity: Some(HistoryEntity::Drink),
+            collision: Collision::Drop,
+        },
+        Link {
+            table: "meal_plan_people",
+            column: "people",
+            owner: "plan",
+            entity: Some(HistoryEntity::MealPlan),
+            collision: Collision::Drop,
+        },
+        Link {
+            table: "people_alias",
+            column: "people",
+            owner: "alias",
+            entity: None,
+            collision: Collision::Drop,
+        },
+    ],
+    template_key: "people_ids",
+};
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -9,27 +134,267 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
             .route(web::get().to(get_people))
             .route(web::post().to(create_person)),
     )
+    // Registered before /people/{id} so "household", "aliases", "duplicates" and "merge" are
+    // not parsed as ids
+    .service(
+        web::resource("/people/household")
+            .route(web::get().to(get_household))
+            .route(web::put().to(update_household)),
+    )
+    .service(
+        web::resource("/people/aliases")
+            .route(web::get().to(get_aliases))
+            .route(web::put().to(set_alias)),
+    )
+    .service(web::resource("/people/aliases/{alias}").route(web::delete().to(delete_alias)))
+    .service(web::resource("/people/duplicates").route(web::get().to(get_duplicates)))
+    .service(web::resource("/people/merge").route(web::post().to(merge_people)))
     .service(
         web::resource("/people/{id}")
             .route(web::get().to(get_person))
             .route(web::put().to(update_person))
             .route(web::delete().to(delete_person)),
+    )
+    .service(web::resource("/people/{id}/timeline").route(web::get().to(get_person_timeline)))
+    .service(web::resource("/people/{id}/summary").route(web::get().to(get_person_summary)));
+}
+
+async fn get_people(pool: web::Data<PgPool>, query: web::Query<ListQuery>) -> Result<HttpResponse> {
+    check_filters(&query, "people", &[])?;
+
+    let mut list = ListBuilder::new(
+        "SELECT id, name, notes, household_order FROM people",
+        &query,
+        None,
+        "id",
     );
+    list.condition("deleted_at IS NULL");
+
+    let page = list
+        .fetch::<People>(&pool, "Failed to fetch people")
+        .await?;
+
+    Ok(page.into_response())
 }
 
-async fn get_people(pool: web::Data<PgPool>) -> Result<HttpResponse> {
-    match sqlx::query_as::<_, People>("SELECT id, name, notes FROM people ORDER BY id")
-        .fetch_all(pool.get_ref())
-        .await
-    {
-        Ok(people) => Ok(HttpResponse::Ok().json(people)),
-        Err(e) => {
-            log::error!("Failed to fetch people: {}", e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch people"
-            })))
+async fn get_household(pool: web::Data<PgPool>) -> Result<HttpResponse> {
+    let members = sqlx::query_as::<_, People>(
+        r#"
+        SELECT id, name, notes, household_order FROM people
+        WHERE household_order IS NOT NULL AND deleted_at IS NULL
+        ORDER BY household_order, name
+        "#,
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to fetch household")?;
+
+    Ok(HttpResponse::Ok().json(members))
+}
+
+/// Replace the household with `people_ids`, numbering members in the given order.
+async fn update_household(
+    pool: web::Data<PgPool>,
+    household: web::Json<UpdateHousehold>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let people_ids = &household.people_ids;
+    for (i, id) in people_ids.iter().enumerate() {
+        if people_ids[..i].contains(id) {
+            return Err(AppError::bad_request(format!(
+                "Person {} is listed more than once",
+                id
+            )));
         }
     }
+
+    let mut tx = pool.begin().await.context("Failed to update household")?;
+
+    let found: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM people WHERE id = ANY($1) AND deleted_at IS NULL")
+            .bind(people_ids)
+            .fetch_one(&mut *tx)
+            .await
+            .context("Failed to update household")?;
+    if found != people_ids.len() as i64 {
+        return Err(AppError::InvalidReference {
+            field: "people_ids".to_string(),
+            message: "Unknown person in people_ids".to_string(),
+        });
+    }
+
+    // array_position is NULL for everyone not listed
+    let changed: Vec<i32> = sqlx::query_scalar(
+        "SELECT id FROM people WHERE household_order IS DISTINCT FROM array_position($1::int[], id) ORDER BY id FOR UPDATE",
+    )
+    .bind(people_ids)
+    .fetch_all(&mut *tx)
+    .await
+    .context("Failed to update household")?;
+    let before = snapshot_people(&mut tx, &changed).await?;
+
+    sqlx::query(
+        "UPDATE people SET household_order = array_position($1::int[], id) WHERE id = ANY($2)",
+    )
+    .bind(people_ids)
+    .bind(&changed)
+    .execute(&mut *tx)
+    .await
+    .context("Failed to update household")?;
+    record_people(&mut tx, before, user.as_ref()).await?;
+
+    tx.commit().await.context("Failed to update household")?;
+
+    get_household(pool).await
+}
+
+async fn get_aliases(pool: web::Data<PgPool>) -> Result<HttpResponse> {
+    let rows: Vec<(String, Vec<i32>)> = sqlx::query_as(
+        r#"
+        SELECT pa.alias, array_agg(pa.people ORDER BY pa.people)
+        FROM people_alias pa
+        JOIN people p ON p.id = pa.people AND p.deleted_at IS NULL
+        GROUP BY pa.alias
+        ORDER BY pa.alias
+        "#,
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to fetch aliases")?;
+
+    let aliases: Vec<PeopleAlias> = rows
+        .into_iter()
+        .map(|(alias, people_ids)| PeopleAlias { alias, people_ids })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(aliases))
+}
+
+/// Point an alias at the given people, replacing whatever it stood for before.
+async fn set_alias(
+    pool: web::Data<PgPool>,
+    alias_data: web::Json<PeopleAlias>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let alias = alias_data.alias.trim();
+    if alias.is_empty() {
+        return Err(AppError::bad_request("Alias must not be empty"));
+    }
+    if alias_data.people_ids.is_empty() {
+        return Err(AppError::bad_request("No people IDs provided"));
+    }
+
+    let mut tx = pool.begin().await.context("Failed to save alias")?;
+    trash::check_live(
+        &mut tx,
+        TrashKind::Person,
+        &alias_data.people_ids,
+        "people_ids",
+    )
+    .await?;
+
+    let changed: Vec<i32> = sqlx::query_scalar(
+        r#"
+        SELECT id FROM people
+        WHERE id = ANY($2) OR id IN (SELECT people FROM people_alias WHERE alias = $1)
+        ORDER BY id
+        "#,
+    )
+    .bind(alias)
+    .bind(&alias_data.people_ids)
+    .fetch_all(&mut *tx)
+    .await
+    .context("Failed to save alias")?;
+    let before = snapshot_people(&mut tx, &changed).await?;
+
+    sqlx::query("DELETE FROM people_alias WHERE alias = $1")
+        .bind(alias)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to save alias")?;
+    sqlx::query(
+        "INSERT INTO people_alias (alias, people) SELECT $1, id FROM unnest($2::int[]) AS t(id) ON CONFLICT DO NOTHING",
+    )
+    .bind(alias)
+    .bind(&alias_data.people_ids)
+    .execute(&mut *tx)
+    .await
+    .context("Failed to save alias")?;
+    record_people(&mut tx, before, user.as_ref()).await?;
+
+    tx.commit().await.context("Failed to save alias")?;
+
+    Ok(HttpResponse::Ok().json(PeopleAlias {
+        alias: alias.to_string(),
+        people_ids: alias_data.people_ids.clone(),
+    }))
+}
+
+async fn delete_alias(
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let alias = path.into_inner();
+    let mut tx = pool.begin().await.context("Failed to delete alias")?;
+
+    let changed: Vec<i32> =
+        sqlx::query_scalar("SELECT people FROM people_alias WHERE alias = $1 ORDER BY people")
+            .bind(&alias)
+            .fetch_all(&mut *tx)
+            .await
+            .context("Failed to delete alias")?;
+    if changed.is_empty() {
+        return Err(AppError::not_found("Alias not found"));
+    }
+    let before = snapshot_people(&mut tx, &changed).await?;
+
+    sqlx::query("DELETE FROM people_alias WHERE alias = $1")
+        .bind(&alias)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete alias")?;
+    record_people(&mut tx, before, user.as_ref()).await?;
+
+    tx.commit().await.context("Failed to delete alias")?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Alias deleted successfully"
+    })))
+}
+
+/// Images of people about to be changed together, for `record_people`.
+async fn snapshot_people(
+    tx: &mut Transaction<'_, Postgres>,
+    people_ids: &[i32],
+) -> Result<Vec<(i32, Option<serde_json::Value>)>> {
+    let mut images = Vec::new();
+    for id in people_ids {
+        images.push((
+            *id,
+            history::snapshot(tx, HistoryEntity::Person, *id).await?,
+        ));
+    }
+    Ok(images)
+}
+
+async fn record_people(
+    tx: &mut Transaction<'_, Postgres>,
+    before: Vec<(i32, Option<serde_json::Value>)>,
+    user: Option<&AuthUser>,
+) -> Result<()> {
+    for (id, image) in before {
+        history::record(
+            tx,
+            HistoryEntity::Person,
+            id,
+            HistoryAction::Update,
+            image,
+            user,
+        )
+        .await?;
+    }
+    Ok(())
 }
 
 #[derive(Debug, Deserialize)]
@@ -47,189 +412,292 @@ pub struct UpdatePerson {
 async fn create_person(
     pool: web::Data<PgPool>,
     person_data: web::Json<CreatePerson>,
+    user: Option<AuthUser>,
 ) -> Result<HttpResponse> {
-    match sqlx::query!(
+    let mut tx = pool.begin().await.context("Failed to create person")?;
+
+    let row = sqlx::query!(
         "INSERT INTO people (name, notes) VALUES ($1, $2) RETURNING id",
         person_data.name,
         person_data.notes
     )
-    .fetch_one(pool.get_ref())
+    .fetch_one(&mut *tx)
     .await
-    {
-        Ok(row) => Ok(HttpResponse::Created().json(serde_json::json!({
-            "id": row.id,
-            "message": "Person created successfully"
-        }))),
-        Err(e) => {
-            log::error!("Failed to create person: {}", e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to create person"
-            })))
-        }
-    }
+    .context("Failed to create person")?;
+    history::record(
+        &mut tx,
+        HistoryEntity::Person,
+        row.id,
+        HistoryAction::Create,
+        None,
+        user.as_ref(),
+    )
+    .await?;
+
+    tx.commit().await.context("Failed to create person")?;
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "id": row.id,
+        "message": "Person created successfully"
+    })))
 }
 
 async fn get_person(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse> {
+    let person = fetch_person(&pool, path.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(person))
+}
+
+async fn fetch_person(pool: &PgPool, person_id: i32) -> Result<People> {
+    sqlx::query_as::<_, People>(
+        "SELECT id, name, notes, household_order FROM people WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(person_id)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch person")?
+    .ok_or_else(|| AppError::not_found("Person not found"))
+}
+
+/// Everything shared with a person, newest first unless `sort=asc`.
+async fn get_person_timeline(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    query: web::Query<ListQuery>,
+) -> Result<HttpResponse> {
+    let person_id = path.into_inner();
+    check_filters(&query, "timeline", &["start_date", "end_date"])?;
+    fetch_person(&pool, person_id).await?;
+
+    let mut list = ListBuilder::new(TIMELINE, &query, Some("t.date"), "t.seq");
+    list.filter("t.person = {}", person_id);
+    let page = list
+        .fetch::<TimelineItem>(&pool, "Failed to fetch timeline")
+        .await?;
+
+    Ok(page.into_response())
+}
+
+async fn get_person_summary(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse> {
     let person_id = path.into_inner();
+    let person = fetch_person(&pool, person_id).await?;
+    let context = "Failed to fetch person summary";
 
-    match sqlx::query_as::<_, People>("SELECT id, name, notes FROM people WHERE id = $1")
+    let dates: Vec<NaiveDate> = sqlx::query_scalar(&format!("{MEETUP_DATES} ORDER BY 1"))
         .bind(person_id)
-        .fetch_optional(pool.get_ref())
+        .fetch_all(pool.get_ref())
         .await
-    {
-        Ok(Some(person)) => Ok(HttpResponse::Ok().json(person)),
-        Ok(None) => Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Person not found"
-        }))),
-        Err(e) => {
-            log::error!("Failed to fetch person {}: {}", person_id, e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch person"
-            })))
-        }
-    }
+        .context(context)?;
+
+    let (total_meals, total_events, total_drinks): (i64, i64, i64) = sqlx::query_as(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM meal_people mp JOIN meal m ON m.id = mp.meal
+             WHERE mp.people = $1 AND m.deleted_at IS NULL),
+            (SELECT COUNT(*) FROM event_people ep JOIN event e ON e.id = ep.event
+             WHERE ep.people = $1 AND e.deleted_at IS NULL),
+            (SELECT COUNT(*) FROM drink_people dp JOIN drink d ON d.id = dp.drink
+             WHERE dp.people = $1 AND d.deleted_at IS NULL)
+        "#,
+    )
+    .bind(person_id)
+    .fetch_one(pool.get_ref())
+    .await
+    .context(context)?;
+
+    let by_month = sqlx::query_as::<_, MonthlyCount>(
+        r#"
+        SELECT
+            month,
+            COUNT(*) FILTER (WHERE kind = 'meal') AS meals,
+            COUNT(*) FILTER (WHERE kind = 'event') AS events
+        FROM (
+            SELECT date_trunc('month', m.date)::date AS month, 'meal' AS kind
+            FROM meal m JOIN meal_people mp ON mp.meal = m.id
+            WHERE mp.people = $1 AND m.deleted_at IS NULL
+            UNION ALL
+            SELECT date_trunc('month', e.date)::date, 'event'
+            FROM event e JOIN event_people ep ON ep.event = e.id
+            WHERE ep.people = $1 AND e.deleted_at IS NULL
+        ) shared
+        GROUP BY month
+        ORDER BY month
+        "#,
+    )
+    .bind(person_id)
+    .fetch_all(pool.get_ref())
+    .await
+    .context(context)?;
+
+    let favorite_restaurants = sqlx::query_as::<_, RestaurantVisits>(
+        r#"
+        SELECT
+            r.id AS restaurant_id, r.name, r.location, r.type AS food_type,
+            COUNT(DISTINCT m.id) AS visits, MAX(m.date) AS last_visit
+        FROM meal m
+        JOIN meal_people mp ON mp.meal = m.id
+        JOIN meal_restaurant mr ON mr.meal = m.id
+        JOIN restaurant r ON r.id = mr.restaurant
+        WHERE mp.people = $1 AND m.deleted_at IS NULL
+        GROUP BY r.id
+        ORDER BY visits DESC, last_visit DESC, r.id
+        LIMIT $2
+        "#,
+    )
+    .bind(person_id)
+    .bind(FAVORITES_LIMIT)
+    .fetch_all(pool.get_ref())
+    .await
+    .context(context)?;
+
+    let favorite_activities = sqlx::query_as::<_, ActivityTogether>(
+        r#"
+        SELECT
+            a.id AS activity_id, a.name, a.type AS activity_type,
+            COUNT(DISTINCT e.id) AS times, MAX(e.date) AS last_date
+        FROM event e
+        JOIN event_people ep ON ep.event = e.id
+        JOIN activity a ON a.id = e.activity
+        WHERE ep.people = $1 AND e.deleted_at IS NULL
+        GROUP BY a.id
+        ORDER BY times DESC, last_date DESC, a.id
+        LIMIT $2
+        "#,
+    )
+    .bind(person_id)
+    .bind(FAVORITES_LIMIT)
+    .fetch_all(pool.get_ref())
+    .await
+    .context(context)?;
+
+    Ok(HttpResponse::Ok().json(PersonSummary {
+        person,
+        first_seen: dates.first().copied(),
+        last_seen: dates.last().copied(),
+        total_meals,
+        total_events,
+        total_drinks,
+        by_month,
+        favorite_restaurants,
+        favorite_activities,
+        longest_gap: longest_gap(&dates),
+    }))
+}
+
+/// The widest gap between consecutive meetup dates; the earliest one wins ties.
+fn longest_gap(dates: &[NaiveDate]) -> Option<MeetupGap> {
+    dates
+        .windows(2)
+        .map(|pair| MeetupGap {
+            from: pair[0],
+            to: pair[1],
+            days: (pair[1] - pair[0]).num_days() as i32,
+        })
+        .fold(None, |longest: Option<MeetupGap>, gap| match longest {
+            Some(longest) if longest.days >= gap.days => Some(longest),
+            _ => Some(gap),
+        })
 }
 
 async fn update_person(
     pool: web::Data<PgPool>,
     path: web::Path<i32>,
     person_data: web::Json<UpdatePerson>,
+    user: Option<AuthUser>,
 ) -> Result<HttpResponse> {
     let person_id = path.into_inner();
+    let mut tx = pool.begin().await.context("Failed to update person")?;
+    let before = history::snapshot(&mut tx, HistoryEntity::Person, person_id).await?;
 
     // Handle different update cases explicitly
     let result = match (&person_data.name, &person_data.notes) {
         (Some(name), Some(notes)) => {
             sqlx::query!(
-                "UPDATE people SET name = $1, notes = $2 WHERE id = $3",
+                "UPDATE people SET name = $1, notes = $2 WHERE id = $3 AND deleted_at IS NULL",
                 name,
                 notes,
                 person_id
             )
-            .execute(pool.get_ref())
+            .execute(&mut *tx)
             .await
         }
         (Some(name), None) => {
-            sqlx::query!("UPDATE people SET name = $1 WHERE id = $2", name, person_id)
-                .execute(pool.get_ref())
-                .await
+            sqlx::query!(
+                "UPDATE people SET name = $1 WHERE id = $2 AND deleted_at IS NULL",
+                name,
+                person_id
+            )
+            .execute(&mut *tx)
+            .await
         }
         (None, Some(notes)) => {
             sqlx::query!(
-                "UPDATE people SET notes = $1 WHERE id = $2",
+                "UPDATE people SET notes = $1 WHERE id = $2 AND deleted_at IS NULL",
                 notes,
                 person_id
             )
-            .execute(pool.get_ref())
+            .execute(&mut *tx)
             .await
         }
         (None, None) => {
-            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                "error": "No fields to update"
-            })));
-        }
-    };
-
-    match result {
-        Ok(query_result) => {
-            if query_result.rows_affected() == 0 {
-                Ok(HttpResponse::NotFound().json(serde_json::json!({
-                    "error": "Person not found"
-                })))
-            } else {
-                Ok(HttpResponse::Ok().json(serde_json::json!({
-                    "message": "Person updated successfully"
-                })))
-            }
-        }
-        Err(e) => {
-            log::error!("Failed to update person {}: {}", person_id, e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to update person"
-            })))
+            return Err(AppError::bad_request("No fields to update"));
         }
     }
-}
-
-async fn delete_person(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse> {
-    let person_id = path.into_inner();
+    .context("Failed to update person")?;
 
-    let mut tx = match pool.begin().await {
-        Ok(tx) => tx,
-        Err(e) => {
-            log::error!("Failed to start transaction: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to delete person"
-            })));
-        }
-    };
-
-    // First check if the person exists
-    match sqlx::query!("SELECT id FROM people WHERE id = $1", person_id)
-        .fetch_optional(&mut *tx)
-        .await
-    {
-        Ok(Some(_)) => {
-            // Person exists, continue with deletion
-        }
-        Ok(None) => {
-            return Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "Person not found"
-            })));
-        }
-        Err(e) => {
-            log::error!("Failed to check if person exists: {}", e);
-            if let Err(rollback_err) = tx.rollback().await {
-                log::error!("Failed to rollback transaction: {}", rollback_err);
-            }
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to delete person"
-            })));
-        }
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Person not found"));
     }
+    history::record(
+        &mut tx,
+        HistoryEntity::Person,
+        person_id,
+        HistoryAction::Update,
+        before,
+        user.as_ref(),
+    )
+    .await?;
 
-    // Delete person relationships (CASCADE should handle this, but being explicit)
-    let relationship_tables = ["meal_people", "event_people", "drink_people"];
+    tx.commit().await.context("Failed to update person")?;
 
-    for table in relationship_tables {
-        if let Err(e) = sqlx::query(&format!("DELETE FROM {} WHERE people = $1", table))
-            .bind(person_id)
-            .execute(&mut *tx)
-            .await
-        {
-            log::error!("Failed to delete {} relationships: {}", table, e);
-            if let Err(rollback_err) = tx.rollback().await {
-                log::error!("Failed to rollback transaction: {}", rollback_err);
-            }
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to delete person"
-            })));
-        }
-    }
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Person updated successfully"
+    })))
+}
 
-    // Delete the person itself
-    if let Err(e) = sqlx::query!("DELETE FROM people WHERE id = $1", person_id)
-        .execute(&mut *tx)
-        .await
-    {
-        log::error!("Failed to delete person: {}", e);
-        if let Err(rollback_err) = tx.rollback().await {
-            log::error!("Failed to rollback transaction: {}", rollback_err);
-        }
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to delete person"
-        })));
-    }
+/// Trashed people keep their links, hidden until they are restored.
+async fn delete_person(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let mut tx = pool.begin().await.context("Failed to delete person")?;
 
-    // Commit the transaction
-    if let Err(e) = tx.commit().await {
-        log::error!("Failed to commit transaction: {}", e);
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to delete person"
-        })));
+    if !trash::move_to_trash(&mut tx, TrashKind::Person, path.into_inner(), user.as_ref()).await? {
+        return Err(AppError::not_found("Person not found"));
     }
 
+    tx.commit().await.context("Failed to delete person")?;
+
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "message": "Person deleted successfully"
     })))
 }
+
+/// Pairs of people that look like the same person, best first.
+async fn get_duplicates(pool: web::Data<PgPool>) -> Result<HttpResponse> {
+    let pairs = PEOPLE.duplicates(&pool).await?;
+
+    Ok(HttpResponse::Ok().json(pairs))
+}
+
+/// Fold duplicates into the survivor, moving everything that refers to them.
+async fn merge_people(
+    pool: web::Data<PgPool>,
+    request: web::Json<MergeRequest>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let report = PEOPLE.merge(&pool, &request, user.as_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(report))
+}
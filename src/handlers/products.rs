@@ -1,13 +1,49 @@
+use crate::auth::AuthUser;
+use crate::error::{AppError, Result, ResultExt};
+use crate::handlers::listing::{check_filters, ListBuilder};
+use crate::handlers::merge::{Collision, Link, Mergeable};
+use crate::history;
+use crate::models::history::{HistoryAction, HistoryEntity};
+use crate::models::list::ListQuery;
+use crate::models::merge::MergeRequest;
 use crate::models::product::{CreateProduct, Product, UpdateProduct};
-use actix_web::{web, HttpResponse, Result};
+use crate::models::trash::TrashKind;
+use crate::trash;
+use actix_web::{web, HttpResponse};
 use sqlx::PgPool;
 
+const PRODUCTS: Mergeable = Mergeable {
+    kind: TrashKind::Product,
+    attributes: &[],
+    fill: &[],
+    links: &[
+        Link {
+            table: "meal_product",
+            column: "product",
+            owner: "meal",
+            entity: Some(HistoryEntity::Meal),
+            collision: Collision::Drop,
+        },
+        Link {
+            table: "meal_plan_source",
+            column: "product",
+            owner: "plan",
+            entity: Some(HistoryEntity::MealPlan),
+            collision: Collision::Move,
+        },
+    ],
+    template_key: "product_id",
+};
+
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::resource("/products")
             .route(web::get().to(get_products))
             .route(web::post().to(create_product)),
     )
+    // Registered before /products/{id} so they are not parsed as ids
+    .service(web::resource("/products/duplicates").route(web::get().to(get_duplicates)))
+    .service(web::resource("/products/merge").route(web::post().to(merge_products)))
     .service(
         web::resource("/products/{id}")
             .route(web::get().to(get_product))
@@ -16,26 +52,30 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
     );
 }
 
-async fn get_products(pool: web::Data<PgPool>) -> Result<HttpResponse> {
-    match sqlx::query_as::<_, Product>("SELECT id, name FROM product ORDER BY id")
-        .fetch_all(pool.get_ref())
-        .await
-    {
-        Ok(products) => Ok(HttpResponse::Ok().json(products)),
-        Err(e) => {
-            log::error!("Failed to fetch products: {}", e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch products"
-            })))
-        }
-    }
+async fn get_products(
+    pool: web::Data<PgPool>,
+    query: web::Query<ListQuery>,
+) -> Result<HttpResponse> {
+    check_filters(&query, "products", &[])?;
+
+    let mut list = ListBuilder::new("SELECT id, name FROM product", &query, None, "id");
+    list.condition("deleted_at IS NULL");
+
+    let page = list
+        .fetch::<Product>(&pool, "Failed to fetch products")
+        .await?;
+
+    Ok(page.into_response())
 }
 
 async fn create_product(
     pool: web::Data<PgPool>,
     product_data: web::Json<CreateProduct>,
+    user: Option<AuthUser>,
 ) -> Result<HttpResponse> {
-    match sqlx::query_as::<_, Product>(
+    let mut tx = pool.begin().await.context("Failed to create product")?;
+
+    let product = sqlx::query_as::<_, Product>(
         r#"
         INSERT INTO product (name)
         VALUES ($1)
@@ -43,155 +83,142 @@ async fn create_product(
         "#,
     )
     .bind(&product_data.name)
-    .fetch_one(pool.get_ref())
+    .fetch_one(&mut *tx)
     .await
-    {
-        Ok(product) => Ok(HttpResponse::Created().json(product)),
-        Err(e) => {
-            log::error!("Failed to create product: {}", e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to create product"
-            })))
-        }
-    }
+    .context("Failed to create product")?;
+    history::record(
+        &mut tx,
+        HistoryEntity::Product,
+        product.id,
+        HistoryAction::Create,
+        None,
+        user.as_ref(),
+    )
+    .await?;
+
+    tx.commit().await.context("Failed to create product")?;
+
+    Ok(HttpResponse::Created().json(product))
 }
 
 async fn get_product(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse> {
     let product_id = path.into_inner();
 
-    match sqlx::query_as::<_, Product>("SELECT id, name FROM product WHERE id = $1")
-        .bind(product_id)
-        .fetch_optional(pool.get_ref())
-        .await
-    {
-        Ok(Some(product)) => Ok(HttpResponse::Ok().json(product)),
-        Ok(None) => Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Product not found"
-        }))),
-        Err(e) => {
-            log::error!("Failed to fetch product {}: {}", product_id, e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch product"
-            })))
-        }
-    }
+    let product = sqlx::query_as::<_, Product>(
+        "SELECT id, name FROM product WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(product_id)
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to fetch product")?
+    .ok_or_else(|| AppError::not_found("Product not found"))?;
+
+    Ok(HttpResponse::Ok().json(product))
 }
 
 async fn update_product(
     pool: web::Data<PgPool>,
     path: web::Path<i32>,
     product_data: web::Json<UpdateProduct>,
+    user: Option<AuthUser>,
 ) -> Result<HttpResponse> {
     let product_id = path.into_inner();
 
     if product_data.name.is_none() {
-        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "No fields to update"
-        })));
+        return Err(AppError::bad_request("No fields to update"));
     }
 
-    match sqlx::query_as::<_, Product>(
-        "UPDATE product SET name = $2 WHERE id = $1 RETURNING id, name",
+    let mut tx = pool.begin().await.context("Failed to update product")?;
+    let before = history::snapshot(&mut tx, HistoryEntity::Product, product_id).await?;
+
+    let product = sqlx::query_as::<_, Product>(
+        "UPDATE product SET name = $2 WHERE id = $1 AND deleted_at IS NULL RETURNING id, name",
     )
     .bind(product_id)
     .bind(&product_data.name)
-    .fetch_optional(pool.get_ref())
+    .fetch_optional(&mut *tx)
     .await
-    {
-        Ok(Some(product)) => Ok(HttpResponse::Ok().json(product)),
-        Ok(None) => Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Product not found"
-        }))),
-        Err(e) => {
-            log::error!("Failed to update product {}: {}", product_id, e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to update product"
-            })))
-        }
-    }
+    .context("Failed to update product")?
+    .ok_or_else(|| AppError::not_found("Product not found"))?;
+    history::record(
+        &mut tx,
+        HistoryEntity::Product,
+        product_id,
+        HistoryAction::Update,
+        before,
+        user.as_ref(),
+    )
+    .await?;
+
+    tx.commit().await.context("Failed to update product")?;
+
+    Ok(HttpResponse::Ok().json(product))
 }
 
-async fn delete_product(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse> {
+/// Only live meals keep a product from going to the trash.
+async fn delete_product(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
     let product_id = path.into_inner();
+    let mut tx = pool.begin().await.context("Failed to delete product")?;
 
     // First check if product exists
-    let product_exists = sqlx::query!("SELECT id FROM product WHERE id = $1", product_id)
-        .fetch_optional(pool.get_ref())
-        .await;
-
-    match product_exists {
-        Ok(None) => {
-            return Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "Product not found"
-            })));
-        }
-        Err(e) => {
-            log::error!("Failed to check product existence {}: {}", product_id, e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to delete product"
-            })));
-        }
-        Ok(Some(_)) => {} // Product exists, continue
-    }
+    sqlx::query!(
+        "SELECT id FROM product WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+        product_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .context("Failed to delete product")?
+    .ok_or_else(|| AppError::not_found("Product not found"))?;
 
     // Check if product is referenced in meals
     let meal_count = sqlx::query!(
-        "SELECT COUNT(*) as count FROM meal_product WHERE product = $1",
+        r#"
+        SELECT COUNT(*) as count FROM meal_product mx
+        JOIN meal m ON m.id = mx.meal
+        WHERE mx.product = $1 AND m.deleted_at IS NULL
+        "#,
         product_id
     )
-    .fetch_one(pool.get_ref())
-    .await;
-
-    match meal_count {
-        Ok(result) => {
-            if result.count.unwrap_or(0) > 0 {
-                return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                    "error": format!("Cannot delete product: it is referenced in {} meal(s). Please delete those meals first.", result.count.unwrap_or(0))
-                })));
-            }
-        }
-        Err(e) => {
-            log::error!(
-                "Failed to check meal references for product {}: {}",
-                product_id,
-                e
-            );
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to delete product"
-            })));
-        }
+    .fetch_one(&mut *tx)
+    .await
+    .context("Failed to delete product")?
+    .count
+    .unwrap_or(0);
+
+    if meal_count > 0 {
+        return Err(AppError::StillReferenced(format!(
+            "Cannot delete product: it is referenced in {} meal(s). Please delete those meals first.",
+            meal_count
+        )));
     }
 
-    // Now safe to delete the product
-    match sqlx::query!("DELETE FROM product WHERE id = $1", product_id)
-        .execute(pool.get_ref())
-        .await
-    {
-        Ok(result) => {
-            if result.rows_affected() > 0 {
-                Ok(HttpResponse::Ok().json(serde_json::json!({
-                    "message": "Product deleted successfully"
-                })))
-            } else {
-                // This shouldn't happen since we checked existence above
-                Ok(HttpResponse::NotFound().json(serde_json::json!({
-                    "error": "Product not found"
-                })))
-            }
-        }
-        Err(e) => {
-            log::error!("Failed to delete product {}: {}", product_id, e);
-
-            // Check if it's a foreign key constraint error
-            let error_message = if e.to_string().contains("foreign key") {
-                "Cannot delete product: it is still referenced by other records"
-            } else {
-                "Failed to delete product"
-            };
-
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": error_message
-            })))
-        }
-    }
+    trash::move_to_trash(&mut tx, TrashKind::Product, product_id, user.as_ref()).await?;
+
+    tx.commit().await.context("Failed to delete product")?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Product deleted successfully"
+    })))
+}
+
+/// Pairs of products that look like the same product, best first.
+async fn get_duplicates(pool: web::Data<PgPool>) -> Result<HttpResponse> {
+    let pairs = PRODUCTS.duplicates(&pool).await?;
+
+    Ok(HttpResponse::Ok().json(pairs))
+}
+
+/// Fold duplicates into the survivor, moving everything that refers to them.
+async fn merge_products(
+    pool: web::Data<PgPool>,
+    request: web::Json<MergeRequest>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let report = PRODUCTS.merge(&pool, &request, user.as_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(report))
 }
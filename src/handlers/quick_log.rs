@@ -0,0 +1,27 @@
+use crate::auth::AuthUser;
+use crate::error::Result;
+use crate::models::quick_log::{QuickLog, QuickLogQuery};
+use crate::quick_log;
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/quick-log").route(web::post().to(quick_log)));
+}
+
+/// Log a meal, events or drinks from a line of shorthand. Responds with what
+/// the line resolved to; `commit=true` also writes it.
+async fn quick_log(
+    pool: web::Data<PgPool>,
+    query: web::Query<QuickLogQuery>,
+    log: web::Json<QuickLog>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let preview = quick_log::quick_log(&pool, &log, query.commit, user.as_ref()).await?;
+
+    if preview.committed {
+        Ok(HttpResponse::Created().json(preview))
+    } else {
+        Ok(HttpResponse::Ok().json(preview))
+    }
+}
@@ -1,93 +1,173 @@
+use crate::auth::AuthUser;
+use crate::error::{AppError, Result, ResultExt};
+use crate::handlers::listing::{check_filters, ListBuilder};
+use crate::handlers::merge::{Collision, Link, Mergeable};
+use crate::history;
+use crate::ingredients;
+use crate::models::history::{HistoryAction, HistoryEntity};
+use crate::models::ingredient::{Ingredient, IngredientsQuery, RecipeIngredients};
+use crate::models::list::ListQuery;
+use crate::models::merge::MergeRequest;
 use crate::models::recipe::{CreateRecipe, Recipe, UpdateRecipe};
-use actix_web::{web, HttpResponse, Result};
+use crate::models::trash::TrashKind;
+use crate::trash;
+use actix_web::{web, HttpResponse};
 use sqlx::PgPool;
 
+const RECIPES: Mergeable = Mergeable {
+    kind: TrashKind::Recipe,
+    attributes: &[],
+    fill: &["cautions", "servings"],
+    links: &[
+        Link {
+            table: "meal_recipe",
+            column: "recipe",
+            owner: "meal",
+            entity: Some(HistoryEntity::Meal),
+            collision: Collision::Drop,
+        },
+        Link {
+            table: "meal_plan_source",
+            column: "recipe",
+            owner: "plan",
+            entity: Some(HistoryEntity::MealPlan),
+            collision: Collision::Move,
+        },
+        Link {
+            table: "recipe_ingredient",
+            column: "recipe",
+            owner: "position",
+            entity: None,
+            collision: Collision::KeepSurvivor,
+        },
+    ],
+    template_key: "recipe_id",
+};
+
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::resource("/recipes")
             .route(web::get().to(get_recipes))
             .route(web::post().to(create_recipe)),
     )
+    // Registered before /recipes/{id} so they are not parsed as ids
+    .service(web::resource("/recipes/duplicates").route(web::get().to(get_duplicates)))
+    .service(web::resource("/recipes/merge").route(web::post().to(merge_recipes)))
     .service(
         web::resource("/recipes/{id}")
             .route(web::get().to(get_recipe))
             .route(web::put().to(update_recipe))
             .route(web::delete().to(delete_recipe)),
+    )
+    .service(
+        web::resource("/recipes/{id}/ingredients").route(web::get().to(get_recipe_ingredients)),
     );
 }
 
-async fn get_recipes(pool: web::Data<PgPool>) -> Result<HttpResponse> {
-    match sqlx::query_as::<_, Recipe>(
-        "SELECT id, name, ingredients, procedure, cautions FROM recipe ORDER BY id",
-    )
-    .fetch_all(pool.get_ref())
-    .await
-    {
-        Ok(recipes) => Ok(HttpResponse::Ok().json(recipes)),
-        Err(e) => {
-            log::error!("Failed to fetch recipes: {}", e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch recipes"
-            })))
-        }
+const RECIPE_COLUMNS: &str = "id, name, ingredients, procedure, cautions, servings";
+
+fn check_servings(servings: Option<i32>) -> Result<()> {
+    if servings.is_some_and(|servings| servings <= 0) {
+        return Err(AppError::bad_request("servings must be positive"));
+    }
+    Ok(())
+}
+
+async fn get_recipes(
+    pool: web::Data<PgPool>,
+    query: web::Query<ListQuery>,
+) -> Result<HttpResponse> {
+    check_filters(&query, "recipes", &["ingredient"])?;
+
+    let mut list = ListBuilder::new(
+        &format!("SELECT {RECIPE_COLUMNS} FROM recipe"),
+        &query,
+        None,
+        "id",
+    );
+    list.condition("deleted_at IS NULL");
+    if let Some(ingredient) = &query.ingredient {
+        let escaped = ingredient
+            .trim()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        list.filter(
+            r#"EXISTS (
+                SELECT 1 FROM recipe_ingredient ri
+                WHERE ri.recipe = recipe.id AND ri.item ILIKE {})"#,
+            format!("%{}%", escaped),
+        );
     }
+
+    let page = list
+        .fetch::<Recipe>(&pool, "Failed to fetch recipes")
+        .await?;
+
+    Ok(page.into_response())
 }
 
 async fn create_recipe(
     pool: web::Data<PgPool>,
     recipe_data: web::Json<CreateRecipe>,
+    user: Option<AuthUser>,
 ) -> Result<HttpResponse> {
-    match sqlx::query_as::<_, Recipe>(
+    check_servings(recipe_data.servings)?;
+
+    let mut tx = pool.begin().await.context("Failed to create recipe")?;
+
+    let recipe = sqlx::query_as::<_, Recipe>(&format!(
         r#"
-        INSERT INTO recipe (name, ingredients, procedure, cautions)
-        VALUES ($1, $2, $3, $4)
-        RETURNING id, name, ingredients, procedure, cautions
-        "#,
-    )
+        INSERT INTO recipe (name, ingredients, procedure, cautions, servings)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING {RECIPE_COLUMNS}
+        "#
+    ))
     .bind(&recipe_data.name)
     .bind(&recipe_data.ingredients)
     .bind(&recipe_data.procedure)
     .bind(&recipe_data.cautions)
-    .fetch_one(pool.get_ref())
+    .bind(recipe_data.servings)
+    .fetch_one(&mut *tx)
     .await
-    {
-        Ok(recipe) => Ok(HttpResponse::Created().json(recipe)),
-        Err(e) => {
-            log::error!("Failed to create recipe: {}", e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to create recipe"
-            })))
-        }
-    }
+    .context("Failed to create recipe")?;
+
+    ingredients::store(&mut tx, recipe.id, &recipe.ingredients).await?;
+    history::record(
+        &mut tx,
+        HistoryEntity::Recipe,
+        recipe.id,
+        HistoryAction::Create,
+        None,
+        user.as_ref(),
+    )
+    .await?;
+
+    tx.commit().await.context("Failed to create recipe")?;
+
+    Ok(HttpResponse::Created().json(recipe))
 }
 
 async fn get_recipe(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse> {
     let recipe_id = path.into_inner();
 
-    match sqlx::query_as::<_, Recipe>(
-        "SELECT id, name, ingredients, procedure, cautions FROM recipe WHERE id = $1",
-    )
+    let recipe = sqlx::query_as::<_, Recipe>(&format!(
+        "SELECT {RECIPE_COLUMNS} FROM recipe WHERE id = $1 AND deleted_at IS NULL"
+    ))
     .bind(recipe_id)
     .fetch_optional(pool.get_ref())
     .await
-    {
-        Ok(Some(recipe)) => Ok(HttpResponse::Ok().json(recipe)),
-        Ok(None) => Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Recipe not found"
-        }))),
-        Err(e) => {
-            log::error!("Failed to fetch recipe {}: {}", recipe_id, e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch recipe"
-            })))
-        }
-    }
+    .context("Failed to fetch recipe")?
+    .ok_or_else(|| AppError::not_found("Recipe not found"))?;
+
+    Ok(HttpResponse::Ok().json(recipe))
 }
 
 async fn update_recipe(
     pool: web::Data<PgPool>,
     path: web::Path<i32>,
     recipe_data: web::Json<UpdateRecipe>,
+    user: Option<AuthUser>,
 ) -> Result<HttpResponse> {
     let recipe_id = path.into_inner();
 
@@ -109,16 +189,19 @@ async fn update_recipe(
     }
     if recipe_data.cautions.is_some() {
         query_parts.push(format!("cautions = ${}", param_index));
+        param_index += 1;
+    }
+    if recipe_data.servings.is_some() {
+        check_servings(recipe_data.servings)?;
+        query_parts.push(format!("servings = ${}", param_index));
     }
 
     if query_parts.is_empty() {
-        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "No fields to update"
-        })));
+        return Err(AppError::bad_request("No fields to update"));
     }
 
     let query = format!(
-        "UPDATE recipe SET {} WHERE id = $1 RETURNING id, name, ingredients, procedure, cautions",
+        "UPDATE recipe SET {} WHERE id = $1 AND deleted_at IS NULL RETURNING {RECIPE_COLUMNS}",
         query_parts.join(", ")
     );
 
@@ -137,102 +220,142 @@ async fn update_recipe(
     if recipe_data.cautions.is_some() {
         query_builder = query_builder.bind(&recipe_data.cautions);
     }
+    if recipe_data.servings.is_some() {
+        query_builder = query_builder.bind(recipe_data.servings);
+    }
+
+    let mut tx = pool.begin().await.context("Failed to update recipe")?;
+    let before = history::snapshot(&mut tx, HistoryEntity::Recipe, recipe_id).await?;
 
-    match query_builder.fetch_optional(pool.get_ref()).await {
-        Ok(Some(recipe)) => Ok(HttpResponse::Ok().json(recipe)),
-        Ok(None) => Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Recipe not found"
-        }))),
-        Err(e) => {
-            log::error!("Failed to update recipe {}: {}", recipe_id, e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to update recipe"
-            })))
-        }
+    let recipe = query_builder
+        .fetch_optional(&mut *tx)
+        .await
+        .context("Failed to update recipe")?
+        .ok_or_else(|| AppError::not_found("Recipe not found"))?;
+
+    if recipe_data.ingredients.is_some() {
+        ingredients::store(&mut tx, recipe.id, &recipe.ingredients).await?;
     }
+    history::record(
+        &mut tx,
+        HistoryEntity::Recipe,
+        recipe_id,
+        HistoryAction::Update,
+        before,
+        user.as_ref(),
+    )
+    .await?;
+
+    tx.commit().await.context("Failed to update recipe")?;
+
+    Ok(HttpResponse::Ok().json(recipe))
 }
 
-async fn delete_recipe(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse> {
+/// Only live meals keep a recipe from going to the trash.
+async fn delete_recipe(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
     let recipe_id = path.into_inner();
+    let mut tx = pool.begin().await.context("Failed to delete recipe")?;
 
     // First check if recipe exists
-    let recipe_exists = sqlx::query!("SELECT id FROM recipe WHERE id = $1", recipe_id)
-        .fetch_optional(pool.get_ref())
-        .await;
-
-    match recipe_exists {
-        Ok(None) => {
-            return Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "Recipe not found"
-            })));
-        }
-        Err(e) => {
-            log::error!("Failed to check recipe existence {}: {}", recipe_id, e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to delete recipe"
-            })));
-        }
-        Ok(Some(_)) => {} // Recipe exists, continue
-    }
+    sqlx::query!(
+        "SELECT id FROM recipe WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+        recipe_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .context("Failed to delete recipe")?
+    .ok_or_else(|| AppError::not_found("Recipe not found"))?;
 
     // Check if recipe is referenced in meals
     let meal_count = sqlx::query!(
-        "SELECT COUNT(*) as count FROM meal_recipe WHERE recipe = $1",
+        r#"
+        SELECT COUNT(*) as count FROM meal_recipe mx
+        JOIN meal m ON m.id = mx.meal
+        WHERE mx.recipe = $1 AND m.deleted_at IS NULL
+        "#,
         recipe_id
     )
-    .fetch_one(pool.get_ref())
-    .await;
-
-    match meal_count {
-        Ok(result) => {
-            if result.count.unwrap_or(0) > 0 {
-                return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                    "error": format!("Cannot delete recipe: it is referenced in {} meal(s). Please delete those meals first.", result.count.unwrap_or(0))
-                })));
-            }
-        }
-        Err(e) => {
-            log::error!(
-                "Failed to check meal references for recipe {}: {}",
-                recipe_id,
-                e
-            );
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to delete recipe"
-            })));
-        }
-    }
-
-    // Now safe to delete the recipe
-    match sqlx::query!("DELETE FROM recipe WHERE id = $1", recipe_id)
-        .execute(pool.get_ref())
-        .await
-    {
-        Ok(result) => {
-            if result.rows_affected() > 0 {
-                Ok(HttpResponse::Ok().json(serde_json::json!({
-                    "message": "Recipe deleted successfully"
-                })))
-            } else {
-                // This shouldn't happen since we checked existence above
-                Ok(HttpResponse::NotFound().json(serde_json::json!({
-                    "error": "Recipe not found"
-                })))
-            }
-        }
-        Err(e) => {
-            log::error!("Failed to delete recipe {}: {}", recipe_id, e);
-
-            // Check if it's a foreign key constraint error
-            let error_message = if e.to_string().contains("foreign key") {
-                "Cannot delete recipe: it is still referenced by other records"
-            } else {
-                "Failed to delete recipe"
-            };
-
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": error_message
-            })))
-        }
+    .fetch_one(&mut *tx)
+    .await
+    .context("Failed to delete recipe")?
+    .count
+    .unwrap_or(0);
+
+    if meal_count > 0 {
+        return Err(AppError::StillReferenced(format!(
+            "Cannot delete recipe: it is referenced in {} meal(s). Please delete those meals first.",
+            meal_count
+        )));
+    }
+
+    trash::move_to_trash(&mut tx, TrashKind::Recipe, recipe_id, user.as_ref()).await?;
+
+    tx.commit().await.context("Failed to delete recipe")?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Recipe deleted successfully"
+    })))
+}
+
+/// The structured ingredients of a recipe, scaled to `servings` when the recipe
+/// says how many it serves.
+async fn get_recipe_ingredients(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    query: web::Query<IngredientsQuery>,
+) -> Result<HttpResponse> {
+    let recipe_id = path.into_inner();
+    check_servings(query.servings)?;
+
+    let servings = sqlx::query_scalar!(
+        "SELECT servings FROM recipe WHERE id = $1 AND deleted_at IS NULL",
+        recipe_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to fetch recipe ingredients")?
+    .ok_or_else(|| AppError::not_found("Recipe not found"))?;
+
+    if query.servings.is_some() && servings.is_none() {
+        return Err(AppError::bad_request(
+            "Recipe has no servings to scale from",
+        ));
     }
+
+    let factor = ingredients::scale_factor(servings, query.servings);
+    let lines = sqlx::query_as::<_, Ingredient>(
+        "SELECT quantity, unit, item, note FROM recipe_ingredient WHERE recipe = $1 ORDER BY position",
+    )
+    .bind(recipe_id)
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to fetch recipe ingredients")?;
+
+    Ok(HttpResponse::Ok().json(RecipeIngredients {
+        recipe_id,
+        servings: query.servings.or(servings),
+        ingredients: ingredients::scale(lines, factor),
+    }))
+}
+
+/// Pairs of recipes that look like the same recipe, best first.
+async fn get_duplicates(pool: web::Data<PgPool>) -> Result<HttpResponse> {
+    let pairs = RECIPES.duplicates(&pool).await?;
+
+    Ok(HttpResponse::Ok().json(pairs))
+}
+
+/// Fold duplicates into the survivor, moving everything that refers to them.
+async fn merge_recipes(
+    pool: web::Data<PgPool>,
+    request: web::Json<MergeRequest>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let report = RECIPES.merge(&pool, &request, user.as_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(report))
 }
@@ -1,43 +1,93 @@
-use crate::models::restaurant::{CreateRestaurant, Restaurant, UpdateRestaurant};
-use actix_web::{web, HttpResponse, Result};
+use crate::auth::AuthUser;
+use crate::error::{AppError, Result, ResultExt};
+use crate::handlers::listing::{check_filters, ListBuilder};
+use crate::handlers::merge::{Collision, Link, Mergeable};
+use crate::history;
+use crate::models::history::{HistoryAction, HistoryEntity};
+use crate::models::list::ListQuery;
+use crate::models::merge::MergeRequest;
+use crate::models::restaurant::{
+    CreateRestaurant, MonthlyRating, Restaurant, RestaurantDetail, RestaurantVisit, Spend,
+    UpdateRestaurant, VisitStats,
+};
+use crate::models::trash::TrashKind;
+use crate::trash;
+use actix_web::{web, HttpResponse};
 use sqlx::PgPool;
 
+const RESTAURANTS: Mergeable = Mergeable {
+    kind: TrashKind::Restaurant,
+    attributes: &["location", "type"],
+    fill: &["price"],
+    links: &[
+        Link {
+            table: "meal_restaurant",
+            column: "restaurant",
+            owner: "meal",
+            entity: Some(HistoryEntity::Meal),
+            collision: Collision::Drop,
+        },
+        Link {
+            table: "meal_plan_source",
+            column: "restaurant",
+            owner: "plan",
+            entity: Some(HistoryEntity::MealPlan),
+            collision: Collision::Move,
+        },
+    ],
+    template_key: "restaurant_id",
+};
+
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::resource("/restaurants")
             .route(web::get().to(get_restaurants))
             .route(web::post().to(create_restaurant)),
     )
+    // Registered before /restaurants/{id} so they are not parsed as ids
+    .service(web::resource("/restaurants/duplicates").route(web::get().to(get_duplicates)))
+    .service(web::resource("/restaurants/merge").route(web::post().to(merge_restaurants)))
     .service(
         web::resource("/restaurants/{id}")
             .route(web::get().to(get_restaurant))
             .route(web::put().to(update_restaurant))
             .route(web::delete().to(delete_restaurant)),
-    );
+    )
+    .service(web::resource("/restaurants/{id}/visits").route(web::get().to(get_visits)));
 }
 
-async fn get_restaurants(pool: web::Data<PgPool>) -> Result<HttpResponse> {
-    match sqlx::query_as::<_, Restaurant>(
-        "SELECT id, name, location, type, price FROM restaurant ORDER BY id",
-    )
-    .fetch_all(pool.get_ref())
-    .await
-    {
-        Ok(restaurants) => Ok(HttpResponse::Ok().json(restaurants)),
-        Err(e) => {
-            log::error!("Failed to fetch restaurants: {}", e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch restaurants"
-            })))
-        }
+async fn get_restaurants(
+    pool: web::Data<PgPool>,
+    query: web::Query<ListQuery>,
+) -> Result<HttpResponse> {
+    check_filters(&query, "restaurants", &["location"])?;
+
+    let mut list = ListBuilder::new(
+        "SELECT id, name, location, type, price FROM restaurant",
+        &query,
+        None,
+        "id",
+    );
+    list.condition("deleted_at IS NULL");
+    if let Some(location) = &query.location {
+        list.filter("location = {}", location.as_str());
     }
+
+    let page = list
+        .fetch::<Restaurant>(&pool, "Failed to fetch restaurants")
+        .await?;
+
+    Ok(page.into_response())
 }
 
 async fn create_restaurant(
     pool: web::Data<PgPool>,
     restaurant_data: web::Json<CreateRestaurant>,
+    user: Option<AuthUser>,
 ) -> Result<HttpResponse> {
-    match sqlx::query_as::<_, Restaurant>(
+    let mut tx = pool.begin().await.context("Failed to create restaurant")?;
+
+    let restaurant = sqlx::query_as::<_, Restaurant>(
         r#"
         INSERT INTO restaurant (name, location, type, price)
         VALUES ($1, $2, $3, $4)
@@ -48,46 +98,146 @@ async fn create_restaurant(
     .bind(&restaurant_data.location)
     .bind(&restaurant_data.food_type)
     .bind(restaurant_data.price)
-    .fetch_one(pool.get_ref())
+    .fetch_one(&mut *tx)
     .await
-    {
-        Ok(restaurant) => Ok(HttpResponse::Created().json(restaurant)),
-        Err(e) => {
-            log::error!("Failed to create restaurant: {}", e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to create restaurant"
-            })))
-        }
-    }
+    .context("Failed to create restaurant")?;
+    history::record(
+        &mut tx,
+        HistoryEntity::Restaurant,
+        restaurant.id,
+        HistoryAction::Create,
+        None,
+        user.as_ref(),
+    )
+    .await?;
+
+    tx.commit().await.context("Failed to create restaurant")?;
+
+    Ok(HttpResponse::Created().json(restaurant))
 }
 
 async fn get_restaurant(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse> {
     let restaurant_id = path.into_inner();
 
-    match sqlx::query_as::<_, Restaurant>(
-        "SELECT id, name, location, type, price FROM restaurant WHERE id = $1",
+    let restaurant = sqlx::query_as::<_, Restaurant>(
+        "SELECT id, name, location, type, price FROM restaurant WHERE id = $1 AND deleted_at IS NULL",
     )
     .bind(restaurant_id)
     .fetch_optional(pool.get_ref())
     .await
-    {
-        Ok(Some(restaurant)) => Ok(HttpResponse::Ok().json(restaurant)),
-        Ok(None) => Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Restaurant not found"
-        }))),
-        Err(e) => {
-            log::error!("Failed to fetch restaurant {}: {}", restaurant_id, e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch restaurant"
-            })))
-        }
-    }
+    .context("Failed to fetch restaurant")?
+    .ok_or_else(|| AppError::not_found("Restaurant not found"))?;
+
+    let stats = visit_stats(&pool, restaurant_id).await?;
+
+    Ok(HttpResponse::Ok().json(RestaurantDetail { restaurant, stats }))
+}
+
+async fn visit_stats(pool: &PgPool, restaurant_id: i32) -> Result<VisitStats> {
+    let context = "Failed to fetch restaurant visits";
+
+    let totals = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) AS "visits!",
+            MAX(m.date) AS last_visit,
+            AVG(mr.rating)::float8 AS average_rating
+        FROM meal_restaurant mr
+        JOIN meal m ON m.id = mr.meal
+        WHERE mr.restaurant = $1 AND m.deleted_at IS NULL
+        "#,
+        restaurant_id
+    )
+    .fetch_one(pool)
+    .await
+    .context(context)?;
+
+    let rating_trend = sqlx::query_as::<_, MonthlyRating>(
+        r#"
+        SELECT
+            date_trunc('month', m.date)::date AS month,
+            AVG(mr.rating)::float8 AS average_rating,
+            COUNT(*) AS ratings
+        FROM meal_restaurant mr
+        JOIN meal m ON m.id = mr.meal
+        WHERE mr.restaurant = $1 AND mr.rating IS NOT NULL AND m.deleted_at IS NULL
+        GROUP BY 1
+        ORDER BY 1
+        "#,
+    )
+    .bind(restaurant_id)
+    .fetch_all(pool)
+    .await
+    .context(context)?;
+
+    let spend = sqlx::query_as::<_, Spend>(
+        r#"
+        SELECT mr.currency, SUM(mr.amount) AS amount, COUNT(*) AS visits
+        FROM meal_restaurant mr
+        JOIN meal m ON m.id = mr.meal
+        WHERE mr.restaurant = $1 AND mr.amount IS NOT NULL AND m.deleted_at IS NULL
+        GROUP BY mr.currency
+        ORDER BY mr.currency
+        "#,
+    )
+    .bind(restaurant_id)
+    .fetch_all(pool)
+    .await
+    .context(context)?;
+
+    Ok(VisitStats {
+        visits: totals.visits,
+        last_visit: totals.last_visit,
+        average_rating: totals.average_rating,
+        rating_trend,
+        spend,
+    })
+}
+
+/// Meals at a restaurant with what was paid, rated and ordered, newest first.
+async fn get_visits(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    query: web::Query<ListQuery>,
+) -> Result<HttpResponse> {
+    check_filters(&query, "restaurant visits", &["start_date", "end_date"])?;
+
+    let restaurant_id = path.into_inner();
+    sqlx::query!(
+        "SELECT id FROM restaurant WHERE id = $1 AND deleted_at IS NULL",
+        restaurant_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to fetch restaurant visits")?
+    .ok_or_else(|| AppError::not_found("Restaurant not found"))?;
+
+    let mut list = ListBuilder::new(
+        r#"
+        SELECT m.id AS meal_id, m.date, m."time", mr.type AS meal_type,
+               mr.amount, mr.currency, mr.rating, COALESCE(mr.dishes, '{}') AS dishes
+        FROM meal_restaurant mr
+        JOIN meal m ON m.id = mr.meal
+        "#,
+        &query,
+        Some("m.date"),
+        "m.id",
+    );
+    list.condition("m.deleted_at IS NULL");
+    list.filter("mr.restaurant = {}", restaurant_id);
+
+    let page = list
+        .fetch::<RestaurantVisit>(&pool, "Failed to fetch restaurant visits")
+        .await?;
+
+    Ok(page.into_response())
 }
 
 async fn update_restaurant(
     pool: web::Data<PgPool>,
     path: web::Path<i32>,
     restaurant_data: web::Json<UpdateRestaurant>,
+    user: Option<AuthUser>,
 ) -> Result<HttpResponse> {
     let restaurant_id = path.into_inner();
 
@@ -112,13 +262,11 @@ async fn update_restaurant(
     }
 
     if query_parts.is_empty() {
-        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "No fields to update"
-        })));
+        return Err(AppError::bad_request("No fields to update"));
     }
 
     let query = format!(
-        "UPDATE restaurant SET {} WHERE id = $1 RETURNING id, name, location, type, price",
+        "UPDATE restaurant SET {} WHERE id = $1 AND deleted_at IS NULL RETURNING id, name, location, type, price",
         query_parts.join(", ")
     );
 
@@ -138,105 +286,93 @@ async fn update_restaurant(
         query_builder = query_builder.bind(restaurant_data.price);
     }
 
-    match query_builder.fetch_optional(pool.get_ref()).await {
-        Ok(Some(restaurant)) => Ok(HttpResponse::Ok().json(restaurant)),
-        Ok(None) => Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Restaurant not found"
-        }))),
-        Err(e) => {
-            log::error!("Failed to update restaurant {}: {}", restaurant_id, e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to update restaurant"
-            })))
-        }
-    }
+    let mut tx = pool.begin().await.context("Failed to update restaurant")?;
+    let before = history::snapshot(&mut tx, HistoryEntity::Restaurant, restaurant_id).await?;
+
+    let restaurant = query_builder
+        .fetch_optional(&mut *tx)
+        .await
+        .context("Failed to update restaurant")?
+        .ok_or_else(|| AppError::not_found("Restaurant not found"))?;
+    history::record(
+        &mut tx,
+        HistoryEntity::Restaurant,
+        restaurant_id,
+        HistoryAction::Update,
+        before,
+        user.as_ref(),
+    )
+    .await?;
+
+    tx.commit().await.context("Failed to update restaurant")?;
+
+    Ok(HttpResponse::Ok().json(restaurant))
 }
 
-async fn delete_restaurant(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse> {
+/// Only live meals keep a restaurant from going to the trash.
+async fn delete_restaurant(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
     let restaurant_id = path.into_inner();
+    let mut tx = pool.begin().await.context("Failed to delete restaurant")?;
 
     // First check if restaurant exists
-    let restaurant_exists = sqlx::query!("SELECT id FROM restaurant WHERE id = $1", restaurant_id)
-        .fetch_optional(pool.get_ref())
-        .await;
-
-    match restaurant_exists {
-        Ok(None) => {
-            return Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "Restaurant not found"
-            })));
-        }
-        Err(e) => {
-            log::error!(
-                "Failed to check restaurant existence {}: {}",
-                restaurant_id,
-                e
-            );
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to delete restaurant"
-            })));
-        }
-        Ok(Some(_)) => {} // Restaurant exists, continue
-    }
+    sqlx::query!(
+        "SELECT id FROM restaurant WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+        restaurant_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .context("Failed to delete restaurant")?
+    .ok_or_else(|| AppError::not_found("Restaurant not found"))?;
 
     // Check if restaurant is referenced in meals
     let meal_count = sqlx::query!(
-        "SELECT COUNT(*) as count FROM meal_restaurant WHERE restaurant = $1",
+        r#"
+        SELECT COUNT(*) as count FROM meal_restaurant mx
+        JOIN meal m ON m.id = mx.meal
+        WHERE mx.restaurant = $1 AND m.deleted_at IS NULL
+        "#,
         restaurant_id
     )
-    .fetch_one(pool.get_ref())
-    .await;
-
-    match meal_count {
-        Ok(result) => {
-            if result.count.unwrap_or(0) > 0 {
-                return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                    "error": format!("Cannot delete restaurant: it is referenced in {} meal(s). Please delete those meals first.", result.count.unwrap_or(0))
-                })));
-            }
-        }
-        Err(e) => {
-            log::error!(
-                "Failed to check meal references for restaurant {}: {}",
-                restaurant_id,
-                e
-            );
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to delete restaurant"
-            })));
-        }
-    }
+    .fetch_one(&mut *tx)
+    .await
+    .context("Failed to delete restaurant")?
+    .count
+    .unwrap_or(0);
 
-    // Now safe to delete the restaurant
-    match sqlx::query!("DELETE FROM restaurant WHERE id = $1", restaurant_id)
-        .execute(pool.get_ref())
-        .await
-    {
-        Ok(result) => {
-            if result.rows_affected() > 0 {
-                Ok(HttpResponse::Ok().json(serde_json::json!({
-                    "message": "Restaurant deleted successfully"
-                })))
-            } else {
-                // This shouldn't happen since we checked existence above
-                Ok(HttpResponse::NotFound().json(serde_json::json!({
-                    "error": "Restaurant not found"
-                })))
-            }
-        }
-        Err(e) => {
-            log::error!("Failed to delete restaurant {}: {}", restaurant_id, e);
-
-            // Check if it's a foreign key constraint error
-            let error_message = if e.to_string().contains("foreign key") {
-                "Cannot delete restaurant: it is still referenced by other records"
-            } else {
-                "Failed to delete restaurant"
-            };
-
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": error_message
-            })))
-        }
+    if meal_count > 0 {
+        return Err(AppError::StillReferenced(format!(
+            "Cannot delete restaurant: it is referenced in {} meal(s). Please delete those meals first.",
+            meal_count
+        )));
     }
+
+    trash::move_to_trash(&mut tx, TrashKind::Restaurant, restaurant_id, user.as_ref()).await?;
+
+    tx.commit().await.context("Failed to delete restaurant")?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Restaurant deleted successfully"
+    })))
+}
+
+/// Pairs of restaurants that look like the same restaurant, best first.
+async fn get_duplicates(pool: web::Data<PgPool>) -> Result<HttpResponse> {
+    let pairs = RESTAURANTS.duplicates(&pool).await?;
+
+    Ok(HttpResponse::Ok().json(pairs))
+}
+
+/// Fold duplicates into the survivor, moving everything that refers to them.
+async fn merge_restaurants(
+    pool: web::Data<PgPool>,
+    request: web::Json<MergeRequest>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let report = RESTAURANTS.merge(&pool, &request, user.as_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(report))
 }
@@ -0,0 +1,210 @@
+use crate::error::{AppError, Result, ResultExt};
+use crate::models::search::{SearchHit, SearchQuery, SearchResponse, SearchResults};
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+
+// Every query matches against the same expression its GIN index in the schema is
+// built on, so that Postgres can use the index. Meals are the exception and have
+// no index: they also match the names of their dishes and people, which are kept
+// in other tables, unless those are in the trash. Dated queries take the date
+// range as $3 and $4.
+const MEAL_SEARCH: &str = r#"
+    WITH meal_text AS (
+        SELECT
+            m.id,
+            m.date,
+            m."time",
+            m.notes,
+            fs.sources,
+            fs.live_sources,
+            (
+                SELECT string_agg(pe.name, ' ') FROM meal_people mp JOIN people pe ON mp.people = pe.id
+                WHERE mp.meal = m.id AND pe.deleted_at IS NULL
+            ) AS people
+        FROM meal m
+        CROSS JOIN LATERAL (
+            SELECT
+                string_agg(name, ', ') AS sources,
+                string_agg(name, ', ') FILTER (WHERE deleted_at IS NULL) AS live_sources
+            FROM (
+                SELECT r.name, r.deleted_at FROM meal_recipe mr JOIN recipe r ON mr.recipe = r.id WHERE mr.meal = m.id
+                UNION ALL SELECT p.name, p.deleted_at FROM meal_product mpr JOIN product p ON mpr.product = p.id WHERE mpr.meal = m.id
+                UNION ALL SELECT rt.name, rt.deleted_at FROM meal_restaurant mrt JOIN restaurant rt ON mrt.restaurant = rt.id WHERE mrt.meal = m.id
+            ) names
+        ) fs
+        WHERE m.deleted_at IS NULL
+            AND ($3::date IS NULL OR m.date >= $3)
+            AND ($4::date IS NULL OR m.date <= $4)
+    )
+    SELECT
+        id,
+        "time" || COALESCE(': ' || sources, '') AS title,
+        notes AS snippet,
+        date,
+        ts_rank(search_document(CONCAT_WS(' ', notes, live_sources, people)), search_query($1)) AS rank,
+        '/api/v1/meals/' || id || '/details' AS link
+    FROM meal_text
+    WHERE search_document(CONCAT_WS(' ', notes, live_sources, people)) @@ search_query($1)
+    ORDER BY rank DESC, date DESC, id DESC
+    LIMIT $2
+"#;
+
+const EVENT_SEARCH: &str = r#"
+    SELECT
+        e.id,
+        a.name AS title,
+        NULLIF(CONCAT_WS(' ', e.measure, e.location, e.notes), '') AS snippet,
+        e.date,
+        ts_rank(
+            search_document(COALESCE(e.notes, '') || ' ' || COALESCE(e.measure, '') || ' ' || COALESCE(e.location, '')),
+            search_query($1)
+        ) AS rank,
+        '/api/v1/events/' || e.id || '/details' AS link
+    FROM event e
+    JOIN activity a ON e.activity = a.id
+    WHERE search_document(COALESCE(e.notes, '') || ' ' || COALESCE(e.measure, '') || ' ' || COALESCE(e.location, ''))
+            @@ search_query($1)
+        AND e.deleted_at IS NULL
+        AND ($3::date IS NULL OR e.date >= $3)
+        AND ($4::date IS NULL OR e.date <= $4)
+    ORDER BY rank DESC, e.date DESC, e.id DESC
+    LIMIT $2
+"#;
+
+const DRINK_SEARCH: &str = r#"
+    SELECT
+        d.id,
+        d.name AS title,
+        NULL::text AS snippet,
+        d.date,
+        ts_rank(search_document(d.name), search_query($1)) AS rank,
+        '/api/v1/drinks/' || d.id || '/details' AS link
+    FROM drink d
+    WHERE search_document(d.name) @@ search_query($1)
+        AND d.deleted_at IS NULL
+        AND ($3::date IS NULL OR d.date >= $3)
+        AND ($4::date IS NULL OR d.date <= $4)
+    ORDER BY rank DESC, d.date DESC, d.id DESC
+    LIMIT $2
+"#;
+
+const RECIPE_SEARCH: &str = r#"
+    SELECT
+        r.id,
+        r.name AS title,
+        r.ingredients AS snippet,
+        NULL::date AS date,
+        ts_rank(search_document(r.name || ' ' || r.ingredients || ' ' || r.procedure), search_query($1)) AS rank,
+        '/api/v1/recipes/' || r.id AS link
+    FROM recipe r
+    WHERE search_document(r.name || ' ' || r.ingredients || ' ' || r.procedure) @@ search_query($1)
+        AND r.deleted_at IS NULL
+    ORDER BY rank DESC, r.id
+    LIMIT $2
+"#;
+
+const RESTAURANT_SEARCH: &str = r#"
+    SELECT
+        rt.id,
+        rt.name AS title,
+        NULLIF(CONCAT_WS(' ', rt.location, rt.type), '') AS snippet,
+        NULL::date AS date,
+        ts_rank(search_document(rt.name), search_query($1)) AS rank,
+        '/api/v1/restaurants/' || rt.id AS link
+    FROM restaurant rt
+    WHERE search_document(rt.name) @@ search_query($1)
+        AND rt.deleted_at IS NULL
+    ORDER BY rank DESC, rt.id
+    LIMIT $2
+"#;
+
+const PRODUCT_SEARCH: &str = r#"
+    SELECT
+        p.id,
+        p.name AS title,
+        NULL::text AS snippet,
+        NULL::date AS date,
+        ts_rank(search_document(p.name), search_query($1)) AS rank,
+        '/api/v1/products/' || p.id AS link
+    FROM product p
+    WHERE search_document(p.name) @@ search_query($1)
+        AND p.deleted_at IS NULL
+    ORDER BY rank DESC, p.id
+    LIMIT $2
+"#;
+
+const PEOPLE_SEARCH: &str = r#"
+    SELECT
+        pe.id,
+        pe.name AS title,
+        pe.notes AS snippet,
+        NULL::date AS date,
+        ts_rank(search_document(pe.name), search_query($1)) AS rank,
+        '/api/v1/people/' || pe.id AS link
+    FROM people pe
+    WHERE search_document(pe.name) @@ search_query($1)
+        AND pe.deleted_at IS NULL
+    ORDER BY rank DESC, pe.id
+    LIMIT $2
+"#;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/search").route(web::get().to(search)));
+}
+
+async fn search(pool: web::Data<PgPool>, query: web::Query<SearchQuery>) -> Result<HttpResponse> {
+    let q = query.q.trim();
+    if q.is_empty() {
+        return Err(AppError::bad_request("Search query must not be empty"));
+    }
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    let results = run_search(&pool, q, query.start_date, query.end_date, limit)
+        .await
+        .context("Failed to search")?;
+
+    let total = results.meals.len()
+        + results.events.len()
+        + results.drinks.len()
+        + results.recipes.len()
+        + results.restaurants.len()
+        + results.products.len()
+        + results.people.len();
+
+    Ok(HttpResponse::Ok().json(SearchResponse {
+        query: q.to_string(),
+        total,
+        results,
+    }))
+}
+
+async fn run_search(
+    pool: &PgPool,
+    q: &str,
+    start_date: Option<chrono::NaiveDate>,
+    end_date: Option<chrono::NaiveDate>,
+    limit: i64,
+) -> std::result::Result<SearchResults, sqlx::Error> {
+    let dated = |sql| {
+        sqlx::query_as::<_, SearchHit>(sql)
+            .bind(q)
+            .bind(limit)
+            .bind(start_date)
+            .bind(end_date)
+    };
+    let undated = |sql| sqlx::query_as::<_, SearchHit>(sql).bind(q).bind(limit);
+
+    Ok(SearchResults {
+        meals: dated(MEAL_SEARCH).fetch_all(pool).await?,
+        events: dated(EVENT_SEARCH).fetch_all(pool).await?,
+        drinks: dated(DRINK_SEARCH).fetch_all(pool).await?,
+        recipes: undated(RECIPE_SEARCH).fetch_all(pool).await?,
+        restaurants: undated(RESTAURANT_SEARCH).fetch_all(pool).await?,
+        products: undated(PRODUCT_SEARCH).fetch_all(pool).await?,
+        people: undated(PEOPLE_SEARCH).fetch_all(pool).await?,
+    })
+}
@@ -0,0 +1,103 @@
+use crate::error::{AppError, Result, ResultExt};
+use crate::handlers::listing::parse_ids;
+use crate::ingredients;
+use crate::models::ingredient::{Ingredient, ShoppingListQuery};
+use actix_web::{web, HttpResponse};
+use sqlx::{FromRow, PgPool};
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/shopping-list").route(web::get().to(get_shopping_list)));
+}
+
+#[derive(FromRow)]
+struct RecipeLine {
+    recipe: i32,
+    #[sqlx(flatten)]
+    line: Ingredient,
+}
+
+/// Everything needed to cook the given recipes, or the recipes of the meals
+/// logged and planned in a date range, with quantities merged per ingredient.
+async fn get_shopping_list(
+    pool: web::Data<PgPool>,
+    query: web::Query<ShoppingListQuery>,
+) -> Result<HttpResponse> {
+    let context = "Failed to build shopping list";
+    if query.servings.is_some_and(|servings| servings <= 0) {
+        return Err(AppError::bad_request("servings must be positive"));
+    }
+
+    let recipe_ids = match (&query.recipe_ids, query.start_date, query.end_date) {
+        (Some(ids), None, None) => parse_ids(ids, "recipe_ids")?,
+        (None, Some(start_date), Some(end_date)) => sqlx::query_scalar!(
+            r#"
+            SELECT recipe AS "recipe!" FROM (
+                SELECT m.date, m.id, mr.recipe FROM meal_recipe mr
+                JOIN meal m ON m.id = mr.meal
+                WHERE m.date BETWEEN $1 AND $2 AND m.deleted_at IS NULL
+                UNION ALL
+                SELECT p.date, p.id, s.recipe FROM meal_plan_source s
+                JOIN meal_plan p ON p.id = s.plan
+                JOIN recipe r ON r.id = s.recipe AND r.deleted_at IS NULL
+                WHERE p.date BETWEEN $1 AND $2 AND p.meal IS NULL
+            ) planned
+            ORDER BY date, id
+            "#,
+            start_date,
+            end_date
+        )
+        .fetch_all(pool.get_ref())
+        .await
+        .context(context)?,
+        _ => {
+            return Err(AppError::bad_request(
+                "Give either recipe_ids or both start_date and end_date",
+            ))
+        }
+    };
+
+    let recipes = sqlx::query!(
+        "SELECT id, name, servings FROM recipe WHERE id = ANY($1) AND deleted_at IS NULL",
+        &recipe_ids
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context(context)?;
+
+    if let Some(unknown) = recipe_ids
+        .iter()
+        .find(|id| !recipes.iter().any(|recipe| recipe.id == **id))
+    {
+        return Err(AppError::InvalidReference {
+            field: "recipe_ids".to_string(),
+            message: format!("Unknown recipe {}", unknown),
+        });
+    }
+
+    let lines = sqlx::query_as::<_, RecipeLine>(
+        r#"
+        SELECT recipe, quantity, unit, item, note FROM recipe_ingredient
+        WHERE recipe = ANY($1)
+        ORDER BY recipe, position
+        "#,
+    )
+    .bind(&recipe_ids)
+    .fetch_all(pool.get_ref())
+    .await
+    .context(context)?;
+
+    // A recipe listed twice is cooked twice
+    let mut scaled = Vec::new();
+    for id in &recipe_ids {
+        let recipe = recipes.iter().find(|recipe| recipe.id == *id).unwrap();
+        let factor = ingredients::scale_factor(recipe.servings, query.servings);
+        scaled.extend(
+            lines
+                .iter()
+                .filter(|line| line.recipe == *id)
+                .map(|line| (recipe.name.clone(), factor, line.line.clone())),
+        );
+    }
+
+    Ok(HttpResponse::Ok().json(ingredients::shopping_list(&scaled)))
+}
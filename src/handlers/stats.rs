@@ -0,0 +1,395 @@
+use crate::error::{AppError, Result, ResultExt};
+use crate::models::stats::{
+    FoodTypeSpend, GroupBy, Period, RecipeCount, RestaurantVisits, Series, SeriesResponse,
+    SpendResponse, StatsEntity, StatsQuery, StatsValue,
+};
+use actix_web::{web, HttpResponse};
+use chrono::{Datelike, Days, Months, NaiveDate};
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+
+const DEFAULT_LIMIT: i64 = 10;
+const MAX_LIMIT: i64 = 100;
+/// Most periods a series may span, about twenty years of weeks.
+const MAX_PERIODS: usize = 1000;
+
+// Every statistics query takes an optional date range as $1 and $2, and leaves
+// out what is in the trash. Queries that bucket by period take the period name
+// as $3.
+const DATE_RANGE: &str = "m.deleted_at IS NULL \
+    AND ($1::date IS NULL OR m.date >= $1) AND ($2::date IS NULL OR m.date <= $2)";
+
+const MEAL_FOOD_SOURCES: &str = r#"
+    meal m
+    JOIN (
+        SELECT meal, type FROM meal_recipe
+        UNION ALL SELECT meal, type FROM meal_product
+        UNION ALL SELECT meal, type FROM meal_restaurant
+    ) fs ON fs.meal = m.id
+"#;
+
+const MEAL_RESTAURANTS: &str = r#"
+    meal m
+    JOIN meal_restaurant mr ON mr.meal = m.id
+    JOIN restaurant r ON r.id = mr.restaurant AND r.deleted_at IS NULL
+"#;
+
+const MEAL_PEOPLE: &str = r#"
+    meal m
+    JOIN meal_people mp ON mp.meal = m.id
+    JOIN people pe ON pe.id = mp.people AND pe.deleted_at IS NULL
+"#;
+
+// Events and drinks are aliased as `m` too, so they share DATE_RANGE.
+const EVENT_ACTIVITIES: &str = "event m JOIN activity a ON a.id = m.activity";
+
+const EVENT_PEOPLE: &str = r#"
+    event m
+    JOIN event_people ep ON ep.event = m.id
+    JOIN people pe ON pe.id = ep.people AND pe.deleted_at IS NULL
+"#;
+
+const DRINK_PEOPLE: &str = r#"
+    drink m
+    JOIN drink_people dp ON dp.drink = m.id
+    JOIN people pe ON pe.id = dp.people AND pe.deleted_at IS NULL
+"#;
+
+// Amounts summed for `value=hours` and `value=km`. Durations over midnight wrap.
+const EVENT_HOURS: &str = r#"
+    COALESCE(
+        CASE WHEN m.unit = 'h' THEN m.quantity END,
+        EXTRACT(EPOCH FROM (m.end_time - m.start_time
+            + CASE WHEN m.end_time < m.start_time THEN interval '1 day' ELSE interval '0' END)) / 3600
+    )
+"#;
+
+const EVENT_KM: &str = "CASE WHEN m.unit = 'km' THEN m.quantity END";
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/stats/series").route(web::get().to(get_series)))
+        .service(web::resource("/stats/restaurants").route(web::get().to(get_top_restaurants)))
+        .service(web::resource("/stats/spend").route(web::get().to(get_spend)))
+        .service(web::resource("/stats/recipes").route(web::get().to(get_recipe_counts)));
+}
+
+/// Tables and label expression counted for an entity grouped a given way, or
+/// None if the grouping does not apply to the entity.
+fn series_source(entity: StatsEntity, group_by: GroupBy) -> Option<(&'static str, &'static str)> {
+    use GroupBy::*;
+    use StatsEntity::*;
+
+    Some(match (entity, group_by) {
+        (Meals, None) => ("meal m", "'meals'"),
+        (Meals, MealType) => (MEAL_FOOD_SOURCES, "fs.type"),
+        (Meals, MealTime) => ("meal m", "m.\"time\""),
+        (Meals, FoodType) => (MEAL_RESTAURANTS, "r.type"),
+        (Meals, Location) => (MEAL_RESTAURANTS, "r.location"),
+        (Meals, Person) => (MEAL_PEOPLE, "pe.name"),
+        (Events, None) => ("event m", "'events'"),
+        (Events, ActivityType) => (EVENT_ACTIVITIES, "a.type"),
+        (Events, Activity) => (EVENT_ACTIVITIES, "a.name"),
+        (Events, Location) => ("event m", "COALESCE(m.location, 'unknown')"),
+        (Events, Person) => (EVENT_PEOPLE, "pe.name"),
+        (Drinks, None) => ("drink m", "'drinks'"),
+        (Drinks, Person) => (DRINK_PEOPLE, "pe.name"),
+        _ => return Option::None,
+    })
+}
+
+async fn get_series(
+    pool: web::Data<PgPool>,
+    query: web::Query<StatsQuery>,
+) -> Result<HttpResponse> {
+    let entity = query.entity.unwrap_or(StatsEntity::Meals);
+    let period = query.period.unwrap_or(Period::Month);
+    let group_by = query.group_by.unwrap_or(GroupBy::None);
+
+    let (from, label) = series_source(entity, group_by).ok_or_else(|| {
+        AppError::bad_request(format!(
+            "Cannot group {} by {}",
+            enum_name(&entity),
+            enum_name(&group_by)
+        ))
+    })?;
+
+    let amount = match (entity, query.value.unwrap_or(StatsValue::Count)) {
+        (_, StatsValue::Count) => None,
+        (StatsEntity::Events, StatsValue::Hours) => Some(EVENT_HOURS),
+        (StatsEntity::Events, StatsValue::Km) => Some(EVENT_KM),
+        (_, value) => {
+            return Err(AppError::bad_request(format!(
+                "Cannot sum {} of {}",
+                enum_name(&value),
+                enum_name(&entity)
+            )))
+        }
+    };
+    if let Some(amount) = amount {
+        let rows: Vec<(NaiveDate, String, f64)> = sqlx::query_as(&format!(
+            r#"
+            SELECT date_trunc($3, m.date)::date AS period, {label} AS label, SUM({amount})::float8 AS value
+            FROM {from}
+            WHERE {DATE_RANGE} AND {amount} IS NOT NULL
+            GROUP BY 1, 2
+            "#
+        ))
+        .bind(query.start_date)
+        .bind(query.end_date)
+        .bind(enum_name(&period))
+        .fetch_all(pool.get_ref())
+        .await
+        .context("Failed to fetch statistics")?;
+
+        return Ok(HttpResponse::Ok().json(build_series(
+            entity,
+            period,
+            group_by,
+            query.start_date,
+            query.end_date,
+            rows,
+        )?));
+    }
+
+    let sql = format!(
+        r#"
+        SELECT date_trunc($3, m.date)::date AS period, {label} AS label, COUNT(DISTINCT m.id) AS value
+        FROM {from}
+        WHERE {DATE_RANGE}
+        GROUP BY 1, 2
+        "#
+    );
+    let rows: Vec<(NaiveDate, String, i64)> = sqlx::query_as(&sql)
+        .bind(query.start_date)
+        .bind(query.end_date)
+        .bind(enum_name(&period))
+        .fetch_all(pool.get_ref())
+        .await
+        .context("Failed to fetch statistics")?;
+
+    Ok(HttpResponse::Ok().json(build_series(
+        entity,
+        period,
+        group_by,
+        query.start_date,
+        query.end_date,
+        rows,
+    )?))
+}
+
+async fn get_top_restaurants(
+    pool: web::Data<PgPool>,
+    query: web::Query<StatsQuery>,
+) -> Result<HttpResponse> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    let restaurants = sqlx::query_as::<_, RestaurantVisits>(&format!(
+        r#"
+        SELECT
+            r.id AS restaurant_id, r.name, r.location, r.type AS food_type,
+            COUNT(DISTINCT m.id) AS visits, MAX(m.date) AS last_visit
+        FROM {MEAL_RESTAURANTS}
+        WHERE {DATE_RANGE}
+        GROUP BY r.id
+        ORDER BY visits DESC, last_visit DESC, r.id
+        LIMIT $3
+        "#
+    ))
+    .bind(query.start_date)
+    .bind(query.end_date)
+    .bind(limit)
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to fetch restaurant statistics")?;
+
+    Ok(HttpResponse::Ok().json(restaurants))
+}
+
+/// Spend in one currency, since amounts are not converted: visits paid in
+/// `currency`, or without it those with no currency recorded, which fall back to
+/// the restaurant's price.
+async fn get_spend(pool: web::Data<PgPool>, query: web::Query<StatsQuery>) -> Result<HttpResponse> {
+    let period = query.period.unwrap_or(Period::Month);
+    let currency = query.currency.as_deref().map(str::to_uppercase);
+    if let Some(currency) = &currency {
+        if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err(AppError::bad_request(format!(
+                "Invalid currency '{}'; use an ISO 4217 code like USD",
+                currency
+            )));
+        }
+    }
+
+    let by_food_type = sqlx::query_as::<_, FoodTypeSpend>(&format!(
+        r#"
+        SELECT
+            r.type AS food_type,
+            COUNT(DISTINCT m.id) AS visits,
+            COALESCE(SUM(COALESCE(mr.amount, r.price)), 0)::float8 AS spend
+        FROM {MEAL_RESTAURANTS}
+        WHERE {DATE_RANGE} AND mr.currency IS NOT DISTINCT FROM $3
+        GROUP BY r.type
+        ORDER BY spend DESC, r.type
+        "#
+    ))
+    .bind(query.start_date)
+    .bind(query.end_date)
+    .bind(&currency)
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to fetch spend statistics")?;
+
+    let rows: Vec<(NaiveDate, String, f64)> = sqlx::query_as(&format!(
+        r#"
+        SELECT date_trunc($3, m.date)::date AS period, r.type AS label, COALESCE(SUM(COALESCE(mr.amount, r.price)), 0)::float8 AS value
+        FROM {MEAL_RESTAURANTS}
+        WHERE {DATE_RANGE} AND mr.currency IS NOT DISTINCT FROM $4
+        GROUP BY 1, 2
+        "#
+    ))
+    .bind(query.start_date)
+    .bind(query.end_date)
+    .bind(enum_name(&period))
+    .bind(&currency)
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to fetch spend statistics")?;
+
+    Ok(HttpResponse::Ok().json(SpendResponse {
+        currency,
+        by_food_type,
+        over_time: build_series(
+            StatsEntity::Meals,
+            period,
+            GroupBy::FoodType,
+            query.start_date,
+            query.end_date,
+            rows,
+        )?,
+    }))
+}
+
+async fn get_recipe_counts(
+    pool: web::Data<PgPool>,
+    query: web::Query<StatsQuery>,
+) -> Result<HttpResponse> {
+    let recipes = sqlx::query_as::<_, RecipeCount>(&format!(
+        r#"
+        SELECT
+            r.id AS recipe_id, r.name,
+            COUNT(DISTINCT m.id) AS times_made, MAX(m.date) AS last_made
+        FROM meal m
+        JOIN meal_recipe mr ON mr.meal = m.id
+        JOIN recipe r ON r.id = mr.recipe AND r.deleted_at IS NULL
+        WHERE {DATE_RANGE}
+        GROUP BY r.id
+        ORDER BY times_made DESC, r.name
+        "#
+    ))
+    .bind(query.start_date)
+    .bind(query.end_date)
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to fetch recipe statistics")?;
+
+    Ok(HttpResponse::Ok().json(recipes))
+}
+
+/// Pivot `(period, label, value)` rows into one series per label, with a value for
+/// every period between the range bounds (or the first and last row if unbounded).
+/// Series are ordered by descending total. Ranges spanning more than
+/// `MAX_PERIODS` periods are rejected.
+fn build_series<T>(
+    entity: StatsEntity,
+    period: Period,
+    group_by: GroupBy,
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+    rows: Vec<(NaiveDate, String, T)>,
+) -> Result<SeriesResponse<T>>
+where
+    T: Copy + Default + PartialOrd + std::ops::AddAssign,
+{
+    let first = start_date
+        .map(|date| period_start(date, period))
+        .or_else(|| rows.iter().map(|(p, _, _)| *p).min());
+    let last = end_date
+        .map(|date| period_start(date, period))
+        .or_else(|| rows.iter().map(|(p, _, _)| *p).max());
+
+    let mut periods = Vec::new();
+    if let (Some(first), Some(last)) = (first, last) {
+        let mut current = first;
+        while current <= last {
+            if periods.len() == MAX_PERIODS {
+                return Err(AppError::bad_request(format!(
+                    "Date range spans more than {} {}s; narrow it or use a longer period",
+                    MAX_PERIODS,
+                    enum_name(&period)
+                )));
+            }
+            periods.push(current);
+            current = next_period(current, period);
+        }
+    }
+
+    let mut by_label: BTreeMap<String, Vec<T>> = BTreeMap::new();
+    for (row_period, label, value) in rows {
+        let Ok(index) = periods.binary_search(&row_period) else {
+            continue;
+        };
+        let data = by_label
+            .entry(label)
+            .or_insert_with(|| vec![T::default(); periods.len()]);
+        data[index] += value;
+    }
+
+    let mut series: Vec<Series<T>> = by_label
+        .into_iter()
+        .map(|(name, data)| {
+            let mut total = T::default();
+            for value in &data {
+                total += *value;
+            }
+            Series { name, total, data }
+        })
+        .collect();
+    series.sort_by(|a, b| {
+        b.total
+            .partial_cmp(&a.total)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    Ok(SeriesResponse {
+        entity,
+        period,
+        group_by,
+        periods,
+        series,
+    })
+}
+
+/// First day of the period containing `date`, matching Postgres' `date_trunc`.
+fn period_start(date: NaiveDate, period: Period) -> NaiveDate {
+    match period {
+        Period::Week => date - Days::new(u64::from(date.weekday().num_days_from_monday())),
+        Period::Month => date.with_day(1).unwrap_or(date),
+        Period::Year => date.with_ordinal(1).unwrap_or(date),
+    }
+}
+
+fn next_period(start: NaiveDate, period: Period) -> NaiveDate {
+    match period {
+        Period::Week => start + Days::new(7),
+        Period::Month => start + Months::new(1),
+        Period::Year => start + Months::new(12),
+    }
+}
+
+/// The query-string spelling of a stats enum value.
+fn enum_name<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
@@ -0,0 +1,176 @@
+use crate::error::{AppError, Result, ResultExt};
+use crate::handlers::listing::parse_ids;
+use crate::models::suggestion::{Candidate, Companion, SuggestionQuery};
+use crate::suggestions::{self, Context};
+use crate::util;
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+const DEFAULT_LIMIT: i64 = 5;
+const MAX_LIMIT: i64 = 50;
+const DEFAULT_VARIETY_DAYS: i32 = 7;
+const MAX_VARIETY_DAYS: i32 = 365;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/suggestions/meal").route(web::get().to(suggest_meal)));
+}
+
+// Every recipe and restaurant with its history before $1. $2 is the meal time
+// and $3 the ids of the joining companions.
+const CANDIDATES: &str = r#"
+    WITH sources AS (
+        SELECT 'recipe' AS kind, mr.recipe AS id, m.id AS meal, m.date, m."time"
+        FROM meal_recipe mr JOIN meal m ON m.id = mr.meal
+        WHERE m.date < $1 AND m.deleted_at IS NULL
+        UNION ALL
+        SELECT 'restaurant', mr.restaurant, m.id, m.date, m."time"
+        FROM meal_restaurant mr JOIN meal m ON m.id = mr.meal
+        WHERE m.date < $1 AND m.deleted_at IS NULL
+    ),
+    candidates AS (
+        SELECT 'recipe' AS kind, id, name, NULL::text AS food_type FROM recipe
+        WHERE deleted_at IS NULL
+        UNION ALL
+        SELECT 'restaurant', id, name, type FROM restaurant WHERE deleted_at IS NULL
+    )
+    SELECT
+        c.kind,
+        c.id,
+        c.name,
+        c.food_type,
+        MAX(s.date) AS last_eaten,
+        COUNT(s.meal) AS times_eaten,
+        COUNT(s.meal) FILTER (WHERE s."time" = $2) AS times_at_time,
+        ARRAY(
+            SELECT DISTINCT mp.people FROM sources s2
+            JOIN meal_people mp ON mp.meal = s2.meal
+            WHERE s2.kind = c.kind AND s2.id = c.id AND mp.people = ANY($3)
+        ) AS companions_joined
+    FROM candidates c
+    LEFT JOIN sources s ON s.kind = c.kind AND s.id = c.id
+    GROUP BY c.kind, c.id, c.name, c.food_type
+"#;
+
+/// Recipes and restaurants worth having at a meal time, best first.
+async fn suggest_meal(
+    pool: web::Data<PgPool>,
+    query: web::Query<SuggestionQuery>,
+) -> Result<HttpResponse> {
+    let context = "Failed to suggest meals";
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(AppError::bad_request(format!(
+            "limit must be between 1 and {}",
+            MAX_LIMIT
+        )));
+    }
+    let variety_days = query.variety_days.unwrap_or(DEFAULT_VARIETY_DAYS);
+    if !(0..=MAX_VARIETY_DAYS).contains(&variety_days) {
+        return Err(AppError::bad_request(format!(
+            "variety_days must be between 0 and {}",
+            MAX_VARIETY_DAYS
+        )));
+    }
+    let people_ids = match &query.people_ids {
+        Some(ids) if !ids.trim().is_empty() => parse_ids(ids, "people_ids")?,
+        _ => Vec::new(),
+    };
+    let today = query.date.unwrap_or_else(util::today);
+
+    let time_exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM meal_time WHERE name = $1) AS "exists!""#,
+        query.time
+    )
+    .fetch_one(pool.get_ref())
+    .await
+    .context(context)?;
+    if !time_exists {
+        return Err(AppError::InvalidReference {
+            field: "time".to_string(),
+            message: format!("Unknown meal time '{}'", query.time),
+        });
+    }
+
+    let people = sqlx::query!(
+        r#"
+        SELECT
+            p.id,
+            p.name,
+            p.household_order IS NOT NULL AS "in_household!",
+            (SELECT COUNT(*) FROM meal_people mp JOIN meal m ON m.id = mp.meal
+             WHERE mp.people = p.id AND m.date < $2 AND m.deleted_at IS NULL) AS "meals!"
+        FROM people p
+        WHERE p.id = ANY($1) AND p.deleted_at IS NULL
+        ORDER BY p.name
+        "#,
+        &people_ids,
+        today
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context(context)?;
+
+    if let Some(unknown) = people_ids
+        .iter()
+        .find(|id| !people.iter().any(|person| person.id == **id))
+    {
+        return Err(AppError::InvalidReference {
+            field: "people_ids".to_string(),
+            message: format!("Unknown person {}", unknown),
+        });
+    }
+
+    // The household eats everything together, so only the others tell
+    // places apart.
+    let companions: Vec<Companion> = people
+        .into_iter()
+        .filter(|person| !person.in_household)
+        .map(|person| Companion {
+            id: person.id,
+            name: person.name,
+            meals: person.meals,
+        })
+        .collect();
+    let companion_ids: Vec<i32> = companions.iter().map(|companion| companion.id).collect();
+
+    let candidates = sqlx::query_as::<_, Candidate>(CANDIDATES)
+        .bind(today)
+        .bind(&query.time)
+        .bind(&companion_ids)
+        .fetch_all(pool.get_ref())
+        .await
+        .context(context)?;
+
+    let recent_food_types = sqlx::query!(
+        r#"
+        SELECT r.type AS food_type, MAX(m.date) AS "last_eaten!"
+        FROM meal m
+        JOIN meal_restaurant mr ON mr.meal = m.id
+        JOIN restaurant r ON r.id = mr.restaurant
+        WHERE m.date < $1 AND m.date >= $1 - $2::int AND m.deleted_at IS NULL
+        GROUP BY r.type
+        "#,
+        today,
+        variety_days
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context(context)?
+    .into_iter()
+    .map(|row| (row.food_type, row.last_eaten))
+    .collect();
+
+    let mut ranked = suggestions::rank(
+        candidates,
+        &Context {
+            today,
+            time: query.time.clone(),
+            variety_days,
+            recent_food_types,
+            companions,
+        },
+    );
+    ranked.truncate(limit as usize);
+
+    Ok(HttpResponse::Ok().json(ranked))
+}
@@ -0,0 +1,296 @@
+use crate::auth::AuthUser;
+use crate::error::{AppError, Result, ResultExt};
+use crate::handlers::listing::{check_filters, ListBuilder};
+use crate::history;
+use crate::models::history::{HistoryAction, HistoryEntity};
+use crate::models::list::ListQuery;
+use crate::models::template::{
+    AppliedTemplate, ApplyQuery, CreateTemplate, EntryKind, PendingEntry, Template,
+};
+use crate::templates;
+use crate::util::{self, capitalize};
+use actix_web::{web, HttpResponse};
+use sqlx::types::Json;
+use sqlx::PgPool;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/templates")
+            .route(web::get().to(get_templates))
+            .route(web::post().to(create_template)),
+    )
+    .service(
+        web::resource("/templates/{id}")
+            .route(web::get().to(get_template))
+            .route(web::put().to(update_template))
+            .route(web::delete().to(delete_template)),
+    )
+    .service(web::resource("/templates/{id}/apply").route(web::post().to(apply_template)))
+    .service(web::resource("/pending").route(web::get().to(get_pending)))
+    .service(web::resource("/pending/{id}").route(web::delete().to(dismiss_pending)))
+    .service(web::resource("/pending/{id}/confirm").route(web::post().to(confirm_pending)));
+}
+
+async fn get_templates(
+    pool: web::Data<PgPool>,
+    query: web::Query<ListQuery>,
+) -> Result<HttpResponse> {
+    check_filters(&query, "templates", &[])?;
+
+    let page = ListBuilder::new(
+        "SELECT id, name, kind, body, recurrence FROM template",
+        &query,
+        None,
+        "id",
+    )
+    .fetch::<Template>(&pool, "Failed to fetch templates")
+    .await?;
+
+    Ok(page.into_response())
+}
+
+async fn fetch_template(pool: &PgPool, template_id: i32) -> Result<Template> {
+    sqlx::query_as::<_, Template>(
+        "SELECT id, name, kind, body, recurrence FROM template WHERE id = $1",
+    )
+    .bind(template_id)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch template")?
+    .ok_or_else(|| AppError::not_found("Template not found"))
+}
+
+async fn get_template(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse> {
+    let template = fetch_template(&pool, path.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(template))
+}
+
+fn validate_template(template_data: &CreateTemplate) -> Result<()> {
+    if template_data.name.trim().is_empty() {
+        return Err(AppError::bad_request("Template name must not be empty"));
+    }
+    templates::validate(template_data.kind, &template_data.body)
+}
+
+async fn create_template(
+    pool: web::Data<PgPool>,
+    template_data: web::Json<CreateTemplate>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    validate_template(&template_data)?;
+
+    let mut tx = pool.begin().await.context("Failed to create template")?;
+
+    let template = sqlx::query_as::<_, Template>(
+        r#"
+        INSERT INTO template (name, kind, body, recurrence)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, name, kind, body, recurrence
+        "#,
+    )
+    .bind(template_data.name.trim())
+    .bind(template_data.kind)
+    .bind(&template_data.body)
+    .bind(template_data.recurrence.clone().map(Json))
+    .fetch_one(&mut *tx)
+    .await
+    .context("Failed to create template")?;
+    history::record(
+        &mut tx,
+        HistoryEntity::Template,
+        template.id,
+        HistoryAction::Create,
+        None,
+        user.as_ref(),
+    )
+    .await?;
+
+    tx.commit().await.context("Failed to create template")?;
+
+    Ok(HttpResponse::Created().json(template))
+}
+
+async fn update_template(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    template_data: web::Json<CreateTemplate>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    validate_template(&template_data)?;
+    let template_id = path.into_inner();
+
+    let mut tx = pool.begin().await.context("Failed to update template")?;
+    let before = history::snapshot(&mut tx, HistoryEntity::Template, template_id).await?;
+
+    let template = sqlx::query_as::<_, Template>(
+        r#"
+        UPDATE template SET name = $1, kind = $2, body = $3, recurrence = $4
+        WHERE id = $5
+        RETURNING id, name, kind, body, recurrence
+        "#,
+    )
+    .bind(template_data.name.trim())
+    .bind(template_data.kind)
+    .bind(&template_data.body)
+    .bind(template_data.recurrence.clone().map(Json))
+    .bind(template_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .context("Failed to update template")?
+    .ok_or_else(|| AppError::not_found("Template not found"))?;
+    history::record(
+        &mut tx,
+        HistoryEntity::Template,
+        template_id,
+        HistoryAction::Update,
+        before,
+        user.as_ref(),
+    )
+    .await?;
+
+    tx.commit().await.context("Failed to update template")?;
+
+    Ok(HttpResponse::Ok().json(template))
+}
+
+/// Deleting a template also deletes its pending entries; entries already
+/// created from it are kept.
+async fn delete_template(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let template_id = path.into_inner();
+    let mut tx = pool.begin().await.context("Failed to delete template")?;
+    let before = history::snapshot(&mut tx, HistoryEntity::Template, template_id).await?;
+
+    let result = sqlx::query("DELETE FROM template WHERE id = $1")
+        .bind(template_id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete template")?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Template not found"));
+    }
+    history::record(
+        &mut tx,
+        HistoryEntity::Template,
+        template_id,
+        HistoryAction::Delete,
+        before,
+        user.as_ref(),
+    )
+    .await?;
+
+    tx.commit().await.context("Failed to delete template")?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Template deleted successfully"
+    })))
+}
+
+fn applied_response(kind: EntryKind, id: i32) -> HttpResponse {
+    HttpResponse::Created().json(AppliedTemplate {
+        kind,
+        id,
+        message: format!("{} created from template", capitalize(kind.as_str())),
+    })
+}
+
+/// Create the template's meal, event or drink on the given date right away.
+async fn apply_template(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    query: web::Query<ApplyQuery>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let template = fetch_template(&pool, path.into_inner()).await?;
+    let date = query.date.unwrap_or_else(util::today);
+
+    let mut tx = pool.begin().await.context("Failed to apply template")?;
+    let id = templates::apply(&mut tx, template.kind, &template.body, date, user.as_ref()).await?;
+    tx.commit().await.context("Failed to apply template")?;
+
+    Ok(applied_response(template.kind, id))
+}
+
+const PENDING: &str = r#"
+    SELECT p.id, p.template AS template_id, t.name, t.kind, t.body, p.date
+    FROM pending_entry p
+    JOIN template t ON t.id = p.template
+"#;
+
+/// Entries waiting to be confirmed, newest first.
+async fn get_pending(
+    pool: web::Data<PgPool>,
+    query: web::Query<ListQuery>,
+) -> Result<HttpResponse> {
+    check_filters(&query, "pending entries", &["start_date", "end_date"])?;
+
+    let mut list = ListBuilder::new(PENDING, &query, Some("p.date"), "p.id");
+    list.filter("p.status = {}", "pending");
+
+    let page = list
+        .fetch::<PendingEntry>(&pool, "Failed to fetch pending entries")
+        .await?;
+
+    Ok(page.into_response())
+}
+
+/// Create the entry a pending entry stands for, from the current template.
+async fn confirm_pending(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let pending_id = path.into_inner();
+    let mut tx = pool.begin().await.context("Failed to confirm entry")?;
+
+    let pending = sqlx::query_as::<_, PendingEntry>(&format!(
+        "{PENDING} WHERE p.id = $1 AND p.status = 'pending' FOR UPDATE OF p"
+    ))
+    .bind(pending_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .context("Failed to confirm entry")?
+    .ok_or_else(|| AppError::not_found("Pending entry not found"))?;
+
+    let id = templates::apply(
+        &mut tx,
+        pending.kind,
+        &pending.body,
+        pending.date,
+        user.as_ref(),
+    )
+    .await?;
+    sqlx::query("UPDATE pending_entry SET status = 'confirmed', entry_id = $1 WHERE id = $2")
+        .bind(id)
+        .bind(pending_id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to confirm entry")?;
+
+    tx.commit().await.context("Failed to confirm entry")?;
+
+    Ok(applied_response(pending.kind, id))
+}
+
+async fn dismiss_pending(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse> {
+    let result = sqlx::query(
+        "UPDATE pending_entry SET status = 'dismissed' WHERE id = $1 AND status = 'pending'",
+    )
+    .bind(path.into_inner())
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to dismiss entry")?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Pending entry not found"));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Pending entry dismissed"
+    })))
+}
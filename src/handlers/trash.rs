@@ -0,0 +1,55 @@
+use crate::auth::AuthUser;
+use crate::error::Result;
+use crate::models::trash::{TrashKind, TrashQuery};
+use crate::trash;
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/trash")
+            .route(web::get().to(get_trash))
+            .route(web::delete().to(empty_trash)),
+    )
+    .service(web::resource("/trash/{kind}/{id}").route(web::delete().to(purge_item)))
+    .service(web::resource("/trash/{kind}/{id}/restore").route(web::post().to(restore_item)));
+}
+
+async fn get_trash(pool: web::Data<PgPool>, query: web::Query<TrashQuery>) -> Result<HttpResponse> {
+    let items = trash::list(&pool, query.kind).await?;
+
+    Ok(HttpResponse::Ok().json(items))
+}
+
+async fn restore_item(
+    pool: web::Data<PgPool>,
+    path: web::Path<(TrashKind, i32)>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let (kind, id) = path.into_inner();
+    trash::restore(&pool, kind, id, user.as_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": format!("{} restored", kind.label())
+    })))
+}
+
+async fn purge_item(
+    pool: web::Data<PgPool>,
+    path: web::Path<(TrashKind, i32)>,
+    user: Option<AuthUser>,
+) -> Result<HttpResponse> {
+    let (kind, id) = path.into_inner();
+    trash::purge(&pool, kind, id, user.as_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": format!("{} purged", kind.label())
+    })))
+}
+
+/// Purge everything in the trash that is no longer used.
+async fn empty_trash(pool: web::Data<PgPool>, user: Option<AuthUser>) -> Result<HttpResponse> {
+    let report = trash::purge_all(&pool, None, user.as_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(report))
+}
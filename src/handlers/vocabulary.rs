@@ -0,0 +1,298 @@
+use crate::auth::AuthUser;
+use crate::error::{AppError, Result, ResultExt};
+use crate::history;
+use crate::models::history::{HistoryAction, HistoryEntity};
+use crate::models::template::EntryKind;
+use crate::models::vocabulary::VocabularyReference;
+use crate::util::capitalize;
+use sqlx::{PgPool, Postgres, Transaction};
+
+/// A column in another table that stores values of a vocabulary.
+pub struct Reference {
+    pub table: &'static str,
+    pub column: &'static str,
+    /// Singular name used in error messages, e.g. "restaurant".
+    pub kind: &'static str,
+    /// Key under which referencing records are listed in 409 responses.
+    pub key: &'static str,
+    /// SQL expression describing a referencing row.
+    pub label: &'static str,
+    /// The kind of record `table` holds, so rewritten rows are logged.
+    pub entity: HistoryEntity,
+}
+
+/// A name-keyed lookup table such as `location` or `drink_option`, together with
+/// every column that refers to it. Columns without a foreign key (like
+/// `event.location`) are listed too, so renames and merges keep them in sync.
+pub struct Vocabulary {
+    pub table: &'static str,
+    /// Human readable name used in messages, e.g. "food type".
+    pub name: &'static str,
+    pub references: &'static [Reference],
+    /// Template body fields holding values of the vocabulary, by the kind of
+    /// entry the template is for.
+    pub template_fields: &'static [(EntryKind, &'static str)],
+}
+
+impl Vocabulary {
+    pub async fn create(&self, pool: &PgPool, name: &str) -> Result<String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::bad_request(format!(
+                "{} name must not be empty",
+                capitalize(self.name)
+            )));
+        }
+
+        sqlx::query(&format!("INSERT INTO {} (name) VALUES ($1)", self.table))
+            .bind(name)
+            .execute(pool)
+            .await
+            .context(&format!("Failed to create {}", self.name))?;
+
+        Ok(name.to_string())
+    }
+
+    /// Rename `old` to `new`, rewriting every reference in one transaction.
+    pub async fn rename(
+        &self,
+        pool: &PgPool,
+        old: &str,
+        new: &str,
+        actor: Option<&AuthUser>,
+    ) -> Result<String> {
+        let new = new.trim();
+        if new.is_empty() {
+            return Err(AppError::bad_request(format!(
+                "{} name must not be empty",
+                capitalize(self.name)
+            )));
+        }
+
+        let context = format!("Failed to rename {}", self.name);
+        let mut tx = pool.begin().await.context(&context)?;
+
+        if !self.exists(&mut tx, old).await? {
+            return Err(self.not_found());
+        }
+        if old == new {
+            return Ok(new.to_string());
+        }
+        if self.exists(&mut tx, new).await? {
+            return Err(AppError::AlreadyExists(format!(
+                "{} '{}' already exists; delete '{}' with merge_into to combine them",
+                capitalize(self.name),
+                new,
+                old
+            )));
+        }
+
+        sqlx::query(&format!("INSERT INTO {} (name) VALUES ($1)", self.table))
+            .bind(new)
+            .execute(&mut *tx)
+            .await
+            .context(&context)?;
+        self.move_references(&mut tx, old, new, actor).await?;
+        self.delete_row(&mut tx, old).await?;
+
+        tx.commit().await.context(&context)?;
+
+        Ok(new.to_string())
+    }
+
+    /// Delete `name`. If it is still referenced the delete is refused with a list of
+    /// the referencing records, unless `merge_into` names a value to move them to.
+    pub async fn delete(
+        &self,
+        pool: &PgPool,
+        name: &str,
+        merge_into: Option<&str>,
+        actor: Option<&AuthUser>,
+    ) -> Result<()> {
+        let context = format!("Failed to delete {}", self.name);
+        let mut tx = pool.begin().await.context(&context)?;
+
+        if !self.exists(&mut tx, name).await? {
+            return Err(self.not_found());
+        }
+
+        match merge_into {
+            Some(target) => {
+                if target == name {
+                    return Err(AppError::bad_request(format!(
+                        "Cannot merge {} '{}' into itself",
+                        self.name, name
+                    )));
+                }
+                if !self.exists(&mut tx, target).await? {
+                    return Err(AppError::InvalidReference {
+                        field: "merge_into".to_string(),
+                        message: format!("Unknown {} '{}'", self.name, target),
+                    });
+                }
+                self.move_references(&mut tx, name, target, actor).await?;
+            }
+            None => self.ensure_unreferenced(&mut tx, name).await?,
+        }
+
+        self.delete_row(&mut tx, name).await?;
+
+        tx.commit().await.context(&context)?;
+
+        Ok(())
+    }
+
+    fn not_found(&self) -> AppError {
+        AppError::not_found(format!("{} not found", capitalize(self.name)))
+    }
+
+    async fn exists(&self, tx: &mut Transaction<'_, Postgres>, name: &str) -> Result<bool> {
+        let found: Option<String> =
+            sqlx::query_scalar(&format!("SELECT name FROM {} WHERE name = $1", self.table))
+                .bind(name)
+                .fetch_optional(&mut **tx)
+                .await
+                .context(&format!("Failed to fetch {}", self.name))?;
+
+        Ok(found.is_some())
+    }
+
+    /// Point every reference to `from` at `to`, logging each rewritten record.
+    async fn move_references(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        from: &str,
+        to: &str,
+        actor: Option<&AuthUser>,
+    ) -> Result<()> {
+        let context = format!("Failed to update {} references", self.name);
+
+        for reference in self.references {
+            let ids: Vec<i32> = sqlx::query_scalar(&format!(
+                "SELECT id FROM {table} WHERE {column} = $1 ORDER BY id FOR UPDATE",
+                table = reference.table,
+                column = reference.column
+            ))
+            .bind(from)
+            .fetch_all(&mut **tx)
+            .await
+            .context(&context)?;
+
+            let mut before = Vec::new();
+            for id in &ids {
+                before.push(history::snapshot(tx, reference.entity, *id).await?);
+            }
+
+            sqlx::query(&format!(
+                "UPDATE {table} SET {column} = $2 WHERE {column} = $1",
+                table = reference.table,
+                column = reference.column
+            ))
+            .bind(from)
+            .bind(to)
+            .execute(&mut **tx)
+            .await
+            .context(&context)?;
+
+            for (id, before) in ids.into_iter().zip(before) {
+                history::record(
+                    tx,
+                    reference.entity,
+                    id,
+                    HistoryAction::Update,
+                    before,
+                    actor,
+                )
+                .await?;
+            }
+        }
+
+        for (kind, field) in self.template_fields {
+            let ids: Vec<i32> = sqlx::query_scalar(
+                "SELECT id FROM template WHERE kind = $1 AND body->>$2 = $3 ORDER BY id FOR UPDATE",
+            )
+            .bind(kind)
+            .bind(field)
+            .bind(from)
+            .fetch_all(&mut **tx)
+            .await
+            .context(&context)?;
+
+            for id in ids {
+                let before = history::snapshot(tx, HistoryEntity::Template, id).await?;
+                sqlx::query(
+                    "UPDATE template SET body = jsonb_set(body, ARRAY[$2], to_jsonb($3::text)) WHERE id = $1",
+                )
+                .bind(id)
+                .bind(field)
+                .bind(to)
+                .execute(&mut **tx)
+                .await
+                .context(&context)?;
+                history::record(
+                    tx,
+                    HistoryEntity::Template,
+                    id,
+                    HistoryAction::Update,
+                    before,
+                    actor,
+                )
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn ensure_unreferenced(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        name: &str,
+    ) -> Result<()> {
+        let mut references = serde_json::Map::new();
+        let mut counts = Vec::new();
+
+        for reference in self.references {
+            let rows = sqlx::query_as::<_, VocabularyReference>(&format!(
+                "SELECT id, {label} AS label FROM {table} WHERE {column} = $1 ORDER BY id",
+                label = reference.label,
+                table = reference.table,
+                column = reference.column
+            ))
+            .bind(name)
+            .fetch_all(&mut **tx)
+            .await
+            .context(&format!("Failed to delete {}", self.name))?;
+
+            if !rows.is_empty() {
+                counts.push(format!("{} {}(s)", rows.len(), reference.kind));
+            }
+            references.insert(reference.key.to_string(), serde_json::json!(rows));
+        }
+
+        if counts.is_empty() {
+            return Ok(());
+        }
+
+        Err(AppError::ReferencedBy {
+            message: format!(
+                "Cannot delete {} '{}': it is referenced by {}. Pass merge_into to move them to another {}.",
+                self.name,
+                name,
+                counts.join(" and "),
+                self.name
+            ),
+            references: serde_json::Value::Object(references),
+        })
+    }
+
+    async fn delete_row(&self, tx: &mut Transaction<'_, Postgres>, name: &str) -> Result<()> {
+        sqlx::query(&format!("DELETE FROM {} WHERE name = $1", self.table))
+            .bind(name)
+            .execute(&mut **tx)
+            .await
+            .context(&format!("Cannot delete {}", self.name))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use crate::auth::AuthUser;
+use crate::error::{AppError, Result, ResultExt};
+use crate::models::history::{HistoryAction, HistoryEntity, HistoryEntry};
+use crate::trash;
+use serde_json::Value;
+use sqlx::{PgPool, Postgres, Transaction};
+
+pub const ENTRY_COLUMNS: &str = "id, entity, entity_id, action, actor, changed_at, before, after";
+
+/// The record as it is now, with its links, or None if it does not exist.
+pub async fn snapshot(
+    tx: &mut Transaction<'_, Postgres>,
+    entity: HistoryEntity,
+    id: i32,
+) -> Result<Option<Value>> {
+    let context = "Failed to record history";
+
+    let row: Option<Value> = sqlx::query_scalar(&format!(
+        "SELECT to_jsonb(t) FROM {} t WHERE id = $1",
+        entity.table()
+    ))
+    .bind(id)
+    .fetch_optional(&mut **tx)
+    .await
+    .context(context)?;
+    let Some(Value::Object(mut image)) = row else {
+        return Ok(None);
+    };
+
+    for (link, column) in entity.links() {
+        let rows: Value = sqlx::query_scalar(&format!(
+            "SELECT COALESCE(jsonb_agg(to_jsonb(l) - '{column}' ORDER BY to_jsonb(l)), '[]') \
+             FROM {link} l WHERE {column} = $1"
+        ))
+        .bind(id)
+        .fetch_one(&mut **tx)
+        .await
+        .context(context)?;
+        image.insert(link.to_string(), rows);
+    }
+
+    Ok(Some(Value::Object(image)))
+}
+
+/// Log a change to a record, taking its image after the change. `before` is
+/// the snapshot taken before it.
+pub async fn record(
+    tx: &mut Transaction<'_, Postgres>,
+    entity: HistoryEntity,
+    id: i32,
+    action: HistoryAction,
+    before: Option<Value>,
+    actor: Option<&AuthUser>,
+) -> Result<HistoryEntry> {
+    let after = snapshot(tx, entity, id).await?;
+
+    sqlx::query_as::<_, HistoryEntry>(&format!(
+        r#"
+        INSERT INTO history (entity, entity_id, action, actor, before, after)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING {ENTRY_COLUMNS}
+        "#
+    ))
+    .bind(entity)
+    .bind(id)
+    .bind(action)
+    .bind(actor.map(|user| user.username.as_str()))
+    .bind(before)
+    .bind(after)
+    .fetch_one(&mut **tx)
+    .await
+    .context("Failed to record history")
+}
+
+fn quote(column: &str) -> String {
+    format!("\"{}\"", column.replace('"', "\"\""))
+}
+
+/// Put a record back the way entry `entry_id` left it, recreating it if it was
+/// purged since, and log that as a change of its own.
+pub async fn revert(
+    pool: &PgPool,
+    entry_id: i32,
+    actor: Option<&AuthUser>,
+) -> Result<HistoryEntry> {
+    let context = "Failed to revert";
+    let mut tx = pool.begin().await.context(context)?;
+
+    let entry = sqlx::query_as::<_, HistoryEntry>(&format!(
+        "SELECT {ENTRY_COLUMNS} FROM history WHERE id = $1"
+    ))
+    .bind(entry_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .context(context)?
+    .ok_or_else(|| AppError::not_found("History entry not found"))?;
+
+    let Some(Value::Object(mut image)) = entry.after else {
+        return Err(AppError::bad_request(format!(
+            "{} {} no longer existed after this change and cannot be reverted to it",
+            entry.entity.label(),
+            entry.entity_id
+        )));
+    };
+
+    let entity = entry.entity;
+    let id = entry.entity_id;
+    let table = entity.table();
+    let before = snapshot(&mut tx, entity, id).await?;
+
+    let links: Vec<(&str, &str, Value)> = entity
+        .links()
+        .iter()
+        .map(|(link, column)| {
+            let rows = image.remove(*link).unwrap_or(Value::Array(Vec::new()));
+            (*link, *column, rows)
+        })
+        .collect();
+
+    let revert_context = format!("Cannot revert {}", entity.label().to_lowercase());
+    if before.is_some() {
+        let columns = image
+            .keys()
+            .map(|column| quote(column))
+            .collect::<Vec<_>>()
+            .join(", ");
+        sqlx::query(&format!(
+            "UPDATE {table} SET ({columns}) = \
+             (SELECT {columns} FROM jsonb_populate_record(NULL::{table}, $1)) WHERE id = $2"
+        ))
+        .bind(Value::Object(image))
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .context(&revert_context)?;
+    } else {
+        sqlx::query(&format!(
+            "INSERT INTO {table} SELECT * FROM jsonb_populate_record(NULL::{table}, $1)"
+        ))
+        .bind(Value::Object(image))
+        .execute(&mut *tx)
+        .await
+        .context(&revert_context)?;
+    }
+
+    for (link, column, rows) in links {
+        sqlx::query(&format!("DELETE FROM {link} WHERE {column} = $1"))
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .context(context)?;
+        sqlx::query(&format!(
+            r#"
+            INSERT INTO {link}
+            SELECT * FROM jsonb_populate_recordset(
+                NULL::{link},
+                (SELECT COALESCE(jsonb_agg(r || jsonb_build_object('{column}', $2::int)), '[]')
+                 FROM jsonb_array_elements($1) r)
+            )
+            "#
+        ))
+        .bind(rows)
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .context(&revert_context)?;
+    }
+
+    // A live meal cannot be made of trashed food sources
+    if entity == HistoryEntity::Meal {
+        trash::check_meal_sources(&mut tx, id).await?;
+    }
+
+    let reverted = record(&mut tx, entity, id, HistoryAction::Revert, before, actor).await?;
+
+    tx.commit().await.context(context)?;
+
+    Ok(reverted)
+}
@@ -0,0 +1,633 @@
+use crate::auth::AuthUser;
+use crate::calendar;
+use crate::error::{AppError, Result, ResultExt};
+use crate::handlers::{events, meals};
+use crate::history;
+use crate::models::event::CreateEvent;
+use crate::models::history::{HistoryAction, HistoryEntity};
+use crate::models::import::{ImportPreview, ImportRecord, NameMatch};
+use crate::models::meal::{CreateMeal, CreateMealFoodSource};
+use crate::models::restaurant::Visit;
+use crate::util::non_empty;
+use chrono::NaiveDate;
+use sqlx::PgPool;
+
+/// Names scoring below this against every candidate are left unmatched.
+pub const MATCH_THRESHOLD: f64 = 0.8;
+
+// Layout of the old spreadsheet. Each meal time has two cells, one per household
+// member in household order; the last five columns hold free-form activities.
+const DATE_COLUMN: usize = 1;
+const MIN_COLUMNS: usize = 10;
+const MEAL_COLUMNS: [(&str, [usize; 2]); 3] =
+    [("breakfast", [2, 3]), ("lunch", [4, 5]), ("dinner", [6, 7])];
+const EVENT_COLUMNS: [(usize, &str); 5] = [
+    (8, "special"),
+    (9, "exercise"),
+    (10, "activity"),
+    (11, "entertainment"),
+    (12, "housekeeping"),
+];
+const DATE_FORMATS: [&str; 3] = ["%Y-%m-%d", "%Y/%m/%d", "%m/%d/%Y"];
+
+/// The parts of a shorthand entry such as
+/// `pho, spring rolls w/ alice + bob @Ballard (birthday) bento`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Shorthand {
+    pub names: Vec<String>,
+    pub people: Vec<String>,
+    pub location: Option<String>,
+    pub comment: Option<String>,
+    /// `bento` marks a takeout meal and `leftover` a leftover one.
+    pub meal_type: Option<&'static str>,
+}
+
+/// Split a shorthand entry into names, `w/` people, `@` location, a `(...)` or
+/// `（...）` comment and the `bento`/`leftover` keywords. The markers may come
+/// in any order; names, people and dishes are separated by `,`, `，` or `+`.
+pub fn parse_shorthand(text: &str) -> Shorthand {
+    let mut shorthand = Shorthand::default();
+    let mut rest = text.to_string();
+
+    for (open, close) in [('(', ')'), ('（', '）')] {
+        if let (Some(start), Some(end)) = (rest.find(open), rest.rfind(close)) {
+            if start < end {
+                shorthand.comment = non_empty(&rest[start + open.len_utf8()..end]);
+                rest.replace_range(start..end + close.len_utf8(), " ");
+            }
+        }
+    }
+
+    // `leftover` wins if both keywords are present
+    for (keyword, meal_type) in [("bento", "takeout"), ("leftover", "leftover")] {
+        while let Some(start) = rest.to_ascii_lowercase().find(keyword) {
+            rest.replace_range(start..start + keyword.len(), " ");
+            shorthand.meal_type = Some(meal_type);
+        }
+    }
+
+    let mut markers: Vec<(usize, &str)> = ["w/", "@"]
+        .into_iter()
+        .filter_map(|marker| rest.find(marker).map(|start| (start, marker)))
+        .collect();
+    markers.sort();
+
+    for (i, (start, marker)) in markers.iter().enumerate() {
+        let end = markers.get(i + 1).map_or(rest.len(), |(next, _)| *next);
+        let value = &rest[start + marker.len()..end];
+        if *marker == "w/" {
+            shorthand.people = split_list(value);
+        } else {
+            shorthand.location = non_empty(value);
+        }
+    }
+
+    let head_end = markers.first().map_or(rest.len(), |(start, _)| *start);
+    shorthand.names = split_list(&rest[..head_end]);
+
+    shorthand
+}
+
+fn split_list(text: &str) -> Vec<String> {
+    text.split([',', '，', '+']).filter_map(non_empty).collect()
+}
+
+pub(crate) fn normalize(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+struct Candidate {
+    source: String,
+    id: i32,
+    name: String,
+    key: String,
+}
+
+/// Existing names that imported text is resolved against.
+pub struct Catalog {
+    /// Recipes, then products, then restaurants; earlier ones win ties.
+    foods: Vec<Candidate>,
+    activities: Vec<Candidate>,
+    drinks: Vec<Candidate>,
+    /// Live people once under their name and once under each alias.
+    people: Vec<Candidate>,
+    /// Household members in household order.
+    pub household: Vec<i32>,
+}
+
+impl Catalog {
+    pub async fn load(pool: &PgPool) -> Result<Self> {
+        let context = "Failed to load names for matching";
+
+        let rows: Vec<(String, i32, String)> = sqlx::query_as(
+            r#"
+            SELECT source, id, name FROM (
+                SELECT 'recipe' AS source, 1 AS rank, id, name FROM recipe
+                WHERE deleted_at IS NULL
+                UNION ALL
+                SELECT 'product', 2, id, name FROM product WHERE deleted_at IS NULL
+                UNION ALL
+                SELECT 'restaurant', 3, id, name FROM restaurant WHERE deleted_at IS NULL
+            ) f
+            ORDER BY rank, id
+            "#,
+        )
+        .fetch_all(pool)
+        .await
+        .context(context)?;
+        let foods = rows
+            .into_iter()
+            .map(|(source, id, name)| Candidate {
+                source,
+                id,
+                key: normalize(&name),
+                name,
+            })
+            .collect();
+
+        let rows: Vec<(i32, String)> = sqlx::query_as("SELECT id, name FROM activity ORDER BY id")
+            .fetch_all(pool)
+            .await
+            .context(context)?;
+        let activities = rows
+            .into_iter()
+            .map(|(id, name)| Candidate {
+                source: "activity".to_string(),
+                id,
+                key: normalize(&name),
+                name,
+            })
+            .collect();
+
+        // Drink options are keyed by name alone
+        let rows: Vec<String> = sqlx::query_scalar("SELECT name FROM drink_option ORDER BY name")
+            .fetch_all(pool)
+            .await
+            .context(context)?;
+        let drinks = rows
+            .into_iter()
+            .map(|name| Candidate {
+                source: "drink_option".to_string(),
+                id: 0,
+                key: normalize(&name),
+                name,
+            })
+            .collect();
+
+        // Matched by name or alias, but always reported under their name
+        let rows: Vec<(i32, String, String)> = sqlx::query_as(
+            r#"
+            SELECT id, name, name FROM people WHERE deleted_at IS NULL
+            UNION
+            SELECT p.id, p.name, pa.alias FROM people_alias pa
+            JOIN people p ON p.id = pa.people AND p.deleted_at IS NULL
+            ORDER BY 1, 3
+            "#,
+        )
+        .fetch_all(pool)
+        .await
+        .context(context)?;
+        let people = rows
+            .into_iter()
+            .map(|(id, name, alias)| Candidate {
+                source: "people".to_string(),
+                id,
+                name,
+                key: normalize(&alias),
+            })
+            .collect();
+
+        let household = sqlx::query_scalar(
+            r#"
+            SELECT id FROM people
+            WHERE household_order IS NOT NULL AND deleted_at IS NULL
+            ORDER BY household_order, name
+            "#,
+        )
+        .fetch_all(pool)
+        .await
+        .context(context)?;
+
+        Ok(Catalog {
+            foods,
+            activities,
+            drinks,
+            people,
+            household,
+        })
+    }
+
+    /// The recipe, product or restaurant best matching `name`.
+    pub fn match_food(&self, name: &str) -> std::result::Result<NameMatch, String> {
+        best_match(&self.foods, name, "recipe, product or restaurant")
+    }
+
+    pub fn match_activity(&self, name: &str) -> std::result::Result<NameMatch, String> {
+        best_match(&self.activities, name, "activity")
+    }
+
+    /// The name of the drink option best matching `name`.
+    pub fn match_drink(&self, name: &str) -> std::result::Result<String, String> {
+        best_match(&self.drinks, name, "drink").map(|matched| matched.name)
+    }
+
+    /// The person whose name or alias best matches `name`. A name that matches
+    /// several people equally well is ambiguous.
+    pub fn match_person(&self, name: &str) -> std::result::Result<NameMatch, String> {
+        let matched = best_match(&self.people, name, "person")?;
+        let key = normalize(name);
+        let mut others: Vec<&str> = self
+            .people
+            .iter()
+            .filter(|candidate| {
+                candidate.id != matched.id && score(candidate, &key) >= matched.score
+            })
+            .map(|candidate| candidate.name.as_str())
+            .collect();
+        others.dedup();
+
+        if others.is_empty() {
+            Ok(matched)
+        } else {
+            Err(format!(
+                "'{}' could be {} or {}",
+                name,
+                matched.name,
+                others.join(" or ")
+            ))
+        }
+    }
+
+    /// Resolve people by name or alias, keeping the given order.
+    pub fn resolve_people(&self, names: &[String]) -> std::result::Result<Vec<i32>, String> {
+        let mut ids = Vec::new();
+        let mut issues = Vec::new();
+        for name in names {
+            match self.match_person(name) {
+                Ok(matched) => push_unique(&mut ids, [matched.id]),
+                Err(issue) => issues.push(issue),
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(ids)
+        } else {
+            Err(issues.join("; "))
+        }
+    }
+}
+
+/// How well a candidate matches a normalized name, from 0 to 1.
+fn score(candidate: &Candidate, key: &str) -> f64 {
+    if candidate.key == key {
+        1.0
+    } else {
+        strsim::normalized_levenshtein(&candidate.key, key)
+    }
+}
+
+fn best_match(
+    candidates: &[Candidate],
+    name: &str,
+    kind: &str,
+) -> std::result::Result<NameMatch, String> {
+    let key = normalize(name);
+    let best = candidates
+        .iter()
+        .map(|candidate| (candidate, score(candidate, &key)))
+        .fold(None, |best, (candidate, score)| match best {
+            Some((_, best_score)) if best_score >= score => best,
+            _ => Some((candidate, score)),
+        });
+
+    match best {
+        Some((candidate, score)) if score >= MATCH_THRESHOLD => Ok(NameMatch {
+            source: candidate.source.clone(),
+            id: candidate.id,
+            name: candidate.name.clone(),
+            score,
+        }),
+        Some((candidate, score)) => Err(format!(
+            "No {} matches '{}' (closest is '{}' at {:.2})",
+            kind, name, candidate.name, score
+        )),
+        None => Err(format!("No {} matches '{}'", kind, name)),
+    }
+}
+
+/// The food source for a matched name, with the meal type implied by the source
+/// unless the entry said otherwise.
+pub fn food_source(matched: &NameMatch, meal_type: Option<&str>) -> CreateMealFoodSource {
+    let default_type = match matched.source.as_str() {
+        "recipe" => "cooked",
+        "product" => "manufactured",
+        _ => "dine-in",
+    };
+    let meal_type = meal_type.unwrap_or(default_type).to_string();
+
+    match matched.source.as_str() {
+        "recipe" => CreateMealFoodSource::Recipe {
+            recipe_id: matched.id,
+            meal_type,
+        },
+        "product" => CreateMealFoodSource::Product {
+            product_id: matched.id,
+            meal_type,
+        },
+        _ => CreateMealFoodSource::Restaurant {
+            restaurant_id: matched.id,
+            meal_type,
+            visit: Visit::default(),
+        },
+    }
+}
+
+pub(crate) fn push_unique(ids: &mut Vec<i32>, more: impl IntoIterator<Item = i32>) {
+    for id in more {
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+}
+
+/// Parse the spreadsheet and resolve every entry, without writing anything.
+pub fn plan(catalog: &Catalog, data: &[u8]) -> Result<ImportPreview> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(data);
+
+    let mut preview = ImportPreview {
+        committed: false,
+        skipped_rows: Vec::new(),
+        meals: Vec::new(),
+        events: Vec::new(),
+        unresolved: 0,
+    };
+
+    for (index, record) in reader.records().enumerate() {
+        let row = index + 1;
+        let record = record
+            .map_err(|e| AppError::bad_request(format!("Invalid CSV at row {}: {}", row, e)))?;
+        let cell = |column: usize| record.get(column).map(str::trim).unwrap_or("");
+
+        let date = DATE_FORMATS
+            .iter()
+            .find_map(|format| NaiveDate::parse_from_str(cell(DATE_COLUMN), format).ok());
+        let Some(date) = date.filter(|_| record.len() >= MIN_COLUMNS) else {
+            preview.skipped_rows.push(row);
+            continue;
+        };
+
+        for (time, columns) in MEAL_COLUMNS {
+            let cells: Vec<&str> = columns
+                .iter()
+                .map(|column| cell(*column))
+                .filter(|text| !text.is_empty())
+                .collect();
+            let mut records = Vec::new();
+            for (position, text) in cells.iter().enumerate() {
+                let planned = plan_meals(catalog, date, row, time, text, position, cells.len());
+                for record in planned {
+                    merge_meal(&mut records, record);
+                }
+            }
+            preview.meals.extend(records);
+        }
+
+        for (column, label) in EVENT_COLUMNS {
+            let text = cell(column);
+            if !text.is_empty() {
+                preview
+                    .events
+                    .extend(plan_events(catalog, date, row, label, text));
+            }
+        }
+    }
+
+    preview.unresolved = preview.meals.iter().filter(|m| m.record.is_none()).count()
+        + preview.events.iter().filter(|e| e.record.is_none()).count();
+
+    Ok(preview)
+}
+
+/// Meals for one cell. A cell belongs to the household member at its position,
+/// unless it is the only cell of its meal time or the dish was cooked at home,
+/// in which case the whole household ate it.
+fn plan_meals(
+    catalog: &Catalog,
+    date: NaiveDate,
+    row: usize,
+    time: &str,
+    text: &str,
+    position: usize,
+    cells: usize,
+) -> Vec<ImportRecord<CreateMeal>> {
+    let shorthand = parse_shorthand(text);
+    let companions = catalog.resolve_people(&shorthand.people);
+
+    shorthand
+        .names
+        .iter()
+        .map(|name| {
+            let mut issues = Vec::new();
+            let matched = catalog.match_food(name).map_err(|e| issues.push(e)).ok();
+            let companions = companions.clone().map_err(|e| issues.push(e)).ok();
+
+            let record = match (&matched, companions) {
+                (Some(matched), Some(companions)) => {
+                    let mut people_ids = match catalog.household.get(position) {
+                        Some(member) if cells > 1 && matched.source != "recipe" => vec![*member],
+                        _ => catalog.household.clone(),
+                    };
+                    push_unique(&mut people_ids, companions);
+
+                    let location = shorthand
+                        .location
+                        .as_ref()
+                        .filter(|_| matched.source != "restaurant")
+                        .map(|location| format!("@{}", location));
+                    let notes = [shorthand.comment.clone(), location]
+                        .into_iter()
+                        .flatten()
+                        .collect::<Vec<_>>()
+                        .join(" ");
+
+                    Some(CreateMeal {
+                        date,
+                        time: time.to_string(),
+                        notes: non_empty(&notes),
+                        food_sources: vec![food_source(matched, shorthand.meal_type)],
+                        people_ids,
+                    })
+                }
+                _ => None,
+            };
+
+            ImportRecord {
+                row,
+                column: time.to_string(),
+                text: text.to_string(),
+                record,
+                matched,
+                issues,
+            }
+        })
+        .collect()
+}
+
+/// Add a meal, folding it into an earlier one with the same dish, time and
+/// notes, so a dish both household members wrote down becomes one shared meal.
+fn merge_meal(records: &mut Vec<ImportRecord<CreateMeal>>, record: ImportRecord<CreateMeal>) {
+    if let Some(new) = &record.record {
+        let existing = records.iter_mut().find_map(|r| {
+            r.record.as_mut().filter(|old| {
+                old.date == new.date
+                    && old.time == new.time
+                    && old.notes == new.notes
+                    && old.food_sources == new.food_sources
+            })
+        });
+        if let Some(existing) = existing {
+            push_unique(&mut existing.people_ids, new.people_ids.iter().copied());
+            return;
+        }
+    }
+    records.push(record);
+}
+
+fn plan_events(
+    catalog: &Catalog,
+    date: NaiveDate,
+    row: usize,
+    column: &str,
+    text: &str,
+) -> Vec<ImportRecord<CreateEvent>> {
+    let shorthand = parse_shorthand(text);
+    let companions = catalog.resolve_people(&shorthand.people);
+
+    shorthand
+        .names
+        .iter()
+        .map(|name| {
+            let mut issues = Vec::new();
+            let matched = catalog
+                .match_activity(name)
+                .map_err(|e| issues.push(e))
+                .ok();
+            let companions = companions.clone().map_err(|e| issues.push(e)).ok();
+
+            let record = match (&matched, companions) {
+                (Some(matched), Some(companions)) => {
+                    let mut people_ids = catalog.household.clone();
+                    push_unique(&mut people_ids, companions);
+                    Some(CreateEvent {
+                        date,
+                        activity_id: matched.id,
+                        measure: None,
+                        location: shorthand.location.clone(),
+                        notes: shorthand.comment.clone(),
+                        people_ids,
+                        start_time: None,
+                        end_time: None,
+                        quantity: None,
+                        unit: None,
+                    })
+                }
+                _ => None,
+            };
+
+            ImportRecord {
+                row,
+                column: column.to_string(),
+                text: text.to_string(),
+                record,
+                matched,
+                issues,
+            }
+        })
+        .collect()
+}
+
+/// Preview an import of the old spreadsheet, and with `commit` write every
+/// resolved record in a single transaction. Unless `skip_unmatched` is set,
+/// nothing is written while any record is unresolved.
+pub async fn import_csv(
+    pool: &PgPool,
+    data: &[u8],
+    commit: bool,
+    skip_unmatched: bool,
+    actor: Option<&AuthUser>,
+) -> Result<ImportPreview> {
+    let catalog = Catalog::load(pool).await?;
+    let preview = plan(&catalog, data)?;
+    write_preview(pool, preview, commit, skip_unmatched, actor).await
+}
+
+/// Like `import_csv`, for the VEVENTs of an iCalendar file.
+pub async fn import_ics(
+    pool: &PgPool,
+    data: &[u8],
+    commit: bool,
+    skip_unmatched: bool,
+    actor: Option<&AuthUser>,
+) -> Result<ImportPreview> {
+    let catalog = Catalog::load(pool).await?;
+    let preview = calendar::plan(&catalog, data)?;
+    write_preview(pool, preview, commit, skip_unmatched, actor).await
+}
+
+async fn write_preview(
+    pool: &PgPool,
+    mut preview: ImportPreview,
+    commit: bool,
+    skip_unmatched: bool,
+    actor: Option<&AuthUser>,
+) -> Result<ImportPreview> {
+    if !commit {
+        return Ok(preview);
+    }
+
+    if preview.unresolved > 0 && !skip_unmatched {
+        return Err(AppError::InvalidReference {
+            field: "records".to_string(),
+            message: format!(
+                "{} record(s) could not be resolved. Preview the import to see them, or pass skip_unmatched to import the rest",
+                preview.unresolved
+            ),
+        });
+    }
+
+    let mut tx = pool.begin().await.context("Failed to import")?;
+    for meal in preview.meals.iter().filter_map(|m| m.record.as_ref()) {
+        let id = meals::insert_meal(&mut tx, meal).await?;
+        history::record(
+            &mut tx,
+            HistoryEntity::Meal,
+            id,
+            HistoryAction::Create,
+            None,
+            actor,
+        )
+        .await?;
+    }
+    for event in preview.events.iter().filter_map(|e| e.record.as_ref()) {
+        let id = events::insert_event(&mut tx, event).await?;
+        history::record(
+            &mut tx,
+            HistoryEntity::Event,
+            id,
+            HistoryAction::Create,
+            None,
+            actor,
+        )
+        .await?;
+    }
+    tx.commit().await.context("Failed to import")?;
+
+    preview.committed = true;
+    Ok(preview)
+}
@@ -0,0 +1,442 @@
+use crate::error::{Result, ResultExt};
+use crate::models::ingredient::{IndexReport, Ingredient, ShoppingItem};
+use sqlx::{PgPool, Postgres, Transaction};
+
+/// Spellings of ingredient units, with the unit they are stored as and the
+/// factor from that unit to the base unit quantities are merged in.
+const UNITS: [(&str, &str, &str, f64); 47] = [
+    ("g", "g", "g", 1.0),
+    ("gram", "g", "g", 1.0),
+    ("grams", "g", "g", 1.0),
+    ("克", "g", "g", 1.0),
+    ("kg", "kg", "g", 1000.0),
+    ("公斤", "kg", "g", 1000.0),
+    ("千克", "kg", "g", 1000.0),
+    ("斤", "斤", "g", 500.0),
+    ("oz", "oz", "g", 28.349523125),
+    ("ounce", "oz", "g", 28.349523125),
+    ("ounces", "oz", "g", 28.349523125),
+    ("lb", "lb", "g", 453.59237),
+    ("lbs", "lb", "g", 453.59237),
+    ("pound", "lb", "g", 453.59237),
+    ("pounds", "lb", "g", 453.59237),
+    ("ml", "ml", "ml", 1.0),
+    ("毫升", "ml", "ml", 1.0),
+    ("l", "l", "ml", 1000.0),
+    ("liter", "l", "ml", 1000.0),
+    ("liters", "l", "ml", 1000.0),
+    ("litre", "l", "ml", 1000.0),
+    ("litres", "l", "ml", 1000.0),
+    ("升", "l", "ml", 1000.0),
+    ("tsp", "tsp", "tsp", 1.0),
+    ("teaspoon", "tsp", "tsp", 1.0),
+    ("teaspoons", "tsp", "tsp", 1.0),
+    ("茶匙", "tsp", "tsp", 1.0),
+    ("小勺", "tsp", "tsp", 1.0),
+    ("tbsp", "tbsp", "tsp", 3.0),
+    ("tablespoon", "tbsp", "tsp", 3.0),
+    ("tablespoons", "tbsp", "tsp", 3.0),
+    ("汤匙", "tbsp", "tsp", 3.0),
+    ("大勺", "tbsp", "tsp", 3.0),
+    ("勺", "tbsp", "tsp", 3.0),
+    ("cup", "cup", "cup", 1.0),
+    ("cups", "cup", "cup", 1.0),
+    ("杯", "cup", "cup", 1.0),
+    ("clove", "clove", "clove", 1.0),
+    ("cloves", "clove", "clove", 1.0),
+    ("瓣", "clove", "clove", 1.0),
+    ("slice", "slice", "slice", 1.0),
+    ("slices", "slice", "slice", 1.0),
+    ("片", "slice", "slice", 1.0),
+    ("piece", "piece", "piece", 1.0),
+    ("pieces", "piece", "piece", 1.0),
+    ("个", "piece", "piece", 1.0),
+    ("只", "piece", "piece", 1.0),
+];
+
+const FRACTIONS: [(char, f64); 6] = [
+    ('½', 0.5),
+    ('⅓', 1.0 / 3.0),
+    ('⅔', 2.0 / 3.0),
+    ('¼', 0.25),
+    ('¾', 0.75),
+    ('⅛', 0.125),
+];
+
+/// Separators between ingredients written on one line.
+const LIST_SEPARATORS: [char; 5] = [',', '，', '、', ';', '；'];
+
+/// Parse free-text ingredients into structured lines. Text with several lines
+/// has one ingredient per line, and what follows a comma is a note, as in
+/// `2 cups flour, sifted`; text on one line is a list like `rice, 2 eggs`.
+/// Notes in parentheses are read either way. Quantities come first
+/// (`200g flour`, `1 1/2 cups milk`, `2个鸡蛋`) or last (`鸡蛋 2个`); lines
+/// without one, like `salt to taste`, are kept as just an item.
+pub fn parse_ingredients(text: &str) -> Vec<Ingredient> {
+    let text = text.trim();
+    let entries: Vec<(String, Option<String>)> = if text.contains('\n') {
+        text.lines()
+            .map(|line| match split_top_level(line).as_slice() {
+                [first, rest @ ..] if !rest.is_empty() => {
+                    (first.to_string(), Some(rest.join(", ").trim().to_string()))
+                }
+                _ => (line.to_string(), None),
+            })
+            .collect()
+    } else {
+        split_top_level(text)
+            .into_iter()
+            .map(|entry| (entry, None))
+            .collect()
+    };
+
+    entries
+        .into_iter()
+        .filter_map(|(entry, comma_note)| parse_line(&entry, comma_note))
+        .collect()
+}
+
+/// Split at list separators outside parentheses.
+fn split_top_level(text: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut depth = 0usize;
+    for c in text.chars() {
+        match c {
+            '(' | '（' => depth += 1,
+            ')' | '）' => depth = depth.saturating_sub(1),
+            _ if depth == 0 && LIST_SEPARATORS.contains(&c) => {
+                parts.push(String::new());
+                continue;
+            }
+            _ => {}
+        }
+        parts.last_mut().unwrap().push(c);
+    }
+    parts
+}
+
+fn parse_line(entry: &str, comma_note: Option<String>) -> Option<Ingredient> {
+    let entry = entry.trim().trim_start_matches(['-', '*', '•', '·']).trim();
+
+    // Take notes out of parentheses
+    let mut text = String::new();
+    let mut notes = Vec::new();
+    let mut rest = entry;
+    while let Some(open) = rest.find(['(', '（']) {
+        text.push_str(&rest[..open]);
+        let inner = &rest[open + rest[open..].chars().next()?.len_utf8()..];
+        let close = inner.find([')', '）']).unwrap_or(inner.len());
+        notes.push(inner[..close].trim().to_string());
+        rest = inner[close..]
+            .strip_prefix([')', '）'])
+            .unwrap_or(&inner[close..]);
+    }
+    text.push_str(rest);
+    notes.extend(comma_note);
+    notes.retain(|note| !note.is_empty());
+    let note = (!notes.is_empty()).then(|| notes.join("; "));
+
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        return None;
+    }
+
+    let (quantity, unit, item) = leading_quantity(&text)
+        .or_else(|| trailing_quantity(&text))
+        .unwrap_or((None, None, text.clone()));
+
+    Some(Ingredient {
+        quantity,
+        unit,
+        item,
+        note,
+    })
+}
+
+type Parsed = (Option<f64>, Option<String>, String);
+
+fn leading_quantity(text: &str) -> Option<Parsed> {
+    let (quantity, rest) = parse_quantity(text)?;
+    let rest = rest.trim_start();
+    let (unit, rest) = match match_unit(rest) {
+        Some((unit, after)) => (Some(unit.to_string()), after.trim_start()),
+        None => (None, rest),
+    };
+    let item = rest.strip_prefix("of ").unwrap_or(rest).trim();
+    (!item.is_empty()).then(|| (Some(quantity), unit, item.to_string()))
+}
+
+fn trailing_quantity(text: &str) -> Option<Parsed> {
+    let starts = text.char_indices().filter(|(i, c)| {
+        let previous = text[..*i].chars().last();
+        *i > 0
+            && is_quantity_start(*c)
+            && !previous.is_some_and(|p| p.is_ascii_digit() || p == '.' || p == '/')
+    });
+    for (i, _) in starts {
+        let Some((quantity, rest)) = parse_quantity(&text[i..]) else {
+            continue;
+        };
+        let rest = rest.trim();
+        let unit = match match_unit(rest) {
+            Some((unit, "")) => Some(unit.to_string()),
+            None if rest.is_empty() => None,
+            _ => continue,
+        };
+        let item = text[..i].trim().trim_end_matches([':', '：']).trim();
+        if !item.is_empty() {
+            return Some((Some(quantity), unit, item.to_string()));
+        }
+    }
+    None
+}
+
+fn is_quantity_start(c: char) -> bool {
+    c.is_ascii_digit() || FRACTIONS.iter().any(|(f, _)| *f == c)
+}
+
+/// Read a quantity such as `2`, `1.5`, `1/2`, `1 1/2`, `1½` or `2-3` (the
+/// larger end of a range) from the start of `text`.
+fn parse_quantity(text: &str) -> Option<(f64, &str)> {
+    let (mut quantity, mut rest) = match parse_fraction(text) {
+        Some(fraction) => fraction,
+        None => {
+            let (whole, rest) = parse_decimal(text)?;
+            // A fraction following a whole number, as in `1 1/2` or `1½`
+            match parse_fraction(rest.strip_prefix(' ').unwrap_or(rest)) {
+                Some((fraction, after)) if whole.fract() == 0.0 => (whole + fraction, after),
+                _ => (whole, rest),
+            }
+        }
+    };
+
+    if let Some(after_dash) = rest.trim_start().strip_prefix(['-', '~', '–']) {
+        let after_dash = after_dash.trim_start();
+        if let Some((upper, after)) =
+            parse_fraction(after_dash).or_else(|| parse_decimal(after_dash))
+        {
+            quantity = quantity.max(upper);
+            rest = after;
+        }
+    }
+
+    Some((quantity, rest))
+}
+
+fn parse_decimal(text: &str) -> Option<(f64, &str)> {
+    let end = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    Some((text[..end].parse().ok()?, &text[end..]))
+}
+
+/// Read `½` or `1/2` from the start of `text`.
+fn parse_fraction(text: &str) -> Option<(f64, &str)> {
+    let first = text.chars().next()?;
+    if let Some((_, value)) = FRACTIONS.iter().find(|(f, _)| *f == first) {
+        return Some((*value, &text[first.len_utf8()..]));
+    }
+
+    let slash = text.find(|c: char| !c.is_ascii_digit())?;
+    let denominator_text = text[slash..].strip_prefix('/')?;
+    let end = denominator_text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(denominator_text.len());
+    let numerator: f64 = text[..slash].parse().ok()?;
+    let denominator: f64 = denominator_text[..end].parse().ok()?;
+    (denominator != 0.0).then(|| (numerator / denominator, &denominator_text[end..]))
+}
+
+/// The longest unit spelling `text` starts with. Latin spellings must end at a
+/// word boundary, so `2 grapes` has no unit.
+fn match_unit(text: &str) -> Option<(&'static str, &str)> {
+    UNITS
+        .iter()
+        .filter_map(|(spelling, unit, _, _)| {
+            let prefix = text.get(..spelling.len())?;
+            let after = &text[spelling.len()..];
+            let matches = if spelling.is_ascii() {
+                prefix.eq_ignore_ascii_case(spelling)
+                    && !after.chars().next().is_some_and(char::is_alphabetic)
+            } else {
+                prefix == *spelling
+            };
+            matches.then_some((spelling.len(), *unit, after))
+        })
+        .max_by_key(|(length, _, _)| *length)
+        .map(|(_, unit, after)| (unit, after.strip_prefix('.').unwrap_or(after)))
+}
+
+/// The base unit and factor a stored unit converts with.
+fn base_unit(unit: &str) -> Option<(&'static str, f64)> {
+    UNITS
+        .iter()
+        .find(|(_, stored, _, _)| *stored == unit)
+        .map(|(_, _, base, factor)| (*base, *factor))
+}
+
+/// Factor that scales a recipe written for `from` servings to `to` servings.
+/// Recipes without servings, and requests without a target, are not scaled.
+pub fn scale_factor(from: Option<i32>, to: Option<i32>) -> f64 {
+    match (from, to) {
+        (Some(from), Some(to)) => f64::from(to) / f64::from(from),
+        _ => 1.0,
+    }
+}
+
+fn round(quantity: f64) -> f64 {
+    (quantity * 1000.0).round() / 1000.0
+}
+
+/// Multiply the quantities of `lines` by `factor`.
+pub fn scale(lines: Vec<Ingredient>, factor: f64) -> Vec<Ingredient> {
+    lines
+        .into_iter()
+        .map(|line| Ingredient {
+            quantity: line.quantity.map(|quantity| round(quantity * factor)),
+            ..line
+        })
+        .collect()
+}
+
+/// Merge ingredient lines of several recipes, given as recipe name, scale
+/// factor and line. Quantities of the same item add up, converted to a common
+/// unit when the lines use different ones; items are sorted by name.
+pub fn shopping_list(lines: &[(String, f64, Ingredient)]) -> Vec<ShoppingItem> {
+    struct Entry {
+        item: String,
+        key: (String, Option<String>),
+        units: Vec<String>,
+        base_quantity: Option<f64>,
+        recipes: Vec<String>,
+    }
+
+    let mut entries: Vec<Entry> = Vec::new();
+    for (recipe, factor, line) in lines {
+        let base = line.unit.as_deref().map(|unit| {
+            base_unit(unit).map_or((unit.to_string(), 1.0), |(b, f)| (b.to_string(), f))
+        });
+        let key = (
+            line.item.to_lowercase(),
+            match (&line.quantity, &base) {
+                (Some(_), Some((base, _))) => Some(base.clone()),
+                (Some(_), None) => Some(String::new()),
+                (None, _) => None,
+            },
+        );
+        let base_quantity = line
+            .quantity
+            .map(|quantity| quantity * factor * base.as_ref().map_or(1.0, |(_, f)| *f));
+
+        let index = match entries.iter().position(|entry| entry.key == key) {
+            Some(index) => index,
+            None => {
+                entries.push(Entry {
+                    item: line.item.clone(),
+                    key,
+                    units: Vec::new(),
+                    base_quantity: None,
+                    recipes: Vec::new(),
+                });
+                entries.len() - 1
+            }
+        };
+        let entry = &mut entries[index];
+        if let Some(quantity) = base_quantity {
+            *entry.base_quantity.get_or_insert(0.0) += quantity;
+        }
+        if let Some(unit) = &line.unit {
+            if !entry.units.contains(unit) {
+                entry.units.push(unit.clone());
+            }
+        }
+        if !entry.recipes.contains(recipe) {
+            entry.recipes.push(recipe.clone());
+        }
+    }
+
+    let mut items: Vec<ShoppingItem> = entries
+        .into_iter()
+        .map(|entry| {
+            // Lines in a single unit keep it; mixed units are shown in the base unit
+            let (quantity, unit) = match (entry.base_quantity, entry.units.as_slice()) {
+                (Some(quantity), [unit]) => {
+                    let factor = base_unit(unit).map_or(1.0, |(_, factor)| factor);
+                    (Some(round(quantity / factor)), Some(unit.clone()))
+                }
+                (Some(quantity), []) => (Some(round(quantity)), None),
+                (Some(quantity), _) => (Some(round(quantity)), entry.key.1),
+                (None, _) => (None, None),
+            };
+            ShoppingItem {
+                item: entry.item,
+                quantity,
+                unit,
+                recipes: entry.recipes,
+            }
+        })
+        .collect();
+    items.sort_by_key(|item| item.item.to_lowercase());
+    items
+}
+
+/// Replace the structured lines of a recipe with those parsed from `text`.
+pub async fn store(tx: &mut Transaction<'_, Postgres>, recipe_id: i32, text: &str) -> Result<u64> {
+    sqlx::query!("DELETE FROM recipe_ingredient WHERE recipe = $1", recipe_id)
+        .execute(&mut **tx)
+        .await
+        .context("Failed to save recipe ingredients")?;
+
+    let lines = parse_ingredients(text);
+    for (position, line) in (0..).zip(&lines) {
+        sqlx::query!(
+            r#"
+            INSERT INTO recipe_ingredient (recipe, position, quantity, unit, item, note)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            recipe_id,
+            position,
+            line.quantity,
+            line.unit,
+            line.item,
+            line.note
+        )
+        .execute(&mut **tx)
+        .await
+        .context("Failed to save recipe ingredients")?;
+    }
+
+    Ok(lines.len() as u64)
+}
+
+/// Parse the ingredients of recipes that have no structured lines yet.
+pub async fn backfill(pool: &PgPool) -> Result<IndexReport> {
+    let recipes: Vec<(i32, String)> = sqlx::query_as(
+        r#"
+        SELECT id, ingredients FROM recipe r
+        WHERE NOT EXISTS (SELECT 1 FROM recipe_ingredient ri WHERE ri.recipe = r.id)
+        ORDER BY id
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to backfill ingredients")?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .context("Failed to backfill ingredients")?;
+    let mut report = IndexReport {
+        recipes: 0,
+        lines: 0,
+    };
+    for (id, text) in recipes {
+        report.lines += store(&mut tx, id, &text).await?;
+        report.recipes += 1;
+    }
+    tx.commit()
+        .await
+        .context("Failed to backfill ingredients")?;
+
+    Ok(report)
+}
@@ -1,2 +1,17 @@
+pub mod auth;
+pub mod calendar;
+pub mod config;
+pub mod daily_summary;
+pub mod error;
+pub mod export;
 pub mod handlers;
+pub mod history;
+pub mod import;
+pub mod ingredients;
+pub mod measure;
 pub mod models;
+pub mod quick_log;
+pub mod suggestions;
+pub mod templates;
+pub mod trash;
+pub mod util;
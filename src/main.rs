@@ -1,11 +1,16 @@
 use actix_files as fs;
-use actix_web::{middleware::Logger, web, App, HttpResponse, HttpServer, Result};
+use actix_web::{middleware, middleware::Logger, web, App, HttpResponse, HttpServer, Result};
 use sqlx::PgPool;
 use std::env;
-
-mod config;
-mod handlers;
-mod models;
+use xnote::auth;
+use xnote::config::{self, database};
+use xnote::export;
+use xnote::handlers;
+use xnote::import;
+use xnote::ingredients;
+use xnote::measure;
+use xnote::templates;
+use xnote::trash;
 
 async fn health() -> Result<HttpResponse> {
     Ok(HttpResponse::Ok().json(serde_json::json!({
@@ -36,29 +41,243 @@ async fn index() -> Result<HttpResponse> {
         .body(html))
 }
 
+const USAGE: &str = "Usage: xnote [serve|migrate|create-user <username>|import-csv <file> [--commit] [--skip-unmatched]|export [file]|restore <file>|backfill-measures|backfill-ingredients]";
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv::dotenv().ok();
     env_logger::init();
 
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let command = env::args().nth(1).unwrap_or_else(|| "serve".to_string());
+    if !matches!(
+        command.as_str(),
+        "serve"
+            | "migrate"
+            | "create-user"
+            | "import-csv"
+            | "export"
+            | "restore"
+            | "backfill-measures"
+            | "backfill-ingredients"
+    ) {
+        eprintln!("Unknown command '{}'. {}", command, USAGE);
+        std::process::exit(2);
+    }
 
-    let pool = PgPool::connect(&database_url)
+    let pool = database::create_pool()
         .await
         .expect("Failed to connect to database");
 
+    // Migrations are applied on every start; already applied versions are skipped
+    database::run_migrations(&pool)
+        .await
+        .expect("Failed to run database migrations");
+
+    match command.as_str() {
+        "migrate" => {
+            log::info!("Database migrations are up to date");
+            Ok(())
+        }
+        "create-user" => create_user(&pool).await,
+        "import-csv" => import_csv(&pool).await,
+        "export" => export_archive(&pool).await,
+        "restore" => restore_archive(&pool).await,
+        "backfill-measures" => backfill_measures(&pool).await,
+        "backfill-ingredients" => backfill_ingredients(&pool).await,
+        _ => serve(pool).await,
+    }
+}
+
+/// Create an account, reading the password from the first line of stdin. This
+/// is the only way to add accounts; every account sees all of the data.
+async fn create_user(pool: &PgPool) -> std::io::Result<()> {
+    let Some(username) = env::args().nth(2) else {
+        eprintln!("Missing username. {}", USAGE);
+        std::process::exit(2);
+    };
+
+    eprint!("Password for {}: ", username);
+    let mut password = String::new();
+    std::io::stdin().read_line(&mut password)?;
+    let password = password.trim_end_matches(['\r', '\n']);
+
+    match auth::create_user(pool, &username, password).await {
+        Ok(user) => {
+            log::info!("Created user '{}' with id {}", user.username, user.id);
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("Failed to create user: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Preview the import of a spreadsheet, listing every record that could not be
+/// resolved; with --commit, import it.
+async fn import_csv(pool: &PgPool) -> std::io::Result<()> {
+    let args: Vec<String> = env::args().skip(2).collect();
+    let Some(path) = args.iter().find(|arg| !arg.starts_with("--")) else {
+        eprintln!("Missing CSV file. {}", USAGE);
+        std::process::exit(2);
+    };
+    let commit = args.iter().any(|arg| arg == "--commit");
+    let skip_unmatched = args.iter().any(|arg| arg == "--skip-unmatched");
+
+    let data = std::fs::read(path)?;
+    let preview = match import::import_csv(pool, &data, commit, skip_unmatched, None).await {
+        Ok(preview) => preview,
+        Err(e) => {
+            eprintln!("Import failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let issues = preview
+        .meals
+        .iter()
+        .map(|m| (m.row, &m.column, &m.text, &m.issues))
+        .chain(
+            preview
+                .events
+                .iter()
+                .map(|e| (e.row, &e.column, &e.text, &e.issues)),
+        );
+    for (row, column, text, issues) in issues {
+        for issue in issues {
+            println!("row {} {} '{}': {}", row, column, text, issue);
+        }
+    }
+    println!(
+        "{} meals and {} events parsed, {} unresolved, {} rows skipped{}",
+        preview.meals.len(),
+        preview.events.len(),
+        preview.unresolved,
+        preview.skipped_rows.len(),
+        if preview.committed {
+            "; imported"
+        } else {
+            "; nothing written (pass --commit to import)"
+        }
+    );
+
+    Ok(())
+}
+
+/// Write an archive of the whole database to the given file, or to stdout.
+async fn export_archive(pool: &PgPool) -> std::io::Result<()> {
+    let archive = match export::export(pool).await {
+        Ok(archive) => archive,
+        Err(e) => {
+            eprintln!("Export failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let json = serde_json::to_string_pretty(&archive)?;
+    match env::args().nth(2) {
+        Some(path) => std::fs::write(path, json + "\n"),
+        None => {
+            println!("{}", json);
+            Ok(())
+        }
+    }
+}
+
+/// Restore an archive written by `export` into an empty database.
+async fn restore_archive(pool: &PgPool) -> std::io::Result<()> {
+    let Some(path) = env::args().nth(2) else {
+        eprintln!("Missing archive file. {}", USAGE);
+        std::process::exit(2);
+    };
+
+    let archive = serde_json::from_slice(&std::fs::read(path)?)?;
+    match export::restore(pool, &archive).await {
+        Ok(restored) => {
+            for table in restored {
+                println!("{}: {} rows", table.name, table.rows);
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("Restore failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Parse the free-text measure of events logged before quantity and unit existed.
+async fn backfill_measures(pool: &PgPool) -> std::io::Result<()> {
+    match measure::backfill(pool).await {
+        Ok(report) => {
+            for (id, text) in &report.unparsed {
+                println!("event {}: cannot parse '{}'", id, text);
+            }
+            println!(
+                "{} events updated, {} left unparsed",
+                report.updated,
+                report.unparsed.len()
+            );
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("Backfill failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Parse the ingredients of recipes written before they were structured.
+async fn backfill_ingredients(pool: &PgPool) -> std::io::Result<()> {
+    match ingredients::backfill(pool).await {
+        Ok(report) => {
+            println!(
+                "{} recipes indexed, {} ingredient lines",
+                report.recipes, report.lines
+            );
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("Backfill failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The default access log, but with the path instead of the whole request line:
+/// query strings can carry a feed token, which must not end up in the logs.
+fn request_logger() -> Logger {
+    Logger::new(r#"%a "%{method}xi %U" %s %b "%{Referer}i" "%{User-Agent}i" %T"#)
+        .custom_request_replace("method", |req| req.method().to_string())
+}
+
+async fn serve(pool: PgPool) -> std::io::Result<()> {
     log::info!("Starting server on http://0.0.0.0:8080");
 
+    actix_web::rt::spawn(templates::run_scheduler(pool.clone()));
+    match config::trash_retention_days() {
+        0 => log::info!("Trashed records are kept until purged"),
+        days => {
+            actix_web::rt::spawn(trash::run_purger(pool.clone(), days));
+        }
+    }
+
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(pool.clone()))
-            .wrap(Logger::default())
+            .app_data(xnote::error::json_config())
+            .app_data(xnote::error::query_config())
+            .wrap(request_logger())
             .route("/", web::get().to(index))
             .route("/health", web::get().to(health))
             .service(fs::Files::new("/static", "./static").show_files_listing())
+            .configure(handlers::auth::configure_public)
             .service(
                 web::scope("/api/v1")
+                    .wrap(middleware::from_fn(auth::require_auth))
+                    .configure(handlers::auth::configure)
                     .configure(handlers::meals::configure)
+                    .configure(handlers::meal_plans::configure)
                     .configure(handlers::events::configure)
                     .configure(handlers::people::configure)
                     .configure(handlers::locations::configure)
@@ -66,11 +285,22 @@ async fn main() -> std::io::Result<()> {
                     .configure(handlers::drinks::configure)
                     .configure(handlers::drink_options::configure)
                     .configure(handlers::recipes::configure)
+                    .configure(handlers::shopping_list::configure)
                     .configure(handlers::products::configure)
                     .configure(handlers::activities::configure)
                     .configure(handlers::activity_types::configure)
                     .configure(handlers::daily_summary::configure)
-                    .configure(handlers::food_types::configure),
+                    .configure(handlers::search::configure)
+                    .configure(handlers::stats::configure)
+                    .configure(handlers::suggestions::configure)
+                    .configure(handlers::food_types::configure)
+                    .configure(handlers::import::configure)
+                    .configure(handlers::quick_log::configure)
+                    .configure(handlers::export::configure)
+                    .configure(handlers::calendar::configure)
+                    .configure(handlers::templates::configure)
+                    .configure(handlers::history::configure)
+                    .configure(handlers::trash::configure),
             )
     })
     .bind("0.0.0.0:8080")?
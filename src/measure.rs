@@ -0,0 +1,153 @@
+use crate::error::{Result, ResultExt};
+use serde::Serialize;
+use sqlx::PgPool;
+
+/// Unit of durations, which are stored in hours.
+pub const HOURS: &str = "h";
+
+/// Unit of distances, which are stored in kilometres.
+pub const KILOMETRES: &str = "km";
+
+/// Spellings of duration and distance units, with their factor to `HOURS` or
+/// `KILOMETRES`. A bare `m` is metres, as in `400m`; minutes are `min`.
+const UNITS: [(&str, &str, f64); 22] = [
+    ("h", HOURS, 1.0),
+    ("hr", HOURS, 1.0),
+    ("hrs", HOURS, 1.0),
+    ("hour", HOURS, 1.0),
+    ("hours", HOURS, 1.0),
+    ("小时", HOURS, 1.0),
+    ("min", HOURS, 1.0 / 60.0),
+    ("mins", HOURS, 1.0 / 60.0),
+    ("minute", HOURS, 1.0 / 60.0),
+    ("minutes", HOURS, 1.0 / 60.0),
+    ("分钟", HOURS, 1.0 / 60.0),
+    ("km", KILOMETRES, 1.0),
+    ("k", KILOMETRES, 1.0),
+    ("公里", KILOMETRES, 1.0),
+    ("mi", KILOMETRES, 1.609344),
+    ("mile", KILOMETRES, 1.609344),
+    ("miles", KILOMETRES, 1.609344),
+    ("m", KILOMETRES, 0.001),
+    ("meter", KILOMETRES, 0.001),
+    ("meters", KILOMETRES, 0.001),
+    ("metre", KILOMETRES, 0.001),
+    ("metres", KILOMETRES, 0.001),
+];
+
+/// A structured event measure.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Measure {
+    pub quantity: f64,
+    pub unit: String,
+}
+
+/// The canonical unit of a spelling in `UNITS`, with its factor.
+fn known(unit: &str) -> Option<(&'static str, f64)> {
+    UNITS
+        .iter()
+        .find(|(spelling, _, _)| *spelling == unit)
+        .map(|(_, canonical, factor)| (*canonical, *factor))
+}
+
+/// An amount in any spelling of a duration or distance unit, converted to
+/// hours or kilometres. None for other units.
+pub fn normalize(quantity: f64, unit: &str) -> Option<Measure> {
+    let (canonical, factor) = known(&unit.trim().to_lowercase())?;
+    Some(Measure {
+        quantity: quantity * factor,
+        unit: canonical.to_string(),
+    })
+}
+
+/// Parse a free-text measure such as `2h`, `1h 30min`, `90 minutes`, `5km` or
+/// `3 miles`. Durations come back in hours and distances in kilometres; a
+/// single amount in any other unit, like `12 pages`, keeps that unit. Returns
+/// None for text that is not just amounts and units.
+pub fn parse_measure(text: &str) -> Option<Measure> {
+    let text = text.trim().to_lowercase();
+    let mut rest = text.as_str();
+    let mut parts = Vec::new();
+
+    while !rest.is_empty() {
+        let number_end = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let quantity: f64 = rest[..number_end].parse().ok()?;
+        rest = rest[number_end..].trim_start();
+
+        let unit_end = rest
+            .find(|c: char| c.is_ascii_digit() || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let unit = &rest[..unit_end];
+        if unit.is_empty() {
+            return None;
+        }
+        parts.push((quantity, unit));
+        rest = rest[unit_end..].trim_start();
+    }
+
+    match parts.as_slice() {
+        [] => None,
+        [(quantity, unit)] if known(unit).is_none() => Some(Measure {
+            quantity: *quantity,
+            unit: unit.to_string(),
+        }),
+        _ => {
+            let mut total = 0.0;
+            let mut canonical_unit = None;
+            for (quantity, unit) in &parts {
+                let (canonical, factor) = known(unit)?;
+                if canonical_unit.is_some_and(|seen| seen != canonical) {
+                    return None;
+                }
+                canonical_unit = Some(canonical);
+                total += quantity * factor;
+            }
+            Some(Measure {
+                quantity: total,
+                unit: canonical_unit?.to_string(),
+            })
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackfillReport {
+    pub updated: u64,
+    /// Events whose measure could not be parsed, with that measure.
+    pub unparsed: Vec<(i32, String)>,
+}
+
+/// Fill in `quantity` and `unit` of events that only have a free-text measure.
+pub async fn backfill(pool: &PgPool) -> Result<BackfillReport> {
+    let events: Vec<(i32, String)> = sqlx::query_as(
+        "SELECT id, measure FROM event WHERE quantity IS NULL AND measure <> '' ORDER BY id",
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to backfill measures")?;
+
+    let mut tx = pool.begin().await.context("Failed to backfill measures")?;
+    let mut report = BackfillReport {
+        updated: 0,
+        unparsed: Vec::new(),
+    };
+    for (id, text) in events {
+        let Some(measure) = parse_measure(&text) else {
+            report.unparsed.push((id, text));
+            continue;
+        };
+        report.updated += sqlx::query("UPDATE event SET quantity = $1, unit = $2 WHERE id = $3")
+            .bind(measure.quantity)
+            .bind(&measure.unit)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to backfill measures")?
+            .rows_affected();
+    }
+    tx.commit().await.context("Failed to backfill measures")?;
+
+    Ok(report)
+}
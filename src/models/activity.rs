@@ -1,3 +1,4 @@
+use crate::models::list::{Cursor, Keyset};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
@@ -15,6 +16,15 @@ pub struct Activity {
     pub activity_type: String,
 }
 
+impl Keyset for Activity {
+    fn cursor(&self) -> Cursor {
+        Cursor {
+            date: None,
+            id: self.id.into(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreateActivity {
     pub name: String,
@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Deserialize)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+/// What a token may be used for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum TokenScope {
+    /// The whole API, sent as `Authorization: Bearer <token>`.
+    #[default]
+    Api,
+    /// Only reading `/calendar.ics`, passed as `?token=` in the feed URL.
+    Feed,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiToken {
+    pub name: String,
+    #[serde(default)]
+    pub scope: TokenScope,
+}
+
+/// An API token as listed to its owner; the token itself is only shown once.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ApiToken {
+    pub id: i32,
+    pub name: String,
+    pub scope: TokenScope,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateApiTokenResponse {
+    pub id: i32,
+    pub name: String,
+    pub scope: TokenScope,
+    /// Send as `Authorization: Bearer <token>`, or for feed tokens as `?token=`.
+    pub token: String,
+}
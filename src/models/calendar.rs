@@ -0,0 +1,18 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct CalendarQuery {
+    /// Only entries this person took part in.
+    pub person_id: Option<i32>,
+    /// Only events of this activity type.
+    pub activity_type: Option<String>,
+    /// Also include meals, one entry per dish.
+    #[serde(default)]
+    pub meals: bool,
+    /// Defaults to 90 days before `end_date`. The range spans at most about ten
+    /// years.
+    pub start_date: Option<NaiveDate>,
+    /// Defaults to today.
+    pub end_date: Option<NaiveDate>,
+}
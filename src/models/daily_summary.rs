@@ -1,9 +1,13 @@
-use chrono::NaiveDate;
+use chrono::{NaiveDate, NaiveTime};
 use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::BTreeMap;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MealItem {
     pub ids: Vec<i32>,         // Meal IDs (multiple for merged meals)
+    pub source: String,        // recipe, product or restaurant
+    pub source_id: i32,        // ID of the recipe/product/restaurant
     pub name: String,          // Recipe/product/restaurant name
     pub people: String,        // Comma-separated people names
     pub notes: Option<String>, // Notes if any
@@ -11,6 +15,20 @@ pub struct MealItem {
     pub meal_type: String, // cooked, dine-in, etc.
 }
 
+/// A planned dish that has not been eaten yet.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlannedItem {
+    pub id: i32, // Meal plan ID
+    pub time: String,
+    pub name: String,
+    pub people: String,
+    pub notes: Option<String>,
+    #[serde(rename = "type")]
+    pub meal_type: String,
+    /// Not eaten by the day after its date.
+    pub skipped: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EventItem {
     pub id: i32,
@@ -26,6 +44,10 @@ pub struct DailySummary {
     pub breakfast: Vec<MealItem>,
     pub lunch: Vec<MealItem>,
     pub dinner: Vec<MealItem>,
+    /// Meals at any other time, keyed by that time.
+    #[serde(default)]
+    pub other_meals: BTreeMap<String, Vec<MealItem>>,
+    pub planned: Vec<PlannedItem>,
     pub drinks: Vec<String>,
     pub events: Vec<EventItem>,
 }
@@ -35,3 +57,63 @@ pub struct DailySummaryQuery {
     pub start_date: Option<NaiveDate>,
     pub end_date: Option<NaiveDate>,
 }
+
+/// A live person, for ordering and naming the people of every record.
+#[derive(Debug, Clone, FromRow)]
+pub struct SummaryPerson {
+    pub id: i32,
+    pub name: String,
+    pub household_order: Option<i32>,
+}
+
+/// One dish of a meal.
+#[derive(Debug, Clone, FromRow)]
+pub struct SummaryMeal {
+    pub id: i32,
+    pub date: NaiveDate,
+    pub time: String,
+    pub notes: Option<String>,
+    /// `recipe`, `product` or `restaurant`; with `source_id` it names the dish.
+    pub source: String,
+    pub source_id: i32,
+    pub name: String,
+    pub meal_type: String,
+    pub people_ids: Vec<i32>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct SummaryEvent {
+    pub id: i32,
+    pub date: NaiveDate,
+    pub activity: String,
+    pub activity_type: String,
+    pub measure: Option<String>,
+    pub quantity: Option<f64>,
+    pub unit: Option<String>,
+    pub start_time: Option<NaiveTime>,
+    pub end_time: Option<NaiveTime>,
+    pub location: Option<String>,
+    pub notes: Option<String>,
+    pub people_ids: Vec<i32>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct SummaryDrink {
+    pub id: i32,
+    pub date: NaiveDate,
+    pub name: String,
+    pub people_ids: Vec<i32>,
+}
+
+/// One planned dish of a plan not eaten yet.
+#[derive(Debug, Clone, FromRow)]
+pub struct SummaryPlan {
+    pub id: i32,
+    pub date: NaiveDate,
+    pub time: String,
+    pub notes: Option<String>,
+    pub name: String,
+    pub meal_type: String,
+    pub skipped: bool,
+    pub people_ids: Vec<i32>,
+}
@@ -1,5 +1,7 @@
-use crate::models::{people::People, product::Product, recipe::Recipe, restaurant::Restaurant};
-use chrono::NaiveDate;
+use crate::models::{
+    people::People, product::Product, recipe::Recipe, restaurant::Restaurant, restaurant::Visit,
+};
+use chrono::{NaiveDate, NaiveTime};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
@@ -9,7 +11,7 @@ pub struct MealDetail {
     pub date: NaiveDate,
     pub time: String,
     pub notes: Option<String>,
-    pub food_source: Option<MealFoodSource>,
+    pub food_sources: Vec<MealFoodSource>,
     pub people: Vec<People>,
 }
 
@@ -24,6 +26,8 @@ pub enum MealFoodSource {
     Restaurant {
         restaurant: Restaurant,
         meal_type: String,
+        #[serde(flatten)]
+        visit: Visit,
     },
 }
 
@@ -36,6 +40,10 @@ pub struct EventDetail {
     pub location: Option<String>,
     pub notes: Option<String>,
     pub people: Vec<People>,
+    pub start_time: Option<NaiveTime>,
+    pub end_time: Option<NaiveTime>,
+    pub quantity: Option<f64>,
+    pub unit: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
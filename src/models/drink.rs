@@ -1,3 +1,4 @@
+use crate::models::list::{Cursor, Keyset};
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
@@ -14,7 +15,16 @@ pub struct Drink {
     pub date: NaiveDate,
 }
 
-#[derive(Debug, Deserialize)]
+impl Keyset for Drink {
+    fn cursor(&self) -> Cursor {
+        Cursor {
+            date: Some(self.date),
+            id: self.id.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CreateDrink {
     pub date: NaiveDate,
     pub name: String,
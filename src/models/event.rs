@@ -1,4 +1,5 @@
-use chrono::NaiveDate;
+use crate::models::list::{Cursor, Keyset};
+use chrono::{NaiveDate, NaiveTime};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
@@ -10,9 +11,22 @@ pub struct Event {
     pub measure: Option<String>,
     pub location: Option<String>,
     pub notes: Option<String>,
+    pub start_time: Option<NaiveTime>,
+    pub end_time: Option<NaiveTime>,
+    pub quantity: Option<f64>,
+    pub unit: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+impl Keyset for Event {
+    fn cursor(&self) -> Cursor {
+        Cursor {
+            date: Some(self.date),
+            id: self.id.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CreateEvent {
     pub date: NaiveDate,
     pub activity_id: i32,
@@ -20,6 +34,12 @@ pub struct CreateEvent {
     pub location: Option<String>,
     pub notes: Option<String>,
     pub people_ids: Vec<i32>,
+    pub start_time: Option<NaiveTime>,
+    pub end_time: Option<NaiveTime>,
+    /// Hours for durations and kilometres for distances. Parsed from `measure`
+    /// when left out.
+    pub quantity: Option<f64>,
+    pub unit: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -0,0 +1,25 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A snapshot of every table, as written by `/export` and read by `/restore`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Archive {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    /// In dependency order, so that replaying them front to back never breaks a
+    /// foreign key.
+    pub tables: Vec<ArchiveTable>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveTable {
+    pub name: String,
+    /// One JSON object per row, keyed by column name.
+    pub rows: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RestoredTable {
+    pub name: String,
+    pub rows: u64,
+}
@@ -0,0 +1,168 @@
+use crate::models::list::{Cursor, Keyset, ListQuery, SortOrder};
+use crate::models::template::EntryKind;
+use crate::models::trash::TrashKind;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// The kinds of records whose changes are logged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum HistoryEntity {
+    Meal,
+    Event,
+    Drink,
+    Person,
+    Recipe,
+    Product,
+    Restaurant,
+    Activity,
+    MealPlan,
+    Template,
+}
+
+impl HistoryEntity {
+    pub fn table(self) -> &'static str {
+        match self {
+            HistoryEntity::Meal => "meal",
+            HistoryEntity::Event => "event",
+            HistoryEntity::Drink => "drink",
+            HistoryEntity::Person => "people",
+            HistoryEntity::Recipe => "recipe",
+            HistoryEntity::Product => "product",
+            HistoryEntity::Restaurant => "restaurant",
+            HistoryEntity::Activity => "activity",
+            HistoryEntity::MealPlan => "meal_plan",
+            HistoryEntity::Template => "template",
+        }
+    }
+
+    /// Tables holding the links that belong to a record, with the column
+    /// pointing back at it. Images include their rows.
+    pub fn links(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            HistoryEntity::Meal => &[
+                ("meal_recipe", "meal"),
+                ("meal_product", "meal"),
+                ("meal_restaurant", "meal"),
+                ("meal_people", "meal"),
+            ],
+            HistoryEntity::Event => &[("event_people", "event")],
+            HistoryEntity::Drink => &[("drink_people", "drink")],
+            HistoryEntity::Person => &[("people_alias", "people")],
+            HistoryEntity::Recipe => &[("recipe_ingredient", "recipe")],
+            HistoryEntity::MealPlan => {
+                &[("meal_plan_source", "plan"), ("meal_plan_people", "plan")]
+            }
+            HistoryEntity::Product
+            | HistoryEntity::Restaurant
+            | HistoryEntity::Activity
+            | HistoryEntity::Template => &[],
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HistoryEntity::Meal => "Meal",
+            HistoryEntity::Event => "Event",
+            HistoryEntity::Drink => "Drink",
+            HistoryEntity::Person => "Person",
+            HistoryEntity::Recipe => "Recipe",
+            HistoryEntity::Product => "Product",
+            HistoryEntity::Restaurant => "Restaurant",
+            HistoryEntity::Activity => "Activity",
+            HistoryEntity::MealPlan => "Meal plan",
+            HistoryEntity::Template => "Template",
+        }
+    }
+}
+
+impl From<TrashKind> for HistoryEntity {
+    fn from(kind: TrashKind) -> Self {
+        match kind {
+            TrashKind::Meal => HistoryEntity::Meal,
+            TrashKind::Event => HistoryEntity::Event,
+            TrashKind::Drink => HistoryEntity::Drink,
+            TrashKind::Person => HistoryEntity::Person,
+            TrashKind::Recipe => HistoryEntity::Recipe,
+            TrashKind::Product => HistoryEntity::Product,
+            TrashKind::Restaurant => HistoryEntity::Restaurant,
+        }
+    }
+}
+
+impl From<EntryKind> for HistoryEntity {
+    fn from(kind: EntryKind) -> Self {
+        match kind {
+            EntryKind::Meal => HistoryEntity::Meal,
+            EntryKind::Event => HistoryEntity::Event,
+            EntryKind::Drink => HistoryEntity::Drink,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum HistoryAction {
+    Create,
+    Update,
+    /// Moved to the trash.
+    Delete,
+    /// Taken out of the trash.
+    Restore,
+    /// Deleted for good.
+    Purge,
+    /// Set back to an earlier image.
+    Revert,
+}
+
+/// One change. Images are the record's row with the rows of each of its link
+/// tables under the table's name.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct HistoryEntry {
+    pub id: i32,
+    pub entity: HistoryEntity,
+    pub entity_id: i32,
+    pub action: HistoryAction,
+    /// The user who made the change, absent for changes made outside requests.
+    pub actor: Option<String>,
+    pub changed_at: DateTime<Utc>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
+
+impl Keyset for HistoryEntry {
+    fn cursor(&self) -> Cursor {
+        Cursor {
+            date: Some(self.changed_at.date_naive()),
+            id: self.id.into(),
+        }
+    }
+}
+
+/// The list parameters that apply to history, plus the record to show it for.
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    pub entity: Option<HistoryEntity>,
+    pub id: Option<i32>,
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub sort: Option<SortOrder>,
+}
+
+impl HistoryQuery {
+    pub fn list_query(&self) -> ListQuery {
+        ListQuery {
+            limit: self.limit,
+            cursor: self.cursor.clone(),
+            start_date: self.start_date,
+            end_date: self.end_date,
+            sort: self.sort,
+            ..ListQuery::default()
+        }
+    }
+}
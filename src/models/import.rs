@@ -0,0 +1,51 @@
+use crate::models::event::CreateEvent;
+use crate::models::meal::CreateMeal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct ImportQuery {
+    /// Write the records; without it the import is only previewed.
+    #[serde(default)]
+    pub commit: bool,
+    /// Import the resolved records even if others could not be resolved.
+    #[serde(default)]
+    pub skip_unmatched: bool,
+}
+
+/// The existing recipe, product, restaurant or activity a name was matched to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameMatch {
+    pub source: String,
+    pub id: i32,
+    pub name: String,
+    /// 1.0 for an exact (case-insensitive) match.
+    pub score: f64,
+}
+
+/// One record parsed from a spreadsheet cell. `record` is None when the cell
+/// could not be fully resolved, in which case `issues` says why.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportRecord<T> {
+    pub row: usize,
+    pub column: String,
+    pub text: String,
+    pub record: Option<T>,
+    pub matched: Option<NameMatch>,
+    pub issues: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportPreview {
+    pub committed: bool,
+    /// Rows skipped because they have no valid date, such as headers.
+    pub skipped_rows: Vec<usize>,
+    pub meals: Vec<ImportRecord<CreateMeal>>,
+    pub events: Vec<ImportRecord<CreateEvent>>,
+    pub unresolved: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PeopleAlias {
+    pub alias: String,
+    pub people_ids: Vec<i32>,
+}
@@ -0,0 +1,52 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// One structured line of a recipe's ingredients.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct Ingredient {
+    pub quantity: Option<f64>,
+    pub unit: Option<String>,
+    pub item: String,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecipeIngredients {
+    pub recipe_id: i32,
+    /// Servings the quantities are for; the recipe's own unless scaled.
+    pub servings: Option<i32>,
+    pub ingredients: Vec<Ingredient>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IngredientsQuery {
+    pub servings: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShoppingListQuery {
+    /// Comma-separated recipe ids; a recipe listed twice is cooked twice.
+    pub recipe_ids: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    /// Scale every recipe that knows its servings to this many.
+    pub servings: Option<i32>,
+}
+
+/// An ingredient merged across recipes. Lines without a quantity, or in units
+/// that do not convert into each other, stay separate items.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShoppingItem {
+    pub item: String,
+    pub quantity: Option<f64>,
+    pub unit: Option<String>,
+    /// Names of the recipes that need it.
+    pub recipes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IndexReport {
+    pub recipes: u64,
+    pub lines: u64,
+}
@@ -0,0 +1,96 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+/// Query parameters shared by the list endpoints. Every field is optional, and an
+/// endpoint rejects filters that do not apply to it. Without `limit` the whole
+/// (filtered) list is returned, as before.
+#[derive(Debug, Default, Deserialize)]
+pub struct ListQuery {
+    pub limit: Option<i64>,
+    /// Opaque value taken from the `X-Next-Cursor` header of the previous page.
+    pub cursor: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub person_id: Option<i32>,
+    pub activity_type: Option<String>,
+    pub meal_time: Option<String>,
+    pub meal_type: Option<String>,
+    pub location: Option<String>,
+    /// Part of an ingredient's name, for recipes.
+    pub ingredient: Option<String>,
+    pub sort: Option<SortOrder>,
+}
+
+impl ListQuery {
+    /// Names of the filter parameters present in the query.
+    pub fn filters(&self) -> Vec<&'static str> {
+        [
+            ("start_date", self.start_date.is_some()),
+            ("end_date", self.end_date.is_some()),
+            ("person_id", self.person_id.is_some()),
+            ("activity_type", self.activity_type.is_some()),
+            ("meal_time", self.meal_time.is_some()),
+            ("meal_type", self.meal_type.is_some()),
+            ("location", self.location.is_some()),
+            ("ingredient", self.ingredient.is_some()),
+        ]
+        .into_iter()
+        .filter(|(_, present)| *present)
+        .map(|(name, _)| name)
+        .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn sql(self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+}
+
+/// Position of the last row of a page. Dated lists page on `(date, id)`, the
+/// others on `id` alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub date: Option<NaiveDate>,
+    pub id: i64,
+}
+
+impl Cursor {
+    /// Parse the `date:id` or `id` form produced by `Display`.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.split_once(':') {
+            Some((date, id)) => Some(Cursor {
+                date: Some(date.parse().ok()?),
+                id: id.parse().ok()?,
+            }),
+            None => Some(Cursor {
+                date: None,
+                id: s.parse().ok()?,
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.date {
+            Some(date) => write!(f, "{}:{}", date, self.id),
+            None => write!(f, "{}", self.id),
+        }
+    }
+}
+
+/// Rows that can be paged through with a `Cursor`.
+pub trait Keyset {
+    fn cursor(&self) -> Cursor;
+}
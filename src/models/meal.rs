@@ -1,5 +1,7 @@
+use crate::models::list::{Cursor, Keyset};
+use crate::models::restaurant::Visit;
 use chrono::NaiveDate;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use sqlx::FromRow;
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -10,16 +12,41 @@ pub struct Meal {
     pub notes: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+impl Keyset for Meal {
+    fn cursor(&self) -> Cursor {
+        Cursor {
+            date: Some(self.date),
+            id: self.id.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CreateMeal {
     pub date: NaiveDate,
     pub time: String,
     pub notes: Option<String>,
-    pub food_source: CreateMealFoodSource,
+    /// Every dish of the meal. Bodies with a single `food_source` object are
+    /// still accepted.
+    #[serde(alias = "food_source", deserialize_with = "one_or_many")]
+    pub food_sources: Vec<CreateMealFoodSource>,
     pub people_ids: Vec<i32>,
 }
 
-#[derive(Debug, Deserialize)]
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<CreateMealFoodSource>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    if value.is_array() {
+        serde_json::from_value(value)
+    } else {
+        serde_json::from_value(value).map(|food_source| vec![food_source])
+    }
+    .map_err(serde::de::Error::custom)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum CreateMealFoodSource {
     #[serde(rename = "recipe")]
@@ -30,6 +57,8 @@ pub enum CreateMealFoodSource {
     Restaurant {
         restaurant_id: i32,
         meal_type: String,
+        #[serde(flatten)]
+        visit: Visit,
     },
 }
 
@@ -0,0 +1,45 @@
+use crate::models::list::{Cursor, Keyset};
+use crate::models::meal::CreateMealFoodSource;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum PlanStatus {
+    Planned,
+    Eaten,
+    /// Not eaten by the day after its date.
+    Skipped,
+}
+
+/// A planned meal. Plans are created from the same body as meals.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct MealPlan {
+    pub id: i32,
+    pub date: NaiveDate,
+    pub time: String,
+    pub notes: Option<String>,
+    pub food_sources: Json<Vec<CreateMealFoodSource>>,
+    pub people_ids: Vec<i32>,
+    /// The meal logged when the plan was eaten.
+    pub meal_id: Option<i32>,
+    pub status: PlanStatus,
+}
+
+impl Keyset for MealPlan {
+    fn cursor(&self) -> Cursor {
+        Cursor {
+            date: Some(self.date),
+            id: self.id.into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EatenQuery {
+    /// Day the meal was eaten, if not the planned one.
+    pub date: Option<NaiveDate>,
+}
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// A record as shown in duplicate proposals.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateRecord {
+    pub id: i32,
+    pub name: String,
+}
+
+/// Two records that look like the same thing. `first` is the older one, the
+/// natural survivor of a merge.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DuplicatePair {
+    pub first: DuplicateRecord,
+    pub second: DuplicateRecord,
+    /// How alike the names are, 1.0 when they only differ in case and spacing.
+    pub similarity: f64,
+    /// Attributes both have the same value for, such as a restaurant's `location`.
+    pub shared: Vec<String>,
+    /// The similarity raised by the shared attributes; pairs are sorted by it.
+    pub score: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MergeRequest {
+    pub survivor_id: i32,
+    pub duplicate_ids: Vec<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MergeReport {
+    pub survivor_id: i32,
+    pub merged_ids: Vec<i32>,
+    /// Links moved from the duplicates to the survivor.
+    pub moved: u64,
+    /// Links dropped because the survivor already had the same one.
+    pub dropped: u64,
+    /// Templates whose bodies referred to a duplicate.
+    pub templates: u64,
+}
@@ -1,11 +1,27 @@
 pub mod activity;
+pub mod auth;
+pub mod calendar;
 pub mod daily_summary;
 pub mod detail;
 pub mod drink;
 pub mod event;
+pub mod export;
+pub mod history;
+pub mod import;
+pub mod ingredient;
+pub mod list;
 pub mod location;
 pub mod meal;
+pub mod meal_plan;
+pub mod merge;
 pub mod people;
 pub mod product;
+pub mod quick_log;
 pub mod recipe;
 pub mod restaurant;
+pub mod search;
+pub mod stats;
+pub mod suggestion;
+pub mod template;
+pub mod trash;
+pub mod vocabulary;
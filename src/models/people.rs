@@ -1,3 +1,6 @@
+use crate::models::list::{Cursor, Keyset};
+use crate::models::stats::RestaurantVisits;
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
@@ -6,4 +9,82 @@ pub struct People {
     pub id: i32,
     pub name: String,
     pub notes: Option<String>,
+    /// Position among household members, or None for everyone else.
+    pub household_order: Option<i32>,
+}
+
+impl Keyset for People {
+    fn cursor(&self) -> Cursor {
+        Cursor {
+            date: None,
+            id: self.id.into(),
+        }
+    }
+}
+
+/// Household members in order; everyone not listed stops being a member.
+#[derive(Debug, Deserialize)]
+pub struct UpdateHousehold {
+    pub people_ids: Vec<i32>,
+}
+
+/// A meal, event or drink shared with a person, as listed on their timeline.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct TimelineItem {
+    pub kind: String, // "meal", "event" or "drink"
+    pub id: i32,
+    pub date: NaiveDate,
+    pub title: String,
+    pub detail: Option<String>,
+    pub link: String, // API path of the detail resource
+    /// Unique across kinds, so the timeline can be paged on `(date, seq)`.
+    #[serde(skip)]
+    pub seq: i64,
+}
+
+impl Keyset for TimelineItem {
+    fn cursor(&self) -> Cursor {
+        Cursor {
+            date: Some(self.date),
+            id: self.seq,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct MonthlyCount {
+    pub month: NaiveDate, // First day of the month
+    pub meals: i64,
+    pub events: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ActivityTogether {
+    pub activity_id: i32,
+    pub name: String,
+    pub activity_type: String,
+    pub times: i64,
+    pub last_date: Option<NaiveDate>,
+}
+
+/// The longest stretch without a shared meal, event or drink.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MeetupGap {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub days: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PersonSummary {
+    pub person: People,
+    pub first_seen: Option<NaiveDate>,
+    pub last_seen: Option<NaiveDate>,
+    pub total_meals: i64,
+    pub total_events: i64,
+    pub total_drinks: i64,
+    pub by_month: Vec<MonthlyCount>,
+    pub favorite_restaurants: Vec<RestaurantVisits>,
+    pub favorite_activities: Vec<ActivityTogether>,
+    pub longest_gap: Option<MeetupGap>,
 }
@@ -1,3 +1,4 @@
+use crate::models::list::{Cursor, Keyset};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
@@ -7,6 +8,15 @@ pub struct Product {
     pub name: String,
 }
 
+impl Keyset for Product {
+    fn cursor(&self) -> Cursor {
+        Cursor {
+            date: None,
+            id: self.id.into(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreateProduct {
     pub name: String,
@@ -0,0 +1,44 @@
+use crate::models::drink::CreateDrink;
+use crate::models::event::CreateEvent;
+use crate::models::import::NameMatch;
+use crate::models::meal::CreateMeal;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct QuickLog {
+    /// Defaults to today.
+    pub date: Option<NaiveDate>,
+    /// A meal time, or `event` or `drink`.
+    pub time: String,
+    /// Shorthand such as `pho, spring rolls w/ alice @Ballard (birthday) bento`.
+    pub text: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuickLogQuery {
+    /// Write the entries; without it they are only resolved.
+    #[serde(default)]
+    pub commit: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "record", rename_all = "snake_case")]
+pub enum QuickLogEntry {
+    Meal(CreateMeal),
+    Event(CreateEvent),
+    Drink(CreateDrink),
+}
+
+/// What a line of shorthand resolved to. A meal line becomes one meal with a
+/// dish per name; event and drink lines become one entry per name. `entries`
+/// is empty while any of `issues` remain.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuickLogPreview {
+    pub committed: bool,
+    pub entries: Vec<QuickLogEntry>,
+    pub matched: Vec<NameMatch>,
+    pub issues: Vec<String>,
+    /// The created entries, in the order of `entries`, once committed.
+    pub ids: Vec<i32>,
+}
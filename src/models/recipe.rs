@@ -1,3 +1,4 @@
+use crate::models::list::{Cursor, Keyset};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
@@ -8,6 +9,17 @@ pub struct Recipe {
     pub ingredients: String,
     pub procedure: String,
     pub cautions: Option<String>,
+    /// Servings the ingredients are for, which scaling starts from.
+    pub servings: Option<i32>,
+}
+
+impl Keyset for Recipe {
+    fn cursor(&self) -> Cursor {
+        Cursor {
+            date: None,
+            id: self.id.into(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -16,6 +28,8 @@ pub struct CreateRecipe {
     pub ingredients: String,
     pub procedure: String,
     pub cautions: Option<String>,
+    #[serde(default)]
+    pub servings: Option<i32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -24,4 +38,5 @@ pub struct UpdateRecipe {
     pub ingredients: Option<String>,
     pub procedure: Option<String>,
     pub cautions: Option<String>,
+    pub servings: Option<i32>,
 }
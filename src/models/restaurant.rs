@@ -1,6 +1,12 @@
+use crate::models::list::{Cursor, Keyset};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
+/// Currency of visit amounts given without one.
+pub const DEFAULT_CURRENCY: &str = "USD";
+
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct FoodType {
     pub name: String,
@@ -14,7 +20,16 @@ pub struct Restaurant {
     #[serde(rename = "type")]
     #[sqlx(rename = "type")]
     pub food_type: String,
-    pub price: Option<f32>,
+    pub price: Option<Decimal>,
+}
+
+impl Keyset for Restaurant {
+    fn cursor(&self) -> Cursor {
+        Cursor {
+            date: None,
+            id: self.id.into(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -23,7 +38,7 @@ pub struct CreateRestaurant {
     pub location: String,
     #[serde(rename = "type")]
     pub food_type: String,
-    pub price: Option<f32>,
+    pub price: Option<Decimal>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -32,5 +47,75 @@ pub struct UpdateRestaurant {
     pub location: Option<String>,
     #[serde(rename = "type")]
     pub food_type: Option<String>,
-    pub price: Option<f32>,
+    pub price: Option<Decimal>,
+}
+
+/// What was paid, rated and ordered on one visit to a restaurant.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct Visit {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<Decimal>,
+    /// ISO 4217 code; only set along with an amount.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    /// From 1 to 5.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<i16>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dishes: Vec<String>,
+}
+
+/// A meal at a restaurant, for its visit history.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct RestaurantVisit {
+    pub meal_id: i32,
+    pub date: NaiveDate,
+    pub time: String,
+    pub meal_type: String,
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub visit: Visit,
+}
+
+impl Keyset for RestaurantVisit {
+    fn cursor(&self) -> Cursor {
+        Cursor {
+            date: Some(self.date),
+            id: self.meal_id.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Spend {
+    pub currency: String,
+    pub amount: Decimal,
+    /// Visits paid in this currency.
+    pub visits: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct MonthlyRating {
+    /// First day of the month.
+    pub month: NaiveDate,
+    pub average_rating: f64,
+    pub ratings: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VisitStats {
+    pub visits: i64,
+    pub last_visit: Option<NaiveDate>,
+    pub average_rating: Option<f64>,
+    /// Average rating of every month with rated visits, oldest first.
+    pub rating_trend: Vec<MonthlyRating>,
+    /// Total paid per currency; visits without an amount are not counted.
+    pub spend: Vec<Spend>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RestaurantDetail {
+    #[serde(flatten)]
+    pub restaurant: Restaurant,
+    pub stats: VisitStats,
 }
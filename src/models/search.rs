@@ -0,0 +1,39 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    pub start_date: Option<NaiveDate>, // Only applies to dated hits (meals, events, drinks)
+    pub end_date: Option<NaiveDate>,
+    pub limit: Option<i64>, // Max hits per entity type
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct SearchHit {
+    pub id: i32,
+    pub title: String,
+    pub snippet: Option<String>,
+    pub date: Option<NaiveDate>,
+    pub rank: f32,
+    pub link: String, // API path of the matching detail resource
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SearchResults {
+    pub meals: Vec<SearchHit>,
+    pub events: Vec<SearchHit>,
+    pub drinks: Vec<SearchHit>,
+    pub recipes: Vec<SearchHit>,
+    pub restaurants: Vec<SearchHit>,
+    pub products: Vec<SearchHit>,
+    pub people: Vec<SearchHit>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchResponse {
+    pub query: String,
+    pub total: usize,
+    pub results: SearchResults,
+}
@@ -0,0 +1,109 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsEntity {
+    Meals,
+    Events,
+    Drinks,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Period {
+    Week,
+    Month,
+    Year,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    None,
+    MealType,
+    MealTime,
+    FoodType,
+    Location,
+    Person,
+    ActivityType,
+    Activity,
+}
+
+/// What a series adds up. Hours and kilometres only apply to events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsValue {
+    Count,
+    /// Event `quantity` in hours, or else the time between start and end.
+    Hours,
+    Km,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatsQuery {
+    pub entity: Option<StatsEntity>,
+    pub period: Option<Period>,
+    pub group_by: Option<GroupBy>,
+    pub value: Option<StatsValue>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub limit: Option<i64>,
+    /// ISO 4217 code the spend is reported in.
+    pub currency: Option<String>,
+}
+
+/// One line of a chart: `data[i]` is the value for `periods[i]` of the response.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Series<T> {
+    pub name: String,
+    pub total: T,
+    pub data: Vec<T>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SeriesResponse<T> {
+    pub entity: StatsEntity,
+    pub period: Period,
+    pub group_by: GroupBy,
+    /// First day of every period in the range, including empty ones.
+    pub periods: Vec<NaiveDate>,
+    pub series: Vec<Series<T>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct RestaurantVisits {
+    pub restaurant_id: i32,
+    pub name: String,
+    pub location: String,
+    pub food_type: String,
+    pub visits: i64,
+    pub last_visit: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct FoodTypeSpend {
+    pub food_type: String,
+    pub visits: i64,
+    /// Sum of what was paid per visit, or else the restaurant's `price`; visits
+    /// with neither count as 0.
+    pub spend: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpendResponse {
+    /// The currency of every amount, or None for visits with no currency
+    /// recorded.
+    pub currency: Option<String>,
+    pub by_food_type: Vec<FoodTypeSpend>,
+    pub over_time: SeriesResponse<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct RecipeCount {
+    pub recipe_id: i32,
+    pub name: String,
+    pub times_made: i64,
+    pub last_made: Option<NaiveDate>,
+}
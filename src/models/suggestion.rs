@@ -0,0 +1,57 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum SourceKind {
+    Recipe,
+    Restaurant,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SuggestionQuery {
+    pub time: String,
+    /// Comma-separated ids of the people joining.
+    pub people_ids: Option<String>,
+    /// Day to suggest for; today by default.
+    pub date: Option<NaiveDate>,
+    pub limit: Option<i64>,
+    /// How many days back a food type counts as recently eaten.
+    pub variety_days: Option<i32>,
+}
+
+/// A recipe or restaurant with what the history says about it.
+#[derive(Debug, Clone, FromRow)]
+pub struct Candidate {
+    pub kind: SourceKind,
+    pub id: i32,
+    pub name: String,
+    /// Only restaurants have a food type.
+    pub food_type: Option<String>,
+    pub last_eaten: Option<NaiveDate>,
+    pub times_eaten: i64,
+    /// How many of those times were at the requested meal time.
+    pub times_at_time: i64,
+    /// Which of the joining companions have eaten it before.
+    pub companions_joined: Vec<i32>,
+}
+
+/// Someone joining who is not part of the household.
+#[derive(Debug, Clone, FromRow)]
+pub struct Companion {
+    pub id: i32,
+    pub name: String,
+    /// Meals they have joined in total.
+    pub meals: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub kind: SourceKind,
+    pub id: i32,
+    pub name: String,
+    pub score: f64,
+    pub explanation: String,
+}
@@ -0,0 +1,106 @@
+use crate::models::list::{Cursor, Keyset};
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum EntryKind {
+    Meal,
+    Event,
+    Drink,
+}
+
+impl EntryKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EntryKind::Meal => "meal",
+            EntryKind::Event => "event",
+            EntryKind::Drink => "drink",
+        }
+    }
+}
+
+/// Days on which a template is materialized as a pending entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "frequency", rename_all = "snake_case")]
+pub enum Recurrence {
+    Daily,
+    /// Monday to Friday.
+    Weekdays,
+    Weekly {
+        days: Vec<Weekday>,
+    },
+}
+
+impl Recurrence {
+    pub fn occurs_on(&self, date: NaiveDate) -> bool {
+        match self {
+            Recurrence::Daily => true,
+            Recurrence::Weekdays => !matches!(date.weekday(), Weekday::Sat | Weekday::Sun),
+            Recurrence::Weekly { days } => days.contains(&date.weekday()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Template {
+    pub id: i32,
+    pub name: String,
+    pub kind: EntryKind,
+    pub body: serde_json::Value,
+    pub recurrence: Option<Json<Recurrence>>,
+}
+
+impl Keyset for Template {
+    fn cursor(&self) -> Cursor {
+        Cursor {
+            date: None,
+            id: self.id.into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTemplate {
+    pub name: String,
+    pub kind: EntryKind,
+    /// The fields of a CreateMeal, CreateEvent or CreateDrink, except `date`.
+    pub body: serde_json::Value,
+    pub recurrence: Option<Recurrence>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApplyQuery {
+    /// Defaults to today.
+    pub date: Option<NaiveDate>,
+}
+
+/// The meal, event or drink created from a template.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AppliedTemplate {
+    pub kind: EntryKind,
+    pub id: i32,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct PendingEntry {
+    pub id: i32,
+    pub template_id: i32,
+    pub name: String,
+    pub kind: EntryKind,
+    pub body: serde_json::Value,
+    pub date: NaiveDate,
+}
+
+impl Keyset for PendingEntry {
+    fn cursor(&self) -> Cursor {
+        Cursor {
+            date: Some(self.date),
+            id: self.id.into(),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// The kinds of records that go to the trash when deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum TrashKind {
+    Meal,
+    Event,
+    Drink,
+    Person,
+    Recipe,
+    Product,
+    Restaurant,
+}
+
+impl TrashKind {
+    /// Entries first, so purging them frees the people and food sources they
+    /// link to.
+    pub const ALL: [TrashKind; 7] = [
+        TrashKind::Meal,
+        TrashKind::Event,
+        TrashKind::Drink,
+        TrashKind::Person,
+        TrashKind::Recipe,
+        TrashKind::Product,
+        TrashKind::Restaurant,
+    ];
+
+    pub fn table(self) -> &'static str {
+        match self {
+            TrashKind::Meal => "meal",
+            TrashKind::Event => "event",
+            TrashKind::Drink => "drink",
+            TrashKind::Person => "people",
+            TrashKind::Recipe => "recipe",
+            TrashKind::Product => "product",
+            TrashKind::Restaurant => "restaurant",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TrashKind::Meal => "Meal",
+            TrashKind::Event => "Event",
+            TrashKind::Drink => "Drink",
+            TrashKind::Person => "Person",
+            TrashKind::Recipe => "Recipe",
+            TrashKind::Product => "Product",
+            TrashKind::Restaurant => "Restaurant",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TrashQuery {
+    pub kind: Option<TrashKind>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct TrashItem {
+    pub kind: TrashKind,
+    pub id: i32,
+    pub title: String,
+    /// The day of a meal, event or drink.
+    pub date: Option<NaiveDate>,
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// How many records of each kind were purged.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PurgeReport {
+    pub purged: Vec<PurgedKind>,
+    /// Trashed food sources kept because meals or plans still use them.
+    pub kept: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurgedKind {
+    pub kind: TrashKind,
+    pub count: u64,
+}
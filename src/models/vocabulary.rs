@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Deserialize)]
+pub struct RenameVocabulary {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteVocabularyQuery {
+    /// Move existing references to this value instead of refusing the delete.
+    pub merge_into: Option<String>,
+}
+
+/// A record that still uses a vocabulary value, as listed in 409 responses.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct VocabularyReference {
+    pub id: i32,
+    pub label: String,
+}
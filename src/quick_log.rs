@@ -0,0 +1,190 @@
+use crate::auth::AuthUser;
+use crate::error::{AppError, Result, ResultExt};
+use crate::handlers::{drinks, events, meals};
+use crate::history;
+use crate::import::{food_source, parse_shorthand, push_unique, Catalog};
+use crate::models::drink::CreateDrink;
+use crate::models::event::CreateEvent;
+use crate::models::history::{HistoryAction, HistoryEntity};
+use crate::models::meal::CreateMeal;
+use crate::models::quick_log::{QuickLog, QuickLogEntry, QuickLogPreview};
+use crate::util::{self, non_empty};
+use chrono::NaiveDate;
+use sqlx::PgPool;
+
+/// What a quick-log line is logged as.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogAs<'a> {
+    Meal(&'a str),
+    Event,
+    Drink,
+}
+
+/// Resolve a line of shorthand against the catalog, without writing anything.
+/// Everyone in the household is taken to be there, along with the `w/` people.
+pub fn plan(catalog: &Catalog, date: NaiveDate, log_as: LogAs, text: &str) -> QuickLogPreview {
+    let shorthand = parse_shorthand(text);
+    let mut preview = QuickLogPreview {
+        committed: false,
+        entries: Vec::new(),
+        matched: Vec::new(),
+        issues: Vec::new(),
+        ids: Vec::new(),
+    };
+
+    if shorthand.names.is_empty() {
+        preview.issues.push("Nothing to log".to_string());
+    }
+
+    let mut people_ids = catalog.household.clone();
+    match catalog.resolve_people(&shorthand.people) {
+        Ok(companions) => push_unique(&mut people_ids, companions),
+        Err(issue) => preview.issues.push(issue),
+    }
+
+    match log_as {
+        LogAs::Meal(time) => {
+            let mut food_sources = Vec::new();
+            let mut at_restaurant = false;
+            for name in &shorthand.names {
+                match catalog.match_food(name) {
+                    Ok(matched) => {
+                        food_sources.push(food_source(&matched, shorthand.meal_type));
+                        at_restaurant |= matched.source == "restaurant";
+                        preview.matched.push(matched);
+                    }
+                    Err(issue) => preview.issues.push(issue),
+                }
+            }
+
+            // A restaurant already says where the meal was
+            let location = shorthand
+                .location
+                .as_ref()
+                .filter(|_| !at_restaurant)
+                .map(|location| format!("@{}", location));
+            let notes = [shorthand.comment.clone(), location]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            preview.entries.push(QuickLogEntry::Meal(CreateMeal {
+                date,
+                time: time.to_string(),
+                notes: non_empty(&notes),
+                food_sources,
+                people_ids,
+            }));
+        }
+        LogAs::Event => {
+            for name in &shorthand.names {
+                match catalog.match_activity(name) {
+                    Ok(matched) => {
+                        preview.entries.push(QuickLogEntry::Event(CreateEvent {
+                            date,
+                            activity_id: matched.id,
+                            measure: None,
+                            location: shorthand.location.clone(),
+                            notes: shorthand.comment.clone(),
+                            people_ids: people_ids.clone(),
+                            start_time: None,
+                            end_time: None,
+                            quantity: None,
+                            unit: None,
+                        }));
+                        preview.matched.push(matched);
+                    }
+                    Err(issue) => preview.issues.push(issue),
+                }
+            }
+        }
+        LogAs::Drink => {
+            for name in &shorthand.names {
+                match catalog.match_drink(name) {
+                    Ok(name) => preview.entries.push(QuickLogEntry::Drink(CreateDrink {
+                        date,
+                        name,
+                        people_ids: people_ids.clone(),
+                    })),
+                    Err(issue) => preview.issues.push(issue),
+                }
+            }
+        }
+    }
+
+    if !preview.issues.is_empty() {
+        preview.entries.clear();
+    }
+
+    preview
+}
+
+/// Resolve a quick-log line, and with `commit` write its entries in a single
+/// transaction. Nothing is written while any name is unresolved.
+pub async fn quick_log(
+    pool: &PgPool,
+    log: &QuickLog,
+    commit: bool,
+    actor: Option<&AuthUser>,
+) -> Result<QuickLogPreview> {
+    let time = log.time.trim();
+    let log_as = match time {
+        "event" => LogAs::Event,
+        "drink" => LogAs::Drink,
+        _ => {
+            let known: bool =
+                sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM meal_time WHERE name = $1)")
+                    .bind(time)
+                    .fetch_one(pool)
+                    .await
+                    .context("Failed to log")?;
+            if !known {
+                return Err(AppError::bad_request(format!(
+                    "Unknown meal time '{}'; use a meal time, 'event' or 'drink'",
+                    time
+                )));
+            }
+            LogAs::Meal(time)
+        }
+    };
+
+    let date = log.date.unwrap_or_else(util::today);
+    let catalog = Catalog::load(pool).await?;
+    let mut preview = plan(&catalog, date, log_as, &log.text);
+
+    if !commit {
+        return Ok(preview);
+    }
+
+    if !preview.issues.is_empty() {
+        return Err(AppError::InvalidReference {
+            field: "text".to_string(),
+            message: preview.issues.join("; "),
+        });
+    }
+
+    let mut tx = pool.begin().await.context("Failed to log")?;
+    for entry in &preview.entries {
+        let (entity, id) = match entry {
+            QuickLogEntry::Meal(meal) => (
+                HistoryEntity::Meal,
+                meals::insert_meal(&mut tx, meal).await?,
+            ),
+            QuickLogEntry::Event(event) => (
+                HistoryEntity::Event,
+                events::insert_event(&mut tx, event).await?,
+            ),
+            QuickLogEntry::Drink(drink) => (
+                HistoryEntity::Drink,
+                drinks::insert_drink(&mut tx, drink).await?,
+            ),
+        };
+        history::record(&mut tx, entity, id, HistoryAction::Create, None, actor).await?;
+        preview.ids.push(id);
+    }
+    tx.commit().await.context("Failed to log")?;
+
+    preview.committed = true;
+    Ok(preview)
+}
@@ -0,0 +1,129 @@
+use crate::models::suggestion::{Candidate, Companion, Suggestion};
+use chrono::NaiveDate;
+
+/// Days after which something counts as fully due again.
+const RECENCY_DAYS: f64 = 30.0;
+
+/// Companions with fewer meals than this are too new to judge by.
+const COMPANION_MIN_MEALS: i64 = 3;
+
+/// What the ranking knows besides the candidates themselves.
+pub struct Context {
+    pub today: NaiveDate,
+    pub time: String,
+    pub variety_days: i32,
+    /// Food types eaten recently, with the last day each was eaten.
+    pub recent_food_types: Vec<(String, NaiveDate)>,
+    /// The joining people who are not part of the household.
+    pub companions: Vec<Companion>,
+}
+
+/// Score every candidate and sort them best first.
+///
+/// Something not had for a while, picked often, usually eaten at this time of
+/// day, of a food type not had lately and that the companions have been to
+/// before ranks highest. Each suggestion explains the signals that moved it.
+pub fn rank(candidates: Vec<Candidate>, context: &Context) -> Vec<Suggestion> {
+    let max_times = candidates
+        .iter()
+        .map(|candidate| candidate.times_eaten)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    let mut suggestions: Vec<Suggestion> = candidates
+        .into_iter()
+        .map(|candidate| score(candidate, max_times, context))
+        .collect();
+
+    suggestions.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    suggestions
+}
+
+fn score(candidate: Candidate, max_times: i64, context: &Context) -> Suggestion {
+    let mut score = 0.0;
+    let mut reasons = Vec::new();
+
+    match candidate.last_eaten {
+        Some(last_eaten) => {
+            let days = (context.today - last_eaten).num_days();
+            score += (days as f64 / RECENCY_DAYS).clamp(0.0, 1.0);
+            reasons.push(format!("last had {}", days_ago(days)));
+        }
+        None => {
+            score += 0.5;
+            reasons.push("never had yet".to_string());
+        }
+    }
+
+    if candidate.times_eaten > 0 {
+        score += candidate.times_eaten as f64 / max_times as f64;
+        score += 0.5 * candidate.times_at_time as f64 / candidate.times_eaten as f64;
+        reasons.push(format!(
+            "picked {} {}, {} for {}",
+            candidate.times_eaten,
+            plural(candidate.times_eaten, "time", "times"),
+            candidate.times_at_time,
+            context.time
+        ));
+    }
+
+    if let Some(food_type) = &candidate.food_type {
+        match context
+            .recent_food_types
+            .iter()
+            .find(|(recent, _)| recent == food_type)
+        {
+            Some((_, last_eaten)) => {
+                score -= 0.5;
+                let days = (context.today - *last_eaten).num_days();
+                reasons.push(format!("had {} {}", food_type, days_ago(days)));
+            }
+            None => reasons.push(format!(
+                "no {} in the last {} days",
+                food_type, context.variety_days
+            )),
+        }
+    }
+
+    for companion in &context.companions {
+        if candidate.companions_joined.contains(&companion.id) {
+            score += 0.5;
+            reasons.push(format!("{} has had it before", companion.name));
+        } else if companion.meals >= COMPANION_MIN_MEALS {
+            score -= 1.0;
+            reasons.push(format!(
+                "{} never came along in {} meals",
+                companion.name, companion.meals
+            ));
+        }
+    }
+
+    Suggestion {
+        kind: candidate.kind,
+        id: candidate.id,
+        name: candidate.name,
+        score: (score * 100.0).round() / 100.0,
+        explanation: reasons.join("; "),
+    }
+}
+
+fn days_ago(days: i64) -> String {
+    match days {
+        0 => "today".to_string(),
+        1 => "yesterday".to_string(),
+        _ => format!("{} days ago", days),
+    }
+}
+
+fn plural<'a>(count: i64, one: &'a str, many: &'a str) -> &'a str {
+    if count == 1 {
+        one
+    } else {
+        many
+    }
+}
@@ -0,0 +1,152 @@
+use crate::auth::AuthUser;
+use crate::error::{AppError, Result, ResultExt};
+use crate::handlers::{drinks, events, meals};
+use crate::history;
+use crate::models::drink::CreateDrink;
+use crate::models::event::CreateEvent;
+use crate::models::history::HistoryAction;
+use crate::models::meal::CreateMeal;
+use crate::models::template::{EntryKind, Recurrence, Template};
+use crate::util;
+use chrono::NaiveDate;
+use serde::de::DeserializeOwned;
+use sqlx::types::Json;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::time::Duration;
+
+/// How often the scheduler checks for templates to materialize.
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How far back the scheduler fills in days it missed while the server was down.
+const MAX_CATCH_UP_DAYS: i64 = 31;
+
+/// The template body completed with a date.
+fn entry<T: DeserializeOwned>(body: &serde_json::Value, date: NaiveDate) -> Result<T> {
+    let mut entry = body.clone();
+    let fields = entry
+        .as_object_mut()
+        .ok_or_else(|| AppError::bad_request("Template body must be an object"))?;
+    fields.insert("date".to_string(), serde_json::json!(date));
+
+    serde_json::from_value(entry)
+        .map_err(|e| AppError::bad_request(format!("Invalid template body: {}", e)))
+}
+
+/// Check that a template body becomes a valid entry once given a date.
+pub fn validate(kind: EntryKind, body: &serde_json::Value) -> Result<()> {
+    let date = NaiveDate::default();
+    match kind {
+        EntryKind::Meal => entry::<CreateMeal>(body, date).map(|_| ()),
+        EntryKind::Event => entry::<CreateEvent>(body, date).map(|_| ()),
+        EntryKind::Drink => entry::<CreateDrink>(body, date).map(|_| ()),
+    }
+}
+
+/// Create the meal, event or drink a template describes on `date`.
+pub async fn apply(
+    tx: &mut Transaction<'_, Postgres>,
+    kind: EntryKind,
+    body: &serde_json::Value,
+    date: NaiveDate,
+    actor: Option<&AuthUser>,
+) -> Result<i32> {
+    let id = match kind {
+        EntryKind::Meal => meals::insert_meal(tx, &entry(body, date)?).await,
+        EntryKind::Event => events::insert_event(tx, &entry(body, date)?).await,
+        EntryKind::Drink => drinks::insert_drink(tx, &entry(body, date)?).await,
+    }?;
+    history::record(tx, kind.into(), id, HistoryAction::Create, None, actor).await?;
+
+    Ok(id)
+}
+
+/// Create pending entries for every recurring template that occurs on `date`,
+/// returning how many were new. Days already materialized are skipped, even if
+/// their entry was confirmed or dismissed since.
+pub async fn materialize(pool: &PgPool, date: NaiveDate) -> Result<u64> {
+    let templates = sqlx::query_as::<_, Template>(
+        "SELECT id, name, kind, body, recurrence FROM template WHERE recurrence IS NOT NULL",
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to materialize templates")?;
+
+    let ids: Vec<i32> = templates
+        .iter()
+        .filter(|template| {
+            template
+                .recurrence
+                .as_ref()
+                .is_some_and(|Json(recurrence): &Json<Recurrence>| recurrence.occurs_on(date))
+        })
+        .map(|template| template.id)
+        .collect();
+
+    let created = sqlx::query(
+        r#"
+        INSERT INTO pending_entry (template, date)
+        SELECT id, $2 FROM unnest($1::int[]) AS t(id)
+        ON CONFLICT (template, date) DO NOTHING
+        "#,
+    )
+    .bind(&ids)
+    .bind(date)
+    .execute(pool)
+    .await
+    .context("Failed to materialize templates")?
+    .rows_affected();
+
+    Ok(created)
+}
+
+/// Materialize every day from the one after the last day the scheduler
+/// materialized through `today`, at most `MAX_CATCH_UP_DAYS` back, so days the
+/// server was down still get their pending entries. The first run only does
+/// `today`, and `today` itself is always materialized again to pick up
+/// templates created since.
+pub async fn catch_up(pool: &PgPool, today: NaiveDate) -> Result<u64> {
+    let last: Option<NaiveDate> =
+        sqlx::query_scalar("SELECT materialized_through FROM template_schedule")
+            .fetch_optional(pool)
+            .await
+            .context("Failed to materialize templates")?;
+
+    let earliest = today - chrono::Duration::days(MAX_CATCH_UP_DAYS);
+    let mut date = last.map_or(today, |last| {
+        (last + chrono::Duration::days(1)).clamp(earliest, today)
+    });
+    let mut created = 0;
+    while date <= today {
+        created += materialize(pool, date).await?;
+        date += chrono::Duration::days(1);
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO template_schedule (materialized_through) VALUES ($1)
+        ON CONFLICT (id) DO UPDATE
+        SET materialized_through = GREATEST(template_schedule.materialized_through, $1)
+        "#,
+    )
+    .bind(today)
+    .execute(pool)
+    .await
+    .context("Failed to materialize templates")?;
+
+    Ok(created)
+}
+
+/// Materialize recurring templates for the current day, once an hour, so they
+/// are waiting to be confirmed in the evening.
+pub async fn run_scheduler(pool: PgPool) {
+    let mut interval = actix_web::rt::time::interval(SCHEDULER_INTERVAL);
+    loop {
+        interval.tick().await;
+        let today = util::today();
+        match catch_up(&pool, today).await {
+            Ok(0) => {}
+            Ok(created) => log::info!("Created {} pending entries up to {}", created, today),
+            Err(e) => log::error!("Failed to materialize templates: {}", e),
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use crate::auth::AuthUser;
+use crate::error::{AppError, Result, ResultExt};
+use crate::history;
+use crate::models::history::HistoryAction;
+use crate::models::trash::{PurgeReport, PurgedKind, TrashItem, TrashKind};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::time::Duration;
+
+/// How often trashed records past the retention period are purged.
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Everything in the trash, titled like search results.
+const TRASH: &str = r#"
+    SELECT 'meal' AS kind, m.id,
+        m."time" || COALESCE(': ' || (
+            SELECT string_agg(fs.name, ', ') FROM (
+                SELECT r.name FROM meal_recipe mr JOIN recipe r ON mr.recipe = r.id WHERE mr.meal = m.id
+                UNION ALL SELECT p.name FROM meal_product mpr JOIN product p ON mpr.product = p.id WHERE mpr.meal = m.id
+                UNION ALL SELECT rt.name FROM meal_restaurant mrt JOIN restaurant rt ON mrt.restaurant = rt.id WHERE mrt.meal = m.id
+            ) fs
+        ), '') AS title,
+        m.date, m.deleted_at
+    FROM meal m WHERE m.deleted_at IS NOT NULL
+    UNION ALL
+    SELECT 'event', e.id, a.name, e.date, e.deleted_at
+    FROM event e JOIN activity a ON a.id = e.activity WHERE e.deleted_at IS NOT NULL
+    UNION ALL
+    SELECT 'drink', id, name, date, deleted_at FROM drink WHERE deleted_at IS NOT NULL
+    UNION ALL
+    SELECT 'person', id, name, NULL, deleted_at FROM people WHERE deleted_at IS NOT NULL
+    UNION ALL
+    SELECT 'recipe', id, name, NULL, deleted_at FROM recipe WHERE deleted_at IS NOT NULL
+    UNION ALL
+    SELECT 'product', id, name, NULL, deleted_at FROM product WHERE deleted_at IS NOT NULL
+    UNION ALL
+    SELECT 'restaurant', id, name, NULL, deleted_at FROM restaurant WHERE deleted_at IS NOT NULL
+"#;
+
+/// Trashed before the cutoff in $1, if there is one.
+const TRASHED_BEFORE: &str =
+    "deleted_at IS NOT NULL AND ($1::timestamptz IS NULL OR deleted_at < $1)";
+
+/// Tables linking to people, whose rows go when a person is purged.
+const PEOPLE_LINKS: [&str; 5] = [
+    "meal_people",
+    "meal_plan_people",
+    "event_people",
+    "drink_people",
+    "people_alias",
+];
+
+/// Trashed items, most recently deleted first.
+pub async fn list(pool: &PgPool, kind: Option<TrashKind>) -> Result<Vec<TrashItem>> {
+    sqlx::query_as::<_, TrashItem>(&format!(
+        "SELECT * FROM ({TRASH}) t WHERE $1::text IS NULL OR t.kind = $1 ORDER BY t.deleted_at DESC, t.kind, t.id"
+    ))
+    .bind(kind)
+    .fetch_all(pool)
+    .await
+    .context("Failed to list trash")
+}
+
+/// Move a record to the trash and log it. Returns false if it does not exist
+/// or is already there.
+pub async fn move_to_trash(
+    tx: &mut Transaction<'_, Postgres>,
+    kind: TrashKind,
+    id: i32,
+    actor: Option<&AuthUser>,
+) -> Result<bool> {
+    let before = history::snapshot(tx, kind.into(), id).await?;
+    let result = sqlx::query(&format!(
+        "UPDATE {} SET deleted_at = now() WHERE id = $1 AND deleted_at IS NULL",
+        kind.table()
+    ))
+    .bind(id)
+    .execute(&mut **tx)
+    .await
+    .context("Failed to move to trash")?;
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    history::record(tx, kind.into(), id, HistoryAction::Delete, before, actor).await?;
+
+    Ok(true)
+}
+
+/// Reject references to records in the trash, as if they did not exist.
+pub async fn check_live(
+    tx: &mut Transaction<'_, Postgres>,
+    kind: TrashKind,
+    ids: &[i32],
+    field: &str,
+) -> Result<()> {
+    let trashed: Option<i32> = sqlx::query_scalar(&format!(
+        "SELECT id FROM {} WHERE id = ANY($1) AND deleted_at IS NOT NULL ORDER BY id LIMIT 1",
+        kind.table()
+    ))
+    .bind(ids)
+    .fetch_optional(&mut **tx)
+    .await
+    .context("Failed to check references")?;
+
+    match trashed {
+        Some(id) => Err(AppError::InvalidReference {
+            field: field.to_string(),
+            message: format!("{} {} is in the trash", kind.label(), id),
+        }),
+        None => Ok(()),
+    }
+}
+
+fn not_in_trash(kind: TrashKind) -> AppError {
+    AppError::not_found(format!("{} not found in the trash", kind.label()))
+}
+
+/// Reject a live meal made of recipes, products or restaurants in the trash.
+pub async fn check_meal_sources(tx: &mut Transaction<'_, Postgres>, meal_id: i32) -> Result<()> {
+    for (source, link, column) in [
+        (TrashKind::Recipe, "meal_recipe", "recipe"),
+        (TrashKind::Product, "meal_product", "product"),
+        (TrashKind::Restaurant, "meal_restaurant", "restaurant"),
+    ] {
+        let ids: Vec<i32> = sqlx::query_scalar(&format!(
+            "SELECT l.{column} FROM {link} l JOIN meal m ON m.id = l.meal \
+             WHERE l.meal = $1 AND m.deleted_at IS NULL"
+        ))
+        .bind(meal_id)
+        .fetch_all(&mut **tx)
+        .await
+        .context("Failed to check references")?;
+        check_live(tx, source, &ids, "food_sources").await?;
+    }
+
+    Ok(())
+}
+
+/// Take a record out of the trash. A meal only comes back once the recipes,
+/// products and restaurants it was made of are restored.
+pub async fn restore(
+    pool: &PgPool,
+    kind: TrashKind,
+    id: i32,
+    actor: Option<&AuthUser>,
+) -> Result<()> {
+    let mut tx = pool.begin().await.context("Failed to restore")?;
+    let before = history::snapshot(&mut tx, kind.into(), id).await?;
+
+    let result = sqlx::query(&format!(
+        "UPDATE {} SET deleted_at = NULL WHERE id = $1 AND deleted_at IS NOT NULL",
+        kind.table()
+    ))
+    .bind(id)
+    .execute(&mut *tx)
+    .await
+    .context("Failed to restore")?;
+    if result.rows_affected() == 0 {
+        return Err(not_in_trash(kind));
+    }
+
+    if kind == TrashKind::Meal {
+        check_meal_sources(&mut tx, id).await?;
+    }
+
+    history::record(
+        &mut tx,
+        kind.into(),
+        id,
+        HistoryAction::Restore,
+        before,
+        actor,
+    )
+    .await?;
+
+    tx.commit().await.context("Failed to restore")?;
+
+    Ok(())
+}
+
+/// Delete a trashed record for good. Food sources still used by trashed
+/// meals or by meal plans cannot be purged.
+pub async fn purge(
+    pool: &PgPool,
+    kind: TrashKind,
+    id: i32,
+    actor: Option<&AuthUser>,
+) -> Result<()> {
+    let table = kind.table();
+    let mut tx = pool.begin().await.context("Failed to purge")?;
+
+    sqlx::query(&format!(
+        "SELECT id FROM {table} WHERE id = $1 AND deleted_at IS NOT NULL FOR UPDATE"
+    ))
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await
+    .context("Failed to purge")?
+    .ok_or_else(|| not_in_trash(kind))?;
+
+    delete_for_good(&mut tx, kind, &[id], actor).await?;
+
+    tx.commit().await.context("Failed to purge")?;
+
+    Ok(())
+}
+
+/// Delete trashed records and log it. People are unlinked from everything first.
+async fn delete_for_good(
+    tx: &mut Transaction<'_, Postgres>,
+    kind: TrashKind,
+    ids: &[i32],
+    actor: Option<&AuthUser>,
+) -> Result<u64> {
+    let table = kind.table();
+
+    let mut images = Vec::new();
+    for id in ids {
+        images.push(history::snapshot(tx, kind.into(), *id).await?);
+    }
+
+    if kind == TrashKind::Person {
+        for link in PEOPLE_LINKS {
+            sqlx::query(&format!("DELETE FROM {link} WHERE people = ANY($1)"))
+                .bind(ids)
+                .execute(&mut **tx)
+                .await
+                .context("Failed to purge")?;
+        }
+    }
+
+    let count = sqlx::query(&format!("DELETE FROM {table} WHERE id = ANY($1)"))
+        .bind(ids)
+        .execute(&mut **tx)
+        .await
+        .context(&format!("Cannot purge {}", kind.label().to_lowercase()))?
+        .rows_affected();
+
+    for (id, before) in ids.iter().zip(images) {
+        history::record(tx, kind.into(), *id, HistoryAction::Purge, before, actor).await?;
+    }
+
+    Ok(count)
+}
+
+/// Purge everything trashed before `cutoff`, or the whole trash without one.
+/// Food sources that are still used are kept and counted instead.
+pub async fn purge_all(
+    pool: &PgPool,
+    cutoff: Option<DateTime<Utc>>,
+    actor: Option<&AuthUser>,
+) -> Result<PurgeReport> {
+    let mut tx = pool.begin().await.context("Failed to empty trash")?;
+    let mut report = PurgeReport::default();
+
+    for kind in TrashKind::ALL {
+        let table = kind.table();
+        let unused = match kind {
+            TrashKind::Recipe | TrashKind::Product | TrashKind::Restaurant => format!(
+                " AND NOT EXISTS (SELECT 1 FROM meal_{table} WHERE {table} = {table}.id)
+                  AND NOT EXISTS (SELECT 1 FROM meal_plan_source WHERE {table} = {table}.id)"
+            ),
+            _ => String::new(),
+        };
+
+        let ids: Vec<i32> = sqlx::query_scalar(&format!(
+            "SELECT id FROM {table} WHERE {TRASHED_BEFORE}{unused} ORDER BY id FOR UPDATE"
+        ))
+        .bind(cutoff)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to empty trash")?;
+
+        let count = delete_for_good(&mut tx, kind, &ids, actor).await?;
+        if !unused.is_empty() {
+            report.kept += sqlx::query_scalar::<_, i64>(&format!(
+                "SELECT COUNT(*) FROM {table} WHERE {TRASHED_BEFORE}"
+            ))
+            .bind(cutoff)
+            .fetch_one(&mut *tx)
+            .await
+            .context("Failed to empty trash")? as u64;
+        }
+        if count > 0 {
+            report.purged.push(PurgedKind { kind, count });
+        }
+    }
+
+    tx.commit().await.context("Failed to empty trash")?;
+
+    Ok(report)
+}
+
+/// Purge records that have been in the trash longer than `retention_days`,
+/// once an hour.
+pub async fn run_purger(pool: PgPool, retention_days: u32) {
+    let mut interval = actix_web::rt::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let cutoff = Utc::now() - chrono::Duration::days(retention_days.into());
+        match purge_all(&pool, Some(cutoff), None).await {
+            Ok(report) => {
+                for purged in report.purged {
+                    log::info!("Purged {} {:?} from the trash", purged.count, purged.kind);
+                }
+            }
+            Err(e) => log::error!("Failed to purge trash: {}", e),
+        }
+    }
+}
@@ -0,0 +1,28 @@
+//! Small helpers shared across modules.
+
+use crate::config;
+use chrono::NaiveDate;
+
+/// The current day in the configured time zone. Everything that defaults to or
+/// compares against "today" uses this, so the day turns over at the same time
+/// everywhere.
+pub fn today() -> NaiveDate {
+    chrono::Utc::now()
+        .with_timezone(&config::timezone())
+        .date_naive()
+}
+
+/// `text` trimmed, or `None` if nothing is left.
+pub(crate) fn non_empty(text: &str) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// `text` with its first letter upper-cased.
+pub(crate) fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -23,6 +23,10 @@ class ApiClient {
         try {
             const response = await fetch(url, config);
 
+            if (response.status === 401) {
+                this.redirectToLogin();
+            }
+
             if (!response.ok) {
                 throw new Error(`HTTP error! status: ${response.status}`);
             }
@@ -34,9 +38,77 @@ class ApiClient {
         }
     }
 
+    /**
+     * Send the browser to the login page, returning here afterwards
+     */
+    redirectToLogin() {
+        const next = encodeURIComponent(window.location.pathname + window.location.search);
+        window.location.href = `/static/login.html?next=${next}`;
+    }
+
+    // Auth API (login and logout live outside /api/v1)
+    async login(username, password) {
+        const response = await fetch('/auth/login', {
+            method: 'POST',
+            headers: { 'Content-Type': 'application/json' },
+            body: JSON.stringify({ username, password })
+        });
+
+        if (!response.ok) {
+            throw new Error(`HTTP error! status: ${response.status}`);
+        }
+
+        return await response.json();
+    }
+
+    async logout() {
+        await fetch('/auth/logout', { method: 'POST' });
+        this.redirectToLogin();
+    }
+
+    async getCurrentUser() {
+        return this.request('/auth/me');
+    }
+
+    async getApiTokens() {
+        return this.request('/auth/tokens');
+    }
+
+    /**
+     * Create a token; scope 'feed' makes one that can only read the calendar feed
+     */
+    async createApiToken(name, scope = 'api') {
+        return this.request('/auth/tokens', {
+            method: 'POST',
+            body: JSON.stringify({ name, scope })
+        });
+    }
+
+    async deleteApiToken(id) {
+        return this.request(`/auth/tokens/${id}`, {
+            method: 'DELETE'
+        });
+    }
+
+    /**
+     * Append list parameters (limit, cursor, start_date, person_id, sort, ...)
+     * to an endpoint, skipping empty values
+     */
+    withListParams(endpoint, params = {}) {
+        const query = new URLSearchParams();
+
+        for (const [key, value] of Object.entries(params)) {
+            if (value !== null && value !== undefined && value !== '') {
+                query.append(key, value);
+            }
+        }
+
+        return query.toString() ? `${endpoint}?${query.toString()}` : endpoint;
+    }
+
     // Meals API
-    async getMeals() {
-        return this.request('/meals');
+    async getMeals(params = {}) {
+        return this.request(this.withListParams('/meals', params));
     }
 
     async getMeal(id) {
@@ -74,9 +146,44 @@ class ApiClient {
         });
     }
 
+    // Meal Plans API
+    async getMealPlans(params = {}) {
+        return this.request(this.withListParams('/meal-plans', params));
+    }
+
+    async getSkippedMealPlans(params = {}) {
+        return this.request(this.withListParams('/meal-plans/skipped', params));
+    }
+
+    async createMealPlan(plan) {
+        return this.request('/meal-plans', {
+            method: 'POST',
+            body: JSON.stringify(plan)
+        });
+    }
+
+    async updateMealPlan(id, plan) {
+        return this.request(`/meal-plans/${id}`, {
+            method: 'PUT',
+            body: JSON.stringify(plan)
+        });
+    }
+
+    async deleteMealPlan(id) {
+        return this.request(`/meal-plans/${id}`, {
+            method: 'DELETE'
+        });
+    }
+
+    async markMealPlanEaten(id, date = null) {
+        return this.request(this.withListParams(`/meal-plans/${id}/eaten`, { date }), {
+            method: 'POST'
+        });
+    }
+
     // Events API
-    async getEvents() {
-        return this.request('/events');
+    async getEvents(params = {}) {
+        return this.request(this.withListParams('/events', params));
     }
 
     async getEvent(id) {
@@ -108,8 +215,8 @@ class ApiClient {
     }
 
     // Drinks API
-    async getDrinks() {
-        return this.request('/drinks');
+    async getDrinks(params = {}) {
+        return this.request(this.withListParams('/drinks', params));
     }
 
     async getDrink(id) {
@@ -140,15 +247,58 @@ class ApiClient {
         });
     }
 
+    async deleteDrinksBatch(drinkIds) {
+        return this.request('/drinks/batch/delete', {
+            method: 'POST',
+            body: JSON.stringify({ drink_ids: drinkIds })
+        });
+    }
+
     // People API
     async getPeople() {
         return this.request('/people');
     }
 
+    async getHousehold() {
+        return this.request('/people/household');
+    }
+
+    async updateHousehold(peopleIds) {
+        return this.request('/people/household', {
+            method: 'PUT',
+            body: JSON.stringify({ people_ids: peopleIds })
+        });
+    }
+
+    async getPeopleAliases() {
+        return this.request('/people/aliases');
+    }
+
+    async savePeopleAlias(alias, peopleIds) {
+        return this.request('/people/aliases', {
+            method: 'PUT',
+            body: JSON.stringify({ alias, people_ids: peopleIds })
+        });
+    }
+
+    async deletePeopleAlias(alias) {
+        return this.request(`/people/aliases/${encodeURIComponent(alias)}`, {
+            method: 'DELETE'
+        });
+    }
+
     async getPerson(id) {
         return this.request(`/people/${id}`);
     }
 
+    async getPersonTimeline(id, params = {}) {
+        return this.request(this.withListParams(`/people/${id}/timeline`, params));
+    }
+
+    async getPersonSummary(id) {
+        return this.request(`/people/${id}/summary`);
+    }
+
     async createPerson(person) {
         return this.request('/people', {
             method: 'POST',
@@ -198,9 +348,30 @@ class ApiClient {
         });
     }
 
+    async getRestaurantVisits(id, params = {}) {
+        return this.request(this.withListParams(`/restaurants/${id}/visits`, params));
+    }
+
     // Recipes API
-    async getRecipes() {
-        return this.request('/recipes');
+    async getRecipes(params = {}) {
+        return this.request(this.withListParams('/recipes', params));
+    }
+
+    async getRecipesUsing(ingredient) {
+        return this.getRecipes({ ingredient });
+    }
+
+    async getRecipeIngredients(id, servings = null) {
+        return this.request(this.withListParams(`/recipes/${id}/ingredients`, { servings }));
+    }
+
+    async getShoppingList({ recipeIds = [], startDate = null, endDate = null, servings = null } = {}) {
+        return this.request(this.withListParams('/shopping-list', {
+            recipe_ids: recipeIds.join(','),
+            start_date: startDate,
+            end_date: endDate,
+            servings
+        }));
     }
 
     async getRecipe(id) {
@@ -297,8 +468,21 @@ class ApiClient {
         });
     }
 
-    async deleteLocation(name) {
+    async renameLocation(name, newName) {
         return this.request(`/locations/${encodeURIComponent(name)}`, {
+            method: 'PUT',
+            body: JSON.stringify({ name: newName })
+        });
+    }
+
+    async deleteLocation(name, mergeInto = null) {
+        let endpoint = `/locations/${encodeURIComponent(name)}`;
+
+        if (mergeInto) {
+            endpoint += '?' + new URLSearchParams({ merge_into: mergeInto }).toString();
+        }
+
+        return this.request(endpoint, {
             method: 'DELETE'
         });
     }
@@ -315,8 +499,21 @@ class ApiClient {
         });
     }
 
-    async deleteFoodType(name) {
+    async renameFoodType(name, newName) {
         return this.request(`/food-types/${encodeURIComponent(name)}`, {
+            method: 'PUT',
+            body: JSON.stringify({ name: newName })
+        });
+    }
+
+    async deleteFoodType(name, mergeInto = null) {
+        let endpoint = `/food-types/${encodeURIComponent(name)}`;
+
+        if (mergeInto) {
+            endpoint += '?' + new URLSearchParams({ merge_into: mergeInto }).toString();
+        }
+
+        return this.request(endpoint, {
             method: 'DELETE'
         });
     }
@@ -333,8 +530,21 @@ class ApiClient {
         });
     }
 
-    async deleteDrinkOption(name) {
+    async renameDrinkOption(name, newName) {
         return this.request(`/drink-options/${encodeURIComponent(name)}`, {
+            method: 'PUT',
+            body: JSON.stringify({ name: newName })
+        });
+    }
+
+    async deleteDrinkOption(name, mergeInto = null) {
+        let endpoint = `/drink-options/${encodeURIComponent(name)}`;
+
+        if (mergeInto) {
+            endpoint += '?' + new URLSearchParams({ merge_into: mergeInto }).toString();
+        }
+
+        return this.request(endpoint, {
             method: 'DELETE'
         });
     }
@@ -351,8 +561,21 @@ class ApiClient {
         });
     }
 
-    async deleteActivityType(name) {
+    async renameActivityType(name, newName) {
         return this.request(`/activity-types/${encodeURIComponent(name)}`, {
+            method: 'PUT',
+            body: JSON.stringify({ name: newName })
+        });
+    }
+
+    async deleteActivityType(name, mergeInto = null) {
+        let endpoint = `/activity-types/${encodeURIComponent(name)}`;
+
+        if (mergeInto) {
+            endpoint += '?' + new URLSearchParams({ merge_into: mergeInto }).toString();
+        }
+
+        return this.request(endpoint, {
             method: 'DELETE'
         });
     }
@@ -376,6 +599,197 @@ class ApiClient {
         return this.request(endpoint);
     }
 
+    // Search API
+    async search(query, startDate = null, endDate = null) {
+        const params = new URLSearchParams({ q: query });
+
+        if (startDate) {
+            params.append('start_date', startDate);
+        }
+        if (endDate) {
+            params.append('end_date', endDate);
+        }
+
+        return this.request('/search?' + params.toString());
+    }
+
+    // Stats API
+    async getStatsSeries(params = {}) {
+        return this.request(this.withListParams('/stats/series', params));
+    }
+
+    async getTopRestaurants(params = {}) {
+        return this.request(this.withListParams('/stats/restaurants', params));
+    }
+
+    async getSpendStats(params = {}) {
+        return this.request(this.withListParams('/stats/spend', params));
+    }
+
+    async getRecipeStats(params = {}) {
+        return this.request(this.withListParams('/stats/recipes', params));
+    }
+
+    // Suggestions API
+    async getMealSuggestions(time, { peopleIds = [], date = null, limit = null } = {}) {
+        return this.request(this.withListParams('/suggestions/meal', {
+            time,
+            people_ids: peopleIds.join(','),
+            date,
+            limit
+        }));
+    }
+
+    // Import API
+    async importCsv(csvText, { commit = false, skipUnmatched = false } = {}) {
+        const params = new URLSearchParams({ commit, skip_unmatched: skipUnmatched });
+        return this.request(`/import?${params}`, {
+            method: 'POST',
+            headers: { 'Content-Type': 'text/csv' },
+            body: csvText
+        });
+    }
+
+    async importIcs(icsText, { commit = false, skipUnmatched = false } = {}) {
+        const params = new URLSearchParams({ commit, skip_unmatched: skipUnmatched });
+        return this.request(`/import/ics?${params}`, {
+            method: 'POST',
+            headers: { 'Content-Type': 'text/calendar' },
+            body: icsText
+        });
+    }
+
+    /**
+     * Log shorthand such as "pho w/ alice @Ballard (birthday) bento" as a meal
+     * at `time`, or as events or drinks with time 'event' or 'drink'
+     */
+    async quickLog({ date = null, time, text }, { commit = false } = {}) {
+        const params = new URLSearchParams({ commit });
+        return this.request(`/quick-log?${params}`, {
+            method: 'POST',
+            body: JSON.stringify({ date, time, text })
+        });
+    }
+
+    /**
+     * Subscription URL of the calendar feed, authenticated by a feed token
+     */
+    calendarUrl(token, params = {}) {
+        const query = new URLSearchParams({ ...params, token });
+        return `${window.location.origin}${this.baseUrl}/calendar.ics?${query}`;
+    }
+
+    // Export API
+    async exportArchive() {
+        return this.request('/export');
+    }
+
+    async restoreArchive(archive) {
+        return this.request('/restore', {
+            method: 'POST',
+            body: JSON.stringify(archive)
+        });
+    }
+
+    // Templates API
+    async getTemplates(params = {}) {
+        return this.request(this.withListParams('/templates', params));
+    }
+
+    async createTemplate(template) {
+        return this.request('/templates', {
+            method: 'POST',
+            body: JSON.stringify(template)
+        });
+    }
+
+    async updateTemplate(id, template) {
+        return this.request(`/templates/${id}`, {
+            method: 'PUT',
+            body: JSON.stringify(template)
+        });
+    }
+
+    async deleteTemplate(id) {
+        return this.request(`/templates/${id}`, {
+            method: 'DELETE'
+        });
+    }
+
+    async applyTemplate(id, date = null) {
+        const query = date ? `?date=${encodeURIComponent(date)}` : '';
+        return this.request(`/templates/${id}/apply${query}`, {
+            method: 'POST'
+        });
+    }
+
+    async getPendingEntries(params = {}) {
+        return this.request(this.withListParams('/pending', params));
+    }
+
+    async confirmPendingEntry(id) {
+        return this.request(`/pending/${id}/confirm`, {
+            method: 'POST'
+        });
+    }
+
+    async dismissPendingEntry(id) {
+        return this.request(`/pending/${id}`, {
+            method: 'DELETE'
+        });
+    }
+
+    // Trash API
+    async getTrash(kind = null) {
+        const query = kind ? `?kind=${encodeURIComponent(kind)}` : '';
+        return this.request(`/trash${query}`);
+    }
+
+    async restoreTrashItem(kind, id) {
+        return this.request(`/trash/${kind}/${id}/restore`, {
+            method: 'POST'
+        });
+    }
+
+    async purgeTrashItem(kind, id) {
+        return this.request(`/trash/${kind}/${id}`, {
+            method: 'DELETE'
+        });
+    }
+
+    async emptyTrash() {
+        return this.request('/trash', {
+            method: 'DELETE'
+        });
+    }
+
+    // Duplicates API, for 'people', 'restaurants', 'recipes' or 'products'
+    async getDuplicates(kind) {
+        return this.request(`/${kind}/duplicates`);
+    }
+
+    async mergeDuplicates(kind, survivorId, duplicateIds) {
+        return this.request(`/${kind}/merge`, {
+            method: 'POST',
+            body: JSON.stringify({ survivor_id: survivorId, duplicate_ids: duplicateIds })
+        });
+    }
+
+    // History API
+    async getHistory(params = {}) {
+        return this.request(this.withListParams('/history', params));
+    }
+
+    async getHistoryEntry(id) {
+        return this.request(`/history/${id}`);
+    }
+
+    async revertHistoryEntry(id) {
+        return this.request(`/history/${id}/revert`, {
+            method: 'POST'
+        });
+    }
+
     // Utility methods for aggregated data
     async getAllEvents() {
         try {
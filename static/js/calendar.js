@@ -201,8 +201,9 @@ class CalendarView {
 
         // Gather items with a per-item match flag.
         const meals = [];
-        for (const mealTime of ['breakfast', 'lunch', 'dinner']) {
-            for (const meal of (day[mealTime] || [])) {
+        const mealTimes = ['breakfast', 'lunch', 'dinner'].map((mealTime) => [mealTime, day[mealTime]]);
+        for (const [mealTime, items] of [...mealTimes, ...Object.entries(day.other_meals || {})]) {
+            for (const meal of (items || [])) {
                 const text = (window.eventSpreadsheet && window.eventSpreadsheet.getMealDisplayText)
                     ? window.eventSpreadsheet.getMealDisplayText(meal)
                     : (meal.name || '');
@@ -118,6 +118,8 @@ class DetailForms {
                     date: useDate,
                     activity: eventInfo && eventInfo.activity ? this.getActivityFromId(eventInfo.activity) : null,
                     measure: '',
+                    start_time: '',
+                    end_time: '',
                     location: '',
                     notes: '',
                     people: eventInfo && eventInfo.people ? this.getPeopleFromIds(eventInfo.people) : []
@@ -170,7 +172,8 @@ class DetailForms {
         const mealTime = isEditMode ? (templateMeal.time || data.editingTime || 'lunch') : (templateMeal.time || 'lunch');
         const mealDate = isEditMode ? (templateMeal.date || window.dateUtils.getTodayLocal()) : (templateMeal.date || window.dateUtils.getTodayLocal());
         const mealNotes = isEditMode ? (templateMeal.notes || '') : (templateMeal.notes || '');
-        const foodSourceType = isEditMode && templateMeal.food_source ? templateMeal.food_source.type : (data.food_source?.type || 'recipe');
+        const templateSource = this.getFirstFoodSource(templateMeal);
+        const foodSourceType = isEditMode && templateSource ? templateSource.type : (data.food_source?.type || 'recipe');
         const mealType = this.getFoodSourceMealType();
         const people = isEditMode ? (templateMeal.people || []) : (data.people || []);
 
@@ -297,13 +300,24 @@ class DetailForms {
         // Always use the first meal as template (data structure is consistent)
         const templateMeal = data.meals && data.meals.length > 0 ? data.meals[0] : data;
         
-        if (templateMeal.food_source && templateMeal.food_source.details && templateMeal.food_source.details.meal_type) {
-            return templateMeal.food_source.details.meal_type;
+        const foodSource = this.getFirstFoodSource(templateMeal);
+        if (foodSource && foodSource.details && foodSource.details.meal_type) {
+            return foodSource.details.meal_type;
         }
         
         return 'cooked';
     }
 
+    /**
+     * Meal details list every food source; the form edits the first one
+     */
+    getFirstFoodSource(meal) {
+        if (Array.isArray(meal.food_sources)) {
+            return meal.food_sources[0] || null;
+        }
+        return meal.food_source || null;
+    }
+
     /**
      * Update meal type selection based on food source type
      */
@@ -331,7 +345,7 @@ class DetailForms {
 
         // Always use the first meal as template (data structure is consistent)
         const templateMeal = isEditMode && data.meals && data.meals.length > 0 ? data.meals[0] : data;
-        const foodSourceData = templateMeal.food_source;
+        const foodSourceData = this.getFirstFoodSource(templateMeal);
 
         let selectedId = null;
         if (isEditMode && foodSourceData?.details) {
@@ -404,6 +418,17 @@ class DetailForms {
                         <input type="text" id="eventMeasure" name="measure" value="${data.measure || ''}" placeholder="e.g., 30 minutes, 5 km">
                     </div>
                 </div>
+
+                <div class="form-row">
+                    <div class="form-group">
+                        <label for="eventStartTime">Start time</label>
+                        <input type="time" id="eventStartTime" name="start_time" value="${(data.start_time || '').slice(0, 5)}">
+                    </div>
+                    <div class="form-group">
+                        <label for="eventEndTime">End time</label>
+                        <input type="time" id="eventEndTime" name="end_time" value="${(data.end_time || '').slice(0, 5)}">
+                    </div>
+                </div>
                 
                 <div class="form-group">
                     <label for="eventActivity">Activity</label>
@@ -649,19 +674,14 @@ class DetailForms {
     }
 
     /**
-     * Get people IDs with default fallback to "xx" and "ww" if empty
+     * Get people IDs, defaulting to the household members if empty
      */
     getPeopleIds(peopleArray) {
         if (!peopleArray || peopleArray.length === 0) {
-            // Find IDs for "xx" and "ww" people
-            const xxPerson = this.enumData.people.find(p => p.name.toLowerCase() === 'xx');
-            const wwPerson = this.enumData.people.find(p => p.name.toLowerCase() === 'ww');
-
-            const defaultIds = [];
-            if (xxPerson) defaultIds.push(xxPerson.id);
-            if (wwPerson) defaultIds.push(wwPerson.id);
-
-            return defaultIds.length > 0 ? defaultIds : [];
+            return this.enumData.people
+                .filter(p => p.household_order !== null && p.household_order !== undefined)
+                .sort((a, b) => a.household_order - b.household_order)
+                .map(p => p.id);
         }
         return peopleArray;
     }
@@ -726,6 +746,9 @@ class DetailForms {
             date: data.date,
             activity_id: parseInt(data.activity),
             measure: data.measure || null,
+            // The structured quantity and unit are parsed from the measure by the server
+            start_time: data.start_time ? `${data.start_time.slice(0, 5)}:00` : null,
+            end_time: data.end_time ? `${data.end_time.slice(0, 5)}:00` : null,
             location: data.location || null,
             notes: data.notes || null,
             people_ids: this.getPeopleIds(data.people)
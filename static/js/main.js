@@ -19,6 +19,9 @@ document.addEventListener('DOMContentLoaded', function () {
     // Populate the activity-type filter dropdown from the API
     populateActivityTypeFilter();
 
+    // Load household members, which decide how people lists are shown
+    loadHousehold();
+
     // Handle browser back/forward buttons
     window.addEventListener('popstate', function (event) {
         // Sync form inputs with URL parameters
@@ -74,6 +77,22 @@ async function populateActivityTypeFilter() {
     }
 }
 
+/**
+ * Load the household members used by utils.formatPeople and re-render the
+ * spreadsheet with them.
+ */
+async function loadHousehold() {
+    try {
+        const members = await apiClient.getHousehold();
+        window.householdNames = (members || []).map(person => person.name);
+        if (window.eventSpreadsheet && window.eventSpreadsheet.hotInstance) {
+            window.eventSpreadsheet.hotInstance.render();
+        }
+    } catch (err) {
+        console.warn('Failed to load household members:', err);
+    }
+}
+
 function setupControlButtons() {
     // Add event buttons
     document.getElementById('addMealBtn').onclick = () => {
@@ -417,28 +436,24 @@ window.utils = {
             return '';
         }
 
-        // Check if it's exactly just xx and ww - if so, omit them
+        // Household members (loaded into window.householdNames) come first, in
+        // household order; a list that is exactly the household is omitted
+        const household = window.householdNames || [];
+        const lowerHousehold = household.map(name => name.toLowerCase());
         const lowerNames = nameArray.map(name => name.toLowerCase());
-        if (lowerNames.length === 2 && lowerNames.includes('xx') && lowerNames.includes('ww')) {
+        if (lowerHousehold.length > 0 &&
+            lowerNames.length === lowerHousehold.length &&
+            lowerHousehold.every(name => lowerNames.includes(name))) {
             return '';
         }
 
-        // Sort people: xx and ww first, then others alphabetically
-        const sortedNames = nameArray.slice().sort((a, b) => {
-            const aName = a.toLowerCase();
-            const bName = b.toLowerCase();
-
-            // xx comes first
-            if (aName === 'xx') return -1;
-            if (bName === 'xx') return 1;
-
-            // ww comes second
-            if (aName === 'ww') return -1;
-            if (bName === 'ww') return 1;
-
-            // Others alphabetically
-            return aName.localeCompare(bName);
-        });
+        const rank = name => {
+            const index = lowerHousehold.indexOf(name.toLowerCase());
+            return index === -1 ? lowerHousehold.length : index;
+        };
+        const sortedNames = nameArray.slice().sort((a, b) =>
+            rank(a) - rank(b) || a.toLowerCase().localeCompare(b.toLowerCase())
+        );
 
         return sortedNames.join(', ');
     },
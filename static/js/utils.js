@@ -56,28 +56,24 @@ window.utils = {
             return '';
         }
 
-        // Check if it's exactly just xx and ww - if so, omit them
+        // Household members (loaded into window.householdNames) come first, in
+        // household order; a list that is exactly the household is omitted
+        const household = window.householdNames || [];
+        const lowerHousehold = household.map(name => name.toLowerCase());
         const lowerNames = nameArray.map(name => name.toLowerCase());
-        if (lowerNames.length === 2 && lowerNames.includes('xx') && lowerNames.includes('ww')) {
+        if (lowerHousehold.length > 0 &&
+            lowerNames.length === lowerHousehold.length &&
+            lowerHousehold.every(name => lowerNames.includes(name))) {
             return '';
         }
 
-        // Sort people: xx and ww first, then others alphabetically
-        const sortedNames = nameArray.slice().sort((a, b) => {
-            const aName = a.toLowerCase();
-            const bName = b.toLowerCase();
-
-            // xx comes first
-            if (aName === 'xx') return -1;
-            if (bName === 'xx') return 1;
-
-            // ww comes second
-            if (aName === 'ww') return -1;
-            if (bName === 'ww') return 1;
-
-            // Others alphabetically
-            return aName.localeCompare(bName);
-        });
+        const rank = name => {
+            const index = lowerHousehold.indexOf(name.toLowerCase());
+            return index === -1 ? lowerHousehold.length : index;
+        };
+        const sortedNames = nameArray.slice().sort((a, b) =>
+            rank(a) - rank(b) || a.toLowerCase().localeCompare(b.toLowerCase())
+        );
 
         return sortedNames.join(', ');
     },
@@ -1,8 +1,11 @@
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 #[cfg(test)]
 mod tests {
     use actix_web::{test, web, App};
     use serial_test::serial;
     use sqlx::PgPool;
+    use xnote::config::database;
     use xnote::handlers::activities;
 
     struct TestContext {
@@ -24,49 +27,22 @@ mod tests {
     }
 
     async fn create_schema(pool: &PgPool) {
-        let schema_sql = include_str!("../init.sql");
-        sqlx::raw_sql(schema_sql)
-            .execute(pool)
+        database::run_migrations(pool)
             .await
-            .expect("Failed to create schema");
+            .expect("Failed to run migrations");
     }
 
     async fn cleanup_database(pool: &PgPool) {
-        let tables = vec![
-            "drink_people",
-            "drink",
-            "drink_option",
-            "event_people",
-            "event",
-            "activity",
-            "activity_type",
-            "meal_people",
-            "meal_restaurant",
-            "meal_product",
-            "meal_recipe",
-            "meal",
-            "meal_time",
-            "meal_type",
-            "people",
-            "restaurant",
-            "product",
-            "recipe",
-            "location",
-            "food_type",
-        ];
-
-        for table in tables {
-            let query = format!("DROP TABLE IF EXISTS {} CASCADE", table);
-            sqlx::query(&query)
-                .execute(pool)
-                .await
-                .expect(&format!("Failed to drop table {}", table));
-        }
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
     }
 
     async fn setup_test_context() -> TestContext {
         let pool = create_test_database_pool().await;
-        // Drop leftover state from previous runs; init.sql inserts are not idempotent
+        // Drop leftover state from previous runs so migrations apply from scratch
         cleanup_database(&pool).await;
         create_schema(&pool).await;
 
@@ -319,4 +295,37 @@ mod tests {
 
         teardown_test_context(ctx).await;
     }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_create_activity_unknown_type() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(activities::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/activities")
+            .set_json(serde_json::json!({
+                "name": "Skydiving",
+                "type": "extreme"
+            }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+
+        let body = test::read_body(resp).await;
+        let error_response: serde_json::Value =
+            serde_json::from_slice(&body).expect("Failed to deserialize error response");
+        assert_eq!(error_response["code"], "invalid_reference");
+        assert_eq!(error_response["field"], "type");
+        assert_eq!(error_response["error"], "Unknown type 'extreme'");
+
+        teardown_test_context(ctx).await;
+    }
 }
@@ -0,0 +1,330 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{middleware, test, web, App};
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use xnote::auth;
+    use xnote::config::database;
+    use xnote::handlers::{self, calendar, people};
+
+    struct TestContext {
+        pool: PgPool,
+    }
+
+    async fn create_test_database_pool() -> PgPool {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let last_slash = database_url
+            .rfind('/')
+            .expect("DATABASE_URL must be a valid connection string");
+        let database_url = format!("{}/xnote_test", &database_url[..last_slash]);
+        PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect to test database")
+    }
+
+    async fn create_schema(pool: &PgPool) {
+        database::run_migrations(pool)
+            .await
+            .expect("Failed to run migrations");
+    }
+
+    async fn cleanup_database(pool: &PgPool) {
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
+    }
+
+    async fn setup_test_context() -> TestContext {
+        let pool = create_test_database_pool().await;
+        // Drop leftover state from previous runs so migrations apply from scratch
+        cleanup_database(&pool).await;
+        create_schema(&pool).await;
+
+        auth::create_user(&pool, "alice", "correct horse")
+            .await
+            .expect("Failed to create user");
+
+        TestContext { pool }
+    }
+
+    async fn teardown_test_context(ctx: TestContext) {
+        cleanup_database(&ctx.pool).await;
+        ctx.pool.close().await;
+    }
+
+    /// The same layout as `serve` in main.rs: public login routes and a protected
+    /// `/api/v1` scope.
+    macro_rules! init_app {
+        ($ctx:expr) => {
+            test::init_service(
+                App::new()
+                    .app_data(web::Data::new($ctx.pool.clone()))
+                    .configure(handlers::auth::configure_public)
+                    .service(
+                        web::scope("/api/v1")
+                            .wrap(middleware::from_fn(auth::require_auth))
+                            .configure(handlers::auth::configure)
+                            .configure(people::configure)
+                            .configure(calendar::configure),
+                    ),
+            )
+            .await
+        };
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_requests_without_credentials_are_rejected() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let req = test::TestRequest::get().uri("/api/v1/people").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+
+        let body = test::read_body(resp).await;
+        let response: serde_json::Value =
+            serde_json::from_slice(&body).expect("Failed to deserialize response");
+        assert_eq!(response["code"], "unauthorized");
+
+        let req = test::TestRequest::get()
+            .uri("/api/v1/people")
+            .insert_header(("Authorization", "Bearer not-a-token"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_login_with_wrong_password() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let req = test::TestRequest::post()
+            .uri("/auth/login")
+            .set_json(serde_json::json!({ "username": "alice", "password": "wrong" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+        assert!(resp.response().cookies().next().is_none());
+        let wrong_password = test::read_body(resp).await;
+
+        // Unknown usernames are rejected the same way
+        let req = test::TestRequest::post()
+            .uri("/auth/login")
+            .set_json(serde_json::json!({ "username": "nobody", "password": "wrong" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+        assert_eq!(test::read_body(resp).await, wrong_password);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_session_cookie_login_and_logout() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let req = test::TestRequest::post()
+            .uri("/auth/login")
+            .set_json(serde_json::json!({ "username": "alice", "password": "correct horse" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let cookie = resp
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == auth::SESSION_COOKIE)
+            .expect("Login should set a session cookie")
+            .into_owned();
+        assert!(cookie.http_only().unwrap_or(false));
+        // Logged in over plain HTTP, so browsers must be allowed to send it back
+        assert!(!cookie.secure().unwrap_or(false));
+
+        let req = test::TestRequest::get()
+            .uri("/api/v1/auth/me")
+            .cookie(cookie.clone())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let body = test::read_body(resp).await;
+        let user: serde_json::Value =
+            serde_json::from_slice(&body).expect("Failed to deserialize user");
+        assert_eq!(user["username"], "alice");
+
+        let req = test::TestRequest::post()
+            .uri("/auth/logout")
+            .cookie(cookie.clone())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let req = test::TestRequest::get()
+            .uri("/api/v1/people")
+            .cookie(cookie)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_bearer_api_token() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let user = auth::authenticate(&ctx.pool, "alice", "correct horse")
+            .await
+            .expect("Failed to authenticate");
+        let session = auth::create_session(&ctx.pool, user.id)
+            .await
+            .expect("Failed to create session");
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/auth/tokens")
+            .cookie(actix_web::cookie::Cookie::new(
+                auth::SESSION_COOKIE,
+                session,
+            ))
+            .set_json(serde_json::json!({ "name": "backup script" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let body = test::read_body(resp).await;
+        let created: xnote::models::auth::CreateApiTokenResponse =
+            serde_json::from_slice(&body).expect("Failed to deserialize token");
+
+        let bearer = format!("Bearer {}", created.token);
+        let req = test::TestRequest::get()
+            .uri("/api/v1/auth/tokens")
+            .insert_header(("Authorization", bearer.as_str()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let body = test::read_body(resp).await;
+        let tokens: Vec<xnote::models::auth::ApiToken> =
+            serde_json::from_slice(&body).expect("Failed to deserialize tokens");
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].name, "backup script");
+        assert!(tokens[0].last_used_at.is_some());
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/api/v1/auth/tokens/{}", created.id))
+            .insert_header(("Authorization", bearer.as_str()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let req = test::TestRequest::get()
+            .uri("/api/v1/people")
+            .insert_header(("Authorization", bearer.as_str()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_create_user_validation() {
+        let ctx = setup_test_context().await;
+
+        let result = auth::create_user(&ctx.pool, "bob", "short").await;
+        assert!(matches!(result, Err(xnote::error::AppError::BadRequest(_))));
+
+        let result = auth::create_user(&ctx.pool, "alice", "another password").await;
+        assert!(matches!(
+            result,
+            Err(xnote::error::AppError::AlreadyExists(_))
+        ));
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_calendar_feed_accepts_token_in_url() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let user = auth::authenticate(&ctx.pool, "alice", "correct horse")
+            .await
+            .expect("Failed to authenticate");
+        let (_, feed_token) = auth::create_feed_token(&ctx.pool, user.id, "calendar")
+            .await
+            .expect("Failed to create token");
+        let (_, api_token) = auth::create_api_token(&ctx.pool, user.id, "script")
+            .await
+            .expect("Failed to create token");
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/v1/calendar.ics?token={}", feed_token))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let req = test::TestRequest::get()
+            .uri("/api/v1/calendar.ics?token=not-a-token")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+
+        // Full API tokens are never taken from the URL
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/v1/calendar.ics?token={}", api_token))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+
+        // Feed tokens open nothing but the feed
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/v1/people?token={}", feed_token))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+
+        let req = test::TestRequest::get()
+            .uri("/api/v1/people")
+            .insert_header(("Authorization", format!("Bearer {}", feed_token)))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_session_cookie_secure_behind_https() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let req = test::TestRequest::post()
+            .uri("/auth/login")
+            .insert_header(("X-Forwarded-Proto", "https"))
+            .set_json(serde_json::json!({ "username": "alice", "password": "correct horse" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let cookie = resp
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == auth::SESSION_COOKIE)
+            .expect("Login should set a session cookie")
+            .into_owned();
+        assert!(cookie.secure().unwrap_or(false));
+
+        teardown_test_context(ctx).await;
+    }
+}
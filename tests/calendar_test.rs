@@ -0,0 +1,255 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use xnote::config::database;
+    use xnote::handlers::calendar;
+
+    struct TestContext {
+        pool: PgPool,
+    }
+
+    async fn create_test_database_pool() -> PgPool {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let last_slash = database_url
+            .rfind('/')
+            .expect("DATABASE_URL must be a valid connection string");
+        let database_url = format!("{}/xnote_test", &database_url[..last_slash]);
+        PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect to test database")
+    }
+
+    async fn create_schema(pool: &PgPool) {
+        database::run_migrations(pool)
+            .await
+            .expect("Failed to run migrations");
+    }
+
+    async fn cleanup_database(pool: &PgPool) {
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
+    }
+
+    /// Zed and Amy form the household (in that order); Bob is a friend.
+    async fn setup_test_context() -> TestContext {
+        let pool = create_test_database_pool().await;
+        // Drop leftover state from previous runs so migrations apply from scratch
+        cleanup_database(&pool).await;
+        create_schema(&pool).await;
+
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO people (id, name, household_order) VALUES
+                (1, 'Amy', 2), (2, 'Bob', NULL), (3, 'Zed', 1);
+            INSERT INTO restaurant (id, name, location, type) VALUES (1, 'Taco Spot', 'Fremont', 'mexican');
+            INSERT INTO activity (id, name, type) VALUES (1, 'Hike', 'sport'), (2, 'Blog, post', 'side project');
+
+            INSERT INTO meal (id, date, "time", notes) VALUES (1, '2024-05-01', 'lunch', 'spicy');
+            INSERT INTO meal_restaurant (meal, restaurant, type) VALUES (1, 1, 'dine-in');
+            INSERT INTO meal_people (meal, people) VALUES (1, 1), (1, 3);
+
+            INSERT INTO event (id, date, activity, measure, location) VALUES
+                (1, '2024-05-01', 1, '3h', 'Fremont'),
+                (2, '2024-05-02', 2, NULL, NULL);
+            INSERT INTO event_people (event, people) VALUES (1, 1), (1, 2), (1, 3), (2, 3);
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .expect("Failed to insert test data");
+
+        TestContext { pool }
+    }
+
+    async fn teardown_test_context(ctx: TestContext) {
+        cleanup_database(&ctx.pool).await;
+        ctx.pool.close().await;
+    }
+
+    async fn fetch_calendar(ctx: &TestContext, query: &str) -> String {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(calendar::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/calendar.ics{}", query))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert!(resp
+            .headers()
+            .get("content-type")
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/calendar")));
+
+        let body = test::read_body(resp).await;
+        String::from_utf8(body.to_vec()).expect("Calendar should be UTF-8")
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_calendar_events() {
+        let ctx = setup_test_context().await;
+
+        let ics = fetch_calendar(&ctx, "?start_date=2024-05-01").await;
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2);
+        assert!(!ics.contains("UID:meal-"));
+
+        assert!(ics.contains("UID:event-1@xnote\r\nDTSTAMP:"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20240501\r\nDTEND;VALUE=DATE:20240502\r\n"));
+        // The summary is the daily summary text, with commas escaped
+        assert!(ics.contains("SUMMARY:Zed\\, Amy\\, Bob Hike @Fremont for 3h\r\n"));
+        assert!(ics.contains("LOCATION:Fremont\r\n"));
+        assert!(ics.contains("CATEGORIES:sport\r\n"));
+        assert!(ics.contains("ATTENDEE;CN=\"Bob\":urn:xnote:people:2\r\n"));
+        assert!(ics.contains("SUMMARY:Zed Blog\\, post\r\n"));
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_calendar_with_meals() {
+        let ctx = setup_test_context().await;
+
+        let ics = fetch_calendar(&ctx, "?start_date=2024-05-01&meals=true").await;
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 3);
+        assert!(ics.contains("UID:meal-1-restaurant-1@xnote"));
+        // Meals eaten by the whole household leave the people out
+        assert!(ics.contains("SUMMARY:Lunch: Taco Spot (spicy)\r\n"));
+        assert!(ics.contains("CATEGORIES:dine-in\r\n"));
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_calendar_filters() {
+        let ctx = setup_test_context().await;
+
+        let ics = fetch_calendar(
+            &ctx,
+            "?start_date=2024-05-01&activity_type=sport&meals=true",
+        )
+        .await;
+        assert!(ics.contains("UID:event-1@xnote"));
+        assert!(!ics.contains("UID:event-2@xnote"));
+        assert!(ics.contains("UID:meal-1-restaurant-1@xnote"));
+
+        // Bob only went on the hike
+        let ics = fetch_calendar(&ctx, "?start_date=2024-05-01&person_id=2&meals=true").await;
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+        assert!(ics.contains("UID:event-1@xnote"));
+
+        let ics = fetch_calendar(&ctx, "?start_date=2024-05-02").await;
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+        assert!(ics.contains("UID:event-2@xnote"));
+
+        // Without dates the feed only covers the last 90 days
+        let ics = fetch_calendar(&ctx, "?meals=true").await;
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 0);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_calendar_meal_with_several_dishes() {
+        let ctx = setup_test_context().await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO recipe (id, name, ingredients, procedure) VALUES (1, 'Horchata', 'rice', 'blend');
+            INSERT INTO meal_recipe (meal, recipe, type) VALUES (1, 1, 'cooked');
+            "#,
+        )
+        .execute(&ctx.pool)
+        .await
+        .expect("Failed to add a dish");
+
+        let ics = fetch_calendar(&ctx, "?start_date=2024-05-01&meals=true").await;
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 4);
+        // Each dish has its own UID, so calendar apps keep both
+        assert!(ics.contains("UID:meal-1-restaurant-1@xnote"));
+        assert!(ics.contains("UID:meal-1-recipe-1@xnote"));
+        assert!(ics.contains("SUMMARY:Lunch: Horchata (spicy)\r\n"));
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_calendar_dish_locations() {
+        let ctx = setup_test_context().await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO location (name) VALUES ('Ballard') ON CONFLICT DO NOTHING;
+            INSERT INTO restaurant (id, name, location, type) VALUES (2, 'Boba Bar', 'Ballard', 'mexican');
+            INSERT INTO meal_restaurant (meal, restaurant, type) VALUES (1, 2, 'takeout');
+            INSERT INTO recipe (id, name, ingredients, procedure) VALUES (1, 'Horchata', 'rice', 'blend');
+            INSERT INTO meal_recipe (meal, recipe, type) VALUES (1, 1, 'cooked');
+            "#,
+        )
+        .execute(&ctx.pool)
+        .await
+        .expect("Failed to add dishes");
+
+        let ics = fetch_calendar(&ctx, "?start_date=2024-05-01&meals=true").await;
+        let vevent = |uid: &str| {
+            ics.split("BEGIN:VEVENT")
+                .find(|vevent| vevent.contains(&format!("UID:{}", uid)))
+                .unwrap_or_else(|| panic!("Missing {}", uid))
+                .to_string()
+        };
+        // Each restaurant dish has its own restaurant's location
+        assert!(vevent("meal-1-restaurant-1@xnote").contains("LOCATION:Fremont\r\n"));
+        assert!(vevent("meal-1-restaurant-2@xnote").contains("LOCATION:Ballard\r\n"));
+        assert!(!vevent("meal-1-recipe-1@xnote").contains("LOCATION:"));
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_calendar_rejects_bad_ranges() {
+        let ctx = setup_test_context().await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(calendar::configure),
+        )
+        .await;
+
+        for (query, error) in [
+            (
+                "start_date=0001-01-01&end_date=9999-12-31",
+                "The calendar covers at most 3660 days; narrow start_date and end_date",
+            ),
+            (
+                "start_date=2024-05-02&end_date=2024-05-01",
+                "start_date must not be after end_date",
+            ),
+            ("end_date=-262143-01-01", "end_date is out of range"),
+        ] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/calendar.ics?{}", query))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 400, "{}", query);
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(body["error"], error);
+        }
+
+        teardown_test_context(ctx).await;
+    }
+}
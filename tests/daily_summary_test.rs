@@ -0,0 +1,258 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use xnote::config::database;
+    use xnote::handlers::daily_summary;
+    use xnote::models::daily_summary::DailySummary;
+
+    struct TestContext {
+        pool: PgPool,
+    }
+
+    async fn create_test_database_pool() -> PgPool {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let last_slash = database_url
+            .rfind('/')
+            .expect("DATABASE_URL must be a valid connection string");
+        let database_url = format!("{}/xnote_test", &database_url[..last_slash]);
+        PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect to test database")
+    }
+
+    async fn create_schema(pool: &PgPool) {
+        database::run_migrations(pool)
+            .await
+            .expect("Failed to run migrations");
+    }
+
+    async fn cleanup_database(pool: &PgPool) {
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
+    }
+
+    /// Zed and Amy form the household (in that order); Bob is a friend.
+    async fn setup_test_context() -> TestContext {
+        let pool = create_test_database_pool().await;
+        // Drop leftover state from previous runs so migrations apply from scratch
+        cleanup_database(&pool).await;
+        create_schema(&pool).await;
+
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO people (id, name, household_order) VALUES
+                (1, 'Amy', 2), (2, 'Bob', NULL), (3, 'Zed', 1);
+            INSERT INTO recipe (id, name, ingredients, procedure) VALUES (1, 'Fried rice', 'rice, egg', 'fry');
+            INSERT INTO activity (id, name, type) VALUES (1, 'Hike', 'sport');
+
+            INSERT INTO meal (id, date, "time") VALUES (1, '2024-05-01', 'dinner');
+            INSERT INTO meal_recipe (meal, recipe, type) VALUES (1, 1, 'cooked');
+            INSERT INTO meal_people (meal, people) VALUES (1, 1), (1, 3);
+
+            INSERT INTO event (id, date, activity) VALUES (1, '2024-05-01', 1), (2, '2024-05-01', 1);
+            INSERT INTO event_people (event, people) VALUES (1, 1), (1, 3), (2, 1), (2, 2), (2, 3);
+
+            INSERT INTO drink (id, name, date) VALUES (1, '吃茶三千', '2024-05-01'), (2, '吃茶三千', '2024-05-01');
+            INSERT INTO drink_people (drink, people) VALUES (1, 1), (1, 3), (2, 1);
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .expect("Failed to insert test data");
+
+        TestContext { pool }
+    }
+
+    async fn teardown_test_context(ctx: TestContext) {
+        cleanup_database(&ctx.pool).await;
+        ctx.pool.close().await;
+    }
+
+    async fn fetch_summary(ctx: &TestContext) -> DailySummary {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(daily_summary::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/daily-summary?start_date=2024-05-01&end_date=2024-05-01")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let body = test::read_body(resp).await;
+        let mut summaries: Vec<DailySummary> =
+            serde_json::from_slice(&body).expect("Failed to deserialize daily summary");
+        assert_eq!(summaries.len(), 1);
+        summaries.remove(0)
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_household_members_are_listed_first() {
+        let ctx = setup_test_context().await;
+
+        let summary = fetch_summary(&ctx).await;
+
+        assert_eq!(summary.dinner.len(), 1);
+        assert_eq!(summary.dinner[0].people, "Zed, Amy");
+
+        let mut events: Vec<&str> = summary.events.iter().map(|e| e.text.as_str()).collect();
+        events.sort();
+        assert_eq!(events, vec!["Hike", "Zed, Amy, Bob Hike"]);
+
+        let mut drinks = summary.drinks.clone();
+        drinks.sort();
+        assert_eq!(drinks, vec!["Amy 吃茶三千", "吃茶三千"]);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_without_household_no_people_are_omitted() {
+        let ctx = setup_test_context().await;
+        sqlx::query("UPDATE people SET household_order = NULL")
+            .execute(&ctx.pool)
+            .await
+            .expect("Failed to clear household");
+
+        let summary = fetch_summary(&ctx).await;
+
+        assert_eq!(summary.dinner[0].people, "Amy, Zed");
+        let mut events: Vec<&str> = summary.events.iter().map(|e| e.text.as_str()).collect();
+        events.sort();
+        assert_eq!(events, vec!["Amy, Bob, Zed Hike", "Amy, Zed Hike"]);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_event_times_and_quantity() {
+        let ctx = setup_test_context().await;
+        sqlx::raw_sql(
+            r#"
+            UPDATE event SET start_time = '09:00', end_time = '11:30', quantity = 2.5, unit = 'h' WHERE id = 1;
+            UPDATE event SET measure = '10 km', quantity = 10, unit = 'km' WHERE id = 2;
+            "#,
+        )
+        .execute(&ctx.pool)
+        .await
+        .expect("Failed to update events");
+
+        let summary = fetch_summary(&ctx).await;
+
+        // The free-text measure is shown as written when there is one
+        let mut events: Vec<&str> = summary.events.iter().map(|e| e.text.as_str()).collect();
+        events.sort();
+        assert_eq!(
+            events,
+            vec!["Hike 09:00-11:30 for 2.5 h", "Zed, Amy, Bob Hike for 10 km"]
+        );
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_every_dish_of_a_meal_is_listed() {
+        let ctx = setup_test_context().await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO product (id, name) VALUES (1, 'Kimchi');
+            INSERT INTO meal_product (meal, product, type) VALUES (1, 1, 'manufactured');
+            "#,
+        )
+        .execute(&ctx.pool)
+        .await
+        .expect("Failed to add a side");
+
+        let summary = fetch_summary(&ctx).await;
+
+        let dishes: Vec<(&str, &str)> = summary
+            .dinner
+            .iter()
+            .map(|m| (m.name.as_str(), m.meal_type.as_str()))
+            .collect();
+        assert_eq!(
+            dishes,
+            vec![("Fried rice", "cooked"), ("Kimchi", "manufactured")]
+        );
+        assert!(summary.dinner.iter().all(|m| m.ids == vec![1]));
+        assert!(summary.dinner.iter().all(|m| m.people == "Zed, Amy"));
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_plans_are_listed_until_eaten() {
+        let ctx = setup_test_context().await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO meal_plan (id, date, "time") VALUES (1, '2024-05-01', 'lunch'), (2, '2024-05-01', 'dinner');
+            INSERT INTO meal_plan_source (plan, position, recipe, type) VALUES (1, 0, 1, 'cooked'), (2, 0, 1, 'cooked');
+            INSERT INTO meal_plan_people (plan, people) VALUES (1, 1), (1, 2);
+            UPDATE meal_plan SET meal = 1 WHERE id = 2;
+            "#,
+        )
+        .execute(&ctx.pool)
+        .await
+        .expect("Failed to insert meal plans");
+
+        let summary = fetch_summary(&ctx).await;
+
+        assert_eq!(summary.planned.len(), 1);
+        let planned = &summary.planned[0];
+        assert_eq!(planned.id, 1);
+        assert_eq!(planned.time, "lunch");
+        assert_eq!(planned.name, "Fried rice");
+        assert_eq!(planned.people, "Amy, Bob");
+        assert!(planned.skipped);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_shared_meals_are_merged() {
+        let ctx = setup_test_context().await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO meal_time (name) VALUES ('snack');
+            INSERT INTO meal (id, date, "time", notes) VALUES
+                (2, '2024-05-01', 'dinner', 'extra egg'), (3, '2024-05-01', 'snack', NULL);
+            INSERT INTO meal_recipe (meal, recipe, type) VALUES (2, 1, 'cooked'), (3, 1, 'cooked');
+            INSERT INTO meal_people (meal, people) VALUES (2, 2), (2, 3), (3, 1);
+            "#,
+        )
+        .execute(&ctx.pool)
+        .await
+        .expect("Failed to insert shared meals");
+
+        let summary = fetch_summary(&ctx).await;
+
+        assert_eq!(summary.dinner.len(), 1);
+        let dinner = &summary.dinner[0];
+        assert_eq!(dinner.ids, vec![1, 2]);
+        assert_eq!(dinner.people, "Zed, Amy, Bob");
+        assert_eq!(dinner.notes.as_deref(), Some("extra egg"));
+
+        // Times other than the usual three are kept under their own name
+        let snacks = &summary.other_meals["snack"];
+        assert_eq!(snacks.len(), 1);
+        assert_eq!(snacks[0].ids, vec![3]);
+        assert_eq!(snacks[0].people, "Amy");
+
+        teardown_test_context(ctx).await;
+    }
+}
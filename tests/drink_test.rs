@@ -1,9 +1,12 @@
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 #[cfg(test)]
 mod tests {
     use actix_web::{test, web, App};
     use serial_test::serial;
     use sqlx::PgPool;
-    use xnote::handlers::drinks;
+    use xnote::config::database;
+    use xnote::handlers::{drink_options, drinks};
     use xnote::models::detail::DrinkDetail;
 
     struct TestContext {
@@ -25,49 +28,22 @@ mod tests {
     }
 
     async fn create_schema(pool: &PgPool) {
-        let schema_sql = include_str!("../init.sql");
-        sqlx::raw_sql(schema_sql)
-            .execute(pool)
+        database::run_migrations(pool)
             .await
-            .expect("Failed to create schema");
+            .expect("Failed to run migrations");
     }
 
     async fn cleanup_database(pool: &PgPool) {
-        let tables = vec![
-            "drink_people",
-            "drink",
-            "drink_option",
-            "event_people",
-            "event",
-            "activity",
-            "activity_type",
-            "meal_people",
-            "meal_restaurant",
-            "meal_product",
-            "meal_recipe",
-            "meal",
-            "meal_time",
-            "meal_type",
-            "people",
-            "restaurant",
-            "product",
-            "recipe",
-            "location",
-            "food_type",
-        ];
-
-        for table in tables {
-            let query = format!("DROP TABLE IF EXISTS {} CASCADE", table);
-            sqlx::query(&query)
-                .execute(pool)
-                .await
-                .expect(&format!("Failed to drop table {}", table));
-        }
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
     }
 
     async fn setup_test_context() -> TestContext {
         let pool = create_test_database_pool().await;
-        // Drop leftover state from previous runs; init.sql inserts are not idempotent
+        // Drop leftover state from previous runs so migrations apply from scratch
         cleanup_database(&pool).await;
         create_schema(&pool).await;
 
@@ -473,7 +449,7 @@ mod tests {
 
     #[actix_web::test]
     #[serial]
-    async fn test_update_drink_placeholder() {
+    async fn test_update_drink() {
         let ctx = setup_test_context().await;
 
         let app = test::init_service(
@@ -485,22 +461,114 @@ mod tests {
 
         let req = test::TestRequest::put()
             .uri(&format!("/drinks/{}", ctx.drink1_id))
+            .set_json(serde_json::json!({
+                "date": "2024-01-20",
+                "name": "吃茶三千",
+                "people_ids": []
+            }))
             .to_request();
 
         let resp = test::call_service(&app, req).await;
-        assert!(resp.status().is_success());
+        assert_eq!(resp.status(), 200);
 
         let body = test::read_body(resp).await;
         let response: serde_json::Value =
             serde_json::from_slice(&body).expect("Failed to deserialize response");
-        assert_eq!(response["message"], "Update drink - TODO: implement");
+        assert_eq!(response["message"], "Drink updated successfully");
+        assert_eq!(response["id"].as_i64().unwrap(), i64::from(ctx.drink1_id));
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/drinks/{}/details", ctx.drink1_id))
+            .to_request();
+        let drink_detail: xnote::models::detail::DrinkDetail =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(drink_detail.name, "吃茶三千");
+        assert_eq!(
+            drink_detail.date,
+            chrono::NaiveDate::from_ymd_opt(2024, 1, 20).unwrap()
+        );
+        assert!(drink_detail.people.is_empty());
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_update_drink_not_found() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(drinks::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::put()
+            .uri("/drinks/99999")
+            .set_json(serde_json::json!({
+                "date": "2024-01-20",
+                "name": "吃茶三千",
+                "people_ids": []
+            }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+
+        let body = test::read_body(resp).await;
+        let error_response: serde_json::Value =
+            serde_json::from_slice(&body).expect("Failed to deserialize error response");
+        assert_eq!(error_response["error"], "Drink not found");
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_update_drink_unknown_option() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(drinks::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/drinks/{}", ctx.drink2_id))
+            .set_json(serde_json::json!({
+                "date": "2024-01-20",
+                "name": "Not A Drink",
+                "people_ids": []
+            }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+
+        let body = test::read_body(resp).await;
+        let error_response: serde_json::Value =
+            serde_json::from_slice(&body).expect("Failed to deserialize error response");
+        assert_eq!(error_response["code"], "invalid_reference");
+        assert_eq!(error_response["field"], "name");
+
+        // The rejected update must leave the drink and its people untouched
+        let req = test::TestRequest::get()
+            .uri(&format!("/drinks/{}/details", ctx.drink2_id))
+            .to_request();
+        let drink_detail: xnote::models::detail::DrinkDetail =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(drink_detail.name, "自己做的latte");
+        assert_eq!(drink_detail.people.len(), 1);
 
         teardown_test_context(ctx).await;
     }
 
     #[actix_web::test]
     #[serial]
-    async fn test_delete_drink_placeholder() {
+    async fn test_delete_drink() {
         let ctx = setup_test_context().await;
 
         let app = test::init_service(
@@ -515,12 +583,158 @@ mod tests {
             .to_request();
 
         let resp = test::call_service(&app, req).await;
-        assert!(resp.status().is_success());
+        assert_eq!(resp.status(), 200);
+
+        let body = test::read_body(resp).await;
+        let response: serde_json::Value =
+            serde_json::from_slice(&body).expect("Failed to deserialize response");
+        assert_eq!(response["message"], "Drink deleted successfully");
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/drinks/{}", ctx.drink1_id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+
+        // The drink waits in the trash with its people until it is purged
+        let trashed = sqlx::query_scalar!(
+            r#"SELECT deleted_at IS NOT NULL AS "trashed!" FROM drink WHERE id = $1"#,
+            ctx.drink1_id
+        )
+        .fetch_one(&ctx.pool)
+        .await
+        .expect("Failed to fetch drink");
+        assert!(trashed);
+
+        let link_count = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM drink_people WHERE drink = $1",
+            ctx.drink1_id
+        )
+        .fetch_one(&ctx.pool)
+        .await
+        .expect("Failed to count drink people");
+        assert!(link_count.unwrap_or(0) > 0);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_delete_drink_not_found() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(drinks::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::delete()
+            .uri("/drinks/99999")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+
+        let body = test::read_body(resp).await;
+        let error_response: serde_json::Value =
+            serde_json::from_slice(&body).expect("Failed to deserialize error response");
+        assert_eq!(error_response["error"], "Drink not found");
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_delete_drinks_batch() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(drinks::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/drinks/batch/delete")
+            .set_json(serde_json::json!({
+                "drink_ids": [ctx.drink1_id, ctx.drink3_id, 99999]
+            }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
 
         let body = test::read_body(resp).await;
         let response: serde_json::Value =
             serde_json::from_slice(&body).expect("Failed to deserialize response");
-        assert_eq!(response["message"], "Delete drink - TODO: implement");
+        assert_eq!(response["deleted_count"], 2);
+
+        let req = test::TestRequest::get().uri("/drinks").to_request();
+        let drinks: Vec<xnote::models::drink::Drink> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(drinks.len(), 1);
+        assert_eq!(drinks[0].id, ctx.drink2_id);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_delete_drinks_batch_empty() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(drinks::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/drinks/batch/delete")
+            .set_json(serde_json::json!({ "drink_ids": [] }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        let body = test::read_body(resp).await;
+        let error_response: serde_json::Value =
+            serde_json::from_slice(&body).expect("Failed to deserialize error response");
+        assert_eq!(error_response["error"], "No drink IDs provided");
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_rename_drink_option() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(drinks::configure)
+                .configure(drink_options::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::put()
+            .uri("/drink-options/Sip%20House%20-%20Ube%20Latte")
+            .set_json(serde_json::json!({ "name": "Sip House - Ube Milk Tea" }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/drinks/{}", ctx.drink1_id))
+            .to_request();
+        let drink: xnote::models::drink::Drink = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(drink.name, "Sip House - Ube Milk Tea");
 
         teardown_test_context(ctx).await;
     }
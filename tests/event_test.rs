@@ -1,8 +1,11 @@
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 #[cfg(test)]
 mod tests {
     use actix_web::{test, web, App};
     use serial_test::serial;
     use sqlx::PgPool;
+    use xnote::config::database;
     use xnote::handlers::events;
     use xnote::models::detail::EventDetail;
 
@@ -28,49 +31,22 @@ mod tests {
     }
 
     async fn create_schema(pool: &PgPool) {
-        let schema_sql = include_str!("../init.sql");
-        sqlx::raw_sql(schema_sql)
-            .execute(pool)
+        database::run_migrations(pool)
             .await
-            .expect("Failed to create schema");
+            .expect("Failed to run migrations");
     }
 
     async fn cleanup_database(pool: &PgPool) {
-        let tables = vec![
-            "drink_people",
-            "drink",
-            "drink_option",
-            "event_people",
-            "event",
-            "activity",
-            "activity_type",
-            "meal_people",
-            "meal_restaurant",
-            "meal_product",
-            "meal_recipe",
-            "meal",
-            "meal_time",
-            "meal_type",
-            "people",
-            "restaurant",
-            "product",
-            "recipe",
-            "location",
-            "food_type",
-        ];
-
-        for table in tables {
-            let query = format!("DROP TABLE IF EXISTS {} CASCADE", table);
-            sqlx::query(&query)
-                .execute(pool)
-                .await
-                .expect(&format!("Failed to drop table {}", table));
-        }
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
     }
 
     async fn setup_test_context() -> TestContext {
         let pool = create_test_database_pool().await;
-        // Drop leftover state from previous runs; init.sql inserts are not idempotent
+        // Drop leftover state from previous runs so migrations apply from scratch
         cleanup_database(&pool).await;
         create_schema(&pool).await;
 
@@ -585,4 +561,249 @@ mod tests {
 
         teardown_test_context(ctx).await;
     }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_get_events_filtered_by_activity_type() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(events::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/events?activity_type=side%20project")
+            .to_request();
+        let events: Vec<xnote::models::event::Event> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id, ctx.event2_id);
+
+        let req = test::TestRequest::get()
+            .uri("/events?location=SLU&end_date=2024-01-16")
+            .to_request();
+        let events: Vec<xnote::models::event::Event> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id, ctx.event2_id);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    async fn test_parse_measure() {
+        use xnote::measure::{parse_measure, Measure};
+
+        let measure = |quantity: f64, unit: &str| {
+            Some(Measure {
+                quantity,
+                unit: unit.to_string(),
+            })
+        };
+        assert_eq!(parse_measure("2h"), measure(2.0, "h"));
+        assert_eq!(parse_measure("1h 30min"), measure(1.5, "h"));
+        assert_eq!(parse_measure("1h30min"), measure(1.5, "h"));
+        // A bare m is metres, not minutes
+        assert_eq!(parse_measure("400m"), measure(0.4, "km"));
+        assert_eq!(parse_measure("1h30m"), None);
+        assert_eq!(parse_measure("45 Minutes"), measure(0.75, "h"));
+        assert_eq!(parse_measure("2小时"), measure(2.0, "h"));
+        assert_eq!(parse_measure("5km"), measure(5.0, "km"));
+        assert_eq!(parse_measure("10 miles"), measure(16.09344, "km"));
+        assert_eq!(parse_measure("12 pages"), measure(12.0, "pages"));
+        assert_eq!(parse_measure("all afternoon"), None);
+        assert_eq!(parse_measure("3"), None);
+        assert_eq!(parse_measure("1h 5km"), None);
+        assert_eq!(parse_measure(""), None);
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_create_event_with_times_and_measure() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(events::configure),
+        )
+        .await;
+
+        // Quantity and unit are parsed from the measure when left out
+        let req = test::TestRequest::post()
+            .uri("/events")
+            .set_json(serde_json::json!({
+                "date": "2024-01-18",
+                "activity_id": ctx.activity2_id,
+                "measure": "1h 30min",
+                "start_time": "09:00:00",
+                "end_time": "10:30:00",
+                "location": null,
+                "notes": null,
+                "people_ids": []
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let body = test::read_body(resp).await;
+        let response: serde_json::Value =
+            serde_json::from_slice(&body).expect("Failed to deserialize response");
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/events/{}/details", response["id"]))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let body = test::read_body(resp).await;
+        let event_detail: EventDetail =
+            serde_json::from_slice(&body).expect("Failed to deserialize event detail");
+        assert_eq!(event_detail.quantity, Some(1.5));
+        assert_eq!(event_detail.unit, Some("h".to_string()));
+        assert_eq!(
+            event_detail.start_time.map(|t| t.to_string()),
+            Some("09:00:00".to_string())
+        );
+        assert_eq!(
+            event_detail.end_time.map(|t| t.to_string()),
+            Some("10:30:00".to_string())
+        );
+
+        // Given ones win over the measure
+        let req = test::TestRequest::put()
+            .uri(&format!("/events/{}", response["id"]))
+            .set_json(serde_json::json!({
+                "date": "2024-01-18",
+                "activity_id": ctx.activity1_id,
+                "measure": "a long run",
+                "quantity": 21.1,
+                "unit": "km",
+                "location": null,
+                "notes": null,
+                "people_ids": []
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/events/{}", response["id"]))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let body = test::read_body(resp).await;
+        let event: xnote::models::event::Event =
+            serde_json::from_slice(&body).expect("Failed to deserialize event");
+        assert_eq!(event.quantity, Some(21.1));
+        assert_eq!(event.unit, Some("km".to_string()));
+        assert_eq!(event.start_time, None);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_create_event_with_quantity_but_no_unit() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(events::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/events")
+            .set_json(serde_json::json!({
+                "date": "2024-01-18",
+                "activity_id": ctx.activity1_id,
+                "measure": null,
+                "quantity": 3,
+                "location": null,
+                "notes": null,
+                "people_ids": []
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_create_event_normalizes_unit() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(events::configure),
+        )
+        .await;
+
+        let event = |quantity: f64, unit: &str| {
+            test::TestRequest::post()
+                .uri("/events")
+                .set_json(serde_json::json!({
+                    "date": "2024-01-18",
+                    "activity_id": ctx.activity1_id,
+                    "measure": null,
+                    "quantity": quantity,
+                    "unit": unit,
+                    "location": null,
+                    "notes": null,
+                    "people_ids": []
+                }))
+                .to_request()
+        };
+
+        // Given units are stored in hours and kilometres, like parsed ones
+        let resp = test::call_service(&app, event(5000.0, "Meters")).await;
+        assert_eq!(resp.status(), 201);
+        let response: serde_json::Value = test::read_body_json(resp).await;
+        let (quantity, unit): (Option<f64>, Option<String>) =
+            sqlx::query_as("SELECT quantity, unit FROM event WHERE id = $1")
+                .bind(response["id"].as_i64().unwrap() as i32)
+                .fetch_one(&ctx.pool)
+                .await
+                .expect("Failed to fetch event");
+        assert_eq!(quantity, Some(5.0));
+        assert_eq!(unit, Some("km".to_string()));
+
+        let resp = test::call_service(&app, event(2.0, "furlongs")).await;
+        assert_eq!(resp.status(), 400);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_backfill_measures() {
+        let ctx = setup_test_context().await;
+
+        let report = xnote::measure::backfill(&ctx.pool)
+            .await
+            .expect("Failed to backfill measures");
+        assert_eq!(report.updated, 2);
+        assert!(report.unparsed.is_empty());
+
+        let (quantity, unit): (Option<f64>, Option<String>) =
+            sqlx::query_as("SELECT quantity, unit FROM event WHERE id = $1")
+                .bind(ctx.event2_id)
+                .fetch_one(&ctx.pool)
+                .await
+                .expect("Failed to fetch event");
+        assert_eq!(quantity, Some(2.0));
+        assert_eq!(unit, Some("h".to_string()));
+
+        // Already structured events are left alone
+        let report = xnote::measure::backfill(&ctx.pool)
+            .await
+            .expect("Failed to backfill measures");
+        assert_eq!(report.updated, 0);
+
+        teardown_test_context(ctx).await;
+    }
 }
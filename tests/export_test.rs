@@ -0,0 +1,236 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use xnote::config::database;
+    use xnote::handlers::export;
+    use xnote::models::export::{Archive, RestoredTable};
+
+    struct TestContext {
+        pool: PgPool,
+    }
+
+    async fn create_test_database_pool() -> PgPool {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let last_slash = database_url
+            .rfind('/')
+            .expect("DATABASE_URL must be a valid connection string");
+        let database_url = format!("{}/xnote_test", &database_url[..last_slash]);
+        PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect to test database")
+    }
+
+    async fn create_schema(pool: &PgPool) {
+        database::run_migrations(pool)
+            .await
+            .expect("Failed to run migrations");
+    }
+
+    async fn cleanup_database(pool: &PgPool) {
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
+    }
+
+    async fn setup_test_context() -> TestContext {
+        let pool = create_test_database_pool().await;
+        // Drop leftover state from previous runs so migrations apply from scratch
+        cleanup_database(&pool).await;
+        create_schema(&pool).await;
+
+        TestContext { pool }
+    }
+
+    async fn teardown_test_context(ctx: TestContext) {
+        cleanup_database(&ctx.pool).await;
+        ctx.pool.close().await;
+    }
+
+    /// Ids are deliberately sparse, so a restore that renumbers rows would show.
+    async fn seed_data(pool: &PgPool) {
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO location (name) VALUES ('Moon base');
+            INSERT INTO people (id, name, household_order) VALUES (5, 'Amy', 1), (9, 'Bob', NULL);
+            INSERT INTO people_alias (alias, people) VALUES ('bobby', 9);
+            INSERT INTO recipe (id, name, ingredients, procedure) VALUES (3, 'Fried rice', 'rice, egg', 'fry');
+            INSERT INTO restaurant (id, name, location, type, price) VALUES (7, 'Taco Spot', 'Moon base', 'mexican', 12.5);
+            INSERT INTO product (id, name) VALUES (2, 'Cup noodles');
+            INSERT INTO activity (id, name, type) VALUES (4, 'Hike', 'sport');
+
+            INSERT INTO meal (id, date, "time", notes) VALUES (10, '2024-05-01', 'lunch', 'tasty'), (12, '2024-05-01', 'dinner', NULL);
+            INSERT INTO meal_restaurant (meal, restaurant, type) VALUES (10, 7, 'dine-in');
+            INSERT INTO meal_recipe (meal, recipe, type) VALUES (12, 3, 'cooked');
+            INSERT INTO meal_people (meal, people) VALUES (10, 5), (10, 9), (12, 5);
+
+            INSERT INTO event (id, date, activity, measure, location) VALUES (20, '2024-05-02', 4, '3h', 'Moon base');
+            INSERT INTO event_people (event, people) VALUES (20, 9);
+
+            INSERT INTO drink (id, name, date) VALUES (30, '吃茶三千', '2024-05-03');
+            INSERT INTO drink_people (drink, people) VALUES (30, 5);
+            "#,
+        )
+        .execute(pool)
+        .await
+        .expect("Failed to insert test data");
+    }
+
+    macro_rules! init_app {
+        ($ctx:expr) => {
+            test::init_service(
+                App::new()
+                    .app_data(web::Data::new($ctx.pool.clone()))
+                    .configure(export::configure),
+            )
+            .await
+        };
+    }
+
+    async fn fetch_archive(ctx: &TestContext) -> Archive {
+        let app = init_app!(ctx);
+        let req = test::TestRequest::get().uri("/export").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert!(resp
+            .headers()
+            .get("content-disposition")
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("attachment")));
+
+        let body = test::read_body(resp).await;
+        serde_json::from_slice(&body).expect("Failed to deserialize archive")
+    }
+
+    fn rows<'a>(archive: &'a Archive, name: &str) -> &'a Vec<serde_json::Value> {
+        &archive
+            .tables
+            .iter()
+            .find(|table| table.name == name)
+            .unwrap_or_else(|| panic!("Archive has no {} table", name))
+            .rows
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_export() {
+        let ctx = setup_test_context().await;
+        seed_data(&ctx.pool).await;
+
+        let archive = fetch_archive(&ctx).await;
+        assert_eq!(archive.version, xnote::export::ARCHIVE_VERSION);
+        assert_eq!(archive.tables[0].name, "location");
+        assert!(!archive.tables.iter().any(|table| table.name == "app_user"));
+
+        assert_eq!(
+            rows(&archive, "people"),
+            &vec![
+                serde_json::json!({ "id": 5, "name": "Amy", "notes": null, "household_order": 1, "deleted_at": null }),
+                serde_json::json!({ "id": 9, "name": "Bob", "notes": null, "household_order": null, "deleted_at": null }),
+            ]
+        );
+        assert_eq!(rows(&archive, "meal_people").len(), 3);
+        assert_eq!(rows(&archive, "event")[0]["measure"], "3h");
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_restore_round_trip() {
+        let ctx = setup_test_context().await;
+        seed_data(&ctx.pool).await;
+        let archive = fetch_archive(&ctx).await;
+
+        // Start over from a freshly migrated database
+        cleanup_database(&ctx.pool).await;
+        create_schema(&ctx.pool).await;
+
+        let app = init_app!(ctx);
+        let req = test::TestRequest::post()
+            .uri("/restore")
+            .set_json(&archive)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let body = test::read_body(resp).await;
+        let restored: Vec<RestoredTable> =
+            serde_json::from_slice(&body).expect("Failed to deserialize restore result");
+        let meals = restored.iter().find(|t| t.name == "meal").unwrap();
+        assert_eq!(meals.rows, 2);
+
+        let restored_archive = fetch_archive(&ctx).await;
+        for table in &archive.tables {
+            assert_eq!(
+                rows(&restored_archive, &table.name),
+                &table.rows,
+                "{} differs after restore",
+                table.name
+            );
+        }
+
+        // New rows continue after the restored ids
+        let id: i32 = sqlx::query_scalar("INSERT INTO people (name) VALUES ('Cat') RETURNING id")
+            .fetch_one(&ctx.pool)
+            .await
+            .expect("Failed to insert person");
+        assert_eq!(id, 10);
+
+        // Restoring twice would duplicate everything
+        let req = test::TestRequest::post()
+            .uri("/restore")
+            .set_json(&archive)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 409);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_restore_rejects_unknown_archives() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let req = test::TestRequest::post()
+            .uri("/restore")
+            .set_json(serde_json::json!({
+                "version": 99,
+                "exported_at": "2024-05-01T00:00:00Z",
+                "tables": []
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        let req = test::TestRequest::post()
+            .uri("/restore")
+            .set_json(serde_json::json!({
+                "version": xnote::export::ARCHIVE_VERSION,
+                "exported_at": "2024-05-01T00:00:00Z",
+                "tables": [{ "name": "app_user", "rows": [] }]
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        // Malformed archives get the shared error body
+        let req = test::TestRequest::post()
+            .uri("/restore")
+            .insert_header(("Content-Type", "application/json"))
+            .set_payload("{\"version\": ")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "bad_request");
+        assert!(body["error"].is_string());
+
+        teardown_test_context(ctx).await;
+    }
+}
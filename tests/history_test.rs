@@ -0,0 +1,295 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use xnote::config::database;
+    use xnote::handlers::{history, locations, meals, people, trash};
+    use xnote::models::detail::MealDetail;
+    use xnote::models::history::{HistoryAction, HistoryEntity, HistoryEntry};
+
+    struct TestContext {
+        pool: PgPool,
+    }
+
+    async fn create_test_database_pool() -> PgPool {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let last_slash = database_url
+            .rfind('/')
+            .expect("DATABASE_URL must be a valid connection string");
+        let database_url = format!("{}/xnote_test", &database_url[..last_slash]);
+        PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect to test database")
+    }
+
+    async fn create_schema(pool: &PgPool) {
+        database::run_migrations(pool)
+            .await
+            .expect("Failed to run migrations");
+    }
+
+    async fn cleanup_database(pool: &PgPool) {
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
+    }
+
+    async fn setup_test_context() -> TestContext {
+        let pool = create_test_database_pool().await;
+        // Drop leftover state from previous runs so migrations apply from scratch
+        cleanup_database(&pool).await;
+        create_schema(&pool).await;
+
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO people (id, name) VALUES (1, 'Amy'), (2, 'Bob');
+            INSERT INTO restaurant (id, name, location, type) VALUES (1, 'Taqueria', 'Fremont', 'mexican');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .expect("Failed to insert test data");
+
+        TestContext { pool }
+    }
+
+    async fn teardown_test_context(ctx: TestContext) {
+        cleanup_database(&ctx.pool).await;
+        ctx.pool.close().await;
+    }
+
+    macro_rules! init_app {
+        ($ctx:expr) => {
+            test::init_service(
+                App::new()
+                    .app_data(web::Data::new($ctx.pool.clone()))
+                    .configure(history::configure)
+                    .configure(locations::configure)
+                    .configure(meals::configure)
+                    .configure(people::configure)
+                    .configure(trash::configure),
+            )
+            .await
+        };
+    }
+
+    fn meal_body(time: &str, people_ids: &[i32]) -> serde_json::Value {
+        serde_json::json!({
+            "date": "2024-03-01",
+            "time": time,
+            "food_sources": [
+                { "type": "restaurant", "restaurant_id": 1, "meal_type": "dine-in" }
+            ],
+            "people_ids": people_ids
+        })
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_meal_history() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let req = test::TestRequest::post()
+            .uri("/meals")
+            .set_json(meal_body("lunch", &[1]))
+            .to_request();
+        let meal: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let meal_id = meal["id"].as_i64().unwrap();
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/meals/{}", meal_id))
+            .set_json(meal_body("dinner", &[1, 2]))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/meals/{}", meal_id))
+            .to_request();
+        test::call_service(&app, req).await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/history?entity=meal&id={}", meal_id))
+            .to_request();
+        let entries: Vec<HistoryEntry> = test::call_and_read_body_json(&app, req).await;
+        let actions: Vec<HistoryAction> = entries.iter().map(|entry| entry.action).collect();
+        assert_eq!(
+            actions,
+            vec![
+                HistoryAction::Delete,
+                HistoryAction::Update,
+                HistoryAction::Create
+            ]
+        );
+
+        // Images carry the links, without the column pointing back at the meal
+        let create = &entries[2];
+        assert!(create.before.is_none());
+        let after = create.after.as_ref().unwrap();
+        assert_eq!(after["time"], "lunch");
+        assert_eq!(after["meal_people"], serde_json::json!([{ "people": 1 }]));
+        assert_eq!(after["meal_restaurant"][0]["restaurant"], 1);
+
+        let update = &entries[1];
+        assert_eq!(update.before, create.after);
+        let after = update.after.as_ref().unwrap();
+        assert_eq!(after["time"], "dinner");
+        assert_eq!(after["meal_people"].as_array().unwrap().len(), 2);
+
+        let delete = &entries[0];
+        assert!(delete.before.as_ref().unwrap()["deleted_at"].is_null());
+        assert!(!delete.after.as_ref().unwrap()["deleted_at"].is_null());
+
+        // Reverting the creation brings the meal back out of the trash as it was
+        let req = test::TestRequest::post()
+            .uri(&format!("/history/{}/revert", create.id))
+            .to_request();
+        let reverted: HistoryEntry = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(reverted.action, HistoryAction::Revert);
+        assert_eq!(reverted.entity, HistoryEntity::Meal);
+        assert_eq!(reverted.before, delete.after);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/meals/{}/details", meal_id))
+            .to_request();
+        let details: MealDetail = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(details.time, "lunch");
+        assert_eq!(details.people.len(), 1);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_revert_purged() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let req = test::TestRequest::put()
+            .uri("/people/2")
+            .set_json(serde_json::json!({ "notes": "likes spicy" }))
+            .to_request();
+        test::call_service(&app, req).await;
+        let req = test::TestRequest::put()
+            .uri("/people/aliases")
+            .set_json(serde_json::json!({ "alias": "kids", "people_ids": [1, 2] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let req = test::TestRequest::delete().uri("/people/2").to_request();
+        test::call_service(&app, req).await;
+        let req = test::TestRequest::delete()
+            .uri("/trash/person/2")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let req = test::TestRequest::get()
+            .uri("/history?entity=person&id=2")
+            .to_request();
+        let entries: Vec<HistoryEntry> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].action, HistoryAction::Purge);
+        assert!(entries[0].after.is_none());
+        assert_eq!(entries[2].action, HistoryAction::Update);
+        assert_eq!(
+            entries[2].after.as_ref().unwrap()["people_alias"],
+            serde_json::json!([{ "alias": "kids" }])
+        );
+
+        // A purge leaves nothing to go back to
+        let req = test::TestRequest::post()
+            .uri(&format!("/history/{}/revert", entries[0].id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        // An earlier image recreates the person, alias included
+        let req = test::TestRequest::post()
+            .uri(&format!("/history/{}/revert", entries[2].id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let req = test::TestRequest::get().uri("/people/2").to_request();
+        let person: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(person["name"], "Bob");
+        assert_eq!(person["notes"], "likes spicy");
+
+        let aliases: Vec<i32> = sqlx::query_scalar(
+            "SELECT people FROM people_alias WHERE alias = 'kids' ORDER BY people",
+        )
+        .fetch_all(&ctx.pool)
+        .await
+        .unwrap();
+        assert_eq!(aliases, vec![1, 2]);
+
+        // The global feed has every change, newest first, and cannot be rewritten
+        let req = test::TestRequest::get()
+            .uri("/history?limit=2")
+            .to_request();
+        let entries: Vec<HistoryEntry> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].action, HistoryAction::Revert);
+        assert!(entries[0].id > entries[1].id);
+
+        let result = sqlx::query("DELETE FROM history").execute(&ctx.pool).await;
+        assert!(result.is_err());
+
+        let req = test::TestRequest::get().uri("/history?id=2").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_vocabulary_rename_history() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO activity (id, name, type) VALUES (1, 'Hike', 'sport');
+            INSERT INTO event (id, date, activity, location) VALUES (1, '2024-03-01', 1, 'Fremont');
+            "#,
+        )
+        .execute(&ctx.pool)
+        .await
+        .expect("Failed to insert event");
+
+        let req = test::TestRequest::put()
+            .uri("/locations/Fremont")
+            .set_json(serde_json::json!({ "name": "Fremont, Seattle" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        // Every record the rename rewrote is logged
+        let req = test::TestRequest::get()
+            .uri("/history?entity=event&id=1")
+            .to_request();
+        let entries: Vec<HistoryEntry> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, HistoryAction::Update);
+        assert_eq!(entries[0].before.as_ref().unwrap()["location"], "Fremont");
+        assert_eq!(
+            entries[0].after.as_ref().unwrap()["location"],
+            "Fremont, Seattle"
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/history?entity=restaurant&id=1")
+            .to_request();
+        let entries: Vec<HistoryEntry> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(entries.len(), 1);
+
+        teardown_test_context(ctx).await;
+    }
+}
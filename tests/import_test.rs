@@ -0,0 +1,298 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use xnote::config::database;
+    use xnote::handlers::import;
+    use xnote::import::{parse_shorthand, Shorthand};
+    use xnote::models::import::ImportPreview;
+    use xnote::models::meal::CreateMealFoodSource;
+    use xnote::models::restaurant::Visit;
+
+    struct TestContext {
+        pool: PgPool,
+    }
+
+    async fn create_test_database_pool() -> PgPool {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let last_slash = database_url
+            .rfind('/')
+            .expect("DATABASE_URL must be a valid connection string");
+        let database_url = format!("{}/xnote_test", &database_url[..last_slash]);
+        PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect to test database")
+    }
+
+    async fn create_schema(pool: &PgPool) {
+        database::run_migrations(pool)
+            .await
+            .expect("Failed to run migrations");
+    }
+
+    async fn cleanup_database(pool: &PgPool) {
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
+    }
+
+    /// Zed and Amy form the household (in that order); Bob, also known as
+    /// "bobby", is a friend.
+    async fn setup_test_context() -> TestContext {
+        let pool = create_test_database_pool().await;
+        // Drop leftover state from previous runs so migrations apply from scratch
+        cleanup_database(&pool).await;
+        create_schema(&pool).await;
+
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO people (id, name, household_order) VALUES
+                (1, 'Amy', 2), (2, 'Bob', NULL), (3, 'Zed', 1);
+            INSERT INTO people_alias (alias, people) VALUES ('bobby', 2);
+            INSERT INTO recipe (id, name, ingredients, procedure) VALUES (1, 'Fried rice', 'rice, egg', 'fry');
+            INSERT INTO restaurant (id, name, location, type) VALUES (1, 'Taco Spot', 'Fremont', 'mexican');
+            INSERT INTO activity (id, name, type) VALUES (1, 'Hike', 'sport');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .expect("Failed to insert test data");
+
+        TestContext { pool }
+    }
+
+    async fn teardown_test_context(ctx: TestContext) {
+        cleanup_database(&ctx.pool).await;
+        ctx.pool.close().await;
+    }
+
+    // Columns: _, date, breakfast x2, lunch x2, dinner x2, special, exercise,
+    // activity, entertainment, housekeeping
+    const CSV: &str = "\
+,date,breakfast,,lunch,,dinner,,special,exercise,activity,entertainment,housekeeping
+,2024-05-01,fried rice,,Taco Spot w/ bobby,taco spot,Pizza Palace,,,hike w/ bob @Fremont (sunny),hike w/ carol,,
+,not a date,fried rice,,,,,,,,,,
+";
+
+    async fn post_import(ctx: &TestContext, query: &str) -> actix_web::dev::ServiceResponse {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(import::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/import{}", query))
+            .insert_header(("Content-Type", "text/csv"))
+            .set_payload(CSV)
+            .to_request();
+        test::call_service(&app, req).await
+    }
+
+    async fn count(ctx: &TestContext, table: &str) -> i64 {
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(&ctx.pool)
+            .await
+            .expect("Failed to count rows")
+    }
+
+    #[actix_web::test]
+    async fn test_parse_shorthand() {
+        assert_eq!(
+            parse_shorthand("pho, spring rolls w/ alice + bob @Ballard (birthday) bento"),
+            Shorthand {
+                names: vec!["pho".to_string(), "spring rolls".to_string()],
+                people: vec!["alice".to_string(), "bob".to_string()],
+                location: Some("Ballard".to_string()),
+                comment: Some("birthday".to_string()),
+                meal_type: Some("takeout"),
+            }
+        );
+
+        // Markers in any order, full-width punctuation, and leftover winning over bento
+        assert_eq!(
+            parse_shorthand("饺子，面条 @home w/ 小明（好吃） leftover bento"),
+            Shorthand {
+                names: vec!["饺子".to_string(), "面条".to_string()],
+                people: vec!["小明".to_string()],
+                location: Some("home".to_string()),
+                comment: Some("好吃".to_string()),
+                meal_type: Some("leftover"),
+            }
+        );
+
+        assert_eq!(parse_shorthand("  "), Shorthand::default());
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_import_preview() {
+        let ctx = setup_test_context().await;
+
+        let resp = post_import(&ctx, "").await;
+        assert_eq!(resp.status(), 200);
+        let body = test::read_body(resp).await;
+        let preview: ImportPreview =
+            serde_json::from_slice(&body).expect("Failed to deserialize preview");
+
+        assert!(!preview.committed);
+        assert_eq!(preview.skipped_rows, vec![1, 3]);
+        assert_eq!(preview.unresolved, 2);
+        assert_eq!(preview.meals.len(), 3);
+        assert_eq!(preview.events.len(), 2);
+
+        // A lone cell belongs to the whole household
+        let breakfast = preview.meals[0]
+            .record
+            .as_ref()
+            .expect("breakfast resolved");
+        assert_eq!(breakfast.time, "breakfast");
+        assert_eq!(breakfast.people_ids, vec![3, 1]);
+        assert_eq!(
+            breakfast.food_sources,
+            vec![CreateMealFoodSource::Recipe {
+                recipe_id: 1,
+                meal_type: "cooked".to_string()
+            }]
+        );
+
+        // The same restaurant in both lunch cells is one meal, with the alias resolved
+        let lunch = preview.meals[1].record.as_ref().expect("lunch resolved");
+        assert_eq!(lunch.people_ids, vec![3, 2, 1]);
+        assert_eq!(
+            lunch.food_sources,
+            vec![CreateMealFoodSource::Restaurant {
+                restaurant_id: 1,
+                meal_type: "dine-in".to_string(),
+                visit: Visit::default()
+            }]
+        );
+
+        let dinner = &preview.meals[2];
+        assert!(dinner.record.is_none());
+        assert!(dinner.matched.is_none());
+        assert_eq!(dinner.issues.len(), 1);
+        assert!(dinner.issues[0].contains("Pizza Palace"));
+
+        let hike = preview.events[0].record.as_ref().expect("hike resolved");
+        assert_eq!(hike.activity_id, 1);
+        assert_eq!(hike.people_ids, vec![3, 1, 2]);
+        assert_eq!(hike.location.as_deref(), Some("Fremont"));
+        assert_eq!(hike.notes.as_deref(), Some("sunny"));
+
+        assert!(preview.events[1].record.is_none());
+        assert_eq!(
+            preview.events[1].issues,
+            vec!["No person matches 'carol' (closest is 'Amy' at 0.20)"]
+        );
+
+        assert_eq!(count(&ctx, "meal").await, 0);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_import_commit_refused_when_unresolved() {
+        let ctx = setup_test_context().await;
+
+        let resp = post_import(&ctx, "?commit=true").await;
+        assert_eq!(resp.status(), 422);
+
+        assert_eq!(count(&ctx, "meal").await, 0);
+        assert_eq!(count(&ctx, "event").await, 0);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_import_commit_skipping_unmatched() {
+        let ctx = setup_test_context().await;
+
+        let resp = post_import(&ctx, "?commit=true&skip_unmatched=true").await;
+        assert_eq!(resp.status(), 201);
+        let body = test::read_body(resp).await;
+        let preview: ImportPreview =
+            serde_json::from_slice(&body).expect("Failed to deserialize preview");
+        assert!(preview.committed);
+
+        assert_eq!(count(&ctx, "meal").await, 2);
+        assert_eq!(count(&ctx, "meal_recipe").await, 1);
+        assert_eq!(count(&ctx, "meal_restaurant").await, 1);
+        assert_eq!(count(&ctx, "meal_people").await, 5);
+        assert_eq!(count(&ctx, "event").await, 1);
+        assert_eq!(count(&ctx, "event_people").await, 3);
+
+        teardown_test_context(ctx).await;
+    }
+
+    const ICS: &str = "BEGIN:VCALENDAR\r\n\
+VERSION:2.0\r\n\
+BEGIN:VEVENT\r\n\
+DTSTART:20240502T090000Z\r\n\
+SUMMARY:hike w/ bobby\r\n\
+LOCATION:Fremont\r\n\
+DESCRIPTION:Rainy\\, but \r\n fun\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+DTSTART;VALUE=DATE:20240503\r\n\
+SUMMARY:Dentist\r\n\
+ATTENDEE;CN=\"Bob\":mailto:bob@example.com\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+SUMMARY:No date\r\n\
+END:VEVENT\r\n\
+END:VCALENDAR\r\n";
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_import_ics() {
+        let ctx = setup_test_context().await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(import::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/import/ics")
+            .insert_header(("Content-Type", "text/calendar"))
+            .set_payload(ICS)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let body = test::read_body(resp).await;
+        let preview: ImportPreview =
+            serde_json::from_slice(&body).expect("Failed to deserialize preview");
+
+        assert_eq!(preview.skipped_rows, vec![3]);
+        assert_eq!(preview.events.len(), 2);
+        assert_eq!(preview.unresolved, 1);
+
+        let hike = preview.events[0].record.as_ref().expect("hike resolved");
+        assert_eq!(hike.date.to_string(), "2024-05-02");
+        assert_eq!(hike.people_ids, vec![3, 1, 2]);
+        assert_eq!(hike.location.as_deref(), Some("Fremont"));
+        assert_eq!(hike.notes.as_deref(), Some("Rainy, but fun"));
+        assert!(preview.events[1].issues[0].contains("Dentist"));
+
+        let req = test::TestRequest::post()
+            .uri("/import/ics?commit=true&skip_unmatched=true")
+            .insert_header(("Content-Type", "text/calendar"))
+            .set_payload(ICS)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        assert_eq!(count(&ctx, "event").await, 1);
+        assert_eq!(count(&ctx, "event_people").await, 3);
+
+        teardown_test_context(ctx).await;
+    }
+}
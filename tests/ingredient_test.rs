@@ -0,0 +1,402 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use xnote::config::database;
+    use xnote::handlers::{recipes, shopping_list};
+    use xnote::ingredients::{parse_ingredients, shopping_list as merge};
+    use xnote::models::ingredient::{Ingredient, RecipeIngredients, ShoppingItem};
+    use xnote::models::recipe::Recipe;
+
+    struct TestContext {
+        pool: PgPool,
+    }
+
+    async fn create_test_database_pool() -> PgPool {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let last_slash = database_url
+            .rfind('/')
+            .expect("DATABASE_URL must be a valid connection string");
+        let database_url = format!("{}/xnote_test", &database_url[..last_slash]);
+        PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect to test database")
+    }
+
+    async fn create_schema(pool: &PgPool) {
+        database::run_migrations(pool)
+            .await
+            .expect("Failed to run migrations");
+    }
+
+    async fn cleanup_database(pool: &PgPool) {
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
+    }
+
+    async fn setup_test_context() -> TestContext {
+        let pool = create_test_database_pool().await;
+        // Drop leftover state from previous runs so migrations apply from scratch
+        cleanup_database(&pool).await;
+        create_schema(&pool).await;
+
+        TestContext { pool }
+    }
+
+    async fn teardown_test_context(ctx: TestContext) {
+        cleanup_database(&ctx.pool).await;
+        ctx.pool.close().await;
+    }
+
+    fn line(
+        quantity: Option<f64>,
+        unit: Option<&str>,
+        item: &str,
+        note: Option<&str>,
+    ) -> Ingredient {
+        Ingredient {
+            quantity,
+            unit: unit.map(str::to_string),
+            item: item.to_string(),
+            note: note.map(str::to_string),
+        }
+    }
+
+    macro_rules! init_app {
+        ($ctx:expr) => {
+            test::init_service(
+                App::new()
+                    .app_data(web::Data::new($ctx.pool.clone()))
+                    .configure(recipes::configure)
+                    .configure(shopping_list::configure),
+            )
+            .await
+        };
+    }
+
+    #[actix_web::test]
+    async fn test_parse_ingredient_lines() {
+        let lines = parse_ingredients(
+            "- 2 cups flour, sifted\n1 1/2 tbsp sugar\n200g butter (soft)\n½ tsp salt\n3 large eggs\nPepper to taste\n",
+        );
+        assert_eq!(
+            lines,
+            vec![
+                line(Some(2.0), Some("cup"), "flour", Some("sifted")),
+                line(Some(1.5), Some("tbsp"), "sugar", None),
+                line(Some(200.0), Some("g"), "butter", Some("soft")),
+                line(Some(0.5), Some("tsp"), "salt", None),
+                line(Some(3.0), None, "large eggs", None),
+                line(None, None, "Pepper to taste", None),
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_parse_ingredient_list() {
+        let lines =
+            parse_ingredients("鸡蛋 2个、米饭 300克、葱（切碎）, 2-3 cloves garlic, 2 grapes");
+        assert_eq!(
+            lines,
+            vec![
+                line(Some(2.0), Some("piece"), "鸡蛋", None),
+                line(Some(300.0), Some("g"), "米饭", None),
+                line(None, None, "葱", Some("切碎")),
+                line(Some(3.0), Some("clove"), "garlic", None),
+                line(Some(2.0), None, "grapes", None),
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_merge_shopping_list() {
+        let items = merge(&[
+            (
+                "Cake".to_string(),
+                1.0,
+                line(Some(1.0), Some("kg"), "Flour", None),
+            ),
+            (
+                "Bread".to_string(),
+                2.0,
+                line(Some(250.0), Some("g"), "flour", None),
+            ),
+            ("Cake".to_string(), 1.0, line(Some(2.0), None, "eggs", None)),
+            (
+                "Bread".to_string(),
+                2.0,
+                line(Some(1.0), None, "eggs", None),
+            ),
+            ("Bread".to_string(), 2.0, line(None, None, "salt", None)),
+            (
+                "Cake".to_string(),
+                1.0,
+                line(Some(1.0), Some("tbsp"), "sugar", None),
+            ),
+            (
+                "Bread".to_string(),
+                2.0,
+                line(Some(1.0), Some("tbsp"), "sugar", None),
+            ),
+        ]);
+
+        let summary: Vec<(&str, Option<f64>, Option<&str>, usize)> = items
+            .iter()
+            .map(|i| {
+                (
+                    i.item.as_str(),
+                    i.quantity,
+                    i.unit.as_deref(),
+                    i.recipes.len(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("eggs", Some(4.0), None, 2),
+                ("Flour", Some(1500.0), Some("g"), 2),
+                ("salt", None, None, 1),
+                ("sugar", Some(3.0), Some("tbsp"), 2),
+            ]
+        );
+    }
+
+    macro_rules! create_recipe {
+        ($app:expr, $body:expr) => {{
+            let req = test::TestRequest::post()
+                .uri("/recipes")
+                .set_json($body)
+                .to_request();
+            let resp = test::call_service(&$app, req).await;
+            assert_eq!(resp.status(), 201);
+            let recipe: Recipe = test::read_body_json(resp).await;
+            recipe
+        }};
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_recipe_ingredients_are_scaled() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let recipe = create_recipe!(
+            app,
+            serde_json::json!({
+                "name": "Pancakes",
+                "ingredients": "200g flour\n2 eggs\n300 ml milk\nbutter",
+                "procedure": "mix and fry",
+                "servings": 2
+            })
+        );
+        assert_eq!(recipe.servings, Some(2));
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/recipes/{}/ingredients?servings=3", recipe.id))
+            .to_request();
+        let scaled: RecipeIngredients = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(scaled.servings, Some(3));
+        assert_eq!(
+            scaled.ingredients,
+            vec![
+                line(Some(300.0), Some("g"), "flour", None),
+                line(Some(3.0), None, "eggs", None),
+                line(Some(450.0), Some("ml"), "milk", None),
+                line(None, None, "butter", None),
+            ]
+        );
+
+        // Editing the text re-indexes the lines
+        let req = test::TestRequest::put()
+            .uri(&format!("/recipes/{}", recipe.id))
+            .set_json(serde_json::json!({ "ingredients": "250g flour, 1 egg" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/recipes/{}/ingredients", recipe.id))
+            .to_request();
+        let unscaled: RecipeIngredients = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(unscaled.servings, Some(2));
+        assert_eq!(unscaled.ingredients.len(), 2);
+        assert_eq!(unscaled.ingredients[0].quantity, Some(250.0));
+
+        // Without servings on the recipe there is nothing to scale from
+        let recipe = create_recipe!(
+            app,
+            serde_json::json!({
+                "name": "Toast",
+                "ingredients": "bread",
+                "procedure": "toast"
+            })
+        );
+        let req = test::TestRequest::get()
+            .uri(&format!("/recipes/{}/ingredients?servings=3", recipe.id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_recipes_using_an_ingredient() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let fried_rice = create_recipe!(
+            app,
+            serde_json::json!({
+                "name": "Fried rice",
+                "ingredients": "rice, 2 eggs, green onion",
+                "procedure": "fry"
+            })
+        );
+        create_recipe!(
+            app,
+            serde_json::json!({
+                "name": "Onion soup",
+                "ingredients": "3 onions, 1 l stock",
+                "procedure": "simmer"
+            })
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/recipes?ingredient=egg")
+            .to_request();
+        let found: Vec<Recipe> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, fried_rice.id);
+
+        let req = test::TestRequest::get()
+            .uri("/recipes?ingredient=ONION")
+            .to_request();
+        let found: Vec<Recipe> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(found.len(), 2);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_shopping_list() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let pancakes = create_recipe!(
+            app,
+            serde_json::json!({
+                "name": "Pancakes",
+                "ingredients": "200g flour\n2 eggs",
+                "procedure": "mix and fry",
+                "servings": 2
+            })
+        );
+        let bread = create_recipe!(
+            app,
+            serde_json::json!({
+                "name": "Bread",
+                "ingredients": "0.5 kg flour\nsalt",
+                "procedure": "bake",
+                "servings": 4
+            })
+        );
+
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/shopping-list?recipe_ids={},{}&servings=4",
+                pancakes.id, bread.id
+            ))
+            .to_request();
+        let items: Vec<ShoppingItem> = test::call_and_read_body_json(&app, req).await;
+        let summary: Vec<(&str, Option<f64>, Option<&str>)> = items
+            .iter()
+            .map(|i| (i.item.as_str(), i.quantity, i.unit.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("eggs", Some(4.0), None),
+                ("flour", Some(900.0), Some("g")),
+                ("salt", None, None),
+            ]
+        );
+        assert_eq!(items[1].recipes, vec!["Pancakes", "Bread"]);
+
+        // Recipes of the meals in a date range, one batch per meal
+        sqlx::raw_sql(&format!(
+            r#"
+            INSERT INTO meal (id, date, "time") VALUES (1, '2024-05-01', 'lunch'), (2, '2024-05-02', 'dinner');
+            INSERT INTO meal_recipe (meal, recipe, type) VALUES (1, {0}, 'cooked'), (2, {0}, 'cooked');
+            "#,
+            pancakes.id
+        ))
+        .execute(&ctx.pool)
+        .await
+        .expect("Failed to insert meals");
+
+        let req = test::TestRequest::get()
+            .uri("/shopping-list?start_date=2024-05-01&end_date=2024-05-31")
+            .to_request();
+        let items: Vec<ShoppingItem> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].quantity, Some(4.0));
+        assert_eq!(items[1].quantity, Some(400.0));
+
+        for uri in [
+            "/shopping-list",
+            "/shopping-list?recipe_ids=1,x",
+            "/shopping-list?start_date=2024-05-01",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 400, "{}", uri);
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/shopping-list?recipe_ids=999")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_backfill_ingredients() {
+        let ctx = setup_test_context().await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO recipe (id, name, ingredients, procedure) VALUES
+                (1, 'Fried rice', 'rice, 2 eggs', 'fry'),
+                (2, 'Tea', 'water', 'boil');
+            "#,
+        )
+        .execute(&ctx.pool)
+        .await
+        .expect("Failed to insert recipes");
+
+        let report = xnote::ingredients::backfill(&ctx.pool)
+            .await
+            .expect("Failed to backfill");
+        assert_eq!(report.recipes, 2);
+        assert_eq!(report.lines, 3);
+
+        // Recipes already indexed are left alone
+        let report = xnote::ingredients::backfill(&ctx.pool)
+            .await
+            .expect("Failed to backfill");
+        assert_eq!(report.recipes, 0);
+
+        teardown_test_context(ctx).await;
+    }
+}
@@ -0,0 +1,375 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use rust_decimal::Decimal;
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use xnote::config::database;
+    use xnote::handlers::locations;
+    use xnote::models::location::Location;
+
+    struct TestContext {
+        pool: PgPool,
+        restaurant_id: i32,
+        event_id: i32,
+    }
+
+    async fn create_test_database_pool() -> PgPool {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let last_slash = database_url
+            .rfind('/')
+            .expect("DATABASE_URL must be a valid connection string");
+        let database_url = format!("{}/xnote_test", &database_url[..last_slash]);
+        PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect to test database")
+    }
+
+    async fn create_schema(pool: &PgPool) {
+        database::run_migrations(pool)
+            .await
+            .expect("Failed to run migrations");
+    }
+
+    async fn cleanup_database(pool: &PgPool) {
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
+    }
+
+    async fn setup_test_context() -> TestContext {
+        let pool = create_test_database_pool().await;
+        // Drop leftover state from previous runs so migrations apply from scratch
+        cleanup_database(&pool).await;
+        create_schema(&pool).await;
+
+        // Insert a restaurant and an event in Ballard
+        let restaurant_id: i32 = sqlx::query_scalar!(
+            "INSERT INTO restaurant (name, location, type, price) VALUES ($1, $2, $3, $4) RETURNING id",
+            "Taco Truck",
+            "Ballard",
+            "mexican",
+            Some(Decimal::new(1275, 2))
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to insert restaurant");
+
+        let activity_id: i32 = sqlx::query_scalar!(
+            "INSERT INTO activity (name, type) VALUES ($1, $2) RETURNING id",
+            "Running",
+            "sport"
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to insert activity");
+
+        let event_id: i32 = sqlx::query_scalar!(
+            "INSERT INTO event (date, activity, location) VALUES ($1, $2, $3) RETURNING id",
+            chrono::NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(),
+            activity_id,
+            "Ballard"
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to insert event");
+
+        TestContext {
+            pool,
+            restaurant_id,
+            event_id,
+        }
+    }
+
+    async fn teardown_test_context(ctx: TestContext) {
+        cleanup_database(&ctx.pool).await;
+        ctx.pool.close().await;
+    }
+
+    async fn restaurant_location(pool: &PgPool, restaurant_id: i32) -> String {
+        sqlx::query_scalar!(
+            "SELECT location FROM restaurant WHERE id = $1",
+            restaurant_id
+        )
+        .fetch_one(pool)
+        .await
+        .expect("Failed to fetch restaurant location")
+    }
+
+    async fn event_location(pool: &PgPool, event_id: i32) -> Option<String> {
+        sqlx::query_scalar!("SELECT location FROM event WHERE id = $1", event_id)
+            .fetch_one(pool)
+            .await
+            .expect("Failed to fetch event location")
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_create_location() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(locations::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/locations")
+            .set_json(serde_json::json!({ "name": "Greenwood" }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+
+        let req = test::TestRequest::get().uri("/locations").to_request();
+        let locations: Vec<Location> = test::call_and_read_body_json(&app, req).await;
+        assert!(locations.iter().any(|l| l.name == "Greenwood"));
+
+        // Creating it a second time conflicts
+        let req = test::TestRequest::post()
+            .uri("/locations")
+            .set_json(serde_json::json!({ "name": "Greenwood" }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 409);
+
+        let body = test::read_body(resp).await;
+        let error_response: serde_json::Value =
+            serde_json::from_slice(&body).expect("Failed to deserialize error response");
+        assert_eq!(error_response["code"], "already_exists");
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_rename_location() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(locations::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::put()
+            .uri("/locations/Ballard")
+            .set_json(serde_json::json!({ "name": "Old Ballard" }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let body = test::read_body(resp).await;
+        let location: Location =
+            serde_json::from_slice(&body).expect("Failed to deserialize location");
+        assert_eq!(location.name, "Old Ballard");
+
+        assert_eq!(
+            restaurant_location(&ctx.pool, ctx.restaurant_id).await,
+            "Old Ballard"
+        );
+        assert_eq!(
+            event_location(&ctx.pool, ctx.event_id).await.as_deref(),
+            Some("Old Ballard")
+        );
+
+        let req = test::TestRequest::get().uri("/locations").to_request();
+        let locations: Vec<Location> = test::call_and_read_body_json(&app, req).await;
+        assert!(!locations.iter().any(|l| l.name == "Ballard"));
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_rename_location_to_existing_name() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(locations::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::put()
+            .uri("/locations/Ballard")
+            .set_json(serde_json::json!({ "name": "Fremont" }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 409);
+
+        let body = test::read_body(resp).await;
+        let error_response: serde_json::Value =
+            serde_json::from_slice(&body).expect("Failed to deserialize error response");
+        assert_eq!(error_response["code"], "already_exists");
+        assert_eq!(
+            restaurant_location(&ctx.pool, ctx.restaurant_id).await,
+            "Ballard"
+        );
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_delete_unused_location() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(locations::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::delete()
+            .uri("/locations/Olympia")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let body = test::read_body(resp).await;
+        let response: serde_json::Value =
+            serde_json::from_slice(&body).expect("Failed to deserialize response");
+        assert_eq!(response["message"], "Location deleted successfully");
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_delete_location_not_found() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(locations::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::delete()
+            .uri("/locations/Atlantis")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+
+        let body = test::read_body(resp).await;
+        let error_response: serde_json::Value =
+            serde_json::from_slice(&body).expect("Failed to deserialize error response");
+        assert_eq!(error_response["error"], "Location not found");
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_delete_referenced_location() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(locations::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::delete()
+            .uri("/locations/Ballard")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 409);
+
+        let body = test::read_body(resp).await;
+        let error_response: serde_json::Value =
+            serde_json::from_slice(&body).expect("Failed to deserialize error response");
+        assert_eq!(error_response["code"], "still_referenced");
+
+        let restaurants = error_response["references"]["restaurants"]
+            .as_array()
+            .expect("Expected restaurant references");
+        assert_eq!(restaurants.len(), 1);
+        assert_eq!(restaurants[0]["id"], ctx.restaurant_id);
+        assert_eq!(restaurants[0]["label"], "Taco Truck");
+
+        let events = error_response["references"]["events"]
+            .as_array()
+            .expect("Expected event references");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["id"], ctx.event_id);
+        assert_eq!(events[0]["label"], "2024-01-15 Running");
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_delete_location_merge_into() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(locations::configure),
+        )
+        .await;
+
+        sqlx::query("INSERT INTO template (id, name, kind, body) VALUES (1, 'Run', 'event', $1)")
+            .bind(serde_json::json!({ "activity_id": 1, "location": "Ballard", "people_ids": [] }))
+            .execute(&ctx.pool)
+            .await
+            .expect("Failed to insert template");
+
+        let req = test::TestRequest::delete()
+            .uri("/locations/Ballard?merge_into=Fremont")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        assert_eq!(
+            restaurant_location(&ctx.pool, ctx.restaurant_id).await,
+            "Fremont"
+        );
+        assert_eq!(
+            event_location(&ctx.pool, ctx.event_id).await.as_deref(),
+            Some("Fremont")
+        );
+
+        // Templates confirmed later use the merged location too
+        let body: serde_json::Value = sqlx::query_scalar("SELECT body FROM template WHERE id = 1")
+            .fetch_one(&ctx.pool)
+            .await
+            .expect("Failed to fetch template");
+        assert_eq!(body["location"], "Fremont");
+        assert_eq!(body["activity_id"], 1);
+
+        // Merging into an unknown location is rejected without touching anything
+        let req = test::TestRequest::delete()
+            .uri("/locations/Fremont?merge_into=Atlantis")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+
+        let body = test::read_body(resp).await;
+        let error_response: serde_json::Value =
+            serde_json::from_slice(&body).expect("Failed to deserialize error response");
+        assert_eq!(error_response["field"], "merge_into");
+
+        teardown_test_context(ctx).await;
+    }
+}
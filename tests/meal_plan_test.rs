@@ -0,0 +1,271 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use xnote::config::database;
+    use xnote::handlers::{meal_plans, shopping_list};
+    use xnote::models::ingredient::ShoppingItem;
+    use xnote::models::meal::CreateMealFoodSource;
+    use xnote::models::meal_plan::{MealPlan, PlanStatus};
+    use xnote::models::restaurant::Visit;
+
+    struct TestContext {
+        pool: PgPool,
+    }
+
+    async fn create_test_database_pool() -> PgPool {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let last_slash = database_url
+            .rfind('/')
+            .expect("DATABASE_URL must be a valid connection string");
+        let database_url = format!("{}/xnote_test", &database_url[..last_slash]);
+        PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect to test database")
+    }
+
+    async fn create_schema(pool: &PgPool) {
+        database::run_migrations(pool)
+            .await
+            .expect("Failed to run migrations");
+    }
+
+    async fn cleanup_database(pool: &PgPool) {
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
+    }
+
+    async fn setup_test_context() -> TestContext {
+        let pool = create_test_database_pool().await;
+        // Drop leftover state from previous runs so migrations apply from scratch
+        cleanup_database(&pool).await;
+        create_schema(&pool).await;
+
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO people (id, name) VALUES (1, 'Amy'), (2, 'Bob');
+            INSERT INTO recipe (id, name, ingredients, procedure) VALUES (1, 'Fried rice', 'rice', 'fry');
+            INSERT INTO recipe_ingredient (recipe, position, quantity, unit, item) VALUES (1, 0, 300, 'g', 'rice');
+            INSERT INTO restaurant (id, name, location, type) VALUES (1, 'Taqueria', 'Fremont', 'mexican');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .expect("Failed to insert test data");
+
+        TestContext { pool }
+    }
+
+    async fn teardown_test_context(ctx: TestContext) {
+        cleanup_database(&ctx.pool).await;
+        ctx.pool.close().await;
+    }
+
+    macro_rules! init_app {
+        ($ctx:expr) => {
+            test::init_service(
+                App::new()
+                    .app_data(web::Data::new($ctx.pool.clone()))
+                    .configure(meal_plans::configure)
+                    .configure(shopping_list::configure),
+            )
+            .await
+        };
+    }
+
+    fn plan(date: &str) -> serde_json::Value {
+        serde_json::json!({
+            "date": date,
+            "time": "dinner",
+            "notes": "Before the movie",
+            "food_sources": [
+                { "type": "recipe", "recipe_id": 1, "meal_type": "cooked" },
+                { "type": "restaurant", "restaurant_id": 1, "meal_type": "takeout" }
+            ],
+            "people_ids": [2, 1]
+        })
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_create_and_list_meal_plans() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let req = test::TestRequest::post()
+            .uri("/meal-plans")
+            .set_json(plan("2100-01-01"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let future: MealPlan = test::read_body_json(resp).await;
+        assert_eq!(future.status, PlanStatus::Planned);
+        assert_eq!(future.people_ids, vec![1, 2]);
+        assert_eq!(
+            future.food_sources.0,
+            vec![
+                CreateMealFoodSource::Recipe {
+                    recipe_id: 1,
+                    meal_type: "cooked".to_string()
+                },
+                CreateMealFoodSource::Restaurant {
+                    restaurant_id: 1,
+                    meal_type: "takeout".to_string(),
+                    visit: Visit::default()
+                },
+            ]
+        );
+
+        // The single food source body of meals works for plans too
+        let req = test::TestRequest::post()
+            .uri("/meal-plans")
+            .set_json(serde_json::json!({
+                "date": "2000-01-01",
+                "time": "lunch",
+                "notes": null,
+                "food_source": { "type": "recipe", "recipe_id": 1, "meal_type": "cooked" },
+                "people_ids": []
+            }))
+            .to_request();
+        let past: MealPlan = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(past.status, PlanStatus::Skipped);
+
+        let req = test::TestRequest::get().uri("/meal-plans").to_request();
+        let plans: Vec<MealPlan> = test::call_and_read_body_json(&app, req).await;
+        let ids: Vec<i32> = plans.iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec![future.id, past.id]);
+
+        let req = test::TestRequest::get()
+            .uri("/meal-plans/skipped")
+            .to_request();
+        let skipped: Vec<MealPlan> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].id, past.id);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_invalid_meal_plans() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let mut body = plan("2100-01-01");
+        body["food_sources"] = serde_json::json!([]);
+        let req = test::TestRequest::post()
+            .uri("/meal-plans")
+            .set_json(body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        let mut body = plan("2100-01-01");
+        body["food_sources"][0]["recipe_id"] = serde_json::json!(99);
+        let req = test::TestRequest::post()
+            .uri("/meal-plans")
+            .set_json(body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+        let error: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(error["field"], "food_sources.recipe_id");
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM meal_plan")
+            .fetch_one(&ctx.pool)
+            .await
+            .expect("Failed to count plans");
+        assert_eq!(count, 0);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_mark_meal_plan_eaten() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let req = test::TestRequest::post()
+            .uri("/meal-plans")
+            .set_json(plan("2100-01-01"))
+            .to_request();
+        let planned: MealPlan = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/meal-plans/{}/eaten?date=2100-01-02", planned.id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let meal: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(meal["date"], "2100-01-02");
+        assert_eq!(meal["time"], "dinner");
+
+        let meal_id = meal["id"].as_i64().unwrap() as i32;
+        let links: (i64, i64, i64) = sqlx::query_as(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM meal_recipe WHERE meal = $1),
+                (SELECT COUNT(*) FROM meal_restaurant WHERE meal = $1),
+                (SELECT COUNT(*) FROM meal_people WHERE meal = $1)
+            "#,
+        )
+        .bind(meal_id)
+        .fetch_one(&ctx.pool)
+        .await
+        .expect("Failed to count meal links");
+        assert_eq!(links, (1, 1, 2));
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/meal-plans/{}", planned.id))
+            .to_request();
+        let eaten: MealPlan = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(eaten.status, PlanStatus::Eaten);
+        assert_eq!(eaten.meal_id, Some(meal_id));
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/meal-plans/{}/eaten", planned.id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 409);
+
+        let req = test::TestRequest::post()
+            .uri("/meal-plans/999/eaten")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_shopping_list_includes_plans() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        for date in ["2100-01-01", "2100-01-03"] {
+            let req = test::TestRequest::post()
+                .uri("/meal-plans")
+                .set_json(plan(date))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 201);
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/shopping-list?start_date=2100-01-01&end_date=2100-01-07")
+            .to_request();
+        let items: Vec<ShoppingItem> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].item, "rice");
+        assert_eq!(items[0].quantity, Some(600.0));
+
+        teardown_test_context(ctx).await;
+    }
+}
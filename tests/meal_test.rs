@@ -1,8 +1,12 @@
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 #[cfg(test)]
 mod tests {
     use actix_web::{test, web, App};
+    use rust_decimal::Decimal;
     use serial_test::serial;
     use sqlx::PgPool;
+    use xnote::config::database;
     use xnote::handlers::meals;
     use xnote::models::detail::{MealDetail, MealFoodSource};
 
@@ -27,49 +31,22 @@ mod tests {
     }
 
     async fn create_schema(pool: &PgPool) {
-        let schema_sql = include_str!("../init.sql");
-        sqlx::raw_sql(schema_sql)
-            .execute(pool)
+        database::run_migrations(pool)
             .await
-            .expect("Failed to create schema");
+            .expect("Failed to run migrations");
     }
 
     async fn cleanup_database(pool: &PgPool) {
-        let tables = vec![
-            "drink_people",
-            "drink",
-            "drink_option",
-            "event_people",
-            "event",
-            "activity",
-            "activity_type",
-            "meal_people",
-            "meal_restaurant",
-            "meal_product",
-            "meal_recipe",
-            "meal",
-            "meal_time",
-            "meal_type",
-            "people",
-            "restaurant",
-            "product",
-            "recipe",
-            "location",
-            "food_type",
-        ];
-
-        for table in tables {
-            let query = format!("DROP TABLE IF EXISTS {} CASCADE", table);
-            sqlx::query(&query)
-                .execute(pool)
-                .await
-                .expect(&format!("Failed to drop table {}", table));
-        }
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
     }
 
     async fn setup_test_context() -> TestContext {
         let pool = create_test_database_pool().await;
-        // Drop leftover state from previous runs; init.sql inserts are not idempotent
+        // Drop leftover state from previous runs so migrations apply from scratch
         cleanup_database(&pool).await;
         create_schema(&pool).await;
 
@@ -95,7 +72,7 @@ mod tests {
         // Insert restaurant
         let restaurant_id: i32 = sqlx::query_scalar!(
             "INSERT INTO restaurant (name, location, type, price) VALUES ($1, $2, $3, $4) RETURNING id",
-            "Test Restaurant", "Seattle Downtown", "Italian", Some(25.50)
+            "Test Restaurant", "Seattle Downtown", "Italian", Some(Decimal::new(2550, 2))
         )
         .fetch_one(&pool)
         .await
@@ -252,16 +229,17 @@ mod tests {
         assert_eq!(meal_detail.notes, Some("Great dinner".to_string()));
         assert_eq!(meal_detail.people.len(), 2);
 
-        match meal_detail.food_source {
+        match meal_detail.food_sources.into_iter().next() {
             Some(MealFoodSource::Restaurant {
                 restaurant,
                 meal_type,
+                ..
             }) => {
                 assert_eq!(restaurant.id, ctx.restaurant_id);
                 assert_eq!(restaurant.name, "Test Restaurant");
                 assert_eq!(restaurant.location, "Seattle Downtown");
                 assert_eq!(restaurant.food_type, "Italian");
-                assert_eq!(restaurant.price, Some(25.50));
+                assert_eq!(restaurant.price, Some(Decimal::new(2550, 2)));
                 assert_eq!(meal_type, "dine-in");
             }
             _ => panic!("Expected restaurant food source"),
@@ -299,7 +277,7 @@ mod tests {
         assert_eq!(meal_detail.people.len(), 1);
         assert_eq!(meal_detail.people[0].name, "Alice");
 
-        match meal_detail.food_source {
+        match meal_detail.food_sources.into_iter().next() {
             Some(MealFoodSource::Recipe { recipe, meal_type }) => {
                 assert_eq!(recipe.id, ctx.recipe_id);
                 assert_eq!(recipe.name, "Test Recipe");
@@ -342,7 +320,7 @@ mod tests {
         assert_eq!(meal_detail.notes, Some("Quick breakfast".to_string()));
         assert_eq!(meal_detail.people.len(), 0);
 
-        match meal_detail.food_source {
+        match meal_detail.food_sources.into_iter().next() {
             Some(MealFoodSource::Product { product, meal_type }) => {
                 assert_eq!(product.name, "Test Product");
                 assert_eq!(meal_type, "manufactured");
@@ -522,6 +500,7 @@ mod tests {
         let error_response: serde_json::Value =
             serde_json::from_slice(&body).expect("Failed to deserialize error response");
         assert_eq!(error_response["error"], "Meal not found");
+        assert_eq!(error_response["code"], "not_found");
 
         teardown_test_context(ctx).await;
     }
@@ -630,4 +609,278 @@ mod tests {
 
         teardown_test_context(ctx).await;
     }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_create_meal_unknown_time() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(meals::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/meals")
+            .set_json(serde_json::json!({
+                "date": "2024-01-18",
+                "time": "brunch",
+                "food_source": {
+                    "type": "recipe",
+                    "recipe_id": ctx.recipe_id,
+                    "meal_type": "cooked"
+                },
+                "people_ids": []
+            }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+
+        let body = test::read_body(resp).await;
+        let error_response: serde_json::Value =
+            serde_json::from_slice(&body).expect("Failed to deserialize error response");
+        assert_eq!(error_response["code"], "invalid_reference");
+        assert_eq!(error_response["field"], "time");
+        assert_eq!(error_response["error"], "Unknown time 'brunch'");
+
+        // The failed insert must not leave a partial meal behind
+        let meal_count = sqlx::query_scalar!("SELECT COUNT(*) FROM meal")
+            .fetch_one(&ctx.pool)
+            .await
+            .expect("Failed to count meals");
+        assert_eq!(meal_count, Some(3));
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_create_meal_unknown_person() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(meals::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/meals")
+            .set_json(serde_json::json!({
+                "date": "2024-01-18",
+                "time": "dinner",
+                "food_source": {
+                    "type": "restaurant",
+                    "restaurant_id": ctx.restaurant_id,
+                    "meal_type": "dine-in"
+                },
+                "people_ids": [99999]
+            }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+
+        let body = test::read_body(resp).await;
+        let error_response: serde_json::Value =
+            serde_json::from_slice(&body).expect("Failed to deserialize error response");
+        assert_eq!(error_response["code"], "invalid_reference");
+        assert_eq!(error_response["field"], "people_ids");
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_get_meals_paginated() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(meals::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/meals?limit=2").to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        let next_cursor = resp
+            .headers()
+            .get("X-Next-Cursor")
+            .expect("Expected a next cursor")
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let body = test::read_body(resp).await;
+        let meals: Vec<xnote::models::meal::Meal> =
+            serde_json::from_slice(&body).expect("Failed to deserialize meals list");
+        assert_eq!(meals.len(), 2);
+        assert_eq!(meals[0].id, ctx.meal3_id);
+        assert_eq!(meals[1].id, ctx.meal2_id);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/meals?limit=2&cursor={}", next_cursor))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        assert!(resp.headers().get("X-Next-Cursor").is_none());
+
+        let body = test::read_body(resp).await;
+        let meals: Vec<xnote::models::meal::Meal> =
+            serde_json::from_slice(&body).expect("Failed to deserialize meals list");
+        assert_eq!(meals.len(), 1);
+        assert_eq!(meals[0].id, ctx.meal1_id);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_get_meals_filtered() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(meals::configure),
+        )
+        .await;
+
+        let bob_id: i32 = sqlx::query_scalar!("SELECT id FROM people WHERE name = 'Bob'")
+            .fetch_one(&ctx.pool)
+            .await
+            .expect("Failed to fetch Bob");
+
+        let cases = [
+            (format!("/meals?person_id={}", bob_id), vec![ctx.meal1_id]),
+            ("/meals?meal_type=cooked".to_string(), vec![ctx.meal2_id]),
+            (
+                "/meals?location=Seattle%20Downtown".to_string(),
+                vec![ctx.meal1_id],
+            ),
+            ("/meals?meal_time=breakfast".to_string(), vec![ctx.meal3_id]),
+            (
+                "/meals?start_date=2024-01-16&sort=asc".to_string(),
+                vec![ctx.meal2_id, ctx.meal3_id],
+            ),
+        ];
+
+        for (uri, expected_ids) in cases {
+            let req = test::TestRequest::get().uri(&uri).to_request();
+            let meals: Vec<xnote::models::meal::Meal> =
+                test::call_and_read_body_json(&app, req).await;
+            let ids: Vec<i32> = meals.iter().map(|m| m.id).collect();
+            assert_eq!(ids, expected_ids, "unexpected meals for {}", uri);
+        }
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_get_meals_invalid_list_query() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(meals::configure),
+        )
+        .await;
+
+        for uri in ["/meals?activity_type=sport", "/meals?limit=2&cursor=bogus"] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 400, "expected 400 for {}", uri);
+        }
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_create_meal_with_several_food_sources() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(meals::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/meals")
+            .set_json(serde_json::json!({
+                "date": "2024-01-18",
+                "time": "dinner",
+                "notes": null,
+                "food_sources": [
+                    {
+                        "type": "restaurant",
+                        "restaurant_id": ctx.restaurant_id,
+                        "meal_type": "takeout"
+                    },
+                    {
+                        "type": "recipe",
+                        "recipe_id": ctx.recipe_id,
+                        "meal_type": "cooked"
+                    }
+                ],
+                "people_ids": []
+            }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let body = test::read_body(resp).await;
+        let response: serde_json::Value =
+            serde_json::from_slice(&body).expect("Failed to deserialize response");
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/meals/{}/details", response["id"]))
+            .to_request();
+        let meal_detail: MealDetail = test::call_and_read_body_json(&app, req).await;
+
+        match meal_detail.food_sources.as_slice() {
+            [MealFoodSource::Recipe {
+                recipe,
+                meal_type: recipe_type,
+            }, MealFoodSource::Restaurant {
+                restaurant,
+                meal_type: restaurant_type,
+                ..
+            }] => {
+                assert_eq!(recipe.id, ctx.recipe_id);
+                assert_eq!(recipe_type, "cooked");
+                assert_eq!(restaurant.id, ctx.restaurant_id);
+                assert_eq!(restaurant_type, "takeout");
+            }
+            other => panic!("Expected recipe and restaurant, got {:?}", other),
+        }
+
+        // A meal without any food source is rejected
+        let req = test::TestRequest::put()
+            .uri(&format!("/meals/{}", response["id"]))
+            .set_json(serde_json::json!({
+                "date": "2024-01-18",
+                "time": "dinner",
+                "notes": null,
+                "food_sources": [],
+                "people_ids": []
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        teardown_test_context(ctx).await;
+    }
 }
@@ -0,0 +1,292 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use xnote::config::database;
+    use xnote::handlers::{people, recipes, restaurants};
+    use xnote::models::history::{HistoryAction, HistoryEntry};
+    use xnote::models::merge::{DuplicatePair, MergeReport};
+
+    struct TestContext {
+        pool: PgPool,
+    }
+
+    async fn create_test_database_pool() -> PgPool {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let last_slash = database_url
+            .rfind('/')
+            .expect("DATABASE_URL must be a valid connection string");
+        let database_url = format!("{}/xnote_test", &database_url[..last_slash]);
+        PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect to test database")
+    }
+
+    async fn create_schema(pool: &PgPool) {
+        database::run_migrations(pool)
+            .await
+            .expect("Failed to run migrations");
+    }
+
+    async fn cleanup_database(pool: &PgPool) {
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
+    }
+
+    /// Two records of Alice, and two of Ramen Danbo and two recipes of fried
+    /// rice, each pair sharing a meal and more.
+    async fn setup_test_context() -> TestContext {
+        let pool = create_test_database_pool().await;
+        // Drop leftover state from previous runs so migrations apply from scratch
+        cleanup_database(&pool).await;
+        create_schema(&pool).await;
+
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO location (name) VALUES ('Seattle');
+            INSERT INTO food_type (name) VALUES ('tex-mex');
+            INSERT INTO people (id, name, notes, household_order) VALUES
+                (1, 'Alice', NULL, 1), (2, 'alice ', 'vegetarian', NULL), (3, 'Bob', NULL, NULL);
+            INSERT INTO people_alias (alias, people) VALUES ('al', 2);
+            INSERT INTO restaurant (id, name, location, type) VALUES
+                (1, 'Ramen Danbo', 'Seattle', 'mexican'),
+                (2, 'ramen  danbo', 'Seattle', 'mexican'),
+                (3, 'Taco Spot', 'Fremont', 'mexican'),
+                (4, 'Taco Shop', 'Fremont', 'mexican'),
+                (5, 'Taco Shop', 'Seattle', 'tex-mex');
+            INSERT INTO recipe (id, name, ingredients, procedure) VALUES
+                (1, 'Fried rice', '', ''), (2, 'Fried Rice', 'rice', 'fry');
+            INSERT INTO recipe_ingredient (recipe, position, item) VALUES (2, 1, 'rice');
+            INSERT INTO meal (id, date, "time") VALUES
+                (100, '2024-03-01', 'lunch'), (101, '2024-03-02', 'dinner');
+            INSERT INTO meal_people (meal, people) VALUES (100, 1), (100, 2), (101, 2);
+            INSERT INTO meal_restaurant (meal, restaurant, type, amount) VALUES
+                (100, 1, 'dine-in', 20), (100, 2, 'dine-in', 30), (101, 2, 'takeout', 12);
+            INSERT INTO meal_recipe (meal, recipe, type) VALUES (101, 2, 'cooked');
+            INSERT INTO activity (id, name, type) VALUES (1, 'Hike', 'sport');
+            INSERT INTO event (id, date, activity) VALUES (100, '2024-03-01', 1);
+            INSERT INTO event_people (event, people) VALUES (100, 2);
+            INSERT INTO template (id, name, kind, body) VALUES
+                (1, 'Lunch with Alice', 'meal',
+                 '{"time": "lunch", "food_sources": [{"type": "restaurant", "restaurant_id": 2, "meal_type": "dine-in"}], "people_ids": [1, 2, 3]}');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .expect("Failed to insert test data");
+
+        TestContext { pool }
+    }
+
+    async fn teardown_test_context(ctx: TestContext) {
+        cleanup_database(&ctx.pool).await;
+        ctx.pool.close().await;
+    }
+
+    macro_rules! init_app {
+        ($ctx:expr) => {
+            test::init_service(
+                App::new()
+                    .app_data(web::Data::new($ctx.pool.clone()))
+                    .configure(people::configure)
+                    .configure(recipes::configure)
+                    .configure(restaurants::configure),
+            )
+            .await
+        };
+    }
+
+    async fn ids(ctx: &TestContext, sql: &str) -> Vec<i32> {
+        sqlx::query_scalar(sql)
+            .fetch_all(&ctx.pool)
+            .await
+            .expect("Failed to fetch ids")
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_duplicates() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let req = test::TestRequest::get()
+            .uri("/restaurants/duplicates")
+            .to_request();
+        let pairs: Vec<DuplicatePair> = test::call_and_read_body_json(&app, req).await;
+        let found: Vec<(i32, i32)> = pairs
+            .iter()
+            .map(|pair| (pair.first.id, pair.second.id))
+            .collect();
+        // Case and spacing do not count; similar names need a shared location or type
+        assert_eq!(found[0], (1, 2));
+        assert_eq!(pairs[0].similarity, 1.0);
+        assert_eq!(pairs[0].shared, vec!["location", "type"]);
+        assert!(found.contains(&(3, 4)));
+        assert!(found.contains(&(4, 5)));
+        assert!(!found.contains(&(3, 5)));
+
+        let req = test::TestRequest::get()
+            .uri("/people/duplicates")
+            .to_request();
+        let pairs: Vec<DuplicatePair> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(pairs.len(), 1);
+        assert_eq!((pairs[0].first.id, pairs[0].second.id), (1, 2));
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_merge_people() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let req = test::TestRequest::post()
+            .uri("/people/merge")
+            .set_json(serde_json::json!({ "survivor_id": 1, "duplicate_ids": [2] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let report: MergeReport = test::read_body_json(resp).await;
+        assert_eq!(report.merged_ids, vec![2]);
+        // Meal 101, the event and the alias move; Alice was already at meal 100
+        assert_eq!(report.moved, 3);
+        assert_eq!(report.dropped, 1);
+        assert_eq!(report.templates, 1);
+
+        assert_eq!(
+            ids(
+                &ctx,
+                "SELECT meal FROM meal_people WHERE people = 1 ORDER BY meal"
+            )
+            .await,
+            vec![100, 101]
+        );
+        assert_eq!(
+            ids(&ctx, "SELECT event FROM event_people WHERE people = 1").await,
+            vec![100]
+        );
+        assert_eq!(
+            ids(&ctx, "SELECT people FROM people_alias WHERE alias = 'al'").await,
+            vec![1]
+        );
+        assert!(ids(&ctx, "SELECT id FROM people WHERE id = 2")
+            .await
+            .is_empty());
+
+        let (notes, household_order): (Option<String>, Option<i32>) =
+            sqlx::query_as("SELECT notes, household_order FROM people WHERE id = 1")
+                .fetch_one(&ctx.pool)
+                .await
+                .unwrap();
+        assert_eq!(notes.as_deref(), Some("vegetarian"));
+        assert_eq!(household_order, Some(1));
+
+        let body: serde_json::Value = sqlx::query_scalar("SELECT body FROM template WHERE id = 1")
+            .fetch_one(&ctx.pool)
+            .await
+            .unwrap();
+        assert_eq!(body["people_ids"], serde_json::json!([1, 3]));
+        assert_eq!(body["food_sources"][0]["restaurant_id"], 2);
+
+        // Rewired records are logged, so the merge can be traced and undone
+        let entries: Vec<HistoryEntry> = sqlx::query_as(
+            "SELECT id, entity, entity_id, action, actor, changed_at, before, after FROM history ORDER BY id",
+        )
+        .fetch_all(&ctx.pool)
+        .await
+        .unwrap();
+        let logged: Vec<(String, i32, HistoryAction)> = entries
+            .iter()
+            .map(|entry| {
+                (
+                    serde_json::to_value(entry.entity)
+                        .unwrap()
+                        .as_str()
+                        .unwrap()
+                        .to_string(),
+                    entry.entity_id,
+                    entry.action,
+                )
+            })
+            .collect();
+        assert!(logged.contains(&("person".to_string(), 2, HistoryAction::Purge)));
+        assert!(logged.contains(&("meal".to_string(), 101, HistoryAction::Update)));
+        assert!(logged.contains(&("event".to_string(), 100, HistoryAction::Update)));
+        assert!(logged.contains(&("template".to_string(), 1, HistoryAction::Update)));
+
+        let req = test::TestRequest::post()
+            .uri("/people/merge")
+            .set_json(serde_json::json!({ "survivor_id": 1, "duplicate_ids": [2] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+
+        let req = test::TestRequest::post()
+            .uri("/people/merge")
+            .set_json(serde_json::json!({ "survivor_id": 1, "duplicate_ids": [1, 3] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_merge_food_sources() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let req = test::TestRequest::post()
+            .uri("/restaurants/merge")
+            .set_json(serde_json::json!({ "survivor_id": 1, "duplicate_ids": [2] }))
+            .to_request();
+        let report: MergeReport = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(report.moved, 1);
+        assert_eq!(report.dropped, 1);
+
+        // The survivor's visit is kept where both were at the same meal
+        let visits: Vec<(i32, String, Option<rust_decimal::Decimal>)> = sqlx::query_as(
+            "SELECT meal, type, amount FROM meal_restaurant WHERE restaurant = 1 ORDER BY meal",
+        )
+        .fetch_all(&ctx.pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            visits,
+            vec![
+                (100, "dine-in".to_string(), Some(20.into())),
+                (101, "takeout".to_string(), Some(12.into())),
+            ]
+        );
+        let body: serde_json::Value = sqlx::query_scalar("SELECT body FROM template WHERE id = 1")
+            .fetch_one(&ctx.pool)
+            .await
+            .unwrap();
+        assert_eq!(body["food_sources"][0]["restaurant_id"], 1);
+
+        // A survivor without ingredients takes the duplicate's
+        let req = test::TestRequest::post()
+            .uri("/recipes/merge")
+            .set_json(serde_json::json!({ "survivor_id": 1, "duplicate_ids": [2] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(
+            ids(&ctx, "SELECT recipe FROM recipe_ingredient").await,
+            vec![1]
+        );
+        assert_eq!(
+            ids(&ctx, "SELECT meal FROM meal_recipe WHERE recipe = 1").await,
+            vec![101]
+        );
+
+        teardown_test_context(ctx).await;
+    }
+}
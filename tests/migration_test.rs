@@ -0,0 +1,130 @@
+#[cfg(test)]
+mod tests {
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use xnote::config::database;
+
+    async fn create_test_database_pool() -> PgPool {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let last_slash = database_url
+            .rfind('/')
+            .expect("DATABASE_URL must be a valid connection string");
+        let database_url = format!("{}/xnote_test", &database_url[..last_slash]);
+        PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect to test database")
+    }
+
+    async fn cleanup_database(pool: &PgPool) {
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
+    }
+
+    async fn seed_counts(pool: &PgPool) -> Vec<i64> {
+        let mut counts = Vec::new();
+        for table in [
+            "location",
+            "food_type",
+            "meal_time",
+            "meal_type",
+            "activity_type",
+            "drink_option",
+        ] {
+            let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+                .fetch_one(pool)
+                .await
+                .unwrap_or_else(|_| panic!("Failed to count {}", table));
+            counts.push(count);
+        }
+        counts
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_migrations_are_recorded() {
+        let pool = create_test_database_pool().await;
+        cleanup_database(&pool).await;
+
+        database::run_migrations(&pool)
+            .await
+            .expect("Failed to run migrations");
+
+        let versions: Vec<i64> = sqlx::query_scalar(
+            "SELECT version FROM _sqlx_migrations WHERE success ORDER BY version",
+        )
+        .fetch_all(&pool)
+        .await
+        .expect("Failed to read migration versions");
+        let expected: Vec<i64> = database::MIGRATOR.iter().map(|m| m.version).collect();
+        assert_eq!(versions, expected);
+
+        let meal_times: Vec<String> =
+            sqlx::query_scalar("SELECT name FROM meal_time ORDER BY name")
+                .fetch_all(&pool)
+                .await
+                .expect("Failed to read meal times");
+        assert_eq!(meal_times, vec!["breakfast", "dinner", "lunch"]);
+
+        cleanup_database(&pool).await;
+        pool.close().await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_running_migrations_twice_is_noop() {
+        let pool = create_test_database_pool().await;
+        cleanup_database(&pool).await;
+
+        database::run_migrations(&pool)
+            .await
+            .expect("Failed to run migrations");
+        let counts_after_first_run = seed_counts(&pool).await;
+
+        database::run_migrations(&pool)
+            .await
+            .expect("Failed to rerun migrations");
+        let counts_after_second_run = seed_counts(&pool).await;
+
+        assert_eq!(counts_after_first_run, counts_after_second_run);
+
+        let applied: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM _sqlx_migrations")
+            .fetch_one(&pool)
+            .await
+            .expect("Failed to count applied migrations");
+        assert_eq!(applied as usize, database::MIGRATOR.iter().count());
+
+        cleanup_database(&pool).await;
+        pool.close().await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_migrations_adopt_database_created_without_version_table() {
+        let pool = create_test_database_pool().await;
+        cleanup_database(&pool).await;
+
+        // Databases created from the old one-shot init.sql already have the
+        // schema and seed rows but no _sqlx_migrations table.
+        database::run_migrations(&pool)
+            .await
+            .expect("Failed to run migrations");
+        let expected_counts = seed_counts(&pool).await;
+        sqlx::query("DROP TABLE _sqlx_migrations")
+            .execute(&pool)
+            .await
+            .expect("Failed to drop migrations table");
+
+        database::run_migrations(&pool)
+            .await
+            .expect("Failed to adopt existing database");
+
+        assert_eq!(seed_counts(&pool).await, expected_counts);
+
+        cleanup_database(&pool).await;
+        pool.close().await;
+    }
+}
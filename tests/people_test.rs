@@ -1,8 +1,11 @@
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 #[cfg(test)]
 mod tests {
     use actix_web::{test, web, App};
     use serial_test::serial;
     use sqlx::PgPool;
+    use xnote::config::database;
     use xnote::handlers::people;
 
     struct TestContext {
@@ -24,49 +27,22 @@ mod tests {
     }
 
     async fn create_schema(pool: &PgPool) {
-        let schema_sql = include_str!("../init.sql");
-        sqlx::raw_sql(schema_sql)
-            .execute(pool)
+        database::run_migrations(pool)
             .await
-            .expect("Failed to create schema");
+            .expect("Failed to run migrations");
     }
 
     async fn cleanup_database(pool: &PgPool) {
-        let tables = vec![
-            "drink_people",
-            "drink",
-            "drink_option",
-            "event_people",
-            "event",
-            "activity",
-            "activity_type",
-            "meal_people",
-            "meal_restaurant",
-            "meal_product",
-            "meal_recipe",
-            "meal",
-            "meal_time",
-            "meal_type",
-            "people",
-            "restaurant",
-            "product",
-            "recipe",
-            "location",
-            "food_type",
-        ];
-
-        for table in tables {
-            let query = format!("DROP TABLE IF EXISTS {} CASCADE", table);
-            sqlx::query(&query)
-                .execute(pool)
-                .await
-                .expect(&format!("Failed to drop table {}", table));
-        }
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
     }
 
     async fn setup_test_context() -> TestContext {
         let pool = create_test_database_pool().await;
-        // Drop leftover state from previous runs; init.sql inserts are not idempotent
+        // Drop leftover state from previous runs so migrations apply from scratch
         cleanup_database(&pool).await;
         create_schema(&pool).await;
 
@@ -315,4 +291,362 @@ mod tests {
 
         teardown_test_context(ctx).await;
     }
+
+    /// Give Alice two meals at the same restaurant, a cooked meal, a hike, a drink
+    /// and a board game night that Bob was not part of.
+    async fn seed_shared_history(ctx: &TestContext) {
+        sqlx::raw_sql(&format!(
+            r#"
+            INSERT INTO restaurant (name, location, type) VALUES ('Taco Spot', 'Fremont', 'mexican');
+            INSERT INTO activity (name, type) VALUES ('Hike', 'sport'), ('Board games', 'side project');
+            INSERT INTO meal (date, "time", notes) VALUES
+                ('2024-01-05', 'dinner', 'tacos'),
+                ('2024-01-20', 'lunch', NULL),
+                ('2024-03-02', 'dinner', 'more tacos');
+            INSERT INTO meal_restaurant (meal, restaurant, type) VALUES (1, 1, 'dine-in'), (3, 1, 'takeout');
+            INSERT INTO meal_people (meal, people) VALUES (1, {alice}), (2, {alice}), (3, {alice}), (3, {bob});
+            INSERT INTO event (date, activity, measure, location) VALUES
+                ('2024-01-20', 1, '5 miles', 'Tiger Mountain'),
+                ('2024-02-10', 2, NULL, NULL);
+            INSERT INTO event_people (event, people) VALUES (1, {alice}), (2, {bob});
+            INSERT INTO drink (name, date) VALUES ('吃茶三千', '2024-01-06');
+            INSERT INTO drink_people (drink, people) VALUES (1, {alice});
+            "#,
+            alice = ctx.person1_id,
+            bob = ctx.person2_id,
+        ))
+        .execute(&ctx.pool)
+        .await
+        .expect("Failed to seed shared history");
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_get_person_timeline() {
+        let ctx = setup_test_context().await;
+        seed_shared_history(&ctx).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(people::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/people/{}/timeline?sort=asc", ctx.person1_id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let body = test::read_body(resp).await;
+        let items: Vec<xnote::models::people::TimelineItem> =
+            serde_json::from_slice(&body).expect("Failed to deserialize timeline");
+        let entries: Vec<(&str, &str)> = items
+            .iter()
+            .map(|item| (item.kind.as_str(), item.title.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("meal", "dinner: Taco Spot"),
+                ("drink", "吃茶三千"),
+                ("event", "Hike"),
+                ("meal", "lunch"),
+                ("meal", "dinner: Taco Spot"),
+            ]
+        );
+        assert_eq!(items[2].detail.as_deref(), Some("5 miles Tiger Mountain"));
+        assert_eq!(items[2].link, "/api/v1/events/1/details");
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_get_person_timeline_paginated() {
+        let ctx = setup_test_context().await;
+        seed_shared_history(&ctx).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(people::configure),
+        )
+        .await;
+
+        let mut dates = Vec::new();
+        let mut uri = format!("/people/{}/timeline?limit=2", ctx.person1_id);
+        loop {
+            let req = test::TestRequest::get().uri(&uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 200);
+
+            let next_cursor = resp
+                .headers()
+                .get("X-Next-Cursor")
+                .map(|value| value.to_str().unwrap().to_string());
+            let body = test::read_body(resp).await;
+            let items: Vec<xnote::models::people::TimelineItem> =
+                serde_json::from_slice(&body).expect("Failed to deserialize timeline");
+            assert!(items.len() <= 2);
+            dates.extend(items.into_iter().map(|item| item.date.to_string()));
+
+            match next_cursor {
+                Some(cursor) => {
+                    uri = format!(
+                        "/people/{}/timeline?limit=2&cursor={}",
+                        ctx.person1_id, cursor
+                    )
+                }
+                None => break,
+            }
+        }
+
+        assert_eq!(
+            dates,
+            vec![
+                "2024-03-02",
+                "2024-01-20",
+                "2024-01-20",
+                "2024-01-06",
+                "2024-01-05"
+            ]
+        );
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_get_person_summary() {
+        let ctx = setup_test_context().await;
+        seed_shared_history(&ctx).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(people::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/people/{}/summary", ctx.person1_id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let body = test::read_body(resp).await;
+        let summary: serde_json::Value =
+            serde_json::from_slice(&body).expect("Failed to deserialize summary");
+
+        assert_eq!(summary["person"]["name"], "Alice");
+        assert_eq!(summary["first_seen"], "2024-01-05");
+        assert_eq!(summary["last_seen"], "2024-03-02");
+        assert_eq!(summary["total_meals"], 3);
+        assert_eq!(summary["total_events"], 1);
+        assert_eq!(summary["total_drinks"], 1);
+        assert_eq!(
+            summary["by_month"],
+            serde_json::json!([
+                { "month": "2024-01-01", "meals": 2, "events": 1 },
+                { "month": "2024-03-01", "meals": 1, "events": 0 },
+            ])
+        );
+        assert_eq!(summary["favorite_restaurants"][0]["name"], "Taco Spot");
+        assert_eq!(summary["favorite_restaurants"][0]["visits"], 2);
+        assert_eq!(summary["favorite_activities"][0]["name"], "Hike");
+        assert_eq!(summary["favorite_activities"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            summary["longest_gap"],
+            serde_json::json!({ "from": "2024-01-20", "to": "2024-03-02", "days": 42 })
+        );
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_get_person_summary_not_found() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(people::configure),
+        )
+        .await;
+
+        for path in ["summary", "timeline"] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/people/99999/{}", path))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 404);
+        }
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_update_household() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(people::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::put()
+            .uri("/people/household")
+            .set_json(serde_json::json!({ "people_ids": [ctx.person2_id, ctx.person1_id] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let body = test::read_body(resp).await;
+        let household: Vec<xnote::models::people::People> =
+            serde_json::from_slice(&body).expect("Failed to deserialize household");
+        let names: Vec<&str> = household.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["Bob", "Alice"]);
+        assert_eq!(household[0].household_order, Some(1));
+
+        // Replacing the household drops members that are no longer listed
+        let req = test::TestRequest::put()
+            .uri("/people/household")
+            .set_json(serde_json::json!({ "people_ids": [ctx.person1_id] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/people/{}", ctx.person2_id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let body = test::read_body(resp).await;
+        let bob: xnote::models::people::People =
+            serde_json::from_slice(&body).expect("Failed to deserialize person");
+        assert_eq!(bob.household_order, None);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_update_household_unknown_person() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(people::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::put()
+            .uri("/people/household")
+            .set_json(serde_json::json!({ "people_ids": [ctx.person1_id, 99999] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+
+        let req = test::TestRequest::put()
+            .uri("/people/household")
+            .set_json(serde_json::json!({ "people_ids": [ctx.person1_id, ctx.person1_id] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_people_aliases() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(people::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::put()
+            .uri("/people/aliases")
+            .set_json(serde_json::json!({ "alias": "the twins", "people_ids": [ctx.person1_id, ctx.person2_id] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        // Saving an alias again replaces what it stands for
+        let req = test::TestRequest::put()
+            .uri("/people/aliases")
+            .set_json(serde_json::json!({ "alias": "bobby", "people_ids": [ctx.person1_id] }))
+            .to_request();
+        test::call_service(&app, req).await;
+        let req = test::TestRequest::put()
+            .uri("/people/aliases")
+            .set_json(serde_json::json!({ "alias": "bobby", "people_ids": [ctx.person2_id] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let req = test::TestRequest::get().uri("/people/aliases").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let body = test::read_body(resp).await;
+        let aliases: Vec<xnote::models::import::PeopleAlias> =
+            serde_json::from_slice(&body).expect("Failed to deserialize aliases");
+        assert_eq!(aliases.len(), 2);
+        assert_eq!(aliases[0].alias, "bobby");
+        assert_eq!(aliases[0].people_ids, vec![ctx.person2_id]);
+        assert_eq!(aliases[1].people_ids, vec![ctx.person1_id, ctx.person2_id]);
+
+        let req = test::TestRequest::delete()
+            .uri("/people/aliases/bobby")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let req = test::TestRequest::delete()
+            .uri("/people/aliases/bobby")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_people_alias_unknown_person() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(people::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::put()
+            .uri("/people/aliases")
+            .set_json(serde_json::json!({ "alias": "ghost", "people_ids": [99999] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+
+        let req = test::TestRequest::put()
+            .uri("/people/aliases")
+            .set_json(serde_json::json!({ "alias": " ", "people_ids": [ctx.person1_id] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        teardown_test_context(ctx).await;
+    }
 }
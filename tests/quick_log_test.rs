@@ -0,0 +1,257 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use xnote::config::database;
+    use xnote::handlers::quick_log;
+    use xnote::models::meal::CreateMealFoodSource;
+    use xnote::models::quick_log::{QuickLogEntry, QuickLogPreview};
+    use xnote::models::restaurant::Visit;
+
+    struct TestContext {
+        pool: PgPool,
+    }
+
+    async fn create_test_database_pool() -> PgPool {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let last_slash = database_url
+            .rfind('/')
+            .expect("DATABASE_URL must be a valid connection string");
+        let database_url = format!("{}/xnote_test", &database_url[..last_slash]);
+        PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect to test database")
+    }
+
+    async fn create_schema(pool: &PgPool) {
+        database::run_migrations(pool)
+            .await
+            .expect("Failed to run migrations");
+    }
+
+    async fn cleanup_database(pool: &PgPool) {
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
+    }
+
+    /// Amy is the household; Bob, also known as "bobby", is a friend.
+    async fn setup_test_context() -> TestContext {
+        let pool = create_test_database_pool().await;
+        // Drop leftover state from previous runs so migrations apply from scratch
+        cleanup_database(&pool).await;
+        create_schema(&pool).await;
+
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO people (id, name, household_order) VALUES (1, 'Amy', 1), (2, 'Bob', NULL);
+            INSERT INTO people_alias (alias, people) VALUES ('bobby', 2);
+            INSERT INTO recipe (id, name, ingredients, procedure) VALUES (1, 'Fried rice', 'rice, egg', 'fry');
+            INSERT INTO product (id, name) VALUES (1, 'Instant ramen');
+            INSERT INTO restaurant (id, name, location, type) VALUES (1, 'Taco Spot', 'Fremont', 'mexican');
+            INSERT INTO activity (id, name, type) VALUES (1, 'Hike', 'sport');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .expect("Failed to insert test data");
+
+        TestContext { pool }
+    }
+
+    async fn teardown_test_context(ctx: TestContext) {
+        cleanup_database(&ctx.pool).await;
+        ctx.pool.close().await;
+    }
+
+    async fn post_quick_log(
+        ctx: &TestContext,
+        query: &str,
+        time: &str,
+        text: &str,
+    ) -> actix_web::dev::ServiceResponse {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(quick_log::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/quick-log{}", query))
+            .set_json(serde_json::json!({
+                "date": "2024-05-01",
+                "time": time,
+                "text": text
+            }))
+            .to_request();
+        test::call_service(&app, req).await
+    }
+
+    async fn count(ctx: &TestContext, table: &str) -> i64 {
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(&ctx.pool)
+            .await
+            .expect("Failed to count rows")
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_quick_log_meal() {
+        let ctx = setup_test_context().await;
+
+        let resp = post_quick_log(
+            &ctx,
+            "",
+            "lunch",
+            "fried rise, instant ramen w/ bobby @park (picnic) bento",
+        )
+        .await;
+        assert_eq!(resp.status(), 200);
+        let preview: QuickLogPreview = test::read_body_json(resp).await;
+        assert!(!preview.committed);
+        assert!(preview.issues.is_empty());
+        assert_eq!(preview.matched.len(), 2);
+        assert!(preview.matched[0].score < 1.0);
+
+        let QuickLogEntry::Meal(meal) = &preview.entries[0] else {
+            panic!("Expected a meal, got {:?}", preview.entries[0]);
+        };
+        assert_eq!(meal.time, "lunch");
+        assert_eq!(meal.notes.as_deref(), Some("picnic @park"));
+        assert_eq!(meal.people_ids, vec![1, 2]);
+        assert_eq!(
+            meal.food_sources,
+            vec![
+                CreateMealFoodSource::Recipe {
+                    recipe_id: 1,
+                    meal_type: "takeout".to_string()
+                },
+                CreateMealFoodSource::Product {
+                    product_id: 1,
+                    meal_type: "takeout".to_string()
+                },
+            ]
+        );
+        assert_eq!(count(&ctx, "meal").await, 0);
+
+        let resp = post_quick_log(&ctx, "?commit=true", "dinner", "taco spot @Fremont").await;
+        assert_eq!(resp.status(), 201);
+        let preview: QuickLogPreview = test::read_body_json(resp).await;
+        assert!(preview.committed);
+        assert_eq!(preview.ids.len(), 1);
+        let QuickLogEntry::Meal(meal) = &preview.entries[0] else {
+            panic!("Expected a meal, got {:?}", preview.entries[0]);
+        };
+        // The restaurant already says where it was
+        assert_eq!(meal.notes, None);
+        assert_eq!(
+            meal.food_sources,
+            vec![CreateMealFoodSource::Restaurant {
+                restaurant_id: 1,
+                meal_type: "dine-in".to_string(),
+                visit: Visit::default()
+            }]
+        );
+
+        let people: Vec<i32> =
+            sqlx::query_scalar("SELECT people FROM meal_people WHERE meal = $1 ORDER BY people")
+                .bind(preview.ids[0])
+                .fetch_all(&ctx.pool)
+                .await
+                .unwrap();
+        assert_eq!(people, vec![1]);
+        assert_eq!(count(&ctx, "history").await, 1);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_quick_log_events_and_drinks() {
+        let ctx = setup_test_context().await;
+
+        let resp = post_quick_log(&ctx, "?commit=true", "event", "hike w/ bob @Mission Peak").await;
+        assert_eq!(resp.status(), 201);
+        let preview: QuickLogPreview = test::read_body_json(resp).await;
+        let QuickLogEntry::Event(event) = &preview.entries[0] else {
+            panic!("Expected an event, got {:?}", preview.entries[0]);
+        };
+        assert_eq!(event.activity_id, 1);
+        assert_eq!(event.location.as_deref(), Some("Mission Peak"));
+        assert_eq!(event.people_ids, vec![1, 2]);
+        assert_eq!(count(&ctx, "event").await, 1);
+
+        let resp = post_quick_log(&ctx, "?commit=true", "drink", "sip house ube latte").await;
+        assert_eq!(resp.status(), 201);
+        let preview: QuickLogPreview = test::read_body_json(resp).await;
+        let QuickLogEntry::Drink(drink) = &preview.entries[0] else {
+            panic!("Expected a drink, got {:?}", preview.entries[0]);
+        };
+        assert_eq!(drink.name, "Sip House - Ube Latte");
+        assert_eq!(count(&ctx, "drink").await, 1);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_quick_log_unresolved() {
+        let ctx = setup_test_context().await;
+
+        let resp = post_quick_log(&ctx, "", "lunch", "fried rice, sushi w/ carol").await;
+        assert_eq!(resp.status(), 200);
+        let preview: QuickLogPreview = test::read_body_json(resp).await;
+        assert!(preview.entries.is_empty());
+        assert_eq!(preview.issues.len(), 2);
+        assert!(preview.issues[0].contains("'carol'"));
+        assert!(preview.issues[1].contains("'sushi'"));
+
+        let resp = post_quick_log(&ctx, "?commit=true", "lunch", "fried rice, sushi").await;
+        assert_eq!(resp.status(), 422);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["field"], "text");
+        assert_eq!(count(&ctx, "meal").await, 0);
+
+        let resp = post_quick_log(&ctx, "", "brunch", "fried rice").await;
+        assert_eq!(resp.status(), 400);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_quick_log_fuzzy_people() {
+        let ctx = setup_test_context().await;
+
+        // Close to the alias 'bobby'
+        let resp = post_quick_log(&ctx, "", "event", "hike w/ bobb").await;
+        let preview: QuickLogPreview = test::read_body_json(resp).await;
+        assert!(preview.issues.is_empty(), "{:?}", preview.issues);
+        let QuickLogEntry::Event(event) = &preview.entries[0] else {
+            panic!("Expected an event, got {:?}", preview.entries[0]);
+        };
+        assert_eq!(event.people_ids, vec![1, 2]);
+
+        let resp = post_quick_log(&ctx, "", "event", "hike w/ zed").await;
+        let preview: QuickLogPreview = test::read_body_json(resp).await;
+        assert!(preview.entries.is_empty());
+        assert_eq!(preview.issues.len(), 1);
+        assert!(preview.issues[0].contains("'zed'"));
+
+        sqlx::raw_sql("INSERT INTO people (id, name) VALUES (3, 'Bobbi')")
+            .execute(&ctx.pool)
+            .await
+            .expect("Failed to insert person");
+        let resp = post_quick_log(&ctx, "", "event", "hike w/ bobb").await;
+        let preview: QuickLogPreview = test::read_body_json(resp).await;
+        assert!(preview.entries.is_empty());
+        assert_eq!(preview.issues, vec!["'bobb' could be Bob or Bobbi"]);
+
+        teardown_test_context(ctx).await;
+    }
+}
@@ -1,9 +1,14 @@
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 #[cfg(test)]
 mod tests {
     use actix_web::{test, web, App};
+    use rust_decimal::Decimal;
     use serial_test::serial;
     use sqlx::PgPool;
-    use xnote::handlers::restaurants;
+    use xnote::config::database;
+    use xnote::handlers::{meals, restaurants};
+    use xnote::models::restaurant::{RestaurantDetail, RestaurantVisit};
 
     struct TestContext {
         pool: PgPool,
@@ -24,56 +29,29 @@ mod tests {
     }
 
     async fn create_schema(pool: &PgPool) {
-        let schema_sql = include_str!("../init.sql");
-        sqlx::raw_sql(schema_sql)
-            .execute(pool)
+        database::run_migrations(pool)
             .await
-            .expect("Failed to create schema");
+            .expect("Failed to run migrations");
     }
 
     async fn cleanup_database(pool: &PgPool) {
-        let tables = vec![
-            "drink_people",
-            "drink",
-            "drink_option",
-            "event_people",
-            "event",
-            "activity",
-            "activity_type",
-            "meal_people",
-            "meal_restaurant",
-            "meal_product",
-            "meal_recipe",
-            "meal",
-            "meal_time",
-            "meal_type",
-            "people",
-            "restaurant",
-            "product",
-            "recipe",
-            "location",
-            "food_type",
-        ];
-
-        for table in tables {
-            let query = format!("DROP TABLE IF EXISTS {} CASCADE", table);
-            sqlx::query(&query)
-                .execute(pool)
-                .await
-                .expect(&format!("Failed to drop table {}", table));
-        }
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
     }
 
     async fn setup_test_context() -> TestContext {
         let pool = create_test_database_pool().await;
-        // Drop leftover state from previous runs; init.sql inserts are not idempotent
+        // Drop leftover state from previous runs so migrations apply from scratch
         cleanup_database(&pool).await;
         create_schema(&pool).await;
 
         // Insert restaurants
         let restaurant1_id: i32 = sqlx::query_scalar!(
             "INSERT INTO restaurant (name, location, type, price) VALUES ($1, $2, $3, $4) RETURNING id",
-            "Pasta Palace", "Seattle Downtown", "Italian", Some(25.50)
+            "Pasta Palace", "Seattle Downtown", "Italian", Some(Decimal::new(2550, 2))
         )
         .fetch_one(&pool)
         .await
@@ -81,7 +59,7 @@ mod tests {
 
         let restaurant2_id: i32 = sqlx::query_scalar!(
             "INSERT INTO restaurant (name, location, type, price) VALUES ($1, $2, $3, $4) RETURNING id",
-            "Burger Joint", "Capitol Hill", "fast food", None::<f32>
+            "Burger Joint", "Capitol Hill", "fast food", None::<Decimal>
         )
         .fetch_one(&pool)
         .await
@@ -89,7 +67,7 @@ mod tests {
 
         let restaurant3_id: i32 = sqlx::query_scalar!(
             "INSERT INTO restaurant (name, location, type, price) VALUES ($1, $2, $3, $4) RETURNING id",
-            "Taco Truck", "Ballard", "mexican", Some(12.75)
+            "Taco Truck", "Ballard", "mexican", Some(Decimal::new(1275, 2))
         )
         .fetch_one(&pool)
         .await
@@ -166,7 +144,7 @@ mod tests {
         assert_eq!(restaurant.name, "Pasta Palace");
         assert_eq!(restaurant.location, "Seattle Downtown");
         assert_eq!(restaurant.food_type, "Italian");
-        assert_eq!(restaurant.price, Some(25.50));
+        assert_eq!(restaurant.price, Some(Decimal::new(2550, 2)));
 
         teardown_test_context(ctx).await;
     }
@@ -261,7 +239,7 @@ mod tests {
         assert_eq!(restaurant.name, "Sushi Bar");
         assert_eq!(restaurant.location, "Seattle Downtown");
         assert_eq!(restaurant.food_type, "japanese");
-        assert_eq!(restaurant.price, Some(45.0));
+        assert_eq!(restaurant.price, Some(Decimal::from(45)));
 
         teardown_test_context(ctx).await;
     }
@@ -293,7 +271,7 @@ mod tests {
             serde_json::from_slice(&body).expect("Failed to deserialize restaurant");
         assert_eq!(restaurant.id, ctx.restaurant1_id);
         assert_eq!(restaurant.name, "Pasta Palace");
-        assert_eq!(restaurant.price, Some(30.0));
+        assert_eq!(restaurant.price, Some(Decimal::from(30)));
 
         teardown_test_context(ctx).await;
     }
@@ -324,4 +302,154 @@ mod tests {
 
         teardown_test_context(ctx).await;
     }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_restaurant_visit_stats() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(meals::configure)
+                .configure(restaurants::configure),
+        )
+        .await;
+
+        let visits = [
+            (
+                "2024-01-10",
+                serde_json::json!({ "amount": 40.10, "rating": 4, "dishes": ["Carbonara", " "] }),
+            ),
+            (
+                "2024-02-03",
+                serde_json::json!({ "amount": 19.95, "currency": "usd", "rating": 2 }),
+            ),
+            (
+                "2024-02-20",
+                serde_json::json!({ "amount": 2000, "currency": "JPY" }),
+            ),
+        ];
+        for (date, visit) in visits {
+            let mut food_source = serde_json::json!({
+                "type": "restaurant",
+                "restaurant_id": ctx.restaurant1_id,
+                "meal_type": "dine-in"
+            });
+            food_source
+                .as_object_mut()
+                .unwrap()
+                .extend(visit.as_object().unwrap().clone());
+            let req = test::TestRequest::post()
+                .uri("/meals")
+                .set_json(serde_json::json!({
+                    "date": date,
+                    "time": "dinner",
+                    "food_source": food_source,
+                    "people_ids": []
+                }))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 201);
+        }
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/restaurants/{}", ctx.restaurant1_id))
+            .to_request();
+        let detail: RestaurantDetail = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(detail.restaurant.name, "Pasta Palace");
+        assert_eq!(detail.stats.visits, 3);
+        assert_eq!(detail.stats.last_visit, "2024-02-20".parse().ok());
+        assert_eq!(detail.stats.average_rating, Some(3.0));
+        let trend: Vec<(String, f64)> = detail
+            .stats
+            .rating_trend
+            .iter()
+            .map(|month| (month.month.to_string(), month.average_rating))
+            .collect();
+        assert_eq!(
+            trend,
+            vec![
+                ("2024-01-01".to_string(), 4.0),
+                ("2024-02-01".to_string(), 2.0)
+            ]
+        );
+        let spend: Vec<(&str, Decimal, i64)> = detail
+            .stats
+            .spend
+            .iter()
+            .map(|spend| (spend.currency.as_str(), spend.amount, spend.visits))
+            .collect();
+        assert_eq!(
+            spend,
+            vec![
+                ("JPY", Decimal::from(2000), 1),
+                ("USD", Decimal::new(6005, 2), 2)
+            ]
+        );
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/restaurants/{}/visits", ctx.restaurant1_id))
+            .to_request();
+        let visits: Vec<RestaurantVisit> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(visits.len(), 3);
+        assert_eq!(visits[0].date.to_string(), "2024-02-20");
+        assert_eq!(visits[0].visit.rating, None);
+        assert_eq!(visits[2].visit.currency.as_deref(), Some("USD"));
+        assert_eq!(visits[2].visit.dishes, vec!["Carbonara"]);
+
+        // A restaurant never visited has empty stats
+        let req = test::TestRequest::get()
+            .uri(&format!("/restaurants/{}", ctx.restaurant2_id))
+            .to_request();
+        let detail: RestaurantDetail = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(detail.stats.visits, 0);
+        assert_eq!(detail.stats.average_rating, None);
+        assert!(detail.stats.spend.is_empty());
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_invalid_restaurant_visit() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(meals::configure),
+        )
+        .await;
+
+        for visit in [
+            serde_json::json!({ "rating": 6 }),
+            serde_json::json!({ "amount": -1 }),
+            serde_json::json!({ "currency": "USD" }),
+            serde_json::json!({ "amount": 10, "currency": "dollars" }),
+        ] {
+            let mut food_source = serde_json::json!({
+                "type": "restaurant",
+                "restaurant_id": ctx.restaurant1_id,
+                "meal_type": "dine-in"
+            });
+            food_source
+                .as_object_mut()
+                .unwrap()
+                .extend(visit.as_object().unwrap().clone());
+            let req = test::TestRequest::post()
+                .uri("/meals")
+                .set_json(serde_json::json!({
+                    "date": "2024-01-10",
+                    "time": "dinner",
+                    "food_source": food_source,
+                    "people_ids": []
+                }))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 400, "{}", visit);
+        }
+
+        teardown_test_context(ctx).await;
+    }
 }
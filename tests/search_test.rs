@@ -0,0 +1,301 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use rust_decimal::Decimal;
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use xnote::config::database;
+    use xnote::handlers::search;
+    use xnote::models::search::SearchResponse;
+
+    struct TestContext {
+        pool: PgPool,
+        restaurant_id: i32,
+        recipe_id: i32,
+        spring_meal_id: i32,
+        autumn_meal_id: i32,
+        drink_id: i32,
+        alice_id: i32,
+    }
+
+    async fn create_test_database_pool() -> PgPool {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let last_slash = database_url
+            .rfind('/')
+            .expect("DATABASE_URL must be a valid connection string");
+        let database_url = format!("{}/xnote_test", &database_url[..last_slash]);
+        PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect to test database")
+    }
+
+    async fn create_schema(pool: &PgPool) {
+        database::run_migrations(pool)
+            .await
+            .expect("Failed to run migrations");
+    }
+
+    async fn cleanup_database(pool: &PgPool) {
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
+    }
+
+    async fn setup_test_context() -> TestContext {
+        let pool = create_test_database_pool().await;
+        // Drop leftover state from previous runs so migrations apply from scratch
+        cleanup_database(&pool).await;
+        create_schema(&pool).await;
+
+        let alice_id: i32 = sqlx::query_scalar!(
+            "INSERT INTO people (name, notes) VALUES ($1, $2) RETURNING id",
+            "Alice",
+            Some("Met at work")
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to insert person");
+
+        let restaurant_id: i32 = sqlx::query_scalar!(
+            "INSERT INTO restaurant (name, location, type, price) VALUES ($1, $2, $3, $4) RETURNING id",
+            "Ramen Danbo", "Capitol Hill", "japanese", Some(Decimal::from(18))
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to insert restaurant");
+
+        let recipe_id: i32 = sqlx::query_scalar!(
+            "INSERT INTO recipe (name, ingredients, procedure, cautions) VALUES ($1, $2, $3, $4) RETURNING id",
+            "番茄炒蛋", "番茄, 鸡蛋, 葱", "先炒蛋再炒番茄", None::<String>
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to insert recipe");
+
+        let spring_meal_id: i32 = sqlx::query_scalar!(
+            "INSERT INTO meal (date, \"time\", notes) VALUES ($1, $2, $3) RETURNING id",
+            chrono::NaiveDate::from_ymd_opt(2024, 4, 10).unwrap(),
+            "dinner",
+            Some("Rich tonkotsu ramen, long line")
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to insert spring meal");
+
+        let autumn_meal_id: i32 = sqlx::query_scalar!(
+            "INSERT INTO meal (date, \"time\", notes) VALUES ($1, $2, $3) RETURNING id",
+            chrono::NaiveDate::from_ymd_opt(2024, 10, 2).unwrap(),
+            "lunch",
+            Some("Spicy ramen")
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to insert autumn meal");
+
+        sqlx::query!(
+            "INSERT INTO meal_restaurant (meal, restaurant, type) VALUES ($1, $2, $3)",
+            spring_meal_id,
+            restaurant_id,
+            "dine-in"
+        )
+        .execute(&pool)
+        .await
+        .expect("Failed to link meal to restaurant");
+
+        let drink_id: i32 = sqlx::query_scalar!(
+            "INSERT INTO drink (name, date) VALUES ($1, $2) RETURNING id",
+            "吃茶三千",
+            chrono::NaiveDate::from_ymd_opt(2024, 4, 11).unwrap()
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to insert drink");
+
+        TestContext {
+            pool,
+            restaurant_id,
+            recipe_id,
+            spring_meal_id,
+            autumn_meal_id,
+            drink_id,
+            alice_id,
+        }
+    }
+
+    async fn teardown_test_context(ctx: TestContext) {
+        cleanup_database(&ctx.pool).await;
+        ctx.pool.close().await;
+    }
+
+    async fn run_search(ctx: &TestContext, query_string: &str) -> SearchResponse {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(search::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/search?{}", query_string))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let body = test::read_body(resp).await;
+        serde_json::from_slice(&body).expect("Failed to deserialize search response")
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_search_groups_hits_by_entity_type() {
+        let ctx = setup_test_context().await;
+
+        let response = run_search(&ctx, "q=ramen").await;
+
+        assert_eq!(response.query, "ramen");
+        assert_eq!(response.results.restaurants.len(), 1);
+        assert_eq!(response.results.restaurants[0].id, ctx.restaurant_id);
+        assert_eq!(
+            response.results.restaurants[0].link,
+            format!("/api/v1/restaurants/{}", ctx.restaurant_id)
+        );
+
+        assert_eq!(response.results.meals.len(), 2);
+        let spring_meal = response
+            .results
+            .meals
+            .iter()
+            .find(|hit| hit.id == ctx.spring_meal_id)
+            .expect("Spring meal should match");
+        assert_eq!(spring_meal.title, "dinner: Ramen Danbo");
+        assert_eq!(
+            spring_meal.date,
+            Some(chrono::NaiveDate::from_ymd_opt(2024, 4, 10).unwrap())
+        );
+        assert_eq!(
+            spring_meal.link,
+            format!("/api/v1/meals/{}/details", ctx.spring_meal_id)
+        );
+
+        assert!(response.results.people.is_empty());
+        assert_eq!(response.total, 3);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_search_matches_cjk_substrings() {
+        let ctx = setup_test_context().await;
+
+        let response = run_search(&ctx, "q=%E5%90%83%E8%8C%B6").await; // 吃茶
+        assert_eq!(response.results.drinks.len(), 1);
+        assert_eq!(response.results.drinks[0].id, ctx.drink_id);
+        assert_eq!(
+            response.results.drinks[0].link,
+            format!("/api/v1/drinks/{}/details", ctx.drink_id)
+        );
+
+        let response = run_search(&ctx, "q=%E9%B8%A1%E8%9B%8B").await; // 鸡蛋
+        assert_eq!(response.results.recipes.len(), 1);
+        assert_eq!(response.results.recipes[0].id, ctx.recipe_id);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_search_date_range_limits_dated_hits() {
+        let ctx = setup_test_context().await;
+
+        let response = run_search(&ctx, "q=ramen&start_date=2024-03-01&end_date=2024-05-31").await;
+
+        assert_eq!(response.results.meals.len(), 1);
+        assert_eq!(response.results.meals[0].id, ctx.spring_meal_id);
+        assert!(response
+            .results
+            .meals
+            .iter()
+            .all(|hit| hit.id != ctx.autumn_meal_id));
+        // Undated entities are not affected by the date range
+        assert_eq!(response.results.restaurants.len(), 1);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_search_people_by_name() {
+        let ctx = setup_test_context().await;
+
+        let response = run_search(&ctx, "q=alice").await;
+
+        assert_eq!(response.results.people.len(), 1);
+        assert_eq!(response.results.people[0].id, ctx.alice_id);
+        assert_eq!(response.results.people[0].date, None);
+        assert!(response.results.meals.is_empty());
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_search_meals_by_source_and_people() {
+        let ctx = setup_test_context().await;
+
+        let response = run_search(&ctx, "q=danbo").await;
+        assert_eq!(response.results.meals.len(), 1);
+        assert_eq!(response.results.meals[0].id, ctx.spring_meal_id);
+
+        // Trashed dishes no longer find the meal
+        sqlx::query!(
+            "UPDATE restaurant SET deleted_at = now() WHERE id = $1",
+            ctx.restaurant_id
+        )
+        .execute(&ctx.pool)
+        .await
+        .expect("Failed to trash restaurant");
+        let response = run_search(&ctx, "q=danbo").await;
+        assert!(response.results.meals.is_empty());
+
+        sqlx::query!(
+            "INSERT INTO meal_people (meal, people) VALUES ($1, $2)",
+            ctx.autumn_meal_id,
+            ctx.alice_id
+        )
+        .execute(&ctx.pool)
+        .await
+        .expect("Failed to link meal to person");
+
+        let response = run_search(&ctx, "q=alice").await;
+        assert_eq!(response.results.people.len(), 1);
+        assert_eq!(response.results.meals.len(), 1);
+        assert_eq!(response.results.meals[0].id, ctx.autumn_meal_id);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_search_empty_query() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(search::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/search?q=%20").to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        teardown_test_context(ctx).await;
+    }
+}
@@ -0,0 +1,517 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use xnote::config::database;
+    use xnote::handlers::stats;
+    use xnote::models::stats::{
+        FoodTypeSpend, RecipeCount, RestaurantVisits, SeriesResponse, SpendResponse,
+    };
+
+    struct TestContext {
+        pool: PgPool,
+        taco_id: i32,
+        pasta_id: i32,
+        recipe_id: i32,
+    }
+
+    async fn create_test_database_pool() -> PgPool {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let last_slash = database_url
+            .rfind('/')
+            .expect("DATABASE_URL must be a valid connection string");
+        let database_url = format!("{}/xnote_test", &database_url[..last_slash]);
+        PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect to test database")
+    }
+
+    async fn create_schema(pool: &PgPool) {
+        database::run_migrations(pool)
+            .await
+            .expect("Failed to run migrations");
+    }
+
+    async fn cleanup_database(pool: &PgPool) {
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    async fn insert_meal(pool: &PgPool, date: NaiveDate, time: &str, people: &[i32]) -> i32 {
+        let meal_id: i32 = sqlx::query_scalar!(
+            "INSERT INTO meal (date, \"time\") VALUES ($1, $2) RETURNING id",
+            date,
+            time
+        )
+        .fetch_one(pool)
+        .await
+        .expect("Failed to insert meal");
+
+        for person_id in people {
+            sqlx::query!(
+                "INSERT INTO meal_people (meal, people) VALUES ($1, $2)",
+                meal_id,
+                person_id
+            )
+            .execute(pool)
+            .await
+            .expect("Failed to link person to meal");
+        }
+
+        meal_id
+    }
+
+    async fn link_restaurant(pool: &PgPool, meal_id: i32, restaurant_id: i32, meal_type: &str) {
+        sqlx::query!(
+            "INSERT INTO meal_restaurant (meal, restaurant, type) VALUES ($1, $2, $3)",
+            meal_id,
+            restaurant_id,
+            meal_type
+        )
+        .execute(pool)
+        .await
+        .expect("Failed to link meal to restaurant");
+    }
+
+    async fn link_recipe(pool: &PgPool, meal_id: i32, recipe_id: i32) {
+        sqlx::query!(
+            "INSERT INTO meal_recipe (meal, recipe, type) VALUES ($1, $2, 'cooked')",
+            meal_id,
+            recipe_id
+        )
+        .execute(pool)
+        .await
+        .expect("Failed to link meal to recipe");
+    }
+
+    async fn setup_test_context() -> TestContext {
+        let pool = create_test_database_pool().await;
+        // Drop leftover state from previous runs so migrations apply from scratch
+        cleanup_database(&pool).await;
+        create_schema(&pool).await;
+
+        let alice_id: i32 =
+            sqlx::query_scalar!("INSERT INTO people (name) VALUES ('Alice') RETURNING id")
+                .fetch_one(&pool)
+                .await
+                .expect("Failed to insert Alice");
+        let bob_id: i32 =
+            sqlx::query_scalar!("INSERT INTO people (name) VALUES ('Bob') RETURNING id")
+                .fetch_one(&pool)
+                .await
+                .expect("Failed to insert Bob");
+
+        let taco_id: i32 = sqlx::query_scalar!(
+            "INSERT INTO restaurant (name, location, type, price) VALUES ($1, $2, $3, $4) RETURNING id",
+            "Taco Truck",
+            "Ballard",
+            "mexican",
+            Some(Decimal::new(125, 1))
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to insert restaurant");
+        let pasta_id: i32 = sqlx::query_scalar!(
+            "INSERT INTO restaurant (name, location, type, price) VALUES ($1, $2, $3, $4) RETURNING id",
+            "Pasta Palace",
+            "SLU",
+            "Italian",
+            Some(Decimal::from(30))
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to insert restaurant");
+
+        let recipe_id: i32 = sqlx::query_scalar!(
+            "INSERT INTO recipe (name, ingredients, procedure) VALUES ($1, $2, $3) RETURNING id",
+            "番茄炒蛋",
+            "tomato, egg",
+            "stir fry"
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to insert recipe");
+
+        let meal = insert_meal(&pool, date(2024, 1, 5), "dinner", &[alice_id]).await;
+        link_restaurant(&pool, meal, taco_id, "dine-in").await;
+        let meal = insert_meal(&pool, date(2024, 1, 20), "lunch", &[alice_id, bob_id]).await;
+        link_restaurant(&pool, meal, taco_id, "takeout").await;
+        let meal = insert_meal(&pool, date(2024, 2, 3), "dinner", &[bob_id]).await;
+        link_recipe(&pool, meal, recipe_id).await;
+        let meal = insert_meal(&pool, date(2024, 3, 10), "dinner", &[]).await;
+        link_restaurant(&pool, meal, pasta_id, "dine-in").await;
+        let meal = insert_meal(&pool, date(2024, 3, 11), "lunch", &[]).await;
+        link_recipe(&pool, meal, recipe_id).await;
+
+        let activity_id: i32 = sqlx::query_scalar!(
+            "INSERT INTO activity (name, type) VALUES ('Running', 'sport') RETURNING id"
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to insert activity");
+        sqlx::query!(
+            "INSERT INTO event (date, activity, location) VALUES ($1, $2, 'Ballard')",
+            date(2024, 2, 14),
+            activity_id
+        )
+        .execute(&pool)
+        .await
+        .expect("Failed to insert event");
+
+        TestContext {
+            pool,
+            taco_id,
+            pasta_id,
+            recipe_id,
+        }
+    }
+
+    async fn teardown_test_context(ctx: TestContext) {
+        cleanup_database(&ctx.pool).await;
+        ctx.pool.close().await;
+    }
+
+    fn series_data<'a>(response: &'a SeriesResponse<i64>, name: &str) -> &'a [i64] {
+        &response
+            .series
+            .iter()
+            .find(|s| s.name == name)
+            .unwrap_or_else(|| panic!("Missing series {}", name))
+            .data
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_meals_by_meal_type_per_month() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(stats::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/stats/series?entity=meals&period=month&group_by=meal_type")
+            .to_request();
+        let response: SeriesResponse<i64> = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(
+            response.periods,
+            vec![date(2024, 1, 1), date(2024, 2, 1), date(2024, 3, 1)]
+        );
+        assert_eq!(series_data(&response, "cooked"), &[0, 1, 1]);
+        assert_eq!(series_data(&response, "dine-in"), &[1, 0, 1]);
+        assert_eq!(series_data(&response, "takeout"), &[1, 0, 0]);
+        // Series are ordered by total, largest first
+        assert_eq!(response.series.last().unwrap().name, "takeout");
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_series_fills_empty_periods_in_range() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(stats::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/stats/series?start_date=2023-12-15&end_date=2024-04-30")
+            .to_request();
+        let response: SeriesResponse<i64> = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(response.periods.len(), 5);
+        assert_eq!(response.periods[0], date(2023, 12, 1));
+        assert_eq!(series_data(&response, "meals"), &[0, 2, 1, 2, 0]);
+
+        let req = test::TestRequest::get()
+            .uri("/stats/series?group_by=person&period=year")
+            .to_request();
+        let response: SeriesResponse<i64> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(series_data(&response, "Alice"), &[2]);
+        assert_eq!(series_data(&response, "Bob"), &[2]);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_events_by_activity_type_per_week() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(stats::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/stats/series?entity=events&period=week&group_by=activity_type")
+            .to_request();
+        let response: SeriesResponse<i64> = test::call_and_read_body_json(&app, req).await;
+
+        // 2024-02-14 is a Wednesday; weeks start on Monday
+        assert_eq!(response.periods, vec![date(2024, 2, 12)]);
+        assert_eq!(series_data(&response, "sport"), &[1]);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_unsupported_grouping() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(stats::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/stats/series?entity=drinks&group_by=meal_type")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        let body = test::read_body(resp).await;
+        let error_response: serde_json::Value =
+            serde_json::from_slice(&body).expect("Failed to deserialize error response");
+        assert_eq!(error_response["error"], "Cannot group drinks by meal_type");
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_series_rejects_too_many_periods() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(stats::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/stats/series?period=week&start_date=0001-01-01&end_date=9999-12-31")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        let req = test::TestRequest::get()
+            .uri("/stats/series?period=year&start_date=0001-01-01&end_date=9999-12-31")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        let req = test::TestRequest::get()
+            .uri("/stats/series?period=year&start_date=1900-01-01&end_date=2099-12-31")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_top_restaurants_and_spend() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(stats::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/stats/restaurants")
+            .to_request();
+        let restaurants: Vec<RestaurantVisits> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(restaurants.len(), 2);
+        assert_eq!(restaurants[0].restaurant_id, ctx.taco_id);
+        assert_eq!(restaurants[0].visits, 2);
+        assert_eq!(restaurants[0].last_visit, Some(date(2024, 1, 20)));
+        assert_eq!(restaurants[1].restaurant_id, ctx.pasta_id);
+
+        let req = test::TestRequest::get().uri("/stats/spend").to_request();
+        let spend: SpendResponse = test::call_and_read_body_json(&app, req).await;
+        let by_type: Vec<(&str, i64, f64)> = spend
+            .by_food_type
+            .iter()
+            .map(
+                |FoodTypeSpend {
+                     food_type,
+                     visits,
+                     spend,
+                 }| (food_type.as_str(), *visits, *spend),
+            )
+            .collect();
+        assert_eq!(by_type, vec![("Italian", 1, 30.0), ("mexican", 2, 25.0)]);
+        assert_eq!(spend.over_time.periods.len(), 3);
+        assert_eq!(spend.currency, None);
+
+        // Amounts in another currency are reported apart instead of being summed in
+        let meal = insert_meal(&ctx.pool, date(2024, 3, 15), "dinner", &[]).await;
+        sqlx::query!(
+            "INSERT INTO meal_restaurant (meal, restaurant, type, amount, currency) VALUES ($1, $2, 'dine-in', 40, 'EUR')",
+            meal,
+            ctx.pasta_id
+        )
+        .execute(&ctx.pool)
+        .await
+        .expect("Failed to link meal to restaurant");
+
+        let req = test::TestRequest::get().uri("/stats/spend").to_request();
+        let spend: SpendResponse = test::call_and_read_body_json(&app, req).await;
+        let totals: Vec<f64> = spend.by_food_type.iter().map(|s| s.spend).collect();
+        assert_eq!(totals, vec![30.0, 25.0]);
+
+        let req = test::TestRequest::get()
+            .uri("/stats/spend?currency=eur")
+            .to_request();
+        let spend: SpendResponse = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(spend.currency.as_deref(), Some("EUR"));
+        assert_eq!(spend.by_food_type.len(), 1);
+        assert_eq!(spend.by_food_type[0].food_type, "Italian");
+        assert_eq!(spend.by_food_type[0].visits, 1);
+        assert_eq!(spend.by_food_type[0].spend, 40.0);
+
+        let req = test::TestRequest::get()
+            .uri("/stats/spend?currency=euro")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        // Trashed restaurants drop out of the rankings
+        sqlx::query!(
+            "UPDATE restaurant SET deleted_at = NOW() WHERE id = $1",
+            ctx.pasta_id
+        )
+        .execute(&ctx.pool)
+        .await
+        .expect("Failed to trash restaurant");
+        let req = test::TestRequest::get()
+            .uri("/stats/restaurants")
+            .to_request();
+        let restaurants: Vec<RestaurantVisits> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(restaurants.len(), 1);
+        assert_eq!(restaurants[0].restaurant_id, ctx.taco_id);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_recipe_counts() {
+        let ctx = setup_test_context().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(stats::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/stats/recipes?start_date=2024-03-01")
+            .to_request();
+        let recipes: Vec<RecipeCount> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(recipes.len(), 1);
+        assert_eq!(recipes[0].recipe_id, ctx.recipe_id);
+        assert_eq!(recipes[0].times_made, 1);
+        assert_eq!(recipes[0].last_made, Some(date(2024, 3, 11)));
+
+        sqlx::query!(
+            "UPDATE recipe SET deleted_at = NOW() WHERE id = $1",
+            ctx.recipe_id
+        )
+        .execute(&ctx.pool)
+        .await
+        .expect("Failed to trash recipe");
+        let req = test::TestRequest::get().uri("/stats/recipes").to_request();
+        let recipes: Vec<RecipeCount> = test::call_and_read_body_json(&app, req).await;
+        assert!(recipes.is_empty());
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_event_hours_and_distance() {
+        let ctx = setup_test_context().await;
+
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO activity (name, type) VALUES ('Blog', 'side project');
+            INSERT INTO event (date, activity, quantity, unit)
+                SELECT '2024-03-02', id, 1.5, 'h' FROM activity WHERE name = 'Blog';
+            -- Without a quantity the hours come from the start and end times, across midnight
+            INSERT INTO event (date, activity, start_time, end_time)
+                SELECT '2024-03-20', id, '23:00', '01:30' FROM activity WHERE name = 'Blog';
+            INSERT INTO event (date, activity, quantity, unit)
+                SELECT '2024-03-05', id, 10, 'km' FROM activity WHERE name = 'Running';
+            INSERT INTO event (date, activity, quantity, unit)
+                SELECT '2024-04-05', id, 5.5, 'km' FROM activity WHERE name = 'Running';
+            "#,
+        )
+        .execute(&ctx.pool)
+        .await
+        .expect("Failed to insert events");
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx.pool.clone()))
+                .configure(stats::configure),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/stats/series?entity=events&value=hours&group_by=activity_type&start_date=2024-03-01&end_date=2024-03-31")
+            .to_request();
+        let response: SeriesResponse<f64> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(response.series.len(), 1);
+        assert_eq!(response.series[0].name, "side project");
+        assert_eq!(response.series[0].data, vec![4.0]);
+
+        let req = test::TestRequest::get()
+            .uri("/stats/series?entity=events&value=km&group_by=activity&period=year")
+            .to_request();
+        let response: SeriesResponse<f64> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(response.periods, vec![date(2024, 1, 1)]);
+        assert_eq!(response.series.len(), 1);
+        assert_eq!(response.series[0].name, "Running");
+        assert_eq!(response.series[0].total, 15.5);
+
+        let req = test::TestRequest::get()
+            .uri("/stats/series?entity=meals&value=hours")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        teardown_test_context(ctx).await;
+    }
+}
@@ -0,0 +1,209 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use chrono::NaiveDate;
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use xnote::config::database;
+    use xnote::handlers::suggestions;
+    use xnote::models::suggestion::{Candidate, Companion, SourceKind, Suggestion};
+    use xnote::suggestions::{rank, Context};
+
+    struct TestContext {
+        pool: PgPool,
+    }
+
+    async fn create_test_database_pool() -> PgPool {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let last_slash = database_url
+            .rfind('/')
+            .expect("DATABASE_URL must be a valid connection string");
+        let database_url = format!("{}/xnote_test", &database_url[..last_slash]);
+        PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect to test database")
+    }
+
+    async fn create_schema(pool: &PgPool) {
+        database::run_migrations(pool)
+            .await
+            .expect("Failed to run migrations");
+    }
+
+    async fn cleanup_database(pool: &PgPool) {
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
+    }
+
+    async fn setup_test_context() -> TestContext {
+        let pool = create_test_database_pool().await;
+        // Drop leftover state from previous runs so migrations apply from scratch
+        cleanup_database(&pool).await;
+        create_schema(&pool).await;
+
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO people (id, name, household_order) VALUES (1, 'Amy', 1), (2, 'Bob', NULL);
+            INSERT INTO recipe (id, name, ingredients, procedure) VALUES (1, 'Fried rice', 'rice', 'fry');
+            INSERT INTO restaurant (id, name, location, type) VALUES
+                (1, 'Taqueria', 'Fremont', 'mexican'),
+                (2, 'Sushi bar', 'Fremont', 'japanese');
+            INSERT INTO meal (id, date, "time") VALUES
+                (1, '2024-03-01', 'dinner'),
+                (2, '2024-03-05', 'dinner'),
+                (3, '2024-03-08', 'lunch'),
+                (4, '2024-03-10', 'dinner'),
+                (5, '2024-03-12', 'lunch'),
+                (6, '2024-03-18', 'dinner'),
+                (7, '2024-03-25', 'dinner');
+            INSERT INTO meal_restaurant (meal, restaurant, type) VALUES
+                (1, 1, 'dine-in'), (2, 2, 'dine-in'), (3, 2, 'takeout'), (6, 1, 'takeout'), (7, 2, 'dine-in');
+            INSERT INTO meal_recipe (meal, recipe, type) VALUES (4, 1, 'cooked'), (5, 1, 'cooked');
+            INSERT INTO meal_people (meal, people) VALUES
+                (1, 1), (1, 2), (2, 1), (3, 1), (4, 1), (5, 2), (6, 1), (6, 2), (7, 1), (7, 2);
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .expect("Failed to insert test data");
+
+        TestContext { pool }
+    }
+
+    async fn teardown_test_context(ctx: TestContext) {
+        cleanup_database(&ctx.pool).await;
+        ctx.pool.close().await;
+    }
+
+    macro_rules! init_app {
+        ($ctx:expr) => {
+            test::init_service(
+                App::new()
+                    .app_data(web::Data::new($ctx.pool.clone()))
+                    .configure(suggestions::configure),
+            )
+            .await
+        };
+    }
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    fn candidate(id: i32, name: &str, food_type: Option<&str>) -> Candidate {
+        Candidate {
+            kind: SourceKind::Restaurant,
+            id,
+            name: name.to_string(),
+            food_type: food_type.map(str::to_string),
+            last_eaten: None,
+            times_eaten: 0,
+            times_at_time: 0,
+            companions_joined: vec![],
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_rank_weighs_history() {
+        let context = Context {
+            today: date("2024-03-20"),
+            time: "dinner".to_string(),
+            variety_days: 7,
+            recent_food_types: vec![("mexican".to_string(), date("2024-03-19"))],
+            companions: vec![Companion {
+                id: 2,
+                name: "Bob".to_string(),
+                meals: 4,
+            }],
+        };
+        let candidates = vec![
+            Candidate {
+                last_eaten: Some(date("2024-03-19")),
+                times_eaten: 4,
+                times_at_time: 4,
+                companions_joined: vec![2],
+                ..candidate(1, "Taqueria", Some("mexican"))
+            },
+            Candidate {
+                last_eaten: Some(date("2024-02-01")),
+                times_eaten: 2,
+                times_at_time: 1,
+                ..candidate(2, "Sushi bar", Some("japanese"))
+            },
+            candidate(3, "Noodle house", Some("chinese")),
+        ];
+
+        let ranked = rank(candidates, &context);
+
+        let names: Vec<&str> = ranked.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Taqueria", "Sushi bar", "Noodle house"]);
+        assert_eq!(ranked[0].score, 1.53);
+        assert_eq!(
+            ranked[0].explanation,
+            "last had yesterday; picked 4 times, 4 for dinner; had mexican yesterday; Bob has had it before"
+        );
+        assert_eq!(ranked[1].score, 0.75);
+        assert_eq!(
+            ranked[2].explanation,
+            "never had yet; no chinese in the last 7 days; Bob never came along in 4 meals"
+        );
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_suggest_meal() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        // Bob has never been to the sushi bar, and later meals do not count
+        let req = test::TestRequest::get()
+            .uri("/suggestions/meal?time=dinner&people_ids=1,2&date=2024-03-20")
+            .to_request();
+        let ranked: Vec<Suggestion> = test::call_and_read_body_json(&app, req).await;
+        let names: Vec<&str> = ranked.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Fried rice", "Taqueria", "Sushi bar"]);
+        assert_eq!(ranked[0].kind, SourceKind::Recipe);
+        assert_eq!(ranked[2].score, 0.65);
+        assert_eq!(
+            ranked[2].explanation,
+            "last had 12 days ago; picked 2 times, 1 for dinner; no japanese in the last 7 days; Bob never came along in 3 meals"
+        );
+
+        // Without Bob the sushi bar is due, and mexican was had two days ago
+        let req = test::TestRequest::get()
+            .uri("/suggestions/meal?time=dinner&people_ids=1&date=2024-03-20&limit=2")
+            .to_request();
+        let ranked: Vec<Suggestion> = test::call_and_read_body_json(&app, req).await;
+        let names: Vec<&str> = ranked.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Sushi bar", "Fried rice"]);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_suggest_meal_rejects_unknown_input() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        for (uri, status) in [
+            ("/suggestions/meal?time=supper", 422),
+            ("/suggestions/meal?time=dinner&people_ids=1,99", 422),
+            ("/suggestions/meal?time=dinner&people_ids=1,x", 400),
+            ("/suggestions/meal?time=dinner&limit=0", 400),
+            ("/suggestions/meal?time=dinner&variety_days=-1", 400),
+            ("/suggestions/meal?time=dinner&variety_days=2147483647", 400),
+            ("/suggestions/meal", 400),
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), status, "{}", uri);
+        }
+
+        teardown_test_context(ctx).await;
+    }
+}
@@ -0,0 +1,331 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use chrono::NaiveDate;
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use xnote::config::database;
+    use xnote::handlers::templates;
+    use xnote::models::template::{AppliedTemplate, EntryKind, PendingEntry, Template};
+
+    struct TestContext {
+        pool: PgPool,
+    }
+
+    async fn create_test_database_pool() -> PgPool {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let last_slash = database_url
+            .rfind('/')
+            .expect("DATABASE_URL must be a valid connection string");
+        let database_url = format!("{}/xnote_test", &database_url[..last_slash]);
+        PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect to test database")
+    }
+
+    async fn create_schema(pool: &PgPool) {
+        database::run_migrations(pool)
+            .await
+            .expect("Failed to run migrations");
+    }
+
+    async fn cleanup_database(pool: &PgPool) {
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
+    }
+
+    async fn setup_test_context() -> TestContext {
+        let pool = create_test_database_pool().await;
+        // Drop leftover state from previous runs so migrations apply from scratch
+        cleanup_database(&pool).await;
+        create_schema(&pool).await;
+
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO people (id, name) VALUES (1, 'Amy'), (2, 'Bob');
+            INSERT INTO activity (id, name, type) VALUES (1, 'Commute', 'sport');
+            INSERT INTO drink_option (name) VALUES ('Latte') ON CONFLICT DO NOTHING;
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .expect("Failed to insert test data");
+
+        TestContext { pool }
+    }
+
+    async fn teardown_test_context(ctx: TestContext) {
+        cleanup_database(&ctx.pool).await;
+        ctx.pool.close().await;
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    macro_rules! init_app {
+        ($ctx:expr) => {
+            test::init_service(
+                App::new()
+                    .app_data(web::Data::new($ctx.pool.clone()))
+                    .configure(templates::configure),
+            )
+            .await
+        };
+    }
+
+    fn commute() -> serde_json::Value {
+        serde_json::json!({
+            "name": "Commute",
+            "kind": "event",
+            "body": {
+                "activity_id": 1,
+                "measure": "40min",
+                "location": "SLU",
+                "notes": null,
+                "people_ids": [1]
+            },
+            "recurrence": { "frequency": "weekdays" }
+        })
+    }
+
+    async fn count(ctx: &TestContext, table: &str) -> i64 {
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(&ctx.pool)
+            .await
+            .expect("Failed to count rows")
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_create_and_apply_template() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let req = test::TestRequest::post()
+            .uri("/templates")
+            .set_json(commute())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let body = test::read_body(resp).await;
+        let template: Template =
+            serde_json::from_slice(&body).expect("Failed to deserialize template");
+        assert_eq!(template.kind, EntryKind::Event);
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/templates/{}/apply?date=2024-05-06", template.id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let body = test::read_body(resp).await;
+        let applied: AppliedTemplate =
+            serde_json::from_slice(&body).expect("Failed to deserialize response");
+        assert_eq!(applied.kind, EntryKind::Event);
+
+        let (event_date, location, quantity): (NaiveDate, Option<String>, Option<f64>) =
+            sqlx::query_as("SELECT date, location, quantity FROM event WHERE id = $1")
+                .bind(applied.id)
+                .fetch_one(&ctx.pool)
+                .await
+                .expect("Failed to fetch event");
+        assert_eq!(event_date, date(2024, 5, 6));
+        assert_eq!(location.as_deref(), Some("SLU"));
+        assert_eq!(quantity, Some(40.0 / 60.0));
+        assert_eq!(count(&ctx, "event_people").await, 1);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_apply_drink_template() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let req = test::TestRequest::post()
+            .uri("/templates")
+            .set_json(serde_json::json!({
+                "name": "Homemade latte",
+                "kind": "drink",
+                "body": { "name": "Latte", "people_ids": [1, 2] }
+            }))
+            .to_request();
+        let template: Template = test::call_and_read_body_json(&app, req).await;
+        assert!(template.recurrence.is_none());
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/templates/{}/apply?date=2024-05-06", template.id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        assert_eq!(count(&ctx, "drink").await, 1);
+        assert_eq!(count(&ctx, "drink_people").await, 2);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_invalid_templates() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        // An event needs an activity
+        let mut template = commute();
+        template["body"]
+            .as_object_mut()
+            .unwrap()
+            .remove("activity_id");
+        let req = test::TestRequest::post()
+            .uri("/templates")
+            .set_json(template)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        let mut template = commute();
+        template["name"] = serde_json::json!(" ");
+        let req = test::TestRequest::post()
+            .uri("/templates")
+            .set_json(template)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        let req = test::TestRequest::post()
+            .uri("/templates/999/apply")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_pending_entries() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let req = test::TestRequest::post()
+            .uri("/templates")
+            .set_json(commute())
+            .to_request();
+        let template: Template = test::call_and_read_body_json(&app, req).await;
+
+        // 2024-05-06 is a Monday, 2024-05-11 a Saturday
+        let monday = date(2024, 5, 6);
+        let created = xnote::templates::materialize(&ctx.pool, monday)
+            .await
+            .expect("Failed to materialize");
+        assert_eq!(created, 1);
+        let created = xnote::templates::materialize(&ctx.pool, monday)
+            .await
+            .expect("Failed to materialize");
+        assert_eq!(created, 0);
+        let created = xnote::templates::materialize(&ctx.pool, date(2024, 5, 11))
+            .await
+            .expect("Failed to materialize");
+        assert_eq!(created, 0);
+        xnote::templates::materialize(&ctx.pool, date(2024, 5, 7))
+            .await
+            .expect("Failed to materialize");
+
+        let req = test::TestRequest::get().uri("/pending").to_request();
+        let pending: Vec<PendingEntry> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].date, date(2024, 5, 7));
+        assert_eq!(pending[0].template_id, template.id);
+        assert_eq!(pending[0].name, "Commute");
+        assert_eq!(count(&ctx, "event").await, 0);
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/pending/{}/confirm", pending[1].id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let event_date: NaiveDate = sqlx::query_scalar("SELECT date FROM event")
+            .fetch_one(&ctx.pool)
+            .await
+            .expect("Failed to fetch event");
+        assert_eq!(event_date, monday);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/pending/{}", pending[0].id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        // Confirmed and dismissed entries are done with
+        let req = test::TestRequest::post()
+            .uri(&format!("/pending/{}/confirm", pending[0].id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+
+        let req = test::TestRequest::get().uri("/pending").to_request();
+        let pending: Vec<PendingEntry> = test::call_and_read_body_json(&app, req).await;
+        assert!(pending.is_empty());
+        assert_eq!(count(&ctx, "event").await, 1);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_catch_up_fills_missed_days() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let req = test::TestRequest::post()
+            .uri("/templates")
+            .set_json(commute())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+
+        // Nothing materialized yet: only today
+        let created = xnote::templates::catch_up(&ctx.pool, date(2024, 5, 6))
+            .await
+            .expect("Failed to catch up");
+        assert_eq!(created, 1);
+
+        // Tuesday to Friday; the weekend has nothing
+        let created = xnote::templates::catch_up(&ctx.pool, date(2024, 5, 12))
+            .await
+            .expect("Failed to catch up");
+        assert_eq!(created, 4);
+        let created = xnote::templates::catch_up(&ctx.pool, date(2024, 5, 12))
+            .await
+            .expect("Failed to catch up");
+        assert_eq!(created, 0);
+
+        // A template created later is not back-filled for days already done
+        let mut saturdays = commute();
+        saturdays["name"] = serde_json::json!("Farmers market");
+        saturdays["recurrence"] = serde_json::json!({ "frequency": "weekly", "days": ["Sat"] });
+        let req = test::TestRequest::post()
+            .uri("/templates")
+            .set_json(saturdays)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let created = xnote::templates::catch_up(&ctx.pool, date(2024, 5, 12))
+            .await
+            .expect("Failed to catch up");
+        assert_eq!(created, 0);
+
+        // Long outages only go back so far: weekdays and Saturdays since 08-30
+        let created = xnote::templates::catch_up(&ctx.pool, date(2024, 9, 30))
+            .await
+            .expect("Failed to catch up");
+        assert_eq!(created, 27);
+
+        teardown_test_context(ctx).await;
+    }
+}
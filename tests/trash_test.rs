@@ -0,0 +1,272 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use chrono::Utc;
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use xnote::config::database;
+    use xnote::handlers::{daily_summary, meals, people, restaurants, trash};
+    use xnote::models::daily_summary::DailySummary;
+    use xnote::models::trash::{PurgeReport, TrashItem, TrashKind};
+
+    struct TestContext {
+        pool: PgPool,
+    }
+
+    async fn create_test_database_pool() -> PgPool {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let last_slash = database_url
+            .rfind('/')
+            .expect("DATABASE_URL must be a valid connection string");
+        let database_url = format!("{}/xnote_test", &database_url[..last_slash]);
+        PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect to test database")
+    }
+
+    async fn create_schema(pool: &PgPool) {
+        database::run_migrations(pool)
+            .await
+            .expect("Failed to run migrations");
+    }
+
+    async fn cleanup_database(pool: &PgPool) {
+        // Dropping the whole schema also drops the migrations table
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .execute(pool)
+            .await
+            .expect("Failed to reset schema");
+    }
+
+    async fn setup_test_context() -> TestContext {
+        let pool = create_test_database_pool().await;
+        // Drop leftover state from previous runs so migrations apply from scratch
+        cleanup_database(&pool).await;
+        create_schema(&pool).await;
+
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO people (id, name, household_order) VALUES (1, 'Amy', 1), (2, 'Bob', NULL);
+            INSERT INTO restaurant (id, name, location, type) VALUES (1, 'Taqueria', 'Fremont', 'mexican');
+            INSERT INTO meal (id, date, "time") VALUES (100, '2024-03-01', 'dinner');
+            INSERT INTO meal_restaurant (meal, restaurant, type) VALUES (100, 1, 'dine-in');
+            INSERT INTO meal_people (meal, people) VALUES (100, 1), (100, 2);
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .expect("Failed to insert test data");
+
+        TestContext { pool }
+    }
+
+    async fn teardown_test_context(ctx: TestContext) {
+        cleanup_database(&ctx.pool).await;
+        ctx.pool.close().await;
+    }
+
+    macro_rules! init_app {
+        ($ctx:expr) => {
+            test::init_service(
+                App::new()
+                    .app_data(web::Data::new($ctx.pool.clone()))
+                    .configure(trash::configure)
+                    .configure(meals::configure)
+                    .configure(people::configure)
+                    .configure(restaurants::configure)
+                    .configure(daily_summary::configure),
+            )
+            .await
+        };
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_delete_and_restore_meal() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let req = test::TestRequest::delete().uri("/meals/100").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let req = test::TestRequest::get().uri("/meals/100").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+
+        let req = test::TestRequest::get()
+            .uri("/daily-summary?start_date=2024-03-01&end_date=2024-03-01")
+            .to_request();
+        let summaries: Vec<DailySummary> = test::call_and_read_body_json(&app, req).await;
+        assert!(summaries[0].dinner.is_empty());
+
+        let req = test::TestRequest::get().uri("/trash").to_request();
+        let items: Vec<TrashItem> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].kind, TrashKind::Meal);
+        assert_eq!(items[0].title, "dinner: Taqueria");
+        assert_eq!(items[0].date.unwrap().to_string(), "2024-03-01");
+
+        let req = test::TestRequest::get()
+            .uri("/trash?kind=person")
+            .to_request();
+        let items: Vec<TrashItem> = test::call_and_read_body_json(&app, req).await;
+        assert!(items.is_empty());
+
+        let req = test::TestRequest::post()
+            .uri("/trash/meal/100/restore")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let req = test::TestRequest::get()
+            .uri("/daily-summary?start_date=2024-03-01&end_date=2024-03-01")
+            .to_request();
+        let summaries: Vec<DailySummary> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(summaries[0].dinner.len(), 1);
+        assert_eq!(summaries[0].dinner[0].people, "Amy, Bob");
+
+        let req = test::TestRequest::post()
+            .uri("/trash/meal/100/restore")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_trashed_references() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        // Only live meals keep a restaurant out of the trash
+        let req = test::TestRequest::delete()
+            .uri("/restaurants/1")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 409);
+
+        let req = test::TestRequest::delete().uri("/meals/100").to_request();
+        test::call_service(&app, req).await;
+        let req = test::TestRequest::delete()
+            .uri("/restaurants/1")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        // The meal cannot come back without its restaurant
+        let req = test::TestRequest::post()
+            .uri("/trash/meal/100/restore")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["field"], "food_sources");
+
+        let req = test::TestRequest::post()
+            .uri("/trash/restaurant/1/restore")
+            .to_request();
+        test::call_service(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/trash/meal/100/restore")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        // Trashed people drop out of details and cannot be added to meals
+        let req = test::TestRequest::delete().uri("/people/2").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let req = test::TestRequest::get()
+            .uri("/meals/100/details")
+            .to_request();
+        let details: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(details["people"].as_array().unwrap().len(), 1);
+
+        let req = test::TestRequest::post()
+            .uri("/meals")
+            .set_json(serde_json::json!({
+                "date": "2024-03-02",
+                "time": "lunch",
+                "food_sources": [
+                    { "type": "restaurant", "restaurant_id": 1, "meal_type": "takeout" }
+                ],
+                "people_ids": [1, 2]
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["field"], "people_ids");
+
+        teardown_test_context(ctx).await;
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_purge() {
+        let ctx = setup_test_context().await;
+        let app = init_app!(ctx);
+
+        let req = test::TestRequest::delete().uri("/meals/100").to_request();
+        test::call_service(&app, req).await;
+        let req = test::TestRequest::delete()
+            .uri("/restaurants/1")
+            .to_request();
+        test::call_service(&app, req).await;
+        let req = test::TestRequest::delete().uri("/people/2").to_request();
+        test::call_service(&app, req).await;
+
+        // The trashed meal still uses the restaurant
+        let req = test::TestRequest::delete()
+            .uri("/trash/restaurant/1")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 409);
+
+        let req = test::TestRequest::delete()
+            .uri("/trash/person/2")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let req = test::TestRequest::delete()
+            .uri("/trash/person/2")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+
+        // Only what was trashed before the cutoff goes
+        let report = xnote::trash::purge_all(
+            &ctx.pool,
+            Some(Utc::now() - chrono::Duration::days(30)),
+            None,
+        )
+        .await
+        .expect("Failed to purge trash");
+        assert!(report.purged.is_empty());
+        assert_eq!(report.kept, 0);
+
+        let req = test::TestRequest::delete().uri("/trash").to_request();
+        let report: PurgeReport = test::call_and_read_body_json(&app, req).await;
+        let purged: Vec<(TrashKind, u64)> = report
+            .purged
+            .iter()
+            .map(|purged| (purged.kind, purged.count))
+            .collect();
+        assert_eq!(
+            purged,
+            vec![(TrashKind::Meal, 1), (TrashKind::Restaurant, 1)]
+        );
+
+        let req = test::TestRequest::get().uri("/trash").to_request();
+        let items: Vec<TrashItem> = test::call_and_read_body_json(&app, req).await;
+        assert!(items.is_empty());
+
+        teardown_test_context(ctx).await;
+    }
+}